@@ -4,15 +4,21 @@ import { BN } from "@coral-xyz/anchor";
 import { PublicKey } from "@solana/web3.js";
 import { PerpetualsClient } from "./client";
 import { Command } from "commander";
+import { readFileSync } from "fs";
 import {
   BorrowRateParams,
+  CustodyBounds,
+  FeeTier,
   Fees,
   InitParams,
   OracleParams,
   Permissions,
+  PoolConfig,
   PositionSide,
   PricingParams,
   SetCustomOraclePriceParams,
+  StakingConfig,
+  TradingSchedule,
 } from "./types";
 
 let client: PerpetualsClient;
@@ -42,15 +48,55 @@ function init(adminSigners: PublicKey[], minSignatures: number): Promise<void> {
 
 function setAuthority(
   adminSigners: PublicKey[],
-  minSignatures: number
+  weights: number[],
+  minWeight: number
 ): Promise<void> {
-  return client.setAdminSigners(adminSigners, minSignatures);
+  return client.setAdminSigners(adminSigners, weights, minWeight);
+}
+
+function setRoleAuthority(
+  role: string,
+  scope: PublicKey,
+  signers: PublicKey[],
+  weights: number[],
+  minWeight: number
+): Promise<void> {
+  return client.setRoleSigners(role, scope, signers, weights, minWeight);
 }
 
 async function getMultisig(): Promise<void> {
   client.prettyPrint(await client.getMultisig());
 }
 
+async function getConfigBounds(): Promise<void> {
+  client.prettyPrint(await client.getConfigBounds());
+}
+
+function setConfigBounds(boundsFile: string): Promise<void> {
+  // file with {"oracle": {"maxPriceError": {"min": 0, "max": 10000}, ...}, ...}
+  const custodyBounds: CustodyBounds = JSON.parse(
+    readFileSync(boundsFile).toString(),
+    (key, value) => (key === "min" || key === "max" ? new BN(value) : value)
+  );
+
+  return client.setConfigBounds(custodyBounds);
+}
+
+function setFeeTiers(tiersFile: string): Promise<void> {
+  // file with [{"minVolumeUsd": 1000000000000, "feeMult": 9000}, ...]
+  const feeTiers: FeeTier[] = JSON.parse(
+    readFileSync(tiersFile).toString(),
+    (key, value) =>
+      key === "minVolumeUsd" || key === "feeMult" ? new BN(value) : value
+  );
+
+  return client.setFeeTiers(feeTiers);
+}
+
+async function getUserStats(wallet: PublicKey): Promise<void> {
+  client.prettyPrint(await client.getUserStats(wallet));
+}
+
 async function getPerpetuals(): Promise<void> {
   client.prettyPrint(await client.getPerpetuals());
 }
@@ -63,6 +109,47 @@ async function getPool(poolName: string): Promise<void> {
   client.prettyPrint(await client.getPool(poolName));
 }
 
+async function getUserDeposit(
+  poolName: string,
+  wallet: PublicKey
+): Promise<void> {
+  client.prettyPrint(await client.getUserDeposit(poolName, wallet));
+}
+
+async function getRedemptionQueue(
+  poolName: string,
+  tokenMint: PublicKey
+): Promise<void> {
+  client.prettyPrint(await client.getRedemptionQueue(poolName, tokenMint));
+}
+
+function unlockLpTokens(poolName: string, junior: boolean): Promise<void> {
+  return client.unlockLpTokens(poolName, junior);
+}
+
+function processRedemptionQueue(
+  poolName: string,
+  tokenMint: PublicKey
+): Promise<void> {
+  return client.processRedemptionQueue(poolName, tokenMint);
+}
+
+function crankPool(poolName: string): Promise<void> {
+  return client.crankPool(poolName);
+}
+
+function fundKeeperRewards(poolName: string, lamports: BN): Promise<void> {
+  return client.fundKeeperRewards(poolName, lamports);
+}
+
+function flashLoan(
+  poolName: string,
+  tokenMint: PublicKey,
+  amount: number
+): Promise<void> {
+  return client.flashLoan(poolName, tokenMint, new BN(amount));
+}
+
 async function getPools(): Promise<void> {
   client.prettyPrint(await client.getPools());
 }
@@ -187,6 +274,177 @@ function upgradeCustody(poolName: string, tokenMint: PublicKey): Promise<void> {
   return client.upgradeCustody(poolName, tokenMint);
 }
 
+function upgradePool(poolName: string): Promise<void> {
+  return client.upgradePool(poolName);
+}
+
+function upgradePerpetuals(): Promise<void> {
+  return client.upgradePerpetuals();
+}
+
+function upgradePosition(
+  wallet: PublicKey,
+  poolName: string,
+  tokenMint: PublicKey,
+  side: PositionSide
+): Promise<void> {
+  return client.upgradePosition(wallet, poolName, tokenMint, side);
+}
+
+function enableTranches(poolName: string): Promise<void> {
+  return client.enableTranches(poolName);
+}
+
+async function setPoolConfig(
+  poolName: string,
+  displayName: string | undefined,
+  addLiquidityAumMode: string | undefined,
+  removeLiquidityAumMode: string | undefined,
+  maxAumUsd: string | undefined,
+  maxUserLpAmount: string | undefined,
+  lpCooldownSec: string | undefined,
+  juniorPremium: string | undefined,
+  referrerShare: string | undefined,
+  traderDiscount: string | undefined,
+  maxAumAgeSec: string | undefined,
+  keeperReward: string | undefined,
+  enable: string[],
+  disable: string[]
+): Promise<void> {
+  // unspecified params are kept unchanged
+  const pool = await client.getPool(poolName);
+  const permissions: Permissions = { ...pool.permissions };
+  for (const permission of enable) {
+    permissions[permission] = true;
+  }
+  for (const permission of disable) {
+    permissions[permission] = false;
+  }
+  const config: PoolConfig = {
+    displayName: displayName ?? client.decodeName(pool.displayName),
+    permissions,
+    addLiquidityAumMode: addLiquidityAumMode
+      ? { [addLiquidityAumMode]: {} }
+      : pool.addLiquidityAumMode,
+    removeLiquidityAumMode: removeLiquidityAumMode
+      ? { [removeLiquidityAumMode]: {} }
+      : pool.removeLiquidityAumMode,
+    maxAumUsd: maxAumUsd ? new BN(maxAumUsd) : pool.maxAumUsd,
+    maxUserLpAmount: maxUserLpAmount
+      ? new BN(maxUserLpAmount)
+      : pool.maxUserLpAmount,
+    lpCooldownSec: lpCooldownSec ? new BN(lpCooldownSec) : pool.lpCooldownSec,
+    juniorPremium: juniorPremium ? new BN(juniorPremium) : pool.juniorPremium,
+    referrerShare: referrerShare ? new BN(referrerShare) : pool.referrerShare,
+    traderDiscount: traderDiscount
+      ? new BN(traderDiscount)
+      : pool.traderDiscount,
+    maxAumAgeSec: maxAumAgeSec ? new BN(maxAumAgeSec) : pool.maxAumAgeSec,
+    keeperReward: keeperReward ? new BN(keeperReward) : pool.keeperReward,
+  };
+  return client.setPoolConfig(poolName, config);
+}
+
+function setStakingConfig(
+  poolName: string,
+  rewardMint: PublicKey,
+  feeShare: number
+): Promise<void> {
+  const config: StakingConfig = {
+    rewardCustody: client.getCustodyKey(poolName, rewardMint),
+    feeShare: new BN(feeShare),
+  };
+  return client.setStakingConfig(poolName, config);
+}
+
+async function getStaking(poolName: string): Promise<void> {
+  client.prettyPrint(await client.getStaking(poolName));
+}
+
+function stake(
+  poolName: string,
+  amount: number,
+  unstake: boolean
+): Promise<void> {
+  return client.stake(poolName, new BN(amount), unstake);
+}
+
+function claimRewards(poolName: string): Promise<void> {
+  return client.claimRewards(poolName);
+}
+
+function createReferrer(poolName: string, code: string): Promise<void> {
+  return client.createReferrer(poolName, code);
+}
+
+function setReferrer(poolName: string, code: string): Promise<void> {
+  return client.setReferrer(poolName, code);
+}
+
+async function getReferrer(poolName: string, code: string): Promise<void> {
+  client.prettyPrint(await client.getReferrer(poolName, code));
+}
+
+function claimReferralFees(
+  poolName: string,
+  code: string,
+  tokenMint: PublicKey
+): Promise<void> {
+  return client.claimReferralFees(poolName, code, tokenMint);
+}
+
+function upgradeMultisig(): Promise<void> {
+  return client.upgradeMultisig();
+}
+
+function setMarketStatus(
+  poolName: string,
+  tokenMint: PublicKey | null,
+  status: string
+): Promise<void> {
+  return client.setMarketStatus(poolName, tokenMint, status);
+}
+
+function setTradingSchedule(
+  poolName: string,
+  tokenMint: PublicKey,
+  isClosed: boolean,
+  weeklyHours: number[] | null,
+  closedSpread: number,
+  accrueBorrowWhenClosed: boolean
+): Promise<void> {
+  const tradingSchedule: TradingSchedule = {
+    isClosed,
+    useWeeklyHours: weeklyHours !== null,
+    weeklyHours: weeklyHours ?? [0, 0, 0, 0, 0, 0, 0],
+    closedSpread: new BN(closedSpread),
+    accrueBorrowWhenClosed,
+  };
+  return client.setTradingSchedule(poolName, tokenMint, tradingSchedule);
+}
+
+function settleCustody(poolName: string, tokenMint: PublicKey): Promise<void> {
+  return client.settleCustody(poolName, tokenMint);
+}
+
+function setBorrowRate(
+  poolName: string,
+  tokenMint: PublicKey,
+  baseRate: number,
+  slope1: number,
+  slope2: number,
+  optimalUtilization: number
+): Promise<void> {
+  const borrowRate: BorrowRateParams = {
+    baseRate: new BN(baseRate),
+    slope1: new BN(slope1),
+    slope2: new BN(slope2),
+    optimalUtilization: new BN(optimalUtilization),
+  };
+
+  return client.setBorrowRate(poolName, tokenMint, borrowRate);
+}
+
 function setCustomOraclePrice(
   poolName: string,
   tokenMint: PublicKey,
@@ -210,16 +468,49 @@ function addLiquidity(
   poolName: string,
   tokenMint: PublicKey,
   amountIn: number,
-  minLpAmountOut: number
+  minLpAmountOut: number,
+  junior: boolean
 ): Promise<void> {
   return client.addLiquidity(
     poolName,
     tokenMint,
     new BN(amountIn),
+    new BN(minLpAmountOut),
+    junior
+  );
+}
+
+function addLiquidityMulti(
+  poolName: string,
+  tokenMints: PublicKey[],
+  amountsIn: number[],
+  minLpAmountOut: number
+): Promise<void> {
+  if (tokenMints.length != amountsIn.length) {
+    throw new Error("Number of token mints and amounts must match");
+  }
+  return client.addLiquidityMulti(
+    poolName,
+    tokenMints,
+    amountsIn.map((amountIn) => new BN(amountIn)),
     new BN(minLpAmountOut)
   );
 }
 
+async function removeLiquidityInKind(
+  poolName: string,
+  lpAmountIn: number,
+  minAmountsOut: number[] | undefined
+): Promise<void> {
+  // one min amount per pool token, no slippage check if not specified
+  const pool = await client.getPool(poolName);
+  return client.removeLiquidityInKind(
+    poolName,
+    new BN(lpAmountIn),
+    pool.custodies.map((_, i) => new BN(minAmountsOut?.[i] ?? 0))
+  );
+}
+
 function openPosition(
   poolName: string,
   tokenMint: PublicKey,
@@ -325,6 +616,13 @@ async function getOraclePrice(
   client.prettyPrint(await client.getOraclePrice(poolName, tokenMint, useEma));
 }
 
+async function getCustodyStats(
+  poolName: string,
+  tokenMint: PublicKey
+): Promise<void> {
+  client.prettyPrint(await client.getCustodyStats(poolName, tokenMint));
+}
+
 function getCustomOracleAccount(poolName: string, tokenMint: PublicKey): void {
   client.prettyPrint(
     client.getCustodyCustomOracleAccountKey(poolName, tokenMint)
@@ -448,12 +746,41 @@ async function getAum(poolName: string): Promise<void> {
   program
     .command("set-authority")
     .description("Set protocol admins")
-    .requiredOption("-m, --min-signatures <int>", "Minimum signatures")
+    .requiredOption("-m, --min-weight <int>", "Minimum total signature weight")
+    .option(
+      "-w, --weights <int...>",
+      "Admin weights in the order of public keys, 1 for all if omitted"
+    )
     .argument("<pubkey...>", "Admin public keys")
     .action(async (args, options) => {
       await setAuthority(
         args.map((x) => new PublicKey(x)),
-        options.minSignatures
+        options.weights ? options.weights.map((x) => parseInt(x)) : [],
+        options.minWeight
+      );
+    });
+
+  program
+    .command("set-role-authority")
+    .description("Set signers of the admin role")
+    .requiredOption(
+      "-r, --role <string>",
+      "Role: guardian, riskManager, treasurer or poolAdmin"
+    )
+    .option("-p, --pool <string>", "Pool name, required for poolAdmin role")
+    .requiredOption("-m, --min-weight <int>", "Minimum total signature weight")
+    .option(
+      "-w, --weights <int...>",
+      "Signer weights in the order of public keys, 1 for all if omitted"
+    )
+    .argument("<pubkey...>", "Role signer public keys")
+    .action(async (args, options) => {
+      await setRoleAuthority(
+        options.role,
+        options.pool ? client.getPoolKey(options.pool) : PublicKey.default,
+        args.map((x) => new PublicKey(x)),
+        options.weights ? options.weights.map((x) => parseInt(x)) : [],
+        options.minWeight
       );
     });
 
@@ -464,6 +791,37 @@ async function getAum(poolName: string): Promise<void> {
       await getMultisig();
     });
 
+  program
+    .command("set-config-bounds")
+    .description("Set governance bounds for custody params")
+    .argument("<string>", "Path to JSON file with custody bounds")
+    .action(async (boundsFile) => {
+      await setConfigBounds(boundsFile);
+    });
+
+  program
+    .command("get-config-bounds")
+    .description("Print governance bounds for custody params")
+    .action(async () => {
+      await getConfigBounds();
+    });
+
+  program
+    .command("set-fee-tiers")
+    .description("Set 30-day volume tiers for trade fee discounts")
+    .argument("<string>", "Path to JSON file with fee tiers")
+    .action(async (tiersFile) => {
+      await setFeeTiers(tiersFile);
+    });
+
+  program
+    .command("get-user-stats")
+    .description("Print trading stats and recent fills of the wallet")
+    .argument("<pubkey>", "User wallet")
+    .action(async (wallet) => {
+      await getUserStats(new PublicKey(wallet));
+    });
+
   program
     .command("get-perpetuals")
     .description("Print perpetuals global state")
@@ -487,6 +845,69 @@ async function getAum(poolName: string): Promise<void> {
       await getPool(poolName);
     });
 
+  program
+    .command("get-user-deposit")
+    .description("Print LP tokens minted by the wallet in the pool")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "User wallet")
+    .action(async (poolName, wallet) => {
+      await getUserDeposit(poolName, new PublicKey(wallet));
+    });
+
+  program
+    .command("unlock-lp-tokens")
+    .description("Release LP tokens held in escrow after the cooldown")
+    .argument("<string>", "Pool name")
+    .option("-j, --junior", "Unlock junior tranche LP tokens")
+    .action(async (poolName, options) => {
+      await unlockLpTokens(poolName, !!options.junior);
+    });
+
+  program
+    .command("get-redemption-queue")
+    .description("Print queued LP redemptions of the custody")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Token mint")
+    .action(async (poolName, tokenMint) => {
+      await getRedemptionQueue(poolName, new PublicKey(tokenMint));
+    });
+
+  program
+    .command("process-redemption-queue")
+    .description("Fill the first queued LP redemption of the custody")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Token mint")
+    .action(async (poolName, tokenMint) => {
+      await processRedemptionQueue(poolName, new PublicKey(tokenMint));
+    });
+
+  program
+    .command("crank-pool")
+    .description("Refresh borrow rates and AUM of all pool custodies")
+    .argument("<string>", "Pool name")
+    .action(async (poolName) => {
+      await crankPool(poolName);
+    });
+
+  program
+    .command("fund-keeper-rewards")
+    .description("Send lamports to the pool keeper rewards account")
+    .argument("<string>", "Pool name")
+    .requiredOption("-a, --amount <int>", "Lamports")
+    .action(async (poolName, options) => {
+      await fundKeeperRewards(poolName, new BN(options.amount));
+    });
+
+  program
+    .command("flash-loan")
+    .description("Borrow free custody liquidity and repay it with the fee")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Token mint")
+    .requiredOption("-a, --amount <int>", "Amount to borrow")
+    .action(async (poolName, tokenMint, options) => {
+      await flashLoan(poolName, new PublicKey(tokenMint), options.amount);
+    });
+
   program
     .command("get-pools")
     .description("Print metadata for all pools")
@@ -557,6 +978,281 @@ async function getAum(poolName: string): Promise<void> {
       await upgradeCustody(poolName, new PublicKey(tokenMint));
     });
 
+  program
+    .command("upgrade-pool")
+    .description("Initialize config of the pool created before it was added")
+    .argument("<string>", "Pool name")
+    .action(async (poolName) => {
+      await upgradePool(poolName);
+    });
+
+  program
+    .command("upgrade-perpetuals")
+    .description("Upgrade perpetuals account to the current version")
+    .action(async () => {
+      await upgradePerpetuals();
+    });
+
+  program
+    .command("upgrade-position")
+    .description("Upgrade position account to the current version")
+    .argument("<pubkey>", "User wallet")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Token mint")
+    .argument("<string>", "Position side (long / short)")
+    .action(async (wallet, poolName, tokenMint, side) => {
+      await upgradePosition(
+        new PublicKey(wallet),
+        poolName,
+        new PublicKey(tokenMint),
+        side
+      );
+    });
+
+  program
+    .command("enable-tranches")
+    .description("Split pool liquidity into senior and junior tranches")
+    .argument("<string>", "Pool name")
+    .action(async (poolName) => {
+      await enableTranches(poolName);
+    });
+
+  program
+    .command("set-pool-config")
+    .description("Update pool config")
+    .argument("<string>", "Pool name")
+    .option("-n, --display-name <string>", "Display name")
+    .option(
+      "-a, --add-aum-mode <string>",
+      "AUM mode for deposits: min, max, last or ema"
+    )
+    .option(
+      "-r, --remove-aum-mode <string>",
+      "AUM mode for withdrawals: min, max, last or ema"
+    )
+    .option("-m, --max-aum-usd <int>", "Max AUM in USD, 0 for no limit")
+    .option(
+      "-u, --max-user-lp <int>",
+      "Max LP tokens minted per wallet, 0 for no limit"
+    )
+    .option(
+      "-c, --lp-cooldown <int>",
+      "Seconds LP tokens are locked after a deposit, 0 to disable"
+    )
+    .option(
+      "-j, --junior-premium <int>",
+      "Share of senior tranche gains paid to juniors in BPS"
+    )
+    .option(
+      "-s, --referrer-share <int>",
+      "Share of referred trade fees paid to referrers in BPS"
+    )
+    .option(
+      "-t, --trader-discount <int>",
+      "Fee discount of referred traders in BPS"
+    )
+    .option(
+      "-g, --max-aum-age <int>",
+      "Max age of cached custody AUM values in seconds, 0 for no limit"
+    )
+    .option(
+      "-w, --keeper-reward <int>",
+      "Lamports paid to keepers for each timely crank, 0 to disable"
+    )
+    .option("-e, --enable <string...>", "Permissions to enable, e.g. allowSwap")
+    .option(
+      "-d, --disable <string...>",
+      "Permissions to disable, e.g. allowSwap"
+    )
+    .action(async (poolName, options) => {
+      await setPoolConfig(
+        poolName,
+        options.displayName,
+        options.addAumMode,
+        options.removeAumMode,
+        options.maxAumUsd,
+        options.maxUserLp,
+        options.lpCooldown,
+        options.juniorPremium,
+        options.referrerShare,
+        options.traderDiscount,
+        options.maxAumAge,
+        options.keeperReward,
+        options.enable ?? [],
+        options.disable ?? []
+      );
+    });
+
+  program
+    .command("set-staking-config")
+    .description("Set the token and share of fees paid to LP stakers")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Reward token mint")
+    .requiredOption("-s, --fee-share <int>", "Share of fees in BPS")
+    .action(async (poolName, rewardMint, options) => {
+      await setStakingConfig(
+        poolName,
+        new PublicKey(rewardMint),
+        options.feeShare
+      );
+    });
+
+  program
+    .command("get-staking")
+    .description("Print staking state of the pool")
+    .argument("<string>", "Pool name")
+    .action(async (poolName) => {
+      await getStaking(poolName);
+    });
+
+  program
+    .command("stake")
+    .description("Stake LP tokens")
+    .argument("<string>", "Pool name")
+    .requiredOption("-a, --amount <int>", "LP amount")
+    .action(async (poolName, options) => {
+      await stake(poolName, options.amount, false);
+    });
+
+  program
+    .command("unstake")
+    .description("Unstake LP tokens")
+    .argument("<string>", "Pool name")
+    .requiredOption("-a, --amount <int>", "LP amount")
+    .action(async (poolName, options) => {
+      await stake(poolName, options.amount, true);
+    });
+
+  program
+    .command("claim-rewards")
+    .description("Claim staking rewards")
+    .argument("<string>", "Pool name")
+    .action(async (poolName) => {
+      await claimRewards(poolName);
+    });
+
+  program
+    .command("create-referrer")
+    .description("Register a referrer code")
+    .argument("<string>", "Pool name")
+    .argument("<string>", "Referrer code")
+    .action(async (poolName, code) => {
+      await createReferrer(poolName, code);
+    });
+
+  program
+    .command("set-referrer")
+    .description("Link the wallet to a referrer code")
+    .argument("<string>", "Pool name")
+    .argument("<string>", "Referrer code")
+    .action(async (poolName, code) => {
+      await setReferrer(poolName, code);
+    });
+
+  program
+    .command("get-referrer")
+    .description("Print referrer stats and unclaimed fees")
+    .argument("<string>", "Pool name")
+    .argument("<string>", "Referrer code")
+    .action(async (poolName, code) => {
+      await getReferrer(poolName, code);
+    });
+
+  program
+    .command("claim-referral-fees")
+    .description("Claim referral fees in the given token")
+    .argument("<string>", "Pool name")
+    .argument("<string>", "Referrer code")
+    .argument("<pubkey>", "Token mint")
+    .action(async (poolName, code, tokenMint) => {
+      await claimReferralFees(poolName, code, new PublicKey(tokenMint));
+    });
+
+  program
+    .command("upgrade-multisig")
+    .description("Upgrade deprecated multisig to the new version")
+    .action(async () => {
+      await upgradeMultisig();
+    });
+
+  program
+    .command("set-market-status")
+    .description("Set market status of the pool or a single custody")
+    .argument("<string>", "Pool name")
+    .argument("[pubkey]", "Token mint, whole pool if omitted")
+    .requiredOption(
+      "-s, --status <string>",
+      "Status: active, reduceOnly or paused"
+    )
+    .action(async (poolName, tokenMint, options) => {
+      await setMarketStatus(
+        poolName,
+        tokenMint ? new PublicKey(tokenMint) : null,
+        options.status
+      );
+    });
+
+  program
+    .command("set-trading-schedule")
+    .description("Set custody trading hours")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Token mint")
+    .option("-c, --closed", "Close the market")
+    .option(
+      "-w, --weekly-hours <int...>",
+      "Open hours bitmask for each day starting on Monday, e.g. 0xffffff, open around the clock if omitted"
+    )
+    .option(
+      "-s, --closed-spread <int>",
+      "Extra spread for closing positions outside trading hours",
+      "0"
+    )
+    .option("-a, --accrue-borrow", "Accrue borrow interest outside trading hours")
+    .action(async (poolName, tokenMint, options) => {
+      if (options.weeklyHours && options.weeklyHours.length != 7) {
+        throw new Error("Weekly hours must be provided for all 7 days");
+      }
+      await setTradingSchedule(
+        poolName,
+        new PublicKey(tokenMint),
+        !!options.closed,
+        options.weeklyHours
+          ? options.weeklyHours.map((x) => parseInt(x))
+          : null,
+        parseInt(options.closedSpread),
+        !!options.accrueBorrow
+      );
+    });
+
+  program
+    .command("settle-custody")
+    .description("Freeze custody price and settle the market")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Token mint")
+    .action(async (poolName, tokenMint) => {
+      await settleCustody(poolName, new PublicKey(tokenMint));
+    });
+
+  program
+    .command("set-borrow-rate")
+    .description("Set custody borrow rate params")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Token mint")
+    .requiredOption("-b, --base-rate <int>", "Base hourly rate")
+    .requiredOption("-s, --slope1 <int>", "Rate slope below optimal utilization")
+    .requiredOption("-t, --slope2 <int>", "Rate slope above optimal utilization")
+    .requiredOption("-u, --optimal-utilization <int>", "Optimal utilization")
+    .action(async (poolName, tokenMint, options) => {
+      await setBorrowRate(
+        poolName,
+        new PublicKey(tokenMint),
+        options.baseRate,
+        options.slope1,
+        options.slope2,
+        options.optimalUtilization
+      );
+    });
+
   program
     .command("set-oracle-price")
     .description("Set custom oracle price")
@@ -587,15 +1283,55 @@ async function getAum(poolName: string): Promise<void> {
       "-o, --min-amount-out <int>",
       "Minimum LP amount to receive"
     )
+    .option("-j, --junior", "Deposit to the junior tranche")
     .action(async (poolName, tokenMint, options) => {
       await addLiquidity(
         poolName,
         new PublicKey(tokenMint),
         options.amountIn,
+        options.minAmountOut,
+        !!options.junior
+      );
+    });
+
+  program
+    .command("add-liquidity-multi")
+    .description("Deposit liquidity to several custodies at once")
+    .argument("<string>", "Pool name")
+    .requiredOption("-t, --token-mints <pubkey...>", "Token mints")
+    .requiredOption("-i, --amounts-in <int...>", "Amounts to deposit")
+    .requiredOption(
+      "-o, --min-amount-out <int>",
+      "Minimum LP amount to receive"
+    )
+    .action(async (poolName, options) => {
+      await addLiquidityMulti(
+        poolName,
+        options.tokenMints.map((tokenMint) => new PublicKey(tokenMint)),
+        options.amountsIn,
         options.minAmountOut
       );
     });
 
+  program
+    .command("remove-liquidity-in-kind")
+    .description(
+      "Withdraw a pro-rata share of every custody without ratio fees"
+    )
+    .argument("<string>", "Pool name")
+    .requiredOption("-i, --amount-in <int>", "LP amount to burn")
+    .option(
+      "-o, --min-amounts-out <int...>",
+      "Minimum amounts to receive, one per pool token"
+    )
+    .action(async (poolName, options) => {
+      await removeLiquidityInKind(
+        poolName,
+        options.amountIn,
+        options.minAmountsOut
+      );
+    });
+
   program
     .command("open-position")
     .description("Open a new perpetuals position")
@@ -732,6 +1468,15 @@ async function getAum(poolName: string): Promise<void> {
       await getOraclePrice(poolName, new PublicKey(tokenMint), options.ema);
     });
 
+  program
+    .command("get-custody-stats")
+    .description("Print custody volume, fees and peak OI over 24h and 7d")
+    .argument("<string>", "Pool name")
+    .argument("<pubkey>", "Token mint")
+    .action(async (poolName, tokenMint) => {
+      await getCustodyStats(poolName, new PublicKey(tokenMint));
+    });
+
   program
     .command("get-custom-oracle-account")
     .description("Get custom oracle account address for the token")
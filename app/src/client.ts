@@ -12,7 +12,10 @@ import {
   SystemProgram,
   Keypair,
   SYSVAR_RENT_PUBKEY,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
   AccountMeta,
+  Transaction,
+  TransactionInstruction,
 } from "@solana/web3.js";
 import { getAssociatedTokenAddress, TOKEN_PROGRAM_ID } from "@solana/spl-token";
 import { sha256 } from "js-sha256";
@@ -27,13 +30,22 @@ import {
   Permissions,
   Fees,
   BorrowRateParams,
+  CustodyBounds,
+  UpdateCustodyConfigParams,
   SetCustomOraclePriceParams,
+  TradingSchedule,
+  PoolConfig,
+  StakingConfig,
+  FeeTier,
   AmountAndFee,
   NewPositionPricesAndFee,
   PriceAndFee,
+  CustodyStats,
   ProfitAndLoss,
   SwapAmountAndFees,
   Custody,
+  AdminInstruction,
+  ADMIN_ROLES,
 } from "./types";
 
 export class PerpetualsClient {
@@ -45,6 +57,7 @@ export class PerpetualsClient {
   multisig: { publicKey: PublicKey; bump: number };
   authority: { publicKey: PublicKey; bump: number };
   perpetuals: { publicKey: PublicKey; bump: number };
+  configBounds: { publicKey: PublicKey; bump: number };
 
   constructor(clusterUrl: string, adminKey: string) {
     this.provider = AnchorProvider.local(clusterUrl, {
@@ -63,6 +76,7 @@ export class PerpetualsClient {
     this.multisig = this.findProgramAddress("multisig");
     this.authority = this.findProgramAddress("transfer_authority");
     this.perpetuals = this.findProgramAddress("perpetuals");
+    this.configBounds = this.findProgramAddress("config_bounds");
 
     BN.prototype.toJSON = function () {
       return this.toString(10);
@@ -129,7 +143,17 @@ export class PerpetualsClient {
   getPool = async (name: string) => {
     console.log(`Pool key: ${this.getPoolKey(name).toBase58()}`);
 
-    return this.program.account.pool.fetch(this.getPoolKey(name));
+    // custodies and ratios are stored in fixed size arrays
+    const pool = await this.program.account.pool.fetch(this.getPoolKey(name));
+    pool.custodies = pool.custodies.slice(0, pool.numCustodies);
+    pool.ratios = pool.ratios.slice(0, pool.numCustodies);
+
+    return pool;
+  };
+
+  // pool names are stored zero padded to 64 bytes
+  decodeName = (name: number[]): string => {
+    return Buffer.from(name).toString().replace(/\0+$/, "");
   };
 
   getPools = async () => {
@@ -143,6 +167,120 @@ export class PerpetualsClient {
       .publicKey;
   };
 
+  getPoolJuniorLpTokenKey = (name: string): PublicKey => {
+    return this.findProgramAddress("junior_lp_token_mint", [
+      this.getPoolKey(name),
+    ]).publicKey;
+  };
+
+  getUserDepositKey = (poolName: string, wallet: PublicKey): PublicKey => {
+    return this.findProgramAddress("user_deposit", [
+      wallet,
+      this.getPoolKey(poolName),
+    ]).publicKey;
+  };
+
+  getLockedLpAccountKey = (poolName: string, junior: boolean): PublicKey => {
+    return this.findProgramAddress(
+      junior ? "locked_junior_lp_account" : "locked_lp_account",
+      [this.getPoolKey(poolName)]
+    ).publicKey;
+  };
+
+  getUserDeposit = async (poolName: string, wallet: PublicKey) => {
+    return this.program.account.userDeposit.fetch(
+      this.getUserDepositKey(poolName, wallet)
+    );
+  };
+
+  getKeeperRewardsKey = (poolName: string): PublicKey => {
+    return this.findProgramAddress("keeper_rewards", [
+      this.getPoolKey(poolName),
+    ]).publicKey;
+  };
+
+  getRedemptionQueueKey = (
+    poolName: string,
+    tokenMint: PublicKey
+  ): PublicKey => {
+    return this.findProgramAddress("redemption_queue", [
+      this.getPoolKey(poolName),
+      this.getCustodyKey(poolName, tokenMint),
+    ]).publicKey;
+  };
+
+  getRedemptionQueue = async (poolName: string, tokenMint: PublicKey) => {
+    return this.program.account.redemptionQueue.fetch(
+      this.getRedemptionQueueKey(poolName, tokenMint)
+    );
+  };
+
+  getReferrerKey = (poolName: string, code: string): PublicKey => {
+    return this.findProgramAddress("referrer", [
+      this.getPoolKey(poolName),
+      Buffer.from(code),
+    ]).publicKey;
+  };
+
+  getReferrer = async (poolName: string, code: string) => {
+    return this.program.account.referrer.fetch(
+      this.getReferrerKey(poolName, code)
+    );
+  };
+
+  getReferralKey = (poolName: string, wallet: PublicKey): PublicKey => {
+    return this.findProgramAddress("referral", [
+      wallet,
+      this.getPoolKey(poolName),
+    ]).publicKey;
+  };
+
+  // returns referral accounts of the trader, or nulls if not referred
+  getReferralAccounts = async (poolName: string, wallet: PublicKey) => {
+    const referral = this.getReferralKey(poolName, wallet);
+    const referralData = await this.program.account.referral.fetchNullable(
+      referral
+    );
+    return referralData
+      ? { referral, referrer: referralData.referrer }
+      : { referral: null, referrer: null };
+  };
+
+  getUserStatsKey = (wallet: PublicKey): PublicKey => {
+    return this.findProgramAddress("user_stats", [wallet]).publicKey;
+  };
+
+  getUserStats = async (wallet: PublicKey) => {
+    return this.program.account.userStats.fetch(this.getUserStatsKey(wallet));
+  };
+
+  // returns user stats key of the wallet, or null if not created yet
+  getUserStatsKeyIfExists = async (
+    wallet: PublicKey
+  ): Promise<PublicKey | null> => {
+    const userStats = this.getUserStatsKey(wallet);
+    const userStatsData = await this.program.account.userStats.fetchNullable(
+      userStats
+    );
+    return userStatsData ? userStats : null;
+  };
+
+  getStakingKey = (poolName: string): PublicKey => {
+    return this.findProgramAddress("staking", [this.getPoolKey(poolName)])
+      .publicKey;
+  };
+
+  getStaking = async (poolName: string) => {
+    return this.program.account.staking.fetch(this.getStakingKey(poolName));
+  };
+
+  getUserStakeKey = (poolName: string, wallet: PublicKey): PublicKey => {
+    return this.findProgramAddress("user_stake", [
+      wallet,
+      this.getPoolKey(poolName),
+    ]).publicKey;
+  };
+
   getCustodyKey = (poolName: string, tokenMint: PublicKey): PublicKey => {
     return this.findProgramAddress("custody", [
       this.getPoolKey(poolName),
@@ -209,10 +347,11 @@ export class PerpetualsClient {
 
     const custodyMetas: AccountMeta[] = [];
 
+    // custodies are writable so their cached AUM values can be refreshed
     for (const custody of pool.custodies) {
       custodyMetas.push({
         isSigner: false,
-        isWritable: false,
+        isWritable: true,
         pubkey: custody,
       });
     }
@@ -241,10 +380,30 @@ export class PerpetualsClient {
     return (await this.program.account.custody.fetch(custodyAccount)).mint;
   };
 
+  getConfigBounds = async () => {
+    return this.program.account.configBounds.fetch(
+      this.configBounds.publicKey
+    );
+  };
+
   getMultisig = async () => {
     return this.program.account.multisig.fetch(this.multisig.publicKey);
   };
 
+  // pool admins are scoped with the pool key, other roles with PublicKey.default
+  getRoleMultisigKey = (role: string, scope: PublicKey): PublicKey => {
+    return this.findProgramAddress("multisig", [
+      [ADMIN_ROLES.indexOf(role)],
+      scope,
+    ]).publicKey;
+  };
+
+  getRoleMultisig = async (role: string, scope: PublicKey) => {
+    return this.program.account.multisig.fetch(
+      this.getRoleMultisigKey(role, scope)
+    );
+  };
+
   getPositionKey = (
     wallet: PublicKey,
     poolName: string,
@@ -343,6 +502,40 @@ export class PerpetualsClient {
     console.log(JSON.stringify(v, null, 2));
   };
 
+  // multisig proposal address is derived from the instruction accounts and data,
+  // admin (index 0) and the proposal itself (index 2) are not part of the hash
+  getProposalKey = (
+    ix: TransactionInstruction,
+    instructionType: AdminInstruction
+  ): PublicKey => {
+    const keys = ix.keys
+      // skip admin, proposal and placeholders of omitted optional accounts
+      .filter(
+        (meta, idx) =>
+          idx != 0 && idx != 2 && !meta.pubkey.equals(this.program.programId)
+      )
+      .map((meta) => meta.pubkey.toBuffer());
+    const data = Buffer.concat([
+      ix.data.subarray(8),
+      Buffer.from([instructionType]),
+    ]);
+    const hash = sha256.digest(Buffer.concat([...keys, data]));
+    return this.findProgramAddress("proposal", [hash]).publicKey;
+  };
+
+  signAdminInstruction = async (
+    method: any,
+    instructionType: AdminInstruction
+  ): Promise<void> => {
+    const ix = await method
+      .accounts({ proposal: PublicKey.default })
+      .instruction();
+    await method
+      .accounts({ proposal: this.getProposalKey(ix, instructionType) })
+      .signers([this.admin])
+      .rpc();
+  };
+
   ///////
   // instructions
 
@@ -384,7 +577,8 @@ export class PerpetualsClient {
 
   setAdminSigners = async (
     admins: PublicKey[],
-    minSignatures: number
+    weights: number[],
+    minWeight: number
   ): Promise<void> => {
     const adminMetas = [];
 
@@ -397,62 +591,106 @@ export class PerpetualsClient {
     }
 
     try {
-      await this.program.methods
-        .setAdminSigners({
-          minSignatures,
-        })
-        .accounts({
-          admin: this.admin.publicKey,
-          multisig: this.multisig.publicKey,
-        })
-        .remainingAccounts(adminMetas)
-        .signers([this.admin])
-        .rpc();
+      await this.signAdminInstruction(
+        this.program.methods
+          .setAdminSigners({
+            weights,
+            minWeight,
+          })
+          .accounts({
+            admin: this.admin.publicKey,
+            multisig: this.multisig.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts(adminMetas),
+        AdminInstruction.SetAdminSigners
+      );
     } catch (err) {
       console.log(err);
       throw err;
     }
   };
 
-  addPool = async (name: string): Promise<void> => {
-    await this.program.methods
-      .addPool({ name })
-      .accounts({
-        admin: this.admin.publicKey,
-        multisig: this.multisig.publicKey,
-        transferAuthority: this.authority.publicKey,
-        perpetuals: this.perpetuals.publicKey,
-        pool: this.getPoolKey(name),
-        lpTokenMint: this.getPoolLpTokenKey(name),
-        systemProgram: SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        rent: SYSVAR_RENT_PUBKEY,
-      })
-      .signers([this.admin])
-      .rpc()
-      .catch((err) => {
-        console.error(err);
-        throw err;
+  setRoleSigners = async (
+    role: string,
+    scope: PublicKey,
+    signers: PublicKey[],
+    weights: number[],
+    minWeight: number
+  ): Promise<void> => {
+    const signerMetas = [];
+
+    for (const signer of signers) {
+      signerMetas.push({
+        isSigner: false,
+        isWritable: false,
+        pubkey: signer,
       });
+    }
+
+    try {
+      await this.signAdminInstruction(
+        this.program.methods
+          .setRoleSigners({
+            role: { [role]: {} },
+            scope,
+            weights,
+            minWeight,
+          })
+          .accounts({
+            admin: this.admin.publicKey,
+            multisig: this.multisig.publicKey,
+            roleMultisig: this.getRoleMultisigKey(role, scope),
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts(signerMetas),
+        AdminInstruction.SetRoleSigners
+      );
+    } catch (err) {
+      console.log(err);
+      throw err;
+    }
+  };
+
+  addPool = async (name: string): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods
+        .addPool({ name })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.getPoolKey(name),
+          lpTokenMint: this.getPoolLpTokenKey(name),
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        }),
+      AdminInstruction.AddPool
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
   };
 
   removePool = async (name: string): Promise<void> => {
-    await this.program.methods
-      .removePool({})
-      .accounts({
-        admin: this.admin.publicKey,
-        multisig: this.multisig.publicKey,
-        transferAuthority: this.authority.publicKey,
-        perpetuals: this.perpetuals.publicKey,
-        pool: this.getPoolKey(name),
-        systemProgram: SystemProgram.programId,
-      })
-      .signers([this.admin])
-      .rpc()
-      .catch((err) => {
-        console.error(err);
-        throw err;
-      });
+    await this.signAdminInstruction(
+      this.program.methods
+        .removePool({})
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.getPoolKey(name),
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.RemovePool
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
   };
 
   addCustody = async (
@@ -467,39 +705,39 @@ export class PerpetualsClient {
     borrowRate: BorrowRateParams,
     ratios: TokenRatio[]
   ): Promise<void> => {
-    await this.program.methods
-      .addCustody({
-        isStable,
-        isVirtual,
-        oracle: oracleConfig,
-        pricing: pricingConfig,
-        permissions,
-        fees,
-        borrowRate,
-        ratios,
-      })
-      .accounts({
-        admin: this.admin.publicKey,
-        multisig: this.multisig.publicKey,
-        transferAuthority: this.authority.publicKey,
-        perpetuals: this.perpetuals.publicKey,
-        pool: this.getPoolKey(poolName),
-        custody: this.getCustodyKey(poolName, tokenMint),
-        custodyTokenAccount: this.getCustodyTokenAccountKey(
-          poolName,
-          tokenMint
-        ),
-        custodyTokenMint: tokenMint,
-        systemProgram: SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        rent: SYSVAR_RENT_PUBKEY,
-      })
-      .signers([this.admin])
-      .rpc()
-      .catch((err) => {
-        console.error(err);
-        throw err;
-      });
+    await this.signAdminInstruction(
+      this.program.methods
+        .addCustody({
+          isStable,
+          isVirtual,
+          oracle: oracleConfig,
+          pricing: pricingConfig,
+          permissions,
+          fees,
+          borrowRate,
+          ratios,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.getPoolKey(poolName),
+          custody: this.getCustodyKey(poolName, tokenMint),
+          custodyTokenAccount: this.getCustodyTokenAccountKey(
+            poolName,
+            tokenMint
+          ),
+          custodyTokenMint: tokenMint,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        }),
+      AdminInstruction.AddCustody
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
   };
 
   removeCustody = async (
@@ -507,71 +745,94 @@ export class PerpetualsClient {
     tokenMint: PublicKey,
     ratios: TokenRatio[]
   ): Promise<void> => {
-    await this.program.methods
-      .removeCustody({ ratios })
-      .accounts({
-        admin: this.admin.publicKey,
-        multisig: this.multisig.publicKey,
-        transferAuthority: this.authority.publicKey,
-        perpetuals: this.perpetuals.publicKey,
-        pool: this.getPoolKey(poolName),
-        custody: this.getCustodyKey(poolName, tokenMint),
-        custodyTokenAccount: this.getCustodyTokenAccountKey(
-          poolName,
-          tokenMint
-        ),
-        systemProgram: SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
-      })
-      .signers([this.admin])
-      .rpc()
-      .catch((err) => {
-        console.error(err);
-        throw err;
-      });
+    await this.signAdminInstruction(
+      this.program.methods
+        .removeCustody({ ratios })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.getPoolKey(poolName),
+          custody: this.getCustodyKey(poolName, tokenMint),
+          custodyTokenAccount: this.getCustodyTokenAccountKey(
+            poolName,
+            tokenMint
+          ),
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        }),
+      AdminInstruction.RemoveCustody
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
   };
 
   upgradeCustody = async (
     poolName: string,
     tokenMint: PublicKey
   ): Promise<void> => {
-    await this.program.methods
-      .upgradeCustody({})
-      .accounts({
+    await this.signAdminInstruction(
+      this.program.methods
+        .upgradeCustody({})
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          pool: this.getPoolKey(poolName),
+          custody: this.getCustodyKey(poolName, tokenMint),
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.UpgradeCustody
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  upgradePool = async (poolName: string): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods.upgradePool({}).accounts({
         admin: this.admin.publicKey,
         multisig: this.multisig.publicKey,
         pool: this.getPoolKey(poolName),
-        custody: this.getCustodyKey(poolName, tokenMint),
         systemProgram: SystemProgram.programId,
-      })
-      .signers([this.admin])
-      .rpc()
-      .catch((err) => {
-        console.error(err);
-        throw err;
-      });
+      }),
+      AdminInstruction.UpgradePool
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
   };
 
-  setCustomOraclePrice = async (
+  upgradePerpetuals = async (): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods.upgradePerpetuals({}).accounts({
+        admin: this.admin.publicKey,
+        multisig: this.multisig.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        systemProgram: SystemProgram.programId,
+      }),
+      AdminInstruction.UpgradePerpetuals
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  upgradePosition = async (
+    wallet: PublicKey,
     poolName: string,
     tokenMint: PublicKey,
-    priceConfig: SetCustomOraclePriceParams
+    side: PositionSide
   ): Promise<void> => {
     await this.program.methods
-      .setCustomOraclePrice(priceConfig)
+      .upgradePosition({})
       .accounts({
-        admin: this.admin.publicKey,
-        multisig: this.multisig.publicKey,
-        perpetuals: this.perpetuals.publicKey,
-        pool: this.getPoolKey(poolName),
-        custody: this.getCustodyKey(poolName, tokenMint),
-        oracleAccount: this.getCustodyCustomOracleAccountKey(
-          poolName,
-          tokenMint
-        ),
+        payer: this.provider.wallet.publicKey,
+        position: this.getPositionKey(wallet, poolName, tokenMint, side),
         systemProgram: SystemProgram.programId,
       })
-      .signers([this.admin])
       .rpc()
       .catch((err) => {
         console.error(err);
@@ -579,24 +840,312 @@ export class PerpetualsClient {
       });
   };
 
-  addLiquidity = async (
+  setPoolConfig = async (
     poolName: string,
-    tokenMint: PublicKey,
-    amountIn: BN,
-    minLpAmountOut: BN
+    config: PoolConfig
   ): Promise<void> => {
-    const lpTokenMint = this.getPoolLpTokenKey(poolName);
+    await this.signAdminInstruction(
+      this.program.methods
+        .setPoolConfig({
+          config,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          pool: this.getPoolKey(poolName),
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.SetPoolConfig
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
 
-    await this.program.methods
-      .addLiquidity({ amountIn, minLpAmountOut })
-      .accounts({
-        owner: this.provider.wallet.publicKey,
-        fundingAccount: await getAssociatedTokenAddress(
-          tokenMint,
-          this.provider.wallet.publicKey
-        ),
-        lpTokenAccount: await getAssociatedTokenAddress(
-          lpTokenMint,
+  enableTranches = async (poolName: string): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods
+        .enableTranches({})
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.getPoolKey(poolName),
+          juniorLpTokenMint: this.getPoolJuniorLpTokenKey(poolName),
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .remainingAccounts(await this.getCustodyMetas(poolName)),
+      AdminInstruction.EnableTranches
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  setStakingConfig = async (
+    poolName: string,
+    config: StakingConfig
+  ): Promise<void> => {
+    // fees are read from custody accounts
+    const custodyMetas = await this.getCustodyMetas(poolName);
+    await this.signAdminInstruction(
+      this.program.methods
+        .setStakingConfig({
+          config,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.getPoolKey(poolName),
+          lpTokenMint: this.getPoolLpTokenKey(poolName),
+          staking: this.getStakingKey(poolName),
+          stakingLpVault: this.findProgramAddress("staking_lp_vault", [
+            this.getPoolKey(poolName),
+          ]).publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .remainingAccounts(custodyMetas.slice(0, custodyMetas.length / 2)),
+      AdminInstruction.SetStakingConfig
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  upgradeMultisig = async (): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods.upgradeMultisig({}).accounts({
+        admin: this.admin.publicKey,
+        multisig: this.multisig.publicKey,
+        systemProgram: SystemProgram.programId,
+      }),
+      AdminInstruction.UpgradeMultisig
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  setConfigBounds = async (custodyBounds: CustodyBounds): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods
+        .setConfigBounds({
+          custody: custodyBounds,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          configBounds: this.configBounds.publicKey,
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.SetConfigBounds
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  setFeeTiers = async (feeTiers: FeeTier[]): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods
+        .setFeeTiers({
+          feeTiers,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.SetFeeTiers
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  updateCustodyConfig = async (
+    poolName: string,
+    tokenMint: PublicKey,
+    config: UpdateCustodyConfigParams
+  ): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods.updateCustodyConfig(config).accounts({
+        admin: this.admin.publicKey,
+        multisig: this.multisig.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        configBounds: this.configBounds.publicKey,
+        pool: this.getPoolKey(poolName),
+        custody: this.getCustodyKey(poolName, tokenMint),
+        systemProgram: SystemProgram.programId,
+      }),
+      AdminInstruction.UpdateCustodyConfig
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  // sets the status for the whole pool if token mint is not provided
+  setMarketStatus = async (
+    poolName: string,
+    tokenMint: PublicKey | null,
+    status: string
+  ): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods
+        .setMarketStatus({
+          status: { [status]: {} },
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          pool: this.getPoolKey(poolName),
+          custody: tokenMint ? this.getCustodyKey(poolName, tokenMint) : null,
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.SetMarketStatus
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  setTradingSchedule = async (
+    poolName: string,
+    tokenMint: PublicKey,
+    tradingSchedule: TradingSchedule
+  ): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods
+        .setTradingSchedule({
+          tradingSchedule,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.getPoolKey(poolName),
+          custody: this.getCustodyKey(poolName, tokenMint),
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.SetTradingSchedule
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  settleCustody = async (
+    poolName: string,
+    tokenMint: PublicKey
+  ): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods.settleCustody({}).accounts({
+        admin: this.admin.publicKey,
+        multisig: this.multisig.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        custody: this.getCustodyKey(poolName, tokenMint),
+        custodyOracleAccount: await this.getCustodyOracleAccountKey(
+          poolName,
+          tokenMint
+        ),
+        systemProgram: SystemProgram.programId,
+      }),
+      AdminInstruction.SettleCustody
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  setBorrowRate = async (
+    poolName: string,
+    tokenMint: PublicKey,
+    borrowRate: BorrowRateParams
+  ): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods
+        .setBorrowRate({
+          borrowRate,
+        })
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          configBounds: this.configBounds.publicKey,
+          pool: this.getPoolKey(poolName),
+          custody: this.getCustodyKey(poolName, tokenMint),
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.SetBorrowRate
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  setCustomOraclePrice = async (
+    poolName: string,
+    tokenMint: PublicKey,
+    priceConfig: SetCustomOraclePriceParams
+  ): Promise<void> => {
+    await this.signAdminInstruction(
+      this.program.methods
+        .setCustomOraclePrice(priceConfig)
+        .accounts({
+          admin: this.admin.publicKey,
+          multisig: this.multisig.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.getPoolKey(poolName),
+          custody: this.getCustodyKey(poolName, tokenMint),
+          oracleAccount: this.getCustodyCustomOracleAccountKey(
+            poolName,
+            tokenMint
+          ),
+          systemProgram: SystemProgram.programId,
+        }),
+      AdminInstruction.SetCustomOraclePrice
+    ).catch((err) => {
+      console.error(err);
+      throw err;
+    });
+  };
+
+  addLiquidity = async (
+    poolName: string,
+    tokenMint: PublicKey,
+    amountIn: BN,
+    minLpAmountOut: BN,
+    junior: boolean
+  ): Promise<void> => {
+    // junior LP tokens are minted to the user's junior token account
+    const juniorLpTokenMint = junior
+      ? this.getPoolJuniorLpTokenKey(poolName)
+      : null;
+    const lpTokenMint = this.getPoolLpTokenKey(poolName);
+    // while the pool has LP cooldown, minted tokens are held in escrow
+    const pool = await this.getPool(poolName);
+    const locked = pool.lpCooldownSec.gtn(0);
+
+    await this.program.methods
+      .addLiquidity({ amountIn, minLpAmountOut })
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        fundingAccount: await getAssociatedTokenAddress(
+          tokenMint,
+          this.provider.wallet.publicKey
+        ),
+        lpTokenAccount: await getAssociatedTokenAddress(
+          juniorLpTokenMint ?? lpTokenMint,
           this.provider.wallet.publicKey
         ),
         transferAuthority: this.authority.publicKey,
@@ -612,6 +1161,18 @@ export class PerpetualsClient {
           tokenMint
         ),
         lpTokenMint,
+        juniorLpTokenMint,
+        userDeposit: this.getUserDepositKey(
+          poolName,
+          this.provider.wallet.publicKey
+        ),
+        lockedLpAccount:
+          locked && !junior
+            ? this.getLockedLpAccountKey(poolName, false)
+            : null,
+        lockedJuniorLpAccount:
+          locked && junior ? this.getLockedLpAccountKey(poolName, true) : null,
+        systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .remainingAccounts(await this.getCustodyMetas(poolName))
@@ -622,6 +1183,509 @@ export class PerpetualsClient {
       });
   };
 
+  addLiquidityMulti = async (
+    poolName: string,
+    tokenMints: PublicKey[],
+    amountsIn: BN[],
+    minLpAmountOut: BN
+  ): Promise<void> => {
+    const lpTokenMint = this.getPoolLpTokenKey(poolName);
+    const custodies = tokenMints.map((tokenMint) =>
+      this.getCustodyKey(poolName, tokenMint)
+    );
+    const pool = await this.getPool(poolName);
+
+    const remainingAccounts: AccountMeta[] = [];
+    for (const [i, tokenMint] of tokenMints.entries()) {
+      remainingAccounts.push(
+        {
+          isSigner: false,
+          isWritable: true,
+          pubkey: custodies[i],
+        },
+        {
+          isSigner: false,
+          isWritable: false,
+          pubkey: await this.getCustodyOracleAccountKey(poolName, tokenMint),
+        },
+        {
+          isSigner: false,
+          isWritable: true,
+          pubkey: await getAssociatedTokenAddress(
+            tokenMint,
+            this.provider.wallet.publicKey
+          ),
+        },
+        {
+          isSigner: false,
+          isWritable: true,
+          pubkey: this.getCustodyTokenAccountKey(poolName, tokenMint),
+        }
+      );
+    }
+
+    await this.program.methods
+      .addLiquidityMulti({
+        deposits: custodies.map((custody, i) => ({
+          custody,
+          amountIn: amountsIn[i],
+        })),
+        minLpAmountOut,
+      })
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        lpTokenAccount: await getAssociatedTokenAddress(
+          lpTokenMint,
+          this.provider.wallet.publicKey
+        ),
+        transferAuthority: this.authority.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        lpTokenMint,
+        userDeposit: this.getUserDepositKey(
+          poolName,
+          this.provider.wallet.publicKey
+        ),
+        lockedLpAccount: pool.lpCooldownSec.gtn(0)
+          ? this.getLockedLpAccountKey(poolName, false)
+          : null,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  stake = async (
+    poolName: string,
+    amount: BN,
+    unstake: boolean
+  ): Promise<void> => {
+    const lpTokenMint = this.getPoolLpTokenKey(poolName);
+    const custodyMetas = await this.getCustodyMetas(poolName);
+    const accounts = {
+      owner: this.provider.wallet.publicKey,
+      lpTokenAccount: await getAssociatedTokenAddress(
+        lpTokenMint,
+        this.provider.wallet.publicKey
+      ),
+      transferAuthority: this.authority.publicKey,
+      perpetuals: this.perpetuals.publicKey,
+      pool: this.getPoolKey(poolName),
+      lpTokenMint,
+      staking: this.getStakingKey(poolName),
+      stakingLpVault: this.findProgramAddress("staking_lp_vault", [
+        this.getPoolKey(poolName),
+      ]).publicKey,
+      userStake: this.getUserStakeKey(
+        poolName,
+        this.provider.wallet.publicKey
+      ),
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+
+    await (
+      unstake
+        ? this.program.methods.unstake({ amount })
+        : this.program.methods.stake({ amount })
+    )
+      .accounts(accounts)
+      .remainingAccounts(custodyMetas.slice(0, custodyMetas.length / 2))
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  createReferrer = async (poolName: string, code: string): Promise<void> => {
+    await this.program.methods
+      .createReferrer({ code })
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        pool: this.getPoolKey(poolName),
+        referrer: this.getReferrerKey(poolName, code),
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  setReferrer = async (poolName: string, code: string): Promise<void> => {
+    await this.program.methods
+      .setReferrer({})
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        pool: this.getPoolKey(poolName),
+        referrer: this.getReferrerKey(poolName, code),
+        referral: this.getReferralKey(
+          poolName,
+          this.provider.wallet.publicKey
+        ),
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  claimReferralFees = async (
+    poolName: string,
+    code: string,
+    tokenMint: PublicKey
+  ): Promise<void> => {
+    await this.program.methods
+      .claimReferralFees({})
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        receivingAccount: await getAssociatedTokenAddress(
+          tokenMint,
+          this.provider.wallet.publicKey
+        ),
+        transferAuthority: this.authority.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        referrer: this.getReferrerKey(poolName, code),
+        custody: this.getCustodyKey(poolName, tokenMint),
+        custodyOracleAccount: await this.getCustodyOracleAccountKey(
+          poolName,
+          tokenMint
+        ),
+        custodyTokenAccount: this.getCustodyTokenAccountKey(
+          poolName,
+          tokenMint
+        ),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  claimRewards = async (poolName: string): Promise<void> => {
+    const staking = await this.getStaking(poolName);
+    const rewardCustody = await this.program.account.custody.fetch(
+      staking.rewardCustody
+    );
+    const custodyMetas = await this.getCustodyMetas(poolName);
+
+    await this.program.methods
+      .claimRewards({})
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        receivingAccount: await getAssociatedTokenAddress(
+          rewardCustody.mint,
+          this.provider.wallet.publicKey
+        ),
+        transferAuthority: this.authority.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        staking: this.getStakingKey(poolName),
+        userStake: this.getUserStakeKey(
+          poolName,
+          this.provider.wallet.publicKey
+        ),
+        rewardCustody: staking.rewardCustody,
+        rewardCustodyOracleAccount: rewardCustody.oracle.oracleAccount,
+        rewardCustodyTokenAccount: rewardCustody.tokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(custodyMetas.slice(0, custodyMetas.length / 2))
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  removeLiquidityInKind = async (
+    poolName: string,
+    lpAmountIn: BN,
+    minAmountsOut: BN[]
+  ): Promise<void> => {
+    const lpTokenMint = this.getPoolLpTokenKey(poolName);
+    const custodies = await this.getCustodies(poolName);
+    const userDeposit = this.getUserDepositKey(
+      poolName,
+      this.provider.wallet.publicKey
+    );
+    const userDepositExists =
+      (await this.program.account.userDeposit.fetchNullable(userDeposit)) !=
+      null;
+
+    const remainingAccounts = await this.getCustodyMetas(poolName);
+    for (const custody of custodies) {
+      remainingAccounts.push({
+        isSigner: false,
+        isWritable: false,
+        pubkey: this.getRedemptionQueueKey(poolName, custody.mint),
+      });
+    }
+    for (const custody of custodies) {
+      if (custody.isVirtual) {
+        continue;
+      }
+      remainingAccounts.push(
+        {
+          isSigner: false,
+          isWritable: true,
+          pubkey: await getAssociatedTokenAddress(
+            custody.mint,
+            this.provider.wallet.publicKey
+          ),
+        },
+        {
+          isSigner: false,
+          isWritable: true,
+          pubkey: custody.tokenAccount,
+        }
+      );
+    }
+
+    await this.program.methods
+      .removeLiquidityInKind({ lpAmountIn, minAmountsOut })
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        lpTokenAccount: await getAssociatedTokenAddress(
+          lpTokenMint,
+          this.provider.wallet.publicKey
+        ),
+        transferAuthority: this.authority.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        lpTokenMint,
+        userDeposit: userDepositExists ? userDeposit : null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  unlockLpTokens = async (poolName: string, junior: boolean): Promise<void> => {
+    const lpTokenMint = junior
+      ? this.getPoolJuniorLpTokenKey(poolName)
+      : this.getPoolLpTokenKey(poolName);
+
+    await this.program.methods
+      .unlockLpTokens({})
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        lpTokenAccount: await getAssociatedTokenAddress(
+          lpTokenMint,
+          this.provider.wallet.publicKey
+        ),
+        transferAuthority: this.authority.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        lpTokenMint: this.getPoolLpTokenKey(poolName),
+        juniorLpTokenMint: junior ? lpTokenMint : null,
+        userDeposit: this.getUserDepositKey(
+          poolName,
+          this.provider.wallet.publicKey
+        ),
+        lockedLpAccount: junior
+          ? null
+          : this.getLockedLpAccountKey(poolName, false),
+        lockedJuniorLpAccount: junior
+          ? this.getLockedLpAccountKey(poolName, true)
+          : null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  processRedemptionQueue = async (
+    poolName: string,
+    tokenMint: PublicKey
+  ): Promise<void> => {
+    const queue = await this.getRedemptionQueue(poolName, tokenMint);
+    if (queue.requests.length == 0) {
+      throw new Error("Redemption queue is empty");
+    }
+    const owner = queue.requests[0].owner;
+    const userDeposit = this.getUserDepositKey(poolName, owner);
+    const userDepositExists =
+      (await this.program.account.userDeposit.fetchNullable(userDeposit)) !=
+      null;
+
+    await this.program.methods
+      .processRedemptionQueue({})
+      .accounts({
+        keeper: this.provider.wallet.publicKey,
+        receivingAccount: await getAssociatedTokenAddress(tokenMint, owner),
+        lpTokenAccount: await getAssociatedTokenAddress(
+          this.getPoolLpTokenKey(poolName),
+          owner
+        ),
+        transferAuthority: this.authority.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        custody: this.getCustodyKey(poolName, tokenMint),
+        custodyOracleAccount: await this.getCustodyOracleAccountKey(
+          poolName,
+          tokenMint
+        ),
+        custodyTokenAccount: this.getCustodyTokenAccountKey(
+          poolName,
+          tokenMint
+        ),
+        lpTokenMint: this.getPoolLpTokenKey(poolName),
+        redemptionQueue: this.getRedemptionQueueKey(poolName, tokenMint),
+        redemptionLpAccount: this.findProgramAddress(
+          "redemption_lp_account",
+          [this.getPoolKey(poolName)]
+        ).publicKey,
+        userDeposit: userDepositExists ? userDeposit : null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(await this.getCustodyMetas(poolName))
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  crankPool = async (poolName: string): Promise<void> => {
+    await this.program.methods
+      .crankPool()
+      .accounts({
+        keeper: this.provider.wallet.publicKey,
+        keeperRewards: this.getKeeperRewardsKey(poolName),
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(await this.getCustodyMetas(poolName))
+      .rpc()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  // keeper rewards are paid only from lamports sent to this account
+  fundKeeperRewards = async (poolName: string, lamports: BN): Promise<void> => {
+    await this.provider
+      .sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: this.provider.wallet.publicKey,
+            toPubkey: this.getKeeperRewardsKey(poolName),
+            lamports: BigInt(lamports.toString()),
+          })
+        )
+      )
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
+  getFlashBorrowInstruction = async (
+    poolName: string,
+    tokenMint: PublicKey,
+    amount: BN
+  ): Promise<TransactionInstruction> => {
+    return this.program.methods
+      .flashBorrow({ amount })
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        receivingAccount: await getAssociatedTokenAddress(
+          tokenMint,
+          this.provider.wallet.publicKey
+        ),
+        transferAuthority: this.authority.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        custody: this.getCustodyKey(poolName, tokenMint),
+        custodyTokenAccount: this.getCustodyTokenAccountKey(
+          poolName,
+          tokenMint
+        ),
+        ixSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+  };
+
+  getFlashRepayInstruction = async (
+    poolName: string,
+    tokenMint: PublicKey,
+    amount: BN
+  ): Promise<TransactionInstruction> => {
+    return this.program.methods
+      .flashRepay({ amount })
+      .accounts({
+        owner: this.provider.wallet.publicKey,
+        fundingAccount: await getAssociatedTokenAddress(
+          tokenMint,
+          this.provider.wallet.publicKey
+        ),
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        custody: this.getCustodyKey(poolName, tokenMint),
+        custodyOracleAccount: await this.getCustodyOracleAccountKey(
+          poolName,
+          tokenMint
+        ),
+        custodyTokenAccount: this.getCustodyTokenAccountKey(
+          poolName,
+          tokenMint
+        ),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+  };
+
+  // borrows the amount, runs the given instructions and repays the loan
+  // with the fee in one transaction
+  flashLoan = async (
+    poolName: string,
+    tokenMint: PublicKey,
+    amount: BN,
+    instructions: TransactionInstruction[] = []
+  ): Promise<void> => {
+    // the fee is rounded up
+    const custody = await this.getCustody(poolName, tokenMint);
+    const repayAmount = amount.add(
+      amount.mul(custody.flashLoanFee).addn(9999).divn(10000)
+    );
+
+    await this.provider
+      .sendAndConfirm(
+        new Transaction().add(
+          await this.getFlashBorrowInstruction(poolName, tokenMint, amount),
+          ...instructions,
+          await this.getFlashRepayInstruction(poolName, tokenMint, repayAmount)
+        )
+      )
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
   liquidate = async (
     wallet: PublicKey,
     poolName: string,
@@ -655,6 +1719,8 @@ export class PerpetualsClient {
           poolName,
           collateralMint
         ),
+        userStats: await this.getUserStatsKeyIfExists(wallet),
+        systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .rpc()
@@ -709,6 +1775,11 @@ export class PerpetualsClient {
           poolName,
           collateralMint
         ),
+        ...(await this.getReferralAccounts(
+          poolName,
+          this.provider.wallet.publicKey
+        )),
+        userStats: this.getUserStatsKey(this.provider.wallet.publicKey),
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
@@ -744,6 +1815,24 @@ export class PerpetualsClient {
       });
   };
 
+  getCustodyStats = async (
+    poolName: string,
+    tokenMint: PublicKey
+  ): Promise<CustodyStats> => {
+    return this.program.methods
+      .getCustodyStats({})
+      .accounts({
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.getPoolKey(poolName),
+        custody: this.getCustodyKey(poolName, tokenMint),
+      })
+      .view()
+      .catch((err) => {
+        console.error(err);
+        throw err;
+      });
+  };
+
   getAddLiquidityAmountAndFee = async (
     poolName: string,
     tokenMint: PublicKey,
@@ -3,22 +3,62 @@ import { Perpetuals } from "../../target/types/perpetuals";
 
 export type PositionSide = "long" | "short";
 
+// must match AdminInstruction in programs/perpetuals/src/state/multisig.rs
+export enum AdminInstruction {
+  AddPool,
+  RemovePool,
+  AddCustody,
+  RemoveCustody,
+  SetAdminSigners,
+  SetCustodyConfig,
+  SetPermissions,
+  SetBorrowRate,
+  WithdrawFees,
+  WithdrawSolFees,
+  SetCustomOraclePrice,
+  SetTestTime,
+  UpgradeCustody,
+  SetRoleSigners,
+  UpgradeMultisig,
+  SetConfigBounds,
+  UpdateCustodyConfig,
+  SetMarketStatus,
+  SettleCustody,
+  SetTradingSchedule,
+  SetPoolConfig,
+  UpgradePool,
+  SetStakingConfig,
+  EnableTranches,
+  SetFeeTiers,
+  UpgradePerpetuals,
+}
+
 export type Methods = MethodsNamespace<Perpetuals>;
 export type Accounts = IdlAccounts<Perpetuals>;
 export type Types = IdlTypes<Perpetuals>;
 
 export type InitParams = Types["InitParams"];
 
+// must match AdminRole in programs/perpetuals/src/state/multisig.rs
+export const ADMIN_ROLES = ["guardian", "riskManager", "treasurer", "poolAdmin"];
+
 export type OracleParams = Types["OracleParams"];
 export type PricingParams = Types["PricingParams"];
 export type Permissions = Types["Permissions"];
 export type Fees = Types["Fees"];
 export type BorrowRateParams = Types["BorrowRateParams"];
 export type TokenRatio = Types["TokenRatios"];
+export type TradingSchedule = Types["TradingSchedule"];
+export type PoolConfig = Types["PoolConfig"];
+export type StakingConfig = Types["StakingConfig"];
+export type FeeTier = Types["FeeTier"];
+export type CustodyBounds = Types["CustodyBounds"];
+export type UpdateCustodyConfigParams = Types["UpdateCustodyConfigParams"];
 export type SetCustomOraclePriceParams = Types["SetCustomOraclePriceParams"];
 export type AmountAndFee = Types["AmountAndFee"];
 export type NewPositionPricesAndFee = Types["NewPositionPricesAndFee"];
 export type PriceAndFee = Types["PriceAndFee"];
+export type CustodyStats = Types["CustodyStats"];
 export type ProfitAndLoss = Types["ProfitAndLoss"];
 export type SwapAmountAndFees = Types["SwapAmountAndFees"];
 
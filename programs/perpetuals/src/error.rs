@@ -10,6 +10,12 @@ pub enum PerpetualsError {
     MultisigAlreadySigned,
     #[msg("This instruction has already been executed")]
     MultisigAlreadyExecuted,
+    #[msg("Proposal doesn't match the instruction")]
+    MultisigProposalMismatch,
+    #[msg("Multisig signers have changed since the proposal was created")]
+    MultisigProposalStale,
+    #[msg("Pending proposal can only be closed by the proposer")]
+    MultisigProposalPending,
     #[msg("Overflow in arithmetic operation")]
     MathOverflow,
     #[msg("Unsupported price oracle")]
@@ -64,4 +70,32 @@ pub enum PerpetualsError {
     PermissionlessOracleSignerMismatch,
     #[msg("Signed message does not match instruction params")]
     PermissionlessOracleMessageMismatch,
+    #[msg("Config parameter is out of governance bounds")]
+    ConfigOutOfBounds,
+    #[msg("Instruction is not allowed in the current market status")]
+    MarketStatusRestricted,
+    #[msg("Market is closed by its trading schedule")]
+    MarketClosed,
+    #[msg("Pool AUM limit exceeded")]
+    PoolAumLimit,
+    #[msg("User LP token limit exceeded")]
+    UserLpLimit,
+    #[msg("LP tokens are locked until the cooldown period ends")]
+    LpCooldown,
+    #[msg("Redemption queue is full")]
+    RedemptionQueueFull,
+    #[msg("Pending redemptions must be processed first")]
+    RedemptionQueueNotEmpty,
+    #[msg("Cached pool AUM is stale")]
+    StaleAum,
+    #[msg("Flash loan must be repaid later in the same transaction")]
+    FlashLoanNotRepaid,
+    #[msg("Custody has an outstanding flash loan")]
+    FlashLoanOutstanding,
+    #[msg("Too many pending redemption requests of the owner")]
+    TooManyRedemptionRequests,
+    #[msg("Pool AUM was refreshed recently, crank is not due yet")]
+    CrankNotDue,
+    #[msg("Custody has enough free liquidity, withdraw directly")]
+    LiquidityAvailable,
 }
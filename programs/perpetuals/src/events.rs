@@ -0,0 +1,49 @@
+//! Program events
+
+use {
+    crate::state::{
+        config_bounds::CustodyBounds,
+        custody::{CustodyConfig, TradingSchedule},
+        pool::{PoolConfig, TokenRatios},
+        staking::StakingConfig,
+    },
+    anchor_lang::prelude::*,
+};
+
+#[event]
+pub struct CustodyConfigUpdated {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub old_config: CustodyConfig,
+    pub new_config: CustodyConfig,
+    pub old_ratios: Vec<TokenRatios>,
+    pub new_ratios: Vec<TokenRatios>,
+}
+
+#[event]
+pub struct ConfigBoundsUpdated {
+    pub old_bounds: CustodyBounds,
+    pub new_bounds: CustodyBounds,
+}
+
+#[event]
+pub struct TradingScheduleUpdated {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub old_schedule: TradingSchedule,
+    pub new_schedule: TradingSchedule,
+}
+
+#[event]
+pub struct PoolConfigUpdated {
+    pub pool: Pubkey,
+    pub old_config: PoolConfig,
+    pub new_config: PoolConfig,
+}
+
+#[event]
+pub struct StakingConfigUpdated {
+    pub pool: Pubkey,
+    pub old_config: StakingConfig,
+    pub new_config: StakingConfig,
+}
@@ -1,14 +1,29 @@
 // admin instructions
 pub mod add_custody;
 pub mod add_pool;
+pub mod close_proposal;
+pub mod enable_tranches;
 pub mod init;
 pub mod remove_custody;
 pub mod remove_pool;
 pub mod set_admin_signers;
+pub mod set_borrow_rate;
+pub mod set_config_bounds;
 pub mod set_custody_config;
 pub mod set_custom_oracle_price;
+pub mod set_fee_tiers;
+pub mod set_market_status;
 pub mod set_permissions;
+pub mod set_pool_config;
+pub mod set_role_signers;
+pub mod set_staking_config;
+pub mod set_trading_schedule;
+pub mod settle_custody;
+pub mod update_custody_config;
 pub mod upgrade_custody;
+pub mod upgrade_multisig;
+pub mod upgrade_perpetuals;
+pub mod upgrade_pool;
 pub mod withdraw_fees;
 pub mod withdraw_sol_fees;
 
@@ -18,9 +33,18 @@ pub mod set_test_time;
 // public instructions
 pub mod add_collateral;
 pub mod add_liquidity;
+pub mod add_liquidity_multi;
+pub mod cancel_redemption;
+pub mod claim_referral_fees;
+pub mod claim_rewards;
 pub mod close_position;
+pub mod crank_pool;
+pub mod create_referrer;
+pub mod flash_borrow;
+pub mod flash_repay;
 pub mod get_add_liquidity_amount_and_fee;
 pub mod get_assets_under_management;
+pub mod get_custody_stats;
 pub mod get_entry_price_and_fee;
 pub mod get_exit_price_and_fee;
 pub mod get_liquidation_price;
@@ -32,21 +56,38 @@ pub mod get_remove_liquidity_amount_and_fee;
 pub mod get_swap_amount_and_fees;
 pub mod liquidate;
 pub mod open_position;
+pub mod process_redemption_queue;
 pub mod remove_collateral;
 pub mod remove_liquidity;
+pub mod remove_liquidity_in_kind;
+pub mod request_redemption;
 pub mod set_custom_oracle_price_permissionless;
+pub mod set_referrer;
+pub mod settle_position;
+pub mod stake;
 pub mod swap;
+pub mod unlock_lp_tokens;
+pub mod unstake;
 pub mod update_pool_aum;
+pub mod upgrade_position;
 
 // bring everything in scope
 pub use {
-    add_collateral::*, add_custody::*, add_liquidity::*, add_pool::*, close_position::*,
-    get_add_liquidity_amount_and_fee::*, get_assets_under_management::*,
-    get_entry_price_and_fee::*, get_exit_price_and_fee::*, get_liquidation_price::*,
-    get_liquidation_state::*, get_lp_token_price::*, get_oracle_price::*, get_pnl::*,
-    get_remove_liquidity_amount_and_fee::*, get_swap_amount_and_fees::*, init::*, liquidate::*,
-    open_position::*, remove_collateral::*, remove_custody::*, remove_liquidity::*, remove_pool::*,
-    set_admin_signers::*, set_custody_config::*, set_custom_oracle_price::*,
-    set_custom_oracle_price_permissionless::*, set_permissions::*, set_test_time::*, swap::*,
-    update_pool_aum::*, upgrade_custody::*, withdraw_fees::*, withdraw_sol_fees::*,
+    add_collateral::*, add_custody::*, add_liquidity::*, add_liquidity_multi::*, add_pool::*,
+    cancel_redemption::*, claim_referral_fees::*, claim_rewards::*, close_position::*,
+    close_proposal::*, crank_pool::*, create_referrer::*, enable_tranches::*, flash_borrow::*,
+    flash_repay::*, get_add_liquidity_amount_and_fee::*, get_assets_under_management::*,
+    get_custody_stats::*, get_entry_price_and_fee::*, get_exit_price_and_fee::*,
+    get_liquidation_price::*, get_liquidation_state::*, get_lp_token_price::*, get_oracle_price::*,
+    get_pnl::*, get_remove_liquidity_amount_and_fee::*, get_swap_amount_and_fees::*, init::*,
+    liquidate::*, open_position::*, process_redemption_queue::*, remove_collateral::*,
+    remove_custody::*, remove_liquidity::*, remove_liquidity_in_kind::*, remove_pool::*,
+    request_redemption::*, set_admin_signers::*, set_borrow_rate::*, set_config_bounds::*,
+    set_custody_config::*, set_custom_oracle_price::*, set_custom_oracle_price_permissionless::*,
+    set_fee_tiers::*, set_market_status::*, set_permissions::*, set_pool_config::*,
+    set_referrer::*, set_role_signers::*, set_staking_config::*, set_test_time::*,
+    set_trading_schedule::*, settle_custody::*, settle_position::*, stake::*, swap::*,
+    unlock_lp_tokens::*, unstake::*, update_custody_config::*, update_pool_aum::*,
+    upgrade_custody::*, upgrade_multisig::*, upgrade_perpetuals::*, upgrade_pool::*,
+    upgrade_position::*, withdraw_fees::*, withdraw_sol_fees::*,
 };
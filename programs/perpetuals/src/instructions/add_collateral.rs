@@ -25,7 +25,7 @@ pub struct AddCollateral<'info> {
 
     #[account(
         mut,
-        constraint = funding_account.mint == custody.mint,
+        constraint = funding_account.mint == custody.load()?.mint,
         has_one = owner
     )]
     pub funding_account: Box<Account<'info, TokenAccount>>,
@@ -46,10 +46,10 @@ pub struct AddCollateral<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
@@ -67,11 +67,11 @@ pub struct AddCollateral<'info> {
         mut,
         constraint = position.custody == custody.key()
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -79,11 +79,11 @@ pub struct AddCollateral<'info> {
         mut,
         constraint = position.collateral_custody == collateral_custody.key()
     )]
-    pub collateral_custody: Box<Account<'info, Custody>>,
+    pub collateral_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -91,8 +91,8 @@ pub struct AddCollateral<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 collateral_custody.mint.as_ref()],
-        bump = collateral_custody.token_account_bump
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.token_account_bump
     )]
     pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
 
@@ -111,24 +111,35 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
         return Err(ProgramError::InvalidArgument.into());
     }
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let custody = ctx.accounts.custody.as_mut();
-    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    // custody and collateral_custody can be the same account, work on copies
+    // and write them back when the position is updated
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    let mut collateral_custody = Box::new(*ctx.accounts.collateral_custody.load()?);
     let position = ctx.accounts.position.as_mut();
-    let pool = ctx.accounts.pool.as_mut();
+
+    // check market status
+    msg!("Check market status");
+    require!(
+        pool.status.allows_reduce()
+            && custody.status.allows_reduce()
+            && collateral_custody.status.allows_reduce(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // compute position price
     let curtime = perpetuals.get_time()?;
 
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
@@ -137,7 +148,7 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         false,
     )?;
@@ -146,7 +157,7 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         collateral_custody.pricing.use_ema,
     )?;
@@ -173,10 +184,10 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
             position,
             &token_price,
             &token_ema_price,
-            custody,
+            &custody,
             &collateral_token_price,
             &collateral_token_ema_price,
-            collateral_custody,
+            &collateral_custody,
             curtime,
             true
         )?,
@@ -202,8 +213,31 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
 
     // if custody and collateral_custody accounts are the same, ensure that data is in sync
     if position.side == Side::Long && !custody.is_virtual {
-        *custody = collateral_custody.clone();
+        custody = collateral_custody.clone();
     }
 
+    // update pool stats
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    if ctx.accounts.custody.key() == ctx.accounts.collateral_custody.key() {
+        collateral_custody = custody.clone();
+    } else {
+        pool.update_custody_aum(
+            &ctx.accounts.collateral_custody.key(),
+            &mut collateral_custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            curtime,
+        )?;
+    }
+
+    *ctx.accounts.custody.load_mut()? = *custody;
+    *ctx.accounts.collateral_custody.load_mut()? = *collateral_custody;
+
     Ok(())
 }
@@ -5,7 +5,7 @@ use {
         error::PerpetualsError,
         state::{
             custody::{BorrowRateParams, Custody, Fees, PricingParams},
-            multisig::{AdminInstruction, Multisig},
+            multisig::{AdminInstruction, AdminRole, Multisig},
             oracle::OracleParams,
             perpetuals::{Permissions, Perpetuals},
             pool::{Pool, TokenRatios},
@@ -21,12 +21,18 @@ pub struct AddCustody<'info> {
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"multisig"],
-        bump = multisig.load()?.bump
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::PoolAdmin,
+            &pool.key()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     /// CHECK: empty PDA, authority for token accounts
     #[account(
         seeds = [b"transfer_authority"],
@@ -42,15 +48,11 @@ pub struct AddCustody<'info> {
 
     #[account(
         mut,
-        realloc = Pool::LEN + (pool.custodies.len() + 1) * std::mem::size_of::<Pubkey>() +
-                              (pool.ratios.len() + 1) * std::mem::size_of::<TokenRatios>(),
-        realloc::payer = admin,
-        realloc::zero = false,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         init_if_needed,
@@ -61,7 +63,7 @@ pub struct AddCustody<'info> {
                  custody_token_mint.key().as_ref()],
         bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     #[account(
         init_if_needed,
@@ -100,15 +102,17 @@ pub fn add_custody<'info>(
     params: &AddCustodyParams,
 ) -> Result<u8> {
     // validate inputs
-    if params.ratios.len() != ctx.accounts.pool.ratios.len() + 1 {
+    if params.ratios.len() != ctx.accounts.pool.load()?.get_custodies().len() + 1 {
         return Err(ProgramError::InvalidArgument.into());
     }
 
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::AddCustody, params)?,
     )?;
@@ -120,22 +124,30 @@ pub fn add_custody<'info>(
         return Ok(signatures_left);
     }
 
-    let pool = ctx.accounts.pool.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
     if pool.get_token_id(&ctx.accounts.custody.key()).is_ok() {
         // return error if custody is already initialized
         return Err(ProgramError::AccountAlreadyInitialized.into());
     }
 
     // update pool data
-    pool.custodies.push(ctx.accounts.custody.key());
-    pool.ratios = params.ratios.clone();
+    let mut custodies = pool.get_custodies().to_vec();
+    custodies.push(ctx.accounts.custody.key());
+    pool.set_custodies(&custodies, &params.ratios)?;
     if !pool.validate() {
         return err!(PerpetualsError::InvalidPoolConfig);
     }
-
-    // record custody data
-    let custody = ctx.accounts.custody.as_mut();
-    custody.pool = pool.key();
+    // new custody holds no assets, so its zero AUM value is up to date
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    pool.set_aum_update_time(custodies.len() - 1, curtime);
+
+    // record custody data, the discriminator is already set if the account
+    // was created by an earlier call that required more signatures
+    let mut custody = match ctx.accounts.custody.load_init() {
+        Ok(custody) => custody,
+        Err(_) => ctx.accounts.custody.load_mut()?,
+    };
+    custody.pool = ctx.accounts.pool.key();
     custody.mint = ctx.accounts.custody_token_mint.key();
     custody.token_account = ctx.accounts.custody_token_account.key();
     custody.decimals = ctx.accounts.custody_token_mint.decimals;
@@ -147,12 +159,13 @@ pub fn add_custody<'info>(
     custody.fees = params.fees;
     custody.borrow_rate = params.borrow_rate;
     custody.borrow_rate_state.current_rate = params.borrow_rate.base_rate;
-    custody.borrow_rate_state.last_update = ctx.accounts.perpetuals.get_time()?;
+    custody.borrow_rate_state.last_update = curtime;
     custody.bump = *ctx.bumps.get("custody").ok_or(ProgramError::InvalidSeeds)?;
     custody.token_account_bump = *ctx
         .bumps
         .get("custody_token_account")
         .ok_or(ProgramError::InvalidSeeds)?;
+    custody.version = Custody::VERSION;
 
     if !custody.validate() {
         err!(PerpetualsError::InvalidCustodyConfig)
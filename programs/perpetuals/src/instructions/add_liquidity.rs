@@ -6,9 +6,11 @@ use {
         math,
         state::{
             custody::Custody,
+            migration::Versioned,
             oracle::OraclePrice,
             perpetuals::Perpetuals,
             pool::{AumCalcMode, Pool},
+            user_deposit::UserDeposit,
         },
     },
     anchor_lang::prelude::*,
@@ -24,14 +26,16 @@ pub struct AddLiquidity<'info> {
 
     #[account(
         mut,
-        constraint = funding_account.mint == custody.mint,
+        constraint = funding_account.mint == custody.load()?.mint,
         has_one = owner
     )]
     pub funding_account: Box<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = lp_token_account.mint == lp_token_mint.key(),
+        constraint = lp_token_account.mint == junior_lp_token_mint
+            .as_ref()
+            .map_or(lp_token_mint.key(), |x| x.key()),
         has_one = owner
     )]
     pub lp_token_account: Box<Account<'info, TokenAccount>>,
@@ -52,23 +56,23 @@ pub struct AddLiquidity<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the receiving token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -76,8 +80,8 @@ pub struct AddLiquidity<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.token_account_bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.token_account_bump
     )]
     pub custody_token_account: Box<Account<'info, TokenAccount>>,
 
@@ -85,14 +89,59 @@ pub struct AddLiquidity<'info> {
         mut,
         seeds = [b"lp_token_mint",
                  pool.key().as_ref()],
-        bump = pool.lp_token_bump
+        bump = pool.load()?.lp_token_bump
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
 
+    // if provided, junior LP tokens are minted
+    #[account(
+        mut,
+        seeds = [b"junior_lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.junior_lp_token_bump
+    )]
+    pub junior_lp_token_mint: Option<Box<Account<'info, Mint>>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = UserDeposit::LEN,
+        seeds = [b"user_deposit",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub user_deposit: Box<Account<'info, UserDeposit>>,
+
+    // required if the pool has LP cooldown, hold minted LP tokens
+    // until they are released with unlock_lp_tokens
+    #[account(
+        init_if_needed,
+        payer = owner,
+        token::mint = lp_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"locked_lp_account",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub locked_lp_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        token::mint = junior_lp_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"locked_junior_lp_account",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub locked_junior_lp_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
-    // remaining accounts:
-    //   pool.tokens.len() custody accounts (read-only, unsigned)
-    //   pool.tokens.len() custody oracles (read-only, unsigned)
+    // optional remaining accounts, AUM values of these custodies are refreshed:
+    //   custody accounts (write, unsigned)
+    //   custody oracles (read-only, unsigned)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -105,43 +154,72 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
     // check permissions
     msg!("Check permissions");
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let custody = ctx.accounts.custody.as_mut();
+    let curtime = perpetuals.get_time()?;
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.update_aum_from_accounts(ctx.remaining_accounts, curtime)?;
+    // custody can be passed in remaining accounts too, work on
+    // a copy loaded after the refresh and write it back at the end
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
     require!(
         perpetuals.permissions.allow_add_liquidity
+            && pool.permissions.allow_add_liquidity
             && custody.permissions.allow_add_liquidity
             && !custody.is_virtual,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        pool.status.allows_increase() && custody.status.allows_increase(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // validate inputs
     msg!("Validate inputs");
     if params.amount_in == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
-    let pool = ctx.accounts.pool.as_mut();
-    let token_id = pool.get_token_id(&custody.key())?;
+    let is_junior = ctx.accounts.junior_lp_token_mint.is_some();
+    if is_junior && !pool.tranches_enabled {
+        return Err(ProgramError::InvalidArgument.into());
+    }
 
-    // calculate fee
-    let curtime = perpetuals.get_time()?;
+    // record user deposit
+    let user_deposit = ctx.accounts.user_deposit.as_mut();
+    if user_deposit.owner == Pubkey::default() {
+        user_deposit.owner = ctx.accounts.owner.key();
+        user_deposit.pool = ctx.accounts.pool.key();
+        user_deposit.bump = *ctx
+            .bumps
+            .get("user_deposit")
+            .ok_or(ProgramError::InvalidSeeds)?;
+        user_deposit.version = UserDeposit::VERSION;
+    }
 
-    // Refresh pool.aum_usm to adapt to token price change
-    pool.aum_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+    let token_id = pool.get_token_id(&ctx.accounts.custody.key())?;
 
+    // calculate fee
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
 
+    // Refresh pool.aum_usd to adapt to token price change
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+
     let min_price = if token_price < token_ema_price {
         token_price
     } else {
@@ -149,7 +227,7 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
     };
 
     let fee_amount =
-        pool.get_add_liquidity_fee(token_id, params.amount_in, custody, &token_ema_price)?;
+        pool.get_add_liquidity_fee(token_id, params.amount_in, &custody, &token_ema_price)?;
     msg!("Collected fee: {}", fee_amount);
 
     // check pool constraints
@@ -157,7 +235,7 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
     let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
     let deposit_amount = math::checked_sub(params.amount_in, protocol_fee)?;
     require!(
-        pool.check_token_ratio(token_id, deposit_amount, 0, custody, &token_ema_price)?,
+        pool.check_token_ratio(token_id, deposit_amount, 0, &custody, &token_ema_price)?,
         PerpetualsError::TokenRatioOutOfRange
     );
 
@@ -173,8 +251,19 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
 
     // compute assets under management
     msg!("Compute assets under management");
-    let pool_amount_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::Max, ctx.remaining_accounts, curtime)?;
+    // tranches are tracked at the EMA value, shares of the pool value
+    // in other AUM modes are split pro rata
+    let aum_usd = pool.get_cached_aum_usd(AumCalcMode::EMA, curtime)?;
+    pool.update_tranches(aum_usd)?;
+    let pool_aum_usd = pool.get_cached_aum_usd(pool.add_liquidity_aum_mode, curtime)?;
+    let (senior_amount_usd, junior_amount_usd) = pool.get_tranche_shares_usd(pool_aum_usd)?;
+
+    let (lp_token_mint, pool_amount_usd) =
+        if let Some(junior_lp_token_mint) = ctx.accounts.junior_lp_token_mint.as_ref() {
+            (junior_lp_token_mint, junior_amount_usd)
+        } else {
+            (&ctx.accounts.lp_token_mint, senior_amount_usd)
+        };
 
     // compute amount of lp tokens to mint
     let no_fee_amount = math::checked_sub(params.amount_in, fee_amount)?;
@@ -190,10 +279,7 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
         token_amount_usd
     } else {
         math::checked_as_u64(math::checked_div(
-            math::checked_mul(
-                token_amount_usd as u128,
-                ctx.accounts.lp_token_mint.supply as u128,
-            )?,
+            math::checked_mul(token_amount_usd as u128, lp_token_mint.supply as u128)?,
             pool_amount_usd,
         )?)?
     };
@@ -204,10 +290,29 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
         PerpetualsError::MaxPriceSlippage
     );
 
-    // mint lp tokens
+    // check user lp limit
+    require!(
+        user_deposit.add_lp_amount(lp_amount, pool.max_user_lp_amount)?,
+        PerpetualsError::UserLpLimit
+    );
+    user_deposit.last_deposit_time = curtime;
+
+    // mint lp tokens, held in escrow while the cooldown lasts
+    let receiving_account = if pool.lp_cooldown_sec > 0 {
+        user_deposit.lock_lp_amount(lp_amount, is_junior)?;
+        if is_junior {
+            ctx.accounts.locked_junior_lp_account.as_ref()
+        } else {
+            ctx.accounts.locked_lp_account.as_ref()
+        }
+        .ok_or(ProgramError::NotEnoughAccountKeys)?
+        .to_account_info()
+    } else {
+        ctx.accounts.lp_token_account.to_account_info()
+    };
     perpetuals.mint_tokens(
-        ctx.accounts.lp_token_mint.to_account_info(),
-        ctx.accounts.lp_token_account.to_account_info(),
+        lp_token_mint.to_account_info(),
+        receiving_account,
         ctx.accounts.transfer_authority.to_account_info(),
         ctx.accounts.token_program.to_account_info(),
         lp_amount,
@@ -215,27 +320,49 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
 
     // update custody stats
     msg!("Update custody stats");
+    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
     custody.collected_fees.add_liquidity_usd = custody
         .collected_fees
         .add_liquidity_usd
-        .wrapping_add(token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?);
+        .saturating_add(fee_amount_usd as u128);
 
-    custody.volume_stats.add_liquidity_usd = custody
-        .volume_stats
-        .add_liquidity_usd
-        .wrapping_add(token_ema_price.get_asset_amount_usd(params.amount_in, custody.decimals)?);
+    custody.volume_stats.add_liquidity_usd = custody.volume_stats.add_liquidity_usd.saturating_add(
+        token_ema_price.get_asset_amount_usd(params.amount_in, custody.decimals)? as u128,
+    );
 
     custody.assets.protocol_fees = math::checked_add(custody.assets.protocol_fees, protocol_fee)?;
 
     custody.assets.owned = math::checked_add(custody.assets.owned, deposit_amount)?;
 
+    custody.update_stats_history(curtime, 0, fee_amount_usd);
     custody.update_borrow_rate(curtime)?;
 
     // update pool stats
     msg!("Update pool stats");
-    custody.exit(&crate::ID)?;
-    pool.aum_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    *ctx.accounts.custody.load_mut()? = *custody;
+
+    if pool.tranches_enabled {
+        if !is_junior {
+            pool.senior_aum_usd = math::checked_add(
+                pool.senior_aum_usd,
+                pool.get_tranche_value_usd(token_amount_usd as u128, pool_aum_usd)?,
+            )?;
+        }
+        pool.tranche_aum_usd = pool.aum_usd;
+    }
+
+    // check pool aum limit
+    require!(
+        pool.max_aum_usd == 0 || pool.aum_usd <= pool.max_aum_usd,
+        PerpetualsError::PoolAumLimit
+    );
 
     Ok(())
 }
@@ -0,0 +1,363 @@
+//! AddLiquidityMulti instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody, migration::Versioned, perpetuals::Perpetuals, pool::Pool,
+            user_deposit::UserDeposit,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: AddLiquidityMultiParams)]
+pub struct AddLiquidityMulti<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = lp_token_account.mint == lp_token_mint.key(),
+        has_one = owner
+    )]
+    pub lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = UserDeposit::LEN,
+        seeds = [b"user_deposit",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub user_deposit: Box<Account<'info, UserDeposit>>,
+
+    // required if the pool has LP cooldown, holds minted LP tokens
+    // until they are released with unlock_lp_tokens
+    #[account(
+        init_if_needed,
+        payer = owner,
+        token::mint = lp_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"locked_lp_account",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub locked_lp_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    // remaining accounts, for each deposit:
+    //   custody account (writable, unsigned)
+    //   custody oracle (read-only, unsigned)
+    //   funding account (writable, unsigned)
+    //   custody token account (writable, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LiquidityDeposit {
+    pub custody: Pubkey,
+    pub amount_in: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddLiquidityMultiParams {
+    pub deposits: Vec<LiquidityDeposit>,
+    pub min_lp_amount_out: u64,
+}
+
+pub fn add_liquidity_multi<'info>(
+    ctx: Context<'_, '_, '_, 'info, AddLiquidityMulti<'info>>,
+    params: &AddLiquidityMultiParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    require!(
+        perpetuals.permissions.allow_add_liquidity
+            && pool.permissions.allow_add_liquidity
+            && !pool.tranches_enabled,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        pool.status.allows_increase(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // validate inputs
+    msg!("Validate inputs");
+    let pool_tokens = pool.get_custodies().len();
+    if params.deposits.is_empty() || params.deposits.len() > pool_tokens {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    if ctx.remaining_accounts.len() < params.deposits.len() * 4 {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+
+    // record user deposit
+    let user_deposit = ctx.accounts.user_deposit.as_mut();
+    if user_deposit.owner == Pubkey::default() {
+        user_deposit.owner = ctx.accounts.owner.key();
+        user_deposit.pool = ctx.accounts.pool.key();
+        user_deposit.bump = *ctx
+            .bumps
+            .get("user_deposit")
+            .ok_or(ProgramError::InvalidSeeds)?;
+        user_deposit.version = UserDeposit::VERSION;
+    }
+
+    let curtime = perpetuals.get_time()?;
+
+    // load deposited custodies, custodies are updated on copies
+    // and written back at the end
+    let mut token_ids = Vec::with_capacity(params.deposits.len());
+    let mut custody_accounts = Vec::with_capacity(params.deposits.len());
+    let mut custodies = Vec::with_capacity(params.deposits.len());
+    let mut token_prices = Vec::with_capacity(params.deposits.len());
+    let mut token_ema_prices = Vec::with_capacity(params.deposits.len());
+    for deposit in params.deposits.iter() {
+        if deposit.amount_in == 0 {
+            return Err(ProgramError::InvalidArgument.into());
+        }
+        let token_id = pool.get_token_id(&deposit.custody)?;
+        if token_ids.contains(&token_id) {
+            return Err(ProgramError::InvalidArgument.into());
+        }
+
+        let custody_info = &ctx.remaining_accounts[token_ids.len() * 4];
+        require_keys_eq!(custody_info.key(), deposit.custody);
+        require!(custody_info.is_writable, ErrorCode::ConstraintMut);
+        let custody_account = AccountLoader::<Custody>::try_from(custody_info)?;
+        let mut custody = *custody_account.load()?;
+        require!(
+            custody.permissions.allow_add_liquidity && !custody.is_virtual,
+            PerpetualsError::InstructionNotAllowed
+        );
+        require!(
+            custody.status.allows_increase(),
+            PerpetualsError::MarketStatusRestricted
+        );
+
+        let oracle_account = &ctx.remaining_accounts[token_ids.len() * 4 + 1];
+        require_keys_eq!(oracle_account.key(), custody.oracle.oracle_account);
+        let token_price = custody.get_oracle_price(oracle_account, curtime, false)?;
+        let token_ema_price =
+            custody.get_oracle_price(oracle_account, curtime, custody.pricing.use_ema)?;
+
+        // Refresh pool.aum_usd to adapt to token price change
+        pool.update_custody_aum(
+            &deposit.custody,
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            curtime,
+        )?;
+        token_prices.push(token_price);
+        token_ema_prices.push(token_ema_price);
+
+        token_ids.push(token_id);
+        custody_accounts.push(custody_account);
+        custodies.push(custody);
+    }
+
+    // compute assets under management before the deposits
+    msg!("Compute assets under management");
+    let pool_amount_usd = pool.get_cached_aum_usd(pool.add_liquidity_aum_mode, curtime)?;
+
+    let mut deposits_usd = Vec::with_capacity(params.deposits.len());
+    for (i, deposit) in params.deposits.iter().enumerate() {
+        deposits_usd.push(
+            token_ema_prices[i].get_asset_amount_usd(deposit.amount_in, custodies[i].decimals)?
+                as u128,
+        );
+    }
+    let total_deposit_usd = deposits_usd
+        .iter()
+        .try_fold(0u128, |acc, &x| math::checked_add(acc, x))?;
+
+    let aum_usd = pool.aum_usd;
+    let mut total_amount_usd: u64 = 0;
+    for (i, deposit) in params.deposits.iter().enumerate() {
+        let custody = &mut custodies[i];
+        let token_ema_price = &token_ema_prices[i];
+
+        // fees and ratios are computed against the pool with all other deposits applied
+        pool.aum_usd = math::checked_add(
+            aum_usd,
+            math::checked_sub(total_deposit_usd, deposits_usd[i])?,
+        )?;
+
+        let fee_amount =
+            pool.get_add_liquidity_fee(token_ids[i], deposit.amount_in, custody, token_ema_price)?;
+        msg!("Collected fee: {}", fee_amount);
+
+        // check pool constraints
+        msg!("Check pool constraints");
+        let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+        let deposit_amount = math::checked_sub(deposit.amount_in, protocol_fee)?;
+        require!(
+            pool.check_token_ratio(token_ids[i], deposit_amount, 0, custody, token_ema_price)?,
+            PerpetualsError::TokenRatioOutOfRange
+        );
+
+        // transfer tokens
+        msg!("Transfer tokens");
+        let funding_account_info = &ctx.remaining_accounts[i * 4 + 2];
+        let funding_account = Account::<TokenAccount>::try_from(funding_account_info)?;
+        require_keys_eq!(funding_account.mint, custody.mint);
+        require_keys_eq!(funding_account.owner, ctx.accounts.owner.key());
+
+        let custody_token_account = &ctx.remaining_accounts[i * 4 + 3];
+        require_keys_eq!(custody_token_account.key(), custody.token_account);
+
+        perpetuals.transfer_tokens_from_user(
+            funding_account_info.clone(),
+            custody_token_account.clone(),
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            deposit.amount_in,
+        )?;
+
+        // compute value of the deposit
+        let no_fee_amount = math::checked_sub(deposit.amount_in, fee_amount)?;
+        require_gte!(
+            no_fee_amount,
+            1u64,
+            PerpetualsError::InsufficientAmountReturned
+        );
+
+        let min_price = if token_prices[i] < *token_ema_price {
+            token_prices[i]
+        } else {
+            *token_ema_price
+        };
+        total_amount_usd = math::checked_add(
+            total_amount_usd,
+            min_price.get_asset_amount_usd(no_fee_amount, custody.decimals)?,
+        )?;
+
+        // update custody stats
+        msg!("Update custody stats");
+        let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+        custody.collected_fees.add_liquidity_usd = custody
+            .collected_fees
+            .add_liquidity_usd
+            .saturating_add(fee_amount_usd as u128);
+
+        custody.volume_stats.add_liquidity_usd = custody
+            .volume_stats
+            .add_liquidity_usd
+            .saturating_add(deposits_usd[i]);
+
+        custody.assets.protocol_fees =
+            math::checked_add(custody.assets.protocol_fees, protocol_fee)?;
+
+        custody.assets.owned = math::checked_add(custody.assets.owned, deposit_amount)?;
+
+        custody.update_stats_history(curtime, 0, fee_amount_usd);
+        custody.update_borrow_rate(curtime)?;
+    }
+    pool.aum_usd = aum_usd;
+
+    // compute amount of lp tokens to mint
+    let lp_amount = if pool_amount_usd == 0 {
+        total_amount_usd
+    } else {
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(
+                total_amount_usd as u128,
+                ctx.accounts.lp_token_mint.supply as u128,
+            )?,
+            pool_amount_usd,
+        )?)?
+    };
+    msg!("LP tokens to mint: {}", lp_amount);
+
+    require!(
+        lp_amount >= params.min_lp_amount_out,
+        PerpetualsError::MaxPriceSlippage
+    );
+
+    // check user lp limit
+    require!(
+        user_deposit.add_lp_amount(lp_amount, pool.max_user_lp_amount)?,
+        PerpetualsError::UserLpLimit
+    );
+    user_deposit.last_deposit_time = curtime;
+
+    // mint lp tokens, held in escrow while the cooldown lasts
+    let receiving_account = if pool.lp_cooldown_sec > 0 {
+        user_deposit.lock_lp_amount(lp_amount, false)?;
+        ctx.accounts
+            .locked_lp_account
+            .as_ref()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?
+            .to_account_info()
+    } else {
+        ctx.accounts.lp_token_account.to_account_info()
+    };
+    perpetuals.mint_tokens(
+        ctx.accounts.lp_token_mint.to_account_info(),
+        receiving_account,
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        lp_amount,
+    )?;
+
+    // update pool stats
+    msg!("Update pool stats");
+    for (i, deposit) in params.deposits.iter().enumerate() {
+        pool.update_custody_aum(
+            &deposit.custody,
+            &mut custodies[i],
+            &token_prices[i],
+            &token_ema_prices[i],
+            curtime,
+        )?;
+        *custody_accounts[i].load_mut()? = custodies[i];
+    }
+
+    // check pool aum limit
+    require!(
+        pool.max_aum_usd == 0 || pool.aum_usd <= pool.max_aum_usd,
+        PerpetualsError::PoolAumLimit
+    );
+
+    Ok(())
+}
@@ -5,7 +5,7 @@ use {
         error::PerpetualsError,
         state::{
             multisig::{AdminInstruction, Multisig},
-            perpetuals::Perpetuals,
+            perpetuals::{FeeTier, Perpetuals},
             pool::Pool,
         },
     },
@@ -20,12 +20,15 @@ pub struct AddPool<'info> {
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
         seeds = [b"multisig"],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     /// CHECK: empty PDA, authority for token accounts
     #[account(
         seeds = [b"transfer_authority"],
@@ -35,7 +38,8 @@ pub struct AddPool<'info> {
 
     #[account(
         mut,
-        realloc = Perpetuals::LEN + (perpetuals.pools.len() + 1) * std::mem::size_of::<Pubkey>(),
+        realloc = Perpetuals::LEN + (perpetuals.pools.len() + 1) * std::mem::size_of::<Pubkey>() +
+                                    perpetuals.fee_tiers.len() * std::mem::size_of::<FeeTier>(),
         realloc::payer = admin,
         realloc::zero = false,
         seeds = [b"perpetuals"],
@@ -56,7 +60,7 @@ pub struct AddPool<'info> {
                  params.name.as_bytes()],
         bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         init_if_needed,
@@ -85,15 +89,17 @@ pub fn add_pool<'info>(
     params: &AddPoolParams,
 ) -> Result<u8> {
     // validate inputs
-    if params.name.is_empty() || params.name.len() > 64 {
+    if params.name.is_empty() || params.name.len() > Pool::MAX_NAME_LEN {
         return Err(ProgramError::InvalidArgument.into());
     }
 
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::AddPool, params)?,
     )?;
@@ -107,7 +113,10 @@ pub fn add_pool<'info>(
 
     // record pool data
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let pool = ctx.accounts.pool.as_mut();
+    let mut pool = match ctx.accounts.pool.load_init() {
+        Ok(pool) => pool,
+        Err(_) => ctx.accounts.pool.load_mut()?,
+    };
 
     if pool.inception_time != 0 {
         // return error if pool is already initialized
@@ -115,12 +124,14 @@ pub fn add_pool<'info>(
     }
     msg!("Record pool: {}", params.name);
     pool.inception_time = perpetuals.get_time()?;
-    pool.name = params.name.clone();
+    pool.set_name(&params.name)?;
     pool.bump = *ctx.bumps.get("pool").ok_or(ProgramError::InvalidSeeds)?;
     pool.lp_token_bump = *ctx
         .bumps
         .get("lp_token_mint")
         .ok_or(ProgramError::InvalidSeeds)?;
+    pool.set_config(&Pool::get_default_config(&params.name))?;
+    pool.version = Pool::VERSION;
 
     if !pool.validate() {
         return err!(PerpetualsError::InvalidPoolConfig);
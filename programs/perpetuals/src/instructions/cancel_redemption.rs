@@ -0,0 +1,106 @@
+//! CancelRedemption instruction handler
+
+use {
+    crate::state::{
+        custody::Custody, perpetuals::Perpetuals, pool::Pool, redemption_queue::RedemptionQueue,
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct CancelRedemption<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = lp_token_account.mint == lp_token_mint.key(),
+        has_one = owner
+    )]
+    pub lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    #[account(
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"redemption_queue",
+                 pool.key().as_ref(),
+                 custody.key().as_ref()],
+        bump = redemption_queue.bump
+    )]
+    pub redemption_queue: Box<Account<'info, RedemptionQueue>>,
+
+    #[account(
+        mut,
+        seeds = [b"redemption_lp_account",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub redemption_lp_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CancelRedemptionParams {}
+
+pub fn cancel_redemption(
+    ctx: Context<CancelRedemption>,
+    _params: &CancelRedemptionParams,
+) -> Result<()> {
+    // remove all requests of the owner
+    let lp_amount = ctx
+        .accounts
+        .redemption_queue
+        .remove_owner_requests(&ctx.accounts.owner.key());
+    if lp_amount == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // return lp tokens
+    msg!("Return LP tokens: {}", lp_amount);
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.redemption_lp_account.to_account_info(),
+        ctx.accounts.lp_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        lp_amount,
+    )?;
+
+    Ok(())
+}
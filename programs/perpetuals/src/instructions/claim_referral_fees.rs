@@ -0,0 +1,170 @@
+//! ClaimReferralFees instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{custody::Custody, perpetuals::Perpetuals, pool::Pool, referral::Referrer},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct ClaimReferralFees<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == custody.load()?.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer",
+                 pool.key().as_ref(),
+                 referrer.code.as_bytes()],
+        bump = referrer.bump,
+        has_one = owner
+    )]
+    pub referrer: Box<Account<'info, Referrer>>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    /// CHECK: oracle account for the paid token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ClaimReferralFeesParams {}
+
+pub fn claim_referral_fees(
+    ctx: Context<ClaimReferralFees>,
+    _params: &ClaimReferralFeesParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let mut custody = ctx.accounts.custody.load_mut()?;
+    require!(!custody.is_virtual, PerpetualsError::InstructionNotAllowed);
+    require!(
+        custody.status.allows_withdrawal(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // compute amount of tokens to pay
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let referrer = ctx.accounts.referrer.as_mut();
+
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+    )?;
+
+    let max_price = if token_price > token_ema_price {
+        token_price
+    } else {
+        token_ema_price
+    };
+
+    // fees are paid from what the custody has reserved for referrers,
+    // fees accrued in other custodies are claimed from them
+    let claim_usd = std::cmp::min(
+        referrer.unclaimed_fees_usd as u128,
+        custody.referral_fees_usd,
+    ) as u64;
+    let fee_amount = max_price.get_token_amount(claim_usd, custody.decimals)?;
+    msg!("Amount out: {}", fee_amount);
+
+    require_gte!(
+        fee_amount,
+        1u64,
+        PerpetualsError::InsufficientAmountReturned
+    );
+
+    // check pool constraints
+    msg!("Check pool constraints");
+    require!(
+        math::checked_sub(custody.assets.owned, custody.assets.locked)? >= fee_amount,
+        PerpetualsError::CustodyAmountLimit
+    );
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        fee_amount,
+    )?;
+
+    // referral fees are paid from the fees accrued to the pool
+    referrer.unclaimed_fees_usd = math::checked_sub(referrer.unclaimed_fees_usd, claim_usd)?;
+    custody.referral_fees_usd = math::checked_sub(custody.referral_fees_usd, claim_usd as u128)?;
+    custody.assets.owned = math::checked_sub(custody.assets.owned, fee_amount)?;
+    custody.update_borrow_rate(curtime)?;
+
+    // update pool stats
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,187 @@
+//! ClaimRewards instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            perpetuals::Perpetuals,
+            pool::Pool,
+            staking::{Staking, UserStake},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == reward_custody.load()?.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"staking",
+                 pool.key().as_ref()],
+        bump = staking.bump
+    )]
+    pub staking: Box<Account<'info, Staking>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stake",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = user_stake.bump
+    )]
+    pub user_stake: Box<Account<'info, UserStake>>,
+
+    #[account(
+        mut,
+        constraint = reward_custody.key() == staking.reward_custody
+    )]
+    pub reward_custody: AccountLoader<'info, Custody>,
+
+    /// CHECK: oracle account for the reward token
+    #[account(
+        constraint = reward_custody_oracle_account.key() == reward_custody.load()?.oracle.oracle_account
+    )]
+    pub reward_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 reward_custody.load()?.mint.as_ref()],
+        bump = reward_custody.load()?.token_account_bump
+    )]
+    pub reward_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   pool.tokens.len() custody accounts (writable, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ClaimRewardsParams {}
+
+pub fn claim_rewards(ctx: Context<ClaimRewards>, _params: &ClaimRewardsParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    require!(
+        ctx.accounts
+            .reward_custody
+            .load()?
+            .status
+            .allows_withdrawal(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // settle rewards, fees are read from all pool custodies including
+    // the reward custody so it is borrowed mutably only after that
+    msg!("Update rewards");
+    let staking = ctx.accounts.staking.as_mut();
+    let user_stake = ctx.accounts.user_stake.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    staking.update_pool_rewards(&mut pool, ctx.remaining_accounts)?;
+    user_stake.update_rewards(staking)?;
+    let mut reward_custody = ctx.accounts.reward_custody.load_mut()?;
+
+    // compute amount of tokens to pay
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    let token_price = reward_custody.get_oracle_price(
+        &ctx.accounts.reward_custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+    )?;
+
+    let token_ema_price = reward_custody.get_oracle_price(
+        &ctx.accounts.reward_custody_oracle_account.to_account_info(),
+        curtime,
+        reward_custody.pricing.use_ema,
+    )?;
+
+    let max_price = if token_price > token_ema_price {
+        token_price
+    } else {
+        token_ema_price
+    };
+
+    let reward_amount =
+        max_price.get_token_amount(user_stake.unclaimed_rewards_usd, reward_custody.decimals)?;
+    msg!("Amount out: {}", reward_amount);
+
+    require_gte!(
+        reward_amount,
+        1u64,
+        PerpetualsError::InsufficientAmountReturned
+    );
+
+    // check pool constraints
+    msg!("Check pool constraints");
+    require!(
+        math::checked_sub(reward_custody.assets.owned, reward_custody.assets.locked)?
+            >= reward_amount,
+        PerpetualsError::CustodyAmountLimit
+    );
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.reward_custody_token_account.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        reward_amount,
+    )?;
+
+    // rewards are paid from the fees accrued to the pool
+    reward_custody.staking_rewards_usd = reward_custody
+        .staking_rewards_usd
+        .saturating_sub(user_stake.unclaimed_rewards_usd as u128);
+    user_stake.unclaimed_rewards_usd = 0;
+    reward_custody.assets.owned = math::checked_sub(reward_custody.assets.owned, reward_amount)?;
+    reward_custody.update_borrow_rate(curtime)?;
+
+    // update pool stats
+    msg!("Update pool stats");
+    pool.update_custody_aum(
+        &ctx.accounts.reward_custody.key(),
+        &mut reward_custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+
+    Ok(())
+}
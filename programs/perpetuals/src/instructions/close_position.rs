@@ -6,10 +6,13 @@ use {
         math,
         state::{
             custody::Custody,
+            migration::Versioned,
             oracle::OraclePrice,
             perpetuals::Perpetuals,
             pool::Pool,
             position::{Position, Side},
+            referral::{Referral, Referrer},
+            user_stats::{Fill, FillType, UserStats},
         },
     },
     anchor_lang::prelude::*,
@@ -23,7 +26,7 @@ pub struct ClosePosition<'info> {
 
     #[account(
         mut,
-        constraint = receiving_account.mint == collateral_custody.mint,
+        constraint = receiving_account.mint == collateral_custody.load()?.mint,
         has_one = owner
     )]
     pub receiving_account: Box<Account<'info, TokenAccount>>,
@@ -44,10 +47,10 @@ pub struct ClosePosition<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
@@ -66,11 +69,11 @@ pub struct ClosePosition<'info> {
         mut,
         constraint = position.custody == custody.key()
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the position token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -78,11 +81,11 @@ pub struct ClosePosition<'info> {
         mut,
         constraint = position.collateral_custody == collateral_custody.key()
     )]
-    pub collateral_custody: Box<Account<'info, Custody>>,
+    pub collateral_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -90,11 +93,37 @@ pub struct ClosePosition<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 collateral_custody.mint.as_ref()],
-        bump = collateral_custody.token_account_bump
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.token_account_bump
     )]
     pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    // if provided, the trader gets a fee discount and the referrer is credited a fee share
+    #[account(
+        seeds = [b"referral",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = referral.bump
+    )]
+    pub referral: Option<Box<Account<'info, Referral>>>,
+
+    #[account(
+        mut,
+        constraint = referral.as_ref().map(|x| x.referrer) == Some(referrer.key())
+    )]
+    pub referrer: Option<Box<Account<'info, Referrer>>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = UserStats::LEN,
+        seeds = [b"user_stats",
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+
+    system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
 }
 
@@ -107,12 +136,23 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     // check permissions
     msg!("Check permissions");
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let custody = ctx.accounts.custody.as_mut();
-    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    // custody and collateral_custody can be the same account, work on copies
+    // and write them back when the position is updated
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    let mut collateral_custody = Box::new(*ctx.accounts.collateral_custody.load()?);
     require!(
-        perpetuals.permissions.allow_close_position && custody.permissions.allow_close_position,
+        perpetuals.permissions.allow_close_position
+            && pool.permissions.allow_close_position
+            && custody.permissions.allow_close_position,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        pool.status.allows_reduce()
+            && custody.status.allows_reduce()
+            && collateral_custody.status.allows_reduce(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // validate inputs
     msg!("Validate inputs");
@@ -120,21 +160,32 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         return Err(ProgramError::InvalidArgument.into());
     }
     let position = ctx.accounts.position.as_mut();
-    let pool = ctx.accounts.pool.as_mut();
 
-    // compute exit price
     let curtime = perpetuals.get_time()?;
 
+    // record user stats, trade volume selects the fee tier
+    let user_stats = ctx.accounts.user_stats.as_mut();
+    if user_stats.owner == Pubkey::default() {
+        user_stats.owner = ctx.accounts.owner.key();
+        user_stats.bump = *ctx
+            .bumps
+            .get("user_stats")
+            .ok_or(ProgramError::InvalidSeeds)?;
+        user_stats.version = UserStats::VERSION;
+    }
+    let fee_mult = perpetuals.get_fee_mult(user_stats.get_volume_usd(curtime));
+
+    // compute exit price
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
@@ -143,7 +194,7 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         false,
     )?;
@@ -152,12 +203,18 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         collateral_custody.pricing.use_ema,
     )?;
 
-    let exit_price = pool.get_exit_price(&token_price, &token_ema_price, position.side, custody)?;
+    let exit_price = pool.get_close_price(
+        &token_price,
+        &token_ema_price,
+        position.side,
+        &custody,
+        curtime,
+    )?;
     msg!("Exit price: {}", exit_price);
 
     if position.side == Side::Long {
@@ -167,24 +224,40 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     }
 
     msg!("Settle position");
-    let (transfer_amount, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
+    let (mut transfer_amount, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
         position,
         &token_price,
         &token_ema_price,
-        custody,
+        &custody,
         &collateral_token_price,
         &collateral_token_ema_price,
-        collateral_custody,
+        &collateral_custody,
         curtime,
         false,
+        fee_mult,
     )?;
 
+    let mut referrer_fee_usd = 0;
+    let mut discount_usd = 0;
+    if ctx.accounts.referrer.is_some() {
+        let (discount, referrer_fee) = pool.get_referral_fees(fee_amount)?;
+        fee_amount = math::checked_sub(fee_amount, discount)?;
+        discount_usd = token_ema_price.get_asset_amount_usd(discount, custody.decimals)?;
+        referrer_fee_usd = token_ema_price.get_asset_amount_usd(referrer_fee, custody.decimals)?;
+    }
+
     let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
     if position.side == Side::Short || custody.is_virtual {
         fee_amount = collateral_token_ema_price
             .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
     }
 
+    // discount is returned to the trader
+    transfer_amount = math::checked_add(
+        transfer_amount,
+        collateral_token_ema_price.get_token_amount(discount_usd, collateral_custody.decimals)?,
+    )?;
+
     msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
     msg!("Collected fee: {}", fee_amount);
     msg!("Amount out: {}", transfer_amount);
@@ -195,7 +268,7 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     // check pool constraints
     msg!("Check pool constraints");
     require!(
-        pool.check_available_amount(transfer_amount, collateral_custody)?,
+        pool.check_available_amount(transfer_amount, &collateral_custody)?,
         PerpetualsError::CustodyAmountLimit
     );
 
@@ -216,7 +289,7 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     collateral_custody.collected_fees.close_position_usd = collateral_custody
         .collected_fees
         .close_position_usd
-        .wrapping_add(fee_amount_usd);
+        .saturating_add(fee_amount_usd as u128);
 
     if transfer_amount > position.collateral_amount {
         let amount_lost = transfer_amount.saturating_sub(position.collateral_amount);
@@ -232,10 +305,30 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         position.collateral_amount,
     )?;
 
+    if let Some(referrer) = ctx.accounts.referrer.as_mut() {
+        referrer.add_trade(position.size_usd, referrer_fee_usd)?;
+        collateral_custody.referral_fees_usd = math::checked_add(
+            collateral_custody.referral_fees_usd,
+            referrer_fee_usd as u128,
+        )?;
+    }
+    user_stats.add_volume(position.size_usd, curtime);
+    user_stats.record_fill(Fill {
+        fill_type: FillType::Close,
+        side: position.side,
+        custody: position.custody,
+        time: curtime,
+        price: exit_price,
+        size_usd: position.size_usd,
+        fee_usd: fee_amount_usd,
+        profit_usd,
+        loss_usd,
+    });
+
     let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
 
     // Pay protocol_fee from custody if possible, otherwise no protocol_fee
-    if pool.check_available_amount(protocol_fee, collateral_custody)? {
+    if pool.check_available_amount(protocol_fee, &collateral_custody)? {
         collateral_custody.assets.protocol_fees =
             math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
 
@@ -248,7 +341,7 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         collateral_custody.volume_stats.close_position_usd = collateral_custody
             .volume_stats
             .close_position_usd
-            .wrapping_add(position.size_usd);
+            .saturating_add(position.size_usd as u128);
 
         if position.side == Side::Long {
             collateral_custody.trade_stats.oi_long_usd = collateral_custody
@@ -271,14 +364,15 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
             .loss_usd
             .wrapping_add(loss_usd);
 
+        collateral_custody.update_stats_history(curtime, position.size_usd, fee_amount_usd);
         collateral_custody.remove_position(position, curtime, None)?;
         collateral_custody.update_borrow_rate(curtime)?;
-        *custody = collateral_custody.clone();
+        custody = collateral_custody.clone();
     } else {
         custody.volume_stats.close_position_usd = custody
             .volume_stats
             .close_position_usd
-            .wrapping_add(position.size_usd);
+            .saturating_add(position.size_usd as u128);
 
         if position.side == Side::Long {
             custody.trade_stats.oi_long_usd = custody
@@ -295,9 +389,34 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         custody.trade_stats.profit_usd = custody.trade_stats.profit_usd.wrapping_add(profit_usd);
         custody.trade_stats.loss_usd = custody.trade_stats.loss_usd.wrapping_add(loss_usd);
 
-        custody.remove_position(position, curtime, Some(collateral_custody))?;
+        custody.update_stats_history(curtime, position.size_usd, 0);
+        collateral_custody.update_stats_history(curtime, 0, fee_amount_usd);
+        custody.remove_position(position, curtime, Some(collateral_custody.as_mut()))?;
         collateral_custody.update_borrow_rate(curtime)?;
     }
 
+    // update pool stats
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    if ctx.accounts.custody.key() == ctx.accounts.collateral_custody.key() {
+        collateral_custody = custody.clone();
+    } else {
+        pool.update_custody_aum(
+            &ctx.accounts.collateral_custody.key(),
+            &mut collateral_custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            curtime,
+        )?;
+    }
+
+    *ctx.accounts.custody.load_mut()? = *custody;
+    *ctx.accounts.collateral_custody.load_mut()? = *collateral_custody;
+
     Ok(())
 }
@@ -0,0 +1,62 @@
+//! CloseProposal instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::multisig::{Multisig, Proposal, ProposalStatus},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct CloseProposal<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    // root or role multisig the proposal was created for
+    #[account()]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    #[account(
+        mut,
+        has_one = proposer,
+        constraint = proposal.instruction_accounts.first() == Some(&multisig.key())
+            @ PerpetualsError::MultisigProposalMismatch,
+        close = proposer,
+        seeds = [b"proposal",
+                 proposal.instruction_hash.as_ref()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    /// CHECK: proposal rent receiver, validated by has_one constraint
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CloseProposalParams {}
+
+pub fn close_proposal(ctx: Context<CloseProposal>, _params: &CloseProposalParams) -> Result<()> {
+    // check permissions
+    let multisig = ctx.accounts.multisig.load()?;
+    if !multisig.is_signer(ctx.accounts.admin.key)? {
+        return err!(PerpetualsError::MultisigAccountNotAuthorized);
+    }
+
+    // pending proposal can be cancelled by its proposer only, unless it can't be
+    // executed anymore because the signer set has changed since it was created
+    let proposal = ctx.accounts.proposal.as_ref();
+    let is_stale = proposal.signers_hash != multisig.get_signers_hash()
+        || !multisig.is_signer(&proposal.proposer)?;
+    if proposal.status == ProposalStatus::Pending
+        && proposal.proposer != ctx.accounts.admin.key()
+        && !is_stale
+    {
+        return err!(PerpetualsError::MultisigProposalPending);
+    }
+
+    msg!("Close proposal");
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! CrankPool instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+    solana_program::sysvar,
+};
+
+#[derive(Accounts)]
+pub struct CrankPool<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    /// CHECK: empty PDA funded by anyone with plain SOL transfers, keeper rewards
+    /// are paid from it and never exceed what was funded
+    #[account(
+        mut,
+        seeds = [b"keeper_rewards",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub keeper_rewards: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    system_program: Program<'info, System>,
+    // remaining accounts, any page of pool custodies:
+    //   custody accounts (write, unsigned)
+    //   custody oracles (read-only, unsigned)
+}
+
+pub fn crank_pool(ctx: Context<CrankPool>) -> Result<u64> {
+    let perpetuals: &Account<'_, Perpetuals> = ctx.accounts.perpetuals.as_ref();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+
+    let curtime: i64 = perpetuals.get_time()?;
+
+    // refresh borrow rates and AUM values
+    msg!("Refresh pool custodies");
+    require!(pool.is_crank_due(curtime), PerpetualsError::CrankNotDue);
+    let prev_aum_update = pool.last_aum_update;
+
+    let aum_usd = pool.refresh_aum(ctx.remaining_accounts, curtime)?;
+
+    msg!("Pool AUM: {}, updated at: {}", aum_usd, {
+        pool.last_aum_update
+    });
+
+    // pay keeper reward
+    let reward = pool.get_keeper_reward(prev_aum_update, curtime);
+    if reward == 0 {
+        return Ok(0);
+    }
+
+    let balance = ctx.accounts.keeper_rewards.try_lamports()?;
+    let min_balance = sysvar::rent::Rent::get()?.minimum_balance(0);
+    let available_balance = if balance > min_balance {
+        math::checked_sub(balance, min_balance)?
+    } else {
+        0
+    };
+
+    // the crank is still useful without the reward, so it doesn't fail
+    if available_balance < reward {
+        msg!(
+            "Not enough keeper rewards funded to pay keeper reward: {}",
+            available_balance
+        );
+        return Ok(0);
+    }
+
+    msg!("Pay keeper reward: {}", reward);
+    let pool_key = ctx.accounts.pool.key();
+    let bump = *ctx
+        .bumps
+        .get("keeper_rewards")
+        .ok_or(ProgramError::InvalidSeeds)?;
+    Perpetuals::transfer_sol_signed(
+        ctx.accounts.keeper_rewards.to_account_info(),
+        ctx.accounts.keeper.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        reward,
+        &[&[b"keeper_rewards", pool_key.as_ref(), &[bump]]],
+    )?;
+
+    Ok(reward)
+}
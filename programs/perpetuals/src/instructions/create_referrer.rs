@@ -0,0 +1,61 @@
+//! CreateReferrer instruction handler
+
+use {
+    crate::state::{migration::Versioned, pool::Pool, referral::Referrer},
+    anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: CreateReferrerParams)]
+pub struct CreateReferrer<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Referrer::LEN,
+        seeds = [b"referrer",
+                 pool.key().as_ref(),
+                 params.code.as_bytes()],
+        bump
+    )]
+    pub referrer: Box<Account<'info, Referrer>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateReferrerParams {
+    pub code: String,
+}
+
+pub fn create_referrer(ctx: Context<CreateReferrer>, params: &CreateReferrerParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    if !Referrer::validate_code(&params.code) {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // record referrer data
+    msg!("Record referrer: {}", params.code);
+    let referrer = ctx.accounts.referrer.as_mut();
+    referrer.owner = ctx.accounts.owner.key();
+    referrer.pool = ctx.accounts.pool.key();
+    referrer.code = params.code.clone();
+    referrer.bump = *ctx
+        .bumps
+        .get("referrer")
+        .ok_or(ProgramError::InvalidSeeds)?;
+    referrer.version = Referrer::VERSION;
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! EnableTranches instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            perpetuals::Perpetuals,
+            pool::{AumCalcMode, Pool},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token},
+};
+
+#[derive(Accounts)]
+pub struct EnableTranches<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::PoolAdmin,
+            &pool.key()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    // instruction can be called multiple times due to multisig use, hence init_if_needed
+    #[account(
+        init_if_needed,
+        payer = admin,
+        mint::authority = transfer_authority,
+        mint::freeze_authority = transfer_authority,
+        mint::decimals = Perpetuals::LP_DECIMALS,
+        seeds = [b"junior_lp_token_mint",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub junior_lp_token_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+    // optional remaining accounts, AUM values of these custodies are refreshed:
+    //   custody accounts (write, unsigned)
+    //   custody oracles (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct EnableTranchesParams {}
+
+pub fn enable_tranches<'info>(
+    ctx: Context<'_, '_, '_, 'info, EnableTranches<'info>>,
+    params: &EnableTranchesParams,
+) -> Result<u8> {
+    // validate inputs
+    if ctx.accounts.pool.load()?.tranches_enabled {
+        return Err(ProgramError::AccountAlreadyInitialized.into());
+    }
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::EnableTranches, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // existing LPs become the senior tranche
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    pool.update_aum_from_accounts(ctx.remaining_accounts, curtime)?;
+    let aum_usd = pool.get_cached_aum_usd(AumCalcMode::EMA, curtime)?;
+
+    msg!("Enable tranches");
+    pool.tranches_enabled = true;
+    pool.junior_lp_token_bump = *ctx
+        .bumps
+        .get("junior_lp_token_mint")
+        .ok_or(ProgramError::InvalidSeeds)?;
+    pool.senior_aum_usd = aum_usd;
+    pool.tranche_aum_usd = aum_usd;
+
+    Ok(0)
+}
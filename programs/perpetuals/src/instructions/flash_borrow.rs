@@ -0,0 +1,177 @@
+//! FlashBorrow instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        instructions::FlashRepayParams,
+        math,
+        state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::{prelude::*, Discriminator},
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::{
+        instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+        sysvar,
+    },
+};
+
+#[derive(Accounts)]
+pub struct FlashBorrow<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == custody.load()?.mint,
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: instructions sysvar, to find flash_repay later in this transaction
+    #[account(address = sysvar::instructions::ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+
+    token_program: Program<'info, Token>,
+}
+
+// positions of pool and custody in FlashRepay accounts
+const FLASH_REPAY_POOL_INDEX: usize = 3;
+const FLASH_REPAY_CUSTODY_INDEX: usize = 4;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct FlashBorrowParams {
+    pub amount: u64,
+}
+
+pub fn flash_borrow(ctx: Context<FlashBorrow>, params: &FlashBorrowParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let pool = ctx.accounts.pool.load()?;
+    let mut custody = ctx.accounts.custody.load_mut()?;
+    require!(
+        custody.flash_loan_fee > 0 && !custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        pool.status.allows_increase() && custody.status.allows_increase(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // validate inputs
+    msg!("Validate inputs");
+    if params.amount == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    if custody.flash_loan_amount > 0 {
+        return err!(PerpetualsError::FlashLoanOutstanding);
+    }
+    let available_amount = math::checked_sub(custody.assets.owned, custody.assets.locked)?;
+    if params.amount > available_amount {
+        msg!(
+            "Error: Flash loan amount ({}) exceeds free liquidity ({})",
+            params.amount,
+            available_amount
+        );
+        return err!(PerpetualsError::CustodyAmountLimit);
+    }
+
+    // only top-level instructions can be seen in the sysvar, so a borrow made
+    // through CPI could be repaid by nothing but a later top-level instruction
+    if get_stack_height() != TRANSACTION_LEVEL_STACK_HEIGHT {
+        msg!("Error: Flash loans can't be taken through CPI");
+        return err!(PerpetualsError::InstructionNotAllowed);
+    }
+    let fee_amount = Pool::get_fee_amount(custody.flash_loan_fee, params.amount)?;
+    require!(
+        is_repaid_later(
+            &ctx.accounts.ix_sysvar,
+            &ctx.accounts.pool.key(),
+            &ctx.accounts.custody.key(),
+            math::checked_add(params.amount, fee_amount)?,
+        )?,
+        PerpetualsError::FlashLoanNotRepaid
+    );
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount,
+    )?;
+
+    // update custody stats, lent tokens stay owned by the pool until repaid
+    msg!("Update custody stats");
+    custody.flash_loan_amount = params.amount;
+
+    Ok(())
+}
+
+/// Returns true if a flash_repay of at least the due amount to the custody
+/// follows the current instruction
+fn is_repaid_later(
+    ix_sysvar: &AccountInfo,
+    pool: &Pubkey,
+    custody: &Pubkey,
+    due_amount: u64,
+) -> Result<bool> {
+    let current_index = sysvar::instructions::load_current_index_checked(ix_sysvar)? as usize;
+    let mut index = current_index + 1;
+    while let Ok(ix) = sysvar::instructions::load_instruction_at_checked(index, ix_sysvar) {
+        // pool and custody are matched at their positions in FlashRepay accounts
+        if ix.program_id == crate::ID
+            && ix.data.len() >= 8
+            && ix.data[..8] == crate::instruction::FlashRepay::DISCRIMINATOR
+            && ix.accounts.len() > FLASH_REPAY_CUSTODY_INDEX
+            && ix.accounts[FLASH_REPAY_POOL_INDEX].pubkey == *pool
+            && ix.accounts[FLASH_REPAY_CUSTODY_INDEX].pubkey == *custody
+        {
+            if let Ok(params) = FlashRepayParams::try_from_slice(&ix.data[8..]) {
+                if params.amount >= due_amount {
+                    return Ok(true);
+                }
+            }
+        }
+        index += 1;
+    }
+    Ok(false)
+}
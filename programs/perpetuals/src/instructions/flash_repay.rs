@@ -0,0 +1,147 @@
+//! FlashRepay instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct FlashRepay<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = funding_account.mint == custody.load()?.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    /// CHECK: oracle account for the repaid token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct FlashRepayParams {
+    // borrowed amount plus the flash loan fee, anything above the loan is kept as the fee
+    pub amount: u64,
+}
+
+pub fn flash_repay(ctx: Context<FlashRepay>, params: &FlashRepayParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let curtime = perpetuals.get_time()?;
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let mut custody = ctx.accounts.custody.load_mut()?;
+    if custody.flash_loan_amount == 0 {
+        msg!("Error: Custody has no outstanding flash loan");
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // compute fee
+    let token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &{ custody.oracle },
+        curtime,
+        false,
+    )?;
+
+    let token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &{ custody.oracle },
+        curtime,
+        custody.pricing.use_ema,
+    )?;
+
+    let min_fee_amount = Pool::get_fee_amount(custody.flash_loan_fee, custody.flash_loan_amount)?;
+    let min_repay_amount = math::checked_add(custody.flash_loan_amount, min_fee_amount)?;
+    if params.amount < min_repay_amount {
+        msg!(
+            "Error: Repaid amount ({}) is less than the loan plus fee ({})",
+            params.amount,
+            min_repay_amount
+        );
+        return err!(PerpetualsError::FlashLoanNotRepaid);
+    }
+    let fee_amount = math::checked_sub(params.amount, custody.flash_loan_amount)?;
+    msg!("Repaid amount: {}, fee: {}", params.amount, fee_amount);
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount,
+    )?;
+
+    // update custody stats, the whole fee is paid to LPs
+    msg!("Update custody stats");
+    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+    custody.collected_fees.flash_loan_usd = custody
+        .collected_fees
+        .flash_loan_usd
+        .saturating_add(fee_amount_usd as u128);
+
+    custody.assets.owned = math::checked_add(custody.assets.owned, fee_amount)?;
+    custody.flash_loan_amount = 0;
+
+    custody.update_stats_history(curtime, 0, fee_amount_usd);
+    custody.update_borrow_rate(curtime)?;
+
+    // update pool stats
+    msg!("Update pool stats");
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+
+    Ok(())
+}
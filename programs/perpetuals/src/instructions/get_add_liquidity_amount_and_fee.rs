@@ -7,7 +7,7 @@ use {
             custody::Custody,
             oracle::OraclePrice,
             perpetuals::{AmountAndFee, Perpetuals},
-            pool::{AumCalcMode, Pool},
+            pool::Pool,
         },
     },
     anchor_lang::prelude::*,
@@ -25,31 +25,34 @@ pub struct GetAddLiquidityAmountAndFee<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
     #[account(
         seeds = [b"lp_token_mint",
                  pool.key().as_ref()],
-        bump = pool.lp_token_bump
+        bump = pool.load()?.lp_token_bump
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
+    // optional remaining accounts, cached AUM is used if not provided:
+    //   pool.tokens.len() custody accounts (read-only, unsigned)
+    //   pool.tokens.len() custody oracles (read-only, unsigned)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -65,34 +68,50 @@ pub fn get_add_liquidity_amount_and_fee(
     if params.amount_in == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
-    let pool = &ctx.accounts.pool;
-    let custody = &ctx.accounts.custody;
-    let token_id = pool.get_token_id(&custody.key())?;
+    // pool and custody are refreshed on copies the same way add_liquidity does
+    let mut pool = Box::new(*ctx.accounts.pool.load()?);
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    let token_id = pool.get_token_id(&ctx.accounts.custody.key())?;
 
     // compute position price
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
 
+    // AUM is computed from all pool custodies if they are passed, otherwise cached values are used
+    let pool_amount_usd = if ctx.remaining_accounts.is_empty() {
+        pool.update_custody_aum(
+            &ctx.accounts.custody.key(),
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            curtime,
+        )?;
+        pool.get_cached_aum_usd(pool.add_liquidity_aum_mode, curtime)?
+    } else {
+        pool.get_assets_under_management_usd(
+            pool.add_liquidity_aum_mode,
+            ctx.remaining_accounts,
+            curtime,
+        )?
+    };
+
     let fee_amount =
-        pool.get_add_liquidity_fee(token_id, params.amount_in, custody, &token_price)?;
+        pool.get_add_liquidity_fee(token_id, params.amount_in, &custody, &token_price)?;
     let no_fee_amount = math::checked_sub(params.amount_in, fee_amount)?;
 
-    let pool_amount_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::Max, ctx.remaining_accounts, curtime)?;
-
     let min_price = if token_price < token_ema_price {
         token_price
     } else {
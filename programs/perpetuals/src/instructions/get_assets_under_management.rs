@@ -18,11 +18,11 @@ pub struct GetAssetsUnderManagement<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
-    // remaining accounts:
+    pub pool: AccountLoader<'info, Pool>,
+    // optional remaining accounts, cached AUM is returned if not provided:
     //   pool.tokens.len() custody accounts (read-only, unsigned)
     //   pool.tokens.len() custody oracles (read-only, unsigned)
 }
@@ -34,9 +34,13 @@ pub fn get_assets_under_management(
     ctx: Context<GetAssetsUnderManagement>,
     _params: &GetAssetsUnderManagementParams,
 ) -> Result<u128> {
-    ctx.accounts.pool.get_assets_under_management_usd(
-        AumCalcMode::EMA,
-        ctx.remaining_accounts,
-        ctx.accounts.perpetuals.get_time()?,
-    )
+    let pool = ctx.accounts.pool.load()?;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    // AUM is computed from all pool custodies if they are passed, otherwise cached value is used
+    if ctx.remaining_accounts.is_empty() {
+        pool.get_cached_aum_usd(AumCalcMode::EMA, curtime)
+    } else {
+        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)
+    }
 }
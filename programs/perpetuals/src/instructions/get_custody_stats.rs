@@ -0,0 +1,57 @@
+//! GetCustodyStats instruction handler
+
+use {
+    crate::state::{
+        custody::Custody,
+        perpetuals::{CustodyStats, Perpetuals},
+        pool::Pool,
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct GetCustodyStats<'info> {
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetCustodyStatsParams {}
+
+pub fn get_custody_stats(
+    ctx: Context<GetCustodyStats>,
+    _params: &GetCustodyStatsParams,
+) -> Result<CustodyStats> {
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let stats_history = ctx.accounts.custody.load()?.stats_history;
+
+    let hourly = stats_history.get_hourly_total(curtime);
+    let daily = stats_history.get_daily_total(curtime);
+
+    Ok(CustodyStats {
+        volume_24h_usd: hourly.volume_usd,
+        fees_24h_usd: hourly.fees_usd,
+        max_oi_24h_usd: hourly.max_oi_usd,
+        volume_7d_usd: daily.volume_usd,
+        fees_7d_usd: daily.fees_usd,
+        max_oi_7d_usd: daily.max_oi_usd,
+    })
+}
@@ -22,36 +22,36 @@ pub struct GetEntryPriceAndFee<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 collateral_custody.mint.as_ref()],
-        bump = collateral_custody.bump
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.bump
     )]
-    pub collateral_custody: Box<Account<'info, Custody>>,
+    pub collateral_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 }
@@ -71,23 +71,23 @@ pub fn get_entry_price_and_fee(
     if params.collateral == 0 || params.size == 0 || params.side == Side::None {
         return Err(ProgramError::InvalidArgument.into());
     }
-    let pool = &ctx.accounts.pool;
-    let custody = &ctx.accounts.custody;
-    let collateral_custody = &ctx.accounts.collateral_custody;
+    let pool = ctx.accounts.pool.load()?;
+    let custody = ctx.accounts.custody.load()?;
+    let collateral_custody = ctx.accounts.collateral_custody.load()?;
 
     // compute position price
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
@@ -96,7 +96,7 @@ pub fn get_entry_price_and_fee(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         false,
     )?;
@@ -105,7 +105,7 @@ pub fn get_entry_price_and_fee(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         collateral_custody.pricing.use_ema,
     )?;
@@ -113,7 +113,8 @@ pub fn get_entry_price_and_fee(
     let min_collateral_price = collateral_token_price
         .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
 
-    let entry_price = pool.get_entry_price(&token_price, &token_ema_price, params.side, custody)?;
+    let entry_price =
+        pool.get_entry_price(&token_price, &token_ema_price, params.side, &custody)?;
 
     let position_oracle_price = OraclePrice {
         price: entry_price,
@@ -144,8 +145,8 @@ pub fn get_entry_price_and_fee(
     let liquidation_price = pool.get_liquidation_price(
         &position,
         &token_ema_price,
-        custody,
-        collateral_custody,
+        &custody,
+        &collateral_custody,
         curtime,
     )?;
 
@@ -153,7 +154,8 @@ pub fn get_entry_price_and_fee(
         custody.fees.open_position,
         params.size,
         locked_amount,
-        collateral_custody,
+        &collateral_custody,
+        Perpetuals::BPS_POWER as u64,
     )?;
 
     if params.side == Side::Short || custody.is_virtual {
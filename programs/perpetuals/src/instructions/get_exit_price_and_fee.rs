@@ -21,10 +21,10 @@ pub struct GetExitPriceAndFee<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"position",
@@ -39,28 +39,28 @@ pub struct GetExitPriceAndFee<'info> {
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 collateral_custody.mint.as_ref()],
-        bump = collateral_custody.bump
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.bump
     )]
-    pub collateral_custody: Box<Account<'info, Custody>>,
+    pub collateral_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 }
@@ -74,21 +74,21 @@ pub fn get_exit_price_and_fee(
 ) -> Result<PriceAndFee> {
     // compute exit price and fee
     let position = &ctx.accounts.position;
-    let pool = &ctx.accounts.pool;
+    let pool = ctx.accounts.pool.load()?;
     let curtime = ctx.accounts.perpetuals.get_time()?;
-    let custody = &ctx.accounts.custody;
-    let collateral_custody = &ctx.accounts.collateral_custody;
+    let custody = ctx.accounts.custody.load()?;
+    let collateral_custody = ctx.accounts.collateral_custody.load()?;
 
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
@@ -97,16 +97,22 @@ pub fn get_exit_price_and_fee(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         collateral_custody.pricing.use_ema,
     )?;
 
-    let price = pool.get_exit_price(&token_price, &token_ema_price, position.side, custody)?;
+    let price = pool.get_close_price(
+        &token_price,
+        &token_ema_price,
+        position.side,
+        &custody,
+        curtime,
+    )?;
 
     let size = token_ema_price.get_token_amount(position.size_usd, custody.decimals)?;
 
-    let mut fee = pool.get_exit_fee(size, custody)?;
+    let mut fee = pool.get_exit_fee(size, &custody, Perpetuals::BPS_POWER as u64)?;
 
     if position.side == Side::Short || custody.is_virtual {
         let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee, custody.decimals)?;
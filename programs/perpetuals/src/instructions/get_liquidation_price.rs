@@ -21,10 +21,10 @@ pub struct GetLiquidationPrice<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"position",
@@ -39,25 +39,25 @@ pub struct GetLiquidationPrice<'info> {
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
     #[account(
         constraint = position.collateral_custody == collateral_custody.key()
     )]
-    pub collateral_custody: Box<Account<'info, Custody>>,
+    pub collateral_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 }
@@ -72,13 +72,13 @@ pub fn get_liquidation_price(
     ctx: Context<GetLiquidationPrice>,
     params: &GetLiquidationPriceParams,
 ) -> Result<u64> {
-    let custody = &ctx.accounts.custody;
-    let collateral_custody = &ctx.accounts.collateral_custody;
+    let custody = ctx.accounts.custody.load()?;
+    let collateral_custody = ctx.accounts.collateral_custody.load()?;
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
@@ -87,7 +87,7 @@ pub fn get_liquidation_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         false,
     )?;
@@ -96,7 +96,7 @@ pub fn get_liquidation_price(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         collateral_custody.pricing.use_ema,
     )?;
@@ -127,11 +127,11 @@ pub fn get_liquidation_price(
             math::checked_sub(position.collateral_amount, params.remove_collateral)?;
     }
 
-    ctx.accounts.pool.get_liquidation_price(
+    ctx.accounts.pool.load()?.get_liquidation_price(
         &position,
         &token_ema_price,
-        custody,
-        collateral_custody,
+        &custody,
+        &collateral_custody,
         curtime,
     )
 }
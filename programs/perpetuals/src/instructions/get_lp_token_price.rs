@@ -23,18 +23,18 @@ pub struct GetLpTokenPrice<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"lp_token_mint",
                  pool.key().as_ref()],
-        bump = pool.lp_token_bump
+        bump = pool.load()?.lp_token_bump
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
-    // remaining accounts:
+    // optional remaining accounts, cached AUM is returned if not provided:
     //   pool.tokens.len() custody accounts (read-only, unsigned)
     //   pool.tokens.len() custody oracles (read-only, unsigned)
 }
@@ -46,11 +46,15 @@ pub fn get_lp_token_price(
     ctx: Context<GetLpTokenPrice>,
     _params: &GetLpTokenPriceParams,
 ) -> Result<u64> {
-    let aum_usd = math::checked_as_u64(ctx.accounts.pool.get_assets_under_management_usd(
-        AumCalcMode::EMA,
-        ctx.remaining_accounts,
-        ctx.accounts.perpetuals.get_time()?,
-    )?)?;
+    let pool = ctx.accounts.pool.load()?;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    // AUM is computed from all pool custodies if they are passed, otherwise cached value is used
+    let aum_usd = math::checked_as_u64(if ctx.remaining_accounts.is_empty() {
+        pool.get_cached_aum_usd(AumCalcMode::EMA, curtime)?
+    } else {
+        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?
+    })?;
 
     msg!("aum_usd: {}", aum_usd);
 
@@ -5,9 +5,8 @@ use {
         math,
         state::{
             custody::Custody,
-            oracle::OraclePrice,
             perpetuals::{AmountAndFee, Perpetuals},
-            pool::{AumCalcMode, Pool},
+            pool::Pool,
         },
     },
     anchor_lang::prelude::*,
@@ -25,31 +24,34 @@ pub struct GetRemoveLiquidityAmountAndFee<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
     #[account(
         seeds = [b"lp_token_mint",
                  pool.key().as_ref()],
-        bump = pool.lp_token_bump
+        bump = pool.load()?.lp_token_bump
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
+    // optional remaining accounts, cached AUM is used if not provided:
+    //   pool.tokens.len() custody accounts (read-only, unsigned)
+    //   pool.tokens.len() custody oracles (read-only, unsigned)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -65,29 +67,43 @@ pub fn get_remove_liquidity_amount_and_fee(
     if params.lp_amount_in == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
-    let pool = &ctx.accounts.pool;
-    let custody = &ctx.accounts.custody;
-    let token_id = pool.get_token_id(&custody.key())?;
+    // pool and custody are refreshed on copies the same way remove_liquidity does
+    let mut pool = Box::new(*ctx.accounts.pool.load()?);
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    let token_id = pool.get_token_id(&ctx.accounts.custody.key())?;
 
     // compute position price
     let curtime = ctx.accounts.perpetuals.get_time()?;
 
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
     )?;
 
-    let pool_amount_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::Min, ctx.remaining_accounts, curtime)?;
+    // AUM is computed from all pool custodies if they are passed, otherwise cached values are used
+    let pool_amount_usd = if ctx.remaining_accounts.is_empty() {
+        pool.update_custody_aum(
+            &ctx.accounts.custody.key(),
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            curtime,
+        )?;
+        pool.get_cached_aum_usd(pool.remove_liquidity_aum_mode, curtime)?
+    } else {
+        pool.get_assets_under_management_usd(
+            pool.remove_liquidity_aum_mode,
+            ctx.remaining_accounts,
+            curtime,
+        )?
+    };
 
     let remove_amount_usd = math::checked_as_u64(math::checked_div(
         math::checked_mul(pool_amount_usd, params.lp_amount_in as u128)?,
@@ -102,7 +118,7 @@ pub fn get_remove_liquidity_amount_and_fee(
     let remove_amount = max_price.get_token_amount(remove_amount_usd, custody.decimals)?;
 
     let fee_amount =
-        pool.get_remove_liquidity_fee(token_id, remove_amount, custody, &token_price)?;
+        pool.get_remove_liquidity_fee(token_id, remove_amount, &custody, &token_price)?;
 
     let transfer_amount = math::checked_sub(remove_amount, fee_amount)?;
 
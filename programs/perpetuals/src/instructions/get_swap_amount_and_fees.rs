@@ -21,36 +21,36 @@ pub struct GetSwapAmountAndFees<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 receiving_custody.mint.as_ref()],
-        bump = receiving_custody.bump
+                 receiving_custody.load()?.mint.as_ref()],
+        bump = receiving_custody.load()?.bump
     )]
-    pub receiving_custody: Box<Account<'info, Custody>>,
+    pub receiving_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the received token
     #[account(
-        constraint = receiving_custody_oracle_account.key() == receiving_custody.oracle.oracle_account
+        constraint = receiving_custody_oracle_account.key() == receiving_custody.load()?.oracle.oracle_account
     )]
     pub receiving_custody_oracle_account: AccountInfo<'info>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 dispensing_custody.mint.as_ref()],
-        bump = dispensing_custody.bump
+                 dispensing_custody.load()?.mint.as_ref()],
+        bump = dispensing_custody.load()?.bump
     )]
-    pub dispensing_custody: Box<Account<'info, Custody>>,
+    pub dispensing_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the returned token
     #[account(
-        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.oracle.oracle_account
+        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.load()?.oracle.oracle_account
     )]
     pub dispensing_custody_oracle_account: AccountInfo<'info>,
 }
@@ -76,17 +76,17 @@ pub fn get_swap_amount_and_fees(
 
     // compute token amount returned to the user
     let curtime = ctx.accounts.perpetuals.get_time()?;
-    let pool = &ctx.accounts.pool;
+    let pool = ctx.accounts.pool.load()?;
     let token_id_in = pool.get_token_id(&ctx.accounts.receiving_custody.key())?;
     let token_id_out = pool.get_token_id(&ctx.accounts.dispensing_custody.key())?;
-    let receiving_custody = &ctx.accounts.receiving_custody;
-    let dispensing_custody = &ctx.accounts.dispensing_custody;
+    let receiving_custody = ctx.accounts.receiving_custody.load()?;
+    let dispensing_custody = ctx.accounts.dispensing_custody.load()?;
 
     let received_token_price = OraclePrice::new_from_oracle(
         &ctx.accounts
             .receiving_custody_oracle_account
             .to_account_info(),
-        &receiving_custody.oracle,
+        &{ receiving_custody.oracle },
         curtime,
         false,
     )?;
@@ -95,7 +95,7 @@ pub fn get_swap_amount_and_fees(
         &ctx.accounts
             .receiving_custody_oracle_account
             .to_account_info(),
-        &receiving_custody.oracle,
+        &{ receiving_custody.oracle },
         curtime,
         receiving_custody.pricing.use_ema,
     )?;
@@ -104,7 +104,7 @@ pub fn get_swap_amount_and_fees(
         &ctx.accounts
             .dispensing_custody_oracle_account
             .to_account_info(),
-        &dispensing_custody.oracle,
+        &{ dispensing_custody.oracle },
         curtime,
         false,
     )?;
@@ -113,7 +113,7 @@ pub fn get_swap_amount_and_fees(
         &ctx.accounts
             .dispensing_custody_oracle_account
             .to_account_info(),
-        &dispensing_custody.oracle,
+        &{ dispensing_custody.oracle },
         curtime,
         dispensing_custody.pricing.use_ema,
     )?;
@@ -123,8 +123,8 @@ pub fn get_swap_amount_and_fees(
         &received_token_ema_price,
         &dispensed_token_price,
         &dispensed_token_ema_price,
-        receiving_custody,
-        dispensing_custody,
+        &receiving_custody,
+        &dispensing_custody,
         params.amount_in,
     )?;
 
@@ -134,10 +134,11 @@ pub fn get_swap_amount_and_fees(
         token_id_out,
         params.amount_in,
         amount_out,
-        receiving_custody,
+        &receiving_custody,
         &received_token_price,
-        dispensing_custody,
+        &dispensing_custody,
         &dispensed_token_price,
+        Perpetuals::BPS_POWER as u64,
     )?;
 
     Ok(SwapAmountAndFees {
@@ -3,7 +3,7 @@
 use {
     crate::{
         error::PerpetualsError,
-        state::{multisig::Multisig, perpetuals::Perpetuals},
+        state::{migration::Versioned, multisig::Multisig, perpetuals::Perpetuals},
     },
     anchor_lang::prelude::*,
     anchor_spl::token::Token,
@@ -77,13 +77,14 @@ pub fn init(ctx: Context<Init>, params: &InitParams) -> Result<()> {
     // initialize multisig, this will fail if account is already initialized
     let mut multisig = ctx.accounts.multisig.load_init()?;
 
-    multisig.set_signers(ctx.remaining_accounts, params.min_signatures)?;
+    multisig.set_signers(ctx.remaining_accounts, &[], params.min_signatures)?;
 
     // record multisig PDA bump
     multisig.bump = *ctx
         .bumps
         .get("multisig")
         .ok_or(ProgramError::InvalidSeeds)?;
+    multisig.version = Multisig::VERSION;
 
     // record perpetuals
     let perpetuals = ctx.accounts.perpetuals.as_mut();
@@ -105,6 +106,7 @@ pub fn init(ctx: Context<Init>, params: &InitParams) -> Result<()> {
         .get("perpetuals")
         .ok_or(ProgramError::InvalidSeeds)?;
     perpetuals.inception_time = perpetuals.get_time()?;
+    perpetuals.version = Perpetuals::VERSION;
 
     if !perpetuals.validate() {
         return err!(PerpetualsError::InvalidPerpetualsConfig);
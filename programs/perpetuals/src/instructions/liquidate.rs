@@ -10,6 +10,7 @@ use {
             perpetuals::Perpetuals,
             pool::Pool,
             position::{Position, Side},
+            user_stats::{Fill, FillType, UserStats},
         },
     },
     anchor_lang::prelude::*,
@@ -23,14 +24,14 @@ pub struct Liquidate<'info> {
 
     #[account(
         mut,
-        constraint = receiving_account.mint == collateral_custody.mint,
+        constraint = receiving_account.mint == collateral_custody.load()?.mint,
         constraint = receiving_account.owner == position.owner
     )]
     pub receiving_account: Box<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = rewards_receiving_account.mint == collateral_custody.mint,
+        constraint = rewards_receiving_account.mint == collateral_custody.load()?.mint,
         constraint = rewards_receiving_account.owner == signer.key()
     )]
     pub rewards_receiving_account: Box<Account<'info, TokenAccount>>,
@@ -51,10 +52,10 @@ pub struct Liquidate<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
@@ -72,11 +73,11 @@ pub struct Liquidate<'info> {
         mut,
         constraint = position.custody == custody.key()
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the position token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -84,11 +85,11 @@ pub struct Liquidate<'info> {
         mut,
         constraint = position.collateral_custody == collateral_custody.key()
     )]
-    pub collateral_custody: Box<Account<'info, Custody>>,
+    pub collateral_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -96,11 +97,21 @@ pub struct Liquidate<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 collateral_custody.mint.as_ref()],
-        bump = collateral_custody.token_account_bump
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.token_account_bump
     )]
     pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    // if the position owner has stats, the liquidation is recorded
+    #[account(
+        mut,
+        seeds = [b"user_stats",
+                 position.owner.as_ref()],
+        bump = user_stats.bump
+    )]
+    pub user_stats: Option<Box<Account<'info, UserStats>>>,
+
+    system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
 }
 
@@ -111,15 +122,25 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     // check permissions
     msg!("Check permissions");
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let custody = ctx.accounts.custody.as_mut();
-    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    // custody and collateral_custody can be the same account, work on copies
+    // and write them back when the position is updated
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    let mut collateral_custody = Box::new(*ctx.accounts.collateral_custody.load()?);
     require!(
-        perpetuals.permissions.allow_close_position && custody.permissions.allow_close_position,
+        perpetuals.permissions.allow_close_position
+            && pool.permissions.allow_close_position
+            && custody.permissions.allow_close_position,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        pool.status.allows_reduce()
+            && custody.status.allows_reduce()
+            && collateral_custody.status.allows_reduce(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     let position = ctx.accounts.position.as_mut();
-    let pool = ctx.accounts.pool.as_mut();
 
     // check if position can be liquidated
     msg!("Check position state");
@@ -127,14 +148,14 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
 
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
@@ -143,7 +164,7 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         false,
     )?;
@@ -152,7 +173,7 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         collateral_custody.pricing.use_ema,
     )?;
@@ -162,10 +183,10 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
             position,
             &token_price,
             &token_ema_price,
-            custody,
+            &custody,
             &collateral_token_price,
             &collateral_token_ema_price,
-            collateral_custody,
+            &collateral_custody,
             curtime,
             false
         )?,
@@ -177,12 +198,13 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         position,
         &token_price,
         &token_ema_price,
-        custody,
+        &custody,
         &collateral_token_price,
         &collateral_token_ema_price,
-        collateral_custody,
+        &collateral_custody,
         curtime,
         true,
+        Perpetuals::BPS_POWER as u64,
     )?;
 
     let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
@@ -194,6 +216,9 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
     msg!("Collected fee: {}", fee_amount);
 
+    let exit_price =
+        pool.get_exit_price(&token_price, &token_ema_price, position.side, &custody)?;
+
     let reward = Pool::get_fee_amount(custody.fees.liquidation, total_amount_out)?;
     let user_amount = math::checked_sub(total_amount_out, reward)?;
 
@@ -206,7 +231,7 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     // check pool constraints
     msg!("Check pool constraints");
     require!(
-        pool.check_available_amount(total_amount_out, collateral_custody)?,
+        pool.check_available_amount(total_amount_out, &collateral_custody)?,
         PerpetualsError::CustodyAmountLimit
     );
 
@@ -237,7 +262,7 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     collateral_custody.collected_fees.liquidation_usd = collateral_custody
         .collected_fees
         .liquidation_usd
-        .wrapping_add(fee_amount_usd);
+        .saturating_add(fee_amount_usd as u128);
 
     if total_amount_out > position.collateral_amount {
         let amount_lost = total_amount_out.saturating_sub(position.collateral_amount);
@@ -253,10 +278,25 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         position.collateral_amount,
     )?;
 
+    // record realized pnl of the position owner
+    if let Some(user_stats) = ctx.accounts.user_stats.as_mut() {
+        user_stats.record_fill(Fill {
+            fill_type: FillType::Liquidation,
+            side: position.side,
+            custody: position.custody,
+            time: curtime,
+            price: exit_price,
+            size_usd: position.size_usd,
+            fee_usd: fee_amount_usd,
+            profit_usd,
+            loss_usd,
+        });
+    }
+
     let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
 
     // Pay protocol_fee from custody if possible, otherwise no protocol_fee
-    if pool.check_available_amount(protocol_fee, collateral_custody)? {
+    if pool.check_available_amount(protocol_fee, &collateral_custody)? {
         collateral_custody.assets.protocol_fees =
             math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
 
@@ -266,10 +306,10 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
 
     // if custody and collateral_custody accounts are the same, ensure that data is in sync
     if position.side == Side::Long && !custody.is_virtual {
-        collateral_custody.volume_stats.liquidation_usd = math::checked_add(
-            collateral_custody.volume_stats.liquidation_usd,
-            position.size_usd,
-        )?;
+        collateral_custody.volume_stats.liquidation_usd = collateral_custody
+            .volume_stats
+            .liquidation_usd
+            .saturating_add(position.size_usd as u128);
 
         if position.side == Side::Long {
             collateral_custody.trade_stats.oi_long_usd = collateral_custody
@@ -292,12 +332,15 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
             .loss_usd
             .wrapping_add(loss_usd);
 
+        collateral_custody.update_stats_history(curtime, position.size_usd, fee_amount_usd);
         collateral_custody.remove_position(position, curtime, None)?;
         collateral_custody.update_borrow_rate(curtime)?;
-        *custody = collateral_custody.clone();
+        custody = collateral_custody.clone();
     } else {
-        custody.volume_stats.liquidation_usd =
-            math::checked_add(custody.volume_stats.liquidation_usd, position.size_usd)?;
+        custody.volume_stats.liquidation_usd = custody
+            .volume_stats
+            .liquidation_usd
+            .saturating_add(position.size_usd as u128);
 
         if position.side == Side::Long {
             custody.trade_stats.oi_long_usd = custody
@@ -314,9 +357,34 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         custody.trade_stats.profit_usd = custody.trade_stats.profit_usd.wrapping_add(profit_usd);
         custody.trade_stats.loss_usd = custody.trade_stats.loss_usd.wrapping_add(loss_usd);
 
-        custody.remove_position(position, curtime, Some(collateral_custody))?;
+        custody.update_stats_history(curtime, position.size_usd, 0);
+        collateral_custody.update_stats_history(curtime, 0, fee_amount_usd);
+        custody.remove_position(position, curtime, Some(collateral_custody.as_mut()))?;
         collateral_custody.update_borrow_rate(curtime)?;
     }
 
+    // update pool stats
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    if ctx.accounts.custody.key() == ctx.accounts.collateral_custody.key() {
+        collateral_custody = custody.clone();
+    } else {
+        pool.update_custody_aum(
+            &ctx.accounts.collateral_custody.key(),
+            &mut collateral_custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            curtime,
+        )?;
+    }
+
+    *ctx.accounts.custody.load_mut()? = *custody;
+    *ctx.accounts.collateral_custody.load_mut()? = *collateral_custody;
+
     Ok(())
 }
@@ -6,10 +6,13 @@ use {
         math,
         state::{
             custody::Custody,
+            migration::Versioned,
             oracle::OraclePrice,
             perpetuals::Perpetuals,
             pool::Pool,
             position::{Position, Side},
+            referral::{Referral, Referrer},
+            user_stats::{Fill, FillType, UserStats},
         },
     },
     anchor_lang::prelude::*,
@@ -25,7 +28,7 @@ pub struct OpenPosition<'info> {
 
     #[account(
         mut,
-        constraint = funding_account.mint == collateral_custody.mint,
+        constraint = funding_account.mint == collateral_custody.load()?.mint,
         has_one = owner
     )]
     pub funding_account: Box<Account<'info, TokenAccount>>,
@@ -46,10 +49,10 @@ pub struct OpenPosition<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         init,
@@ -68,14 +71,14 @@ pub struct OpenPosition<'info> {
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the position token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -83,14 +86,14 @@ pub struct OpenPosition<'info> {
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 collateral_custody.mint.as_ref()],
-        bump = collateral_custody.bump
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.bump
     )]
-    pub collateral_custody: Box<Account<'info, Custody>>,
+    pub collateral_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -98,11 +101,36 @@ pub struct OpenPosition<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 collateral_custody.mint.as_ref()],
-        bump = collateral_custody.token_account_bump
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.token_account_bump
     )]
     pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    // if provided, the trader gets a fee discount and the referrer is credited a fee share
+    #[account(
+        seeds = [b"referral",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = referral.bump
+    )]
+    pub referral: Option<Box<Account<'info, Referral>>>,
+
+    #[account(
+        mut,
+        constraint = referral.as_ref().map(|x| x.referrer) == Some(referrer.key())
+    )]
+    pub referrer: Option<Box<Account<'info, Referrer>>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = UserStats::LEN,
+        seeds = [b"user_stats",
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
 }
@@ -119,14 +147,24 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     // check permissions
     msg!("Check permissions");
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let custody = ctx.accounts.custody.as_mut();
-    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    // custody and collateral_custody can be the same account, work on copies
+    // and write them back when the position is updated
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    let mut collateral_custody = Box::new(*ctx.accounts.collateral_custody.load()?);
     require!(
         perpetuals.permissions.allow_open_position
+            && pool.permissions.allow_open_position
             && custody.permissions.allow_open_position
             && !custody.is_stable,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        pool.status.allows_increase()
+            && custody.status.allows_increase()
+            && collateral_custody.status.allows_increase(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // validate inputs
     msg!("Validate inputs");
@@ -136,30 +174,51 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     }
     let use_collateral_custody = params.side == Side::Short || custody.is_virtual;
     if use_collateral_custody {
-        require_keys_neq!(custody.key(), collateral_custody.key());
+        require_keys_neq!(
+            ctx.accounts.custody.key(),
+            ctx.accounts.collateral_custody.key()
+        );
         require!(
             collateral_custody.is_stable && !collateral_custody.is_virtual,
             PerpetualsError::InvalidCollateralCustody
         );
     } else {
-        require_keys_eq!(custody.key(), collateral_custody.key());
+        require_keys_eq!(
+            ctx.accounts.custody.key(),
+            ctx.accounts.collateral_custody.key()
+        );
     };
     let position = ctx.accounts.position.as_mut();
-    let pool = ctx.accounts.pool.as_mut();
 
-    // compute position price
     let curtime = perpetuals.get_time()?;
+    require!(
+        { custody.trading_schedule }.is_open(curtime),
+        PerpetualsError::MarketClosed
+    );
+
+    // record user stats, trade volume selects the fee tier
+    let user_stats = ctx.accounts.user_stats.as_mut();
+    if user_stats.owner == Pubkey::default() {
+        user_stats.owner = ctx.accounts.owner.key();
+        user_stats.bump = *ctx
+            .bumps
+            .get("user_stats")
+            .ok_or(ProgramError::InvalidSeeds)?;
+        user_stats.version = UserStats::VERSION;
+    }
+    let fee_mult = perpetuals.get_fee_mult(user_stats.get_volume_usd(curtime));
 
+    // compute position price
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
@@ -168,7 +227,7 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         false,
     )?;
@@ -177,7 +236,7 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         collateral_custody.pricing.use_ema,
     )?;
@@ -186,7 +245,7 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
 
     let position_price =
-        pool.get_entry_price(&token_price, &token_ema_price, params.side, custody)?;
+        pool.get_entry_price(&token_price, &token_ema_price, params.side, &custody)?;
     msg!("Entry price: {}", position_price);
 
     if params.side == Side::Long {
@@ -241,8 +300,15 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         custody.fees.open_position,
         params.size,
         locked_amount,
-        collateral_custody,
+        &collateral_custody,
+        fee_mult,
     )?;
+    let mut referrer_fee_usd = 0;
+    if ctx.accounts.referrer.is_some() {
+        let (discount, referrer_fee) = pool.get_referral_fees(fee_amount)?;
+        fee_amount = math::checked_sub(fee_amount, discount)?;
+        referrer_fee_usd = token_ema_price.get_asset_amount_usd(referrer_fee, custody.decimals)?;
+    }
     let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
     if use_collateral_custody {
         fee_amount = collateral_token_ema_price
@@ -257,9 +323,9 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     // init new position
     msg!("Initialize new position");
     position.owner = ctx.accounts.owner.key();
-    position.pool = pool.key();
-    position.custody = custody.key();
-    position.collateral_custody = collateral_custody.key();
+    position.pool = ctx.accounts.pool.key();
+    position.custody = ctx.accounts.custody.key();
+    position.collateral_custody = ctx.accounts.collateral_custody.key();
     position.open_time = perpetuals.get_time()?;
     position.update_time = 0;
     position.side = params.side;
@@ -276,6 +342,7 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         .bumps
         .get("position")
         .ok_or(ProgramError::InvalidSeeds)?;
+    position.version = Position::VERSION;
 
     // check position risk
     msg!("Check position risks");
@@ -288,10 +355,10 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
             position,
             &token_price,
             &token_ema_price,
-            custody,
+            &custody,
             &collateral_token_price,
             &collateral_token_ema_price,
-            collateral_custody,
+            &collateral_custody,
             curtime,
             true
         )?,
@@ -318,11 +385,30 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     collateral_custody.collected_fees.open_position_usd = collateral_custody
         .collected_fees
         .open_position_usd
-        .wrapping_add(fee_amount_usd);
+        .saturating_add(fee_amount_usd as u128);
 
     collateral_custody.assets.collateral =
         math::checked_add(collateral_custody.assets.collateral, params.collateral)?;
 
+    if let Some(referrer) = ctx.accounts.referrer.as_mut() {
+        referrer.add_trade(size_usd, referrer_fee_usd)?;
+        collateral_custody.referral_fees_usd = math::checked_add(
+            collateral_custody.referral_fees_usd,
+            referrer_fee_usd as u128,
+        )?;
+    }
+    user_stats.add_volume(size_usd, curtime);
+    user_stats.record_fill(Fill {
+        fill_type: FillType::Open,
+        side: params.side,
+        custody: ctx.accounts.custody.key(),
+        time: curtime,
+        price: position_price,
+        size_usd,
+        fee_usd: fee_amount_usd,
+        ..Fill::default()
+    });
+
     let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
     collateral_custody.assets.protocol_fees =
         math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
@@ -332,7 +418,7 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         collateral_custody.volume_stats.open_position_usd = collateral_custody
             .volume_stats
             .open_position_usd
-            .wrapping_add(size_usd);
+            .saturating_add(size_usd as u128);
 
         if params.side == Side::Long {
             collateral_custody.trade_stats.oi_long_usd =
@@ -342,14 +428,15 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
                 math::checked_add(collateral_custody.trade_stats.oi_short_usd, size_usd)?;
         }
 
+        collateral_custody.update_stats_history(curtime, size_usd, fee_amount_usd);
         collateral_custody.add_position(position, &token_ema_price, curtime, None)?;
         collateral_custody.update_borrow_rate(curtime)?;
-        *custody = collateral_custody.clone();
+        custody = collateral_custody.clone();
     } else {
         custody.volume_stats.open_position_usd = custody
             .volume_stats
             .open_position_usd
-            .wrapping_add(size_usd);
+            .saturating_add(size_usd as u128);
 
         if params.side == Side::Long {
             custody.trade_stats.oi_long_usd =
@@ -359,14 +446,39 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
                 math::checked_add(custody.trade_stats.oi_short_usd, size_usd)?;
         }
 
+        custody.update_stats_history(curtime, size_usd, 0);
+        collateral_custody.update_stats_history(curtime, 0, fee_amount_usd);
         custody.add_position(
             position,
             &token_ema_price,
             curtime,
-            Some(collateral_custody),
+            Some(collateral_custody.as_mut()),
         )?;
         collateral_custody.update_borrow_rate(curtime)?;
     }
 
+    // update pool stats
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    if ctx.accounts.custody.key() == ctx.accounts.collateral_custody.key() {
+        collateral_custody = custody.clone();
+    } else {
+        pool.update_custody_aum(
+            &ctx.accounts.collateral_custody.key(),
+            &mut collateral_custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            curtime,
+        )?;
+    }
+
+    *ctx.accounts.custody.load_mut()? = *custody;
+    *ctx.accounts.collateral_custody.load_mut()? = *collateral_custody;
+
     Ok(())
 }
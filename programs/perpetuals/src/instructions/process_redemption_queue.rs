@@ -0,0 +1,305 @@
+//! ProcessRedemptionQueue instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            perpetuals::{MarketStatus, Perpetuals},
+            pool::Pool,
+            redemption_queue::RedemptionQueue,
+            user_deposit::UserDeposit,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct ProcessRedemptionQueue<'info> {
+    pub keeper: Signer<'info>,
+
+    // must be owned by the owner of the first queued request
+    #[account(
+        mut,
+        constraint = receiving_account.mint == custody.load()?.mint
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    // receives escrowed LP tokens if the first request can't be filled,
+    // must be owned by the owner of the first queued request
+    #[account(
+        mut,
+        constraint = lp_token_account.mint == lp_token_mint.key()
+    )]
+    pub lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    /// CHECK: oracle account for the returned token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"redemption_queue",
+                 pool.key().as_ref(),
+                 custody.key().as_ref()],
+        bump = redemption_queue.bump
+    )]
+    pub redemption_queue: Box<Account<'info, RedemptionQueue>>,
+
+    #[account(
+        mut,
+        seeds = [b"redemption_lp_account",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub redemption_lp_account: Box<Account<'info, TokenAccount>>,
+
+    // if provided, burned LP tokens are released from the user's LP cap
+    #[account(
+        mut,
+        constraint = user_deposit.pool == pool.key()
+    )]
+    pub user_deposit: Option<Box<Account<'info, UserDeposit>>>,
+
+    token_program: Program<'info, Token>,
+    // optional remaining accounts, AUM values of these custodies are refreshed:
+    //   custody accounts (write, unsigned)
+    //   custody oracles (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ProcessRedemptionQueueParams {}
+
+pub fn process_redemption_queue(
+    ctx: Context<ProcessRedemptionQueue>,
+    _params: &ProcessRedemptionQueueParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let curtime = perpetuals.get_time()?;
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.update_aum_from_accounts(ctx.remaining_accounts, curtime)?;
+    // custody can be passed in remaining accounts too, work on
+    // a copy loaded after the refresh and write it back at the end
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    require!(
+        perpetuals.permissions.allow_remove_liquidity
+            && pool.permissions.allow_remove_liquidity
+            && !pool.tranches_enabled
+            && custody.permissions.allow_remove_liquidity
+            && !custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        pool.status.allows_reduce() && custody.status.allows_withdrawal(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // validate inputs
+    msg!("Validate inputs");
+    let request = *ctx
+        .accounts
+        .redemption_queue
+        .front()
+        .ok_or(ProgramError::InvalidArgument)?;
+    require_keys_eq!(ctx.accounts.receiving_account.owner, request.owner);
+    require_keys_eq!(ctx.accounts.lp_token_account.owner, request.owner);
+    if let Some(user_deposit) = ctx.accounts.user_deposit.as_ref() {
+        require_keys_eq!(user_deposit.owner, request.owner);
+    }
+    require!(
+        curtime >= math::checked_add(request.request_time, pool.lp_cooldown_sec as i64)?,
+        PerpetualsError::LpCooldown
+    );
+    let token_id = pool.get_token_id(&ctx.accounts.custody.key())?;
+
+    // compute assets under management
+    msg!("Compute assets under management");
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+    )?;
+
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+
+    let max_price = if token_price > token_ema_price {
+        token_price
+    } else {
+        token_ema_price
+    };
+
+    let pool_amount_usd = pool.get_cached_aum_usd(pool.remove_liquidity_aum_mode, curtime)?;
+
+    // compute amount of tokens to return
+    let remove_amount_usd = math::checked_as_u64(math::checked_div(
+        math::checked_mul(pool_amount_usd, request.lp_amount as u128)?,
+        ctx.accounts.lp_token_mint.supply as u128,
+    )?)?;
+
+    let remove_amount = max_price.get_token_amount(remove_amount_usd, custody.decimals)?;
+
+    // calculate fee
+    let fee_amount =
+        pool.get_remove_liquidity_fee(token_id, remove_amount, &custody, &token_ema_price)?;
+    msg!("Collected fee: {}", fee_amount);
+
+    let transfer_amount = math::checked_sub(remove_amount, fee_amount)?;
+    msg!("Amount out: {}", transfer_amount);
+
+    // check pool constraints
+    msg!("Check pool constraints");
+    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+    let withdrawal_amount = math::checked_add(transfer_amount, protocol_fee)?;
+    // requests that can't be filled once funds are unlocked would stall
+    // the queue, they are cancelled and LP tokens are returned instead
+    if transfer_amount < request.min_amount_out
+        || (custody.status != MarketStatus::Settled
+            && !pool.check_token_ratio(
+                token_id,
+                0,
+                withdrawal_amount,
+                &custody,
+                &token_ema_price,
+            )?)
+    {
+        msg!("Cancel request, return LP tokens: {}", request.lp_amount);
+        perpetuals.transfer_tokens(
+            ctx.accounts.redemption_lp_account.to_account_info(),
+            ctx.accounts.lp_token_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            request.lp_amount,
+        )?;
+        ctx.accounts.redemption_queue.pop_front();
+        *ctx.accounts.custody.load_mut()? = *custody;
+        return Ok(());
+    }
+
+    require!(
+        math::checked_sub(custody.assets.owned, custody.assets.locked)? >= withdrawal_amount,
+        PerpetualsError::CustodyAmountLimit
+    );
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        transfer_amount,
+    )?;
+
+    // burn lp tokens
+    msg!("Burn LP tokens");
+    perpetuals.burn_owned_tokens(
+        ctx.accounts.lp_token_mint.to_account_info(),
+        ctx.accounts.redemption_lp_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        request.lp_amount,
+    )?;
+    if let Some(user_deposit) = ctx.accounts.user_deposit.as_mut() {
+        user_deposit.remove_lp_amount(request.lp_amount);
+    }
+    ctx.accounts.redemption_queue.pop_front();
+
+    // update custody stats
+    msg!("Update custody stats");
+    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+    custody.collected_fees.remove_liquidity_usd = custody
+        .collected_fees
+        .remove_liquidity_usd
+        .saturating_add(fee_amount_usd as u128);
+
+    custody.volume_stats.remove_liquidity_usd = custody
+        .volume_stats
+        .remove_liquidity_usd
+        .saturating_add(remove_amount_usd as u128);
+
+    custody.assets.protocol_fees = math::checked_add(custody.assets.protocol_fees, protocol_fee)?;
+
+    custody.assets.owned = math::checked_sub(custody.assets.owned, withdrawal_amount)?;
+
+    custody.update_stats_history(curtime, 0, fee_amount_usd);
+    custody.update_borrow_rate(curtime)?;
+
+    // update pool stats
+    msg!("Update pool stats");
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    *ctx.accounts.custody.load_mut()? = *custody;
+
+    Ok(())
+}
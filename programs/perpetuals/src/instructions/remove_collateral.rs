@@ -25,7 +25,7 @@ pub struct RemoveCollateral<'info> {
 
     #[account(
         mut,
-        constraint = receiving_account.mint == custody.mint,
+        constraint = receiving_account.mint == custody.load()?.mint,
         has_one = owner
     )]
     pub receiving_account: Box<Account<'info, TokenAccount>>,
@@ -46,10 +46,10 @@ pub struct RemoveCollateral<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
@@ -67,11 +67,11 @@ pub struct RemoveCollateral<'info> {
         mut,
         constraint = position.custody == custody.key()
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -79,11 +79,11 @@ pub struct RemoveCollateral<'info> {
         mut,
         constraint = position.collateral_custody == collateral_custody.key()
     )]
-    pub collateral_custody: Box<Account<'info, Custody>>,
+    pub collateral_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the collateral token
     #[account(
-        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.oracle_account
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
 
@@ -91,8 +91,8 @@ pub struct RemoveCollateral<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 collateral_custody.mint.as_ref()],
-        bump = collateral_custody.token_account_bump
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.token_account_bump
     )]
     pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
 
@@ -111,13 +111,23 @@ pub fn remove_collateral(
     // check permissions
     msg!("Check permissions");
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let custody = ctx.accounts.custody.as_mut();
-    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    // custody and collateral_custody can be the same account, work on copies
+    // and write them back when the position is updated
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    let mut collateral_custody = Box::new(*ctx.accounts.collateral_custody.load()?);
     require!(
         perpetuals.permissions.allow_collateral_withdrawal
+            && pool.permissions.allow_collateral_withdrawal
             && custody.permissions.allow_collateral_withdrawal,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        pool.status.allows_increase()
+            && custody.status.allows_increase()
+            && collateral_custody.status.allows_increase(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // validate inputs
     msg!("Validate inputs");
@@ -125,21 +135,20 @@ pub fn remove_collateral(
     if params.collateral_usd == 0 || params.collateral_usd >= position.collateral_usd {
         return Err(ProgramError::InvalidArgument.into());
     }
-    let pool = ctx.accounts.pool.as_mut();
 
     // compute position price
     let curtime = perpetuals.get_time()?;
 
     let token_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         false,
     )?;
 
     let token_ema_price = OraclePrice::new_from_oracle(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
+        &{ custody.oracle },
         curtime,
         custody.pricing.use_ema,
     )?;
@@ -148,7 +157,7 @@ pub fn remove_collateral(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         false,
     )?;
@@ -157,7 +166,7 @@ pub fn remove_collateral(
         &ctx.accounts
             .collateral_custody_oracle_account
             .to_account_info(),
-        &collateral_custody.oracle,
+        &{ collateral_custody.oracle },
         curtime,
         collateral_custody.pricing.use_ema,
     )?;
@@ -189,10 +198,10 @@ pub fn remove_collateral(
             position,
             &token_price,
             &token_ema_price,
-            custody,
+            &custody,
             &collateral_token_price,
             &collateral_token_ema_price,
-            collateral_custody,
+            &collateral_custody,
             curtime,
             true
         )?,
@@ -218,8 +227,31 @@ pub fn remove_collateral(
 
     // if custody and collateral_custody accounts are the same, ensure that data is in sync
     if position.side == Side::Long && !custody.is_virtual {
-        *custody = collateral_custody.clone();
+        custody = collateral_custody.clone();
     }
 
+    // update pool stats
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    if ctx.accounts.custody.key() == ctx.accounts.collateral_custody.key() {
+        collateral_custody = custody.clone();
+    } else {
+        pool.update_custody_aum(
+            &ctx.accounts.collateral_custody.key(),
+            &mut collateral_custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            curtime,
+        )?;
+    }
+
+    *ctx.accounts.custody.load_mut()? = *custody;
+    *ctx.accounts.collateral_custody.load_mut()? = *collateral_custody;
+
     Ok(())
 }
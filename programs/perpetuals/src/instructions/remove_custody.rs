@@ -5,8 +5,8 @@ use {
         error::PerpetualsError,
         state::{
             custody::Custody,
-            multisig::{AdminInstruction, Multisig},
-            perpetuals::Perpetuals,
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            perpetuals::{MarketStatus, Perpetuals},
             pool::{Pool, TokenRatios},
         },
     },
@@ -20,12 +20,18 @@ pub struct RemoveCustody<'info> {
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"multisig"],
-        bump = multisig.load()?.bump
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::PoolAdmin,
+            &pool.key()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     /// CHECK: empty PDA, authority for token accounts
     #[account(
         mut,
@@ -42,32 +48,28 @@ pub struct RemoveCustody<'info> {
 
     #[account(
         mut,
-        realloc = Pool::LEN + (pool.custodies.len() - 1) * std::mem::size_of::<Pubkey>() +
-                              (pool.ratios.len() - 1) * std::mem::size_of::<TokenRatios>(),
-        realloc::payer = admin,
-        realloc::zero = false,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump,
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump,
         close = transfer_authority
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     #[account(
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.token_account_bump,
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.token_account_bump,
     )]
     pub custody_token_account: Box<Account<'info, TokenAccount>>,
 
@@ -85,17 +87,18 @@ pub fn remove_custody<'info>(
     params: &RemoveCustodyParams,
 ) -> Result<u8> {
     // validate inputs
-    if ctx.accounts.pool.ratios.is_empty()
-        || params.ratios.len() != ctx.accounts.pool.ratios.len() - 1
-    {
+    let num_custodies = ctx.accounts.pool.load()?.get_custodies().len();
+    if num_custodies == 0 || params.ratios.len() != num_custodies - 1 {
         return Err(ProgramError::InvalidArgument.into());
     }
 
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::RemoveCustody, params)?,
     )?;
@@ -107,16 +110,31 @@ pub fn remove_custody<'info>(
         return Ok(signatures_left);
     }
 
+    // custody must be empty, liquidity of a settled custody is withdrawn by LPs
+    // and protocol fees by the treasurer with withdraw_fees before it is removed
+    let custody = ctx.accounts.custody.load()?;
+    if custody.status == MarketStatus::Settled {
+        require!(
+            custody.long_positions.open_positions == 0
+                && custody.short_positions.open_positions == 0
+                && custody.assets.collateral == 0
+                && custody.assets.owned == 0
+                && custody.assets.protocol_fees == 0,
+            PerpetualsError::InvalidCustodyState
+        );
+    }
     require!(
         ctx.accounts.custody_token_account.amount == 0,
         PerpetualsError::InvalidCustodyState
     );
 
     // remove token from the list
-    let pool = ctx.accounts.pool.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
     let token_id = pool.get_token_id(&ctx.accounts.custody.key())?;
-    pool.custodies.remove(token_id);
-    pool.ratios = params.ratios.clone();
+    let mut custodies = pool.get_custodies().to_vec();
+    custodies.remove(token_id);
+    pool.set_custodies(&custodies, &params.ratios)?;
+    pool.remove_custody_aum(&custody);
     if !pool.validate() {
         return err!(PerpetualsError::InvalidPoolConfig);
     }
@@ -6,9 +6,10 @@ use {
         math,
         state::{
             custody::Custody,
-            oracle::OraclePrice,
-            perpetuals::Perpetuals,
+            perpetuals::{MarketStatus, Perpetuals},
             pool::{AumCalcMode, Pool},
+            redemption_queue::RedemptionQueue,
+            user_deposit::UserDeposit,
         },
     },
     anchor_lang::prelude::*,
@@ -24,14 +25,16 @@ pub struct RemoveLiquidity<'info> {
 
     #[account(
         mut,
-        constraint = receiving_account.mint == custody.mint,
+        constraint = receiving_account.mint == custody.load()?.mint,
         has_one = owner
     )]
     pub receiving_account: Box<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = lp_token_account.mint == lp_token_mint.key(),
+        constraint = lp_token_account.mint == junior_lp_token_mint
+            .as_ref()
+            .map_or(lp_token_mint.key(), |x| x.key()),
         has_one = owner
     )]
     pub lp_token_account: Box<Account<'info, TokenAccount>>,
@@ -52,23 +55,23 @@ pub struct RemoveLiquidity<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the returned token
     #[account(
-        constraint = custody_oracle_account.key() == custody.oracle.oracle_account
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
     )]
     pub custody_oracle_account: AccountInfo<'info>,
 
@@ -76,8 +79,8 @@ pub struct RemoveLiquidity<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.token_account_bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.token_account_bump
     )]
     pub custody_token_account: Box<Account<'info, TokenAccount>>,
 
@@ -85,14 +88,42 @@ pub struct RemoveLiquidity<'info> {
         mut,
         seeds = [b"lp_token_mint",
                  pool.key().as_ref()],
-        bump = pool.lp_token_bump
+        bump = pool.load()?.lp_token_bump
     )]
     pub lp_token_mint: Box<Account<'info, Mint>>,
 
+    // if provided, junior LP tokens are burned
+    #[account(
+        mut,
+        seeds = [b"junior_lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.junior_lp_token_bump
+    )]
+    pub junior_lp_token_mint: Option<Box<Account<'info, Mint>>>,
+
+    /// CHECK: redemption queue of the custody, can be uninitialized
+    #[account(
+        seeds = [b"redemption_queue",
+                 pool.key().as_ref(),
+                 custody.key().as_ref()],
+        bump
+    )]
+    pub redemption_queue: AccountInfo<'info>,
+
+    // if provided, burned LP tokens are released from the user's LP cap
+    #[account(
+        mut,
+        seeds = [b"user_deposit",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = user_deposit.bump
+    )]
+    pub user_deposit: Option<Box<Account<'info, UserDeposit>>>,
+
     token_program: Program<'info, Token>,
-    // remaining accounts:
-    //   pool.tokens.len() custody accounts (read-only, unsigned)
-    //   pool.tokens.len() custody oracles (read-only, unsigned)
+    // optional remaining accounts, AUM values of these custodies are refreshed:
+    //   custody accounts (write, unsigned)
+    //   custody oracles (read-only, unsigned)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -108,64 +139,97 @@ pub fn remove_liquidity(
     // check permissions
     msg!("Check permissions");
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let custody = ctx.accounts.custody.as_mut();
+    let curtime = perpetuals.get_time()?;
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.update_aum_from_accounts(ctx.remaining_accounts, curtime)?;
+    // custody can be passed in remaining accounts too, work on
+    // a copy loaded after the refresh and write it back at the end
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
     require!(
         perpetuals.permissions.allow_remove_liquidity
+            && pool.permissions.allow_remove_liquidity
             && custody.permissions.allow_remove_liquidity
             && !custody.is_virtual,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        pool.status.allows_reduce() && custody.status.allows_withdrawal(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // validate inputs
     msg!("Validate inputs");
     if params.lp_amount_in == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
-    let pool = ctx.accounts.pool.as_mut();
-    let token_id = pool.get_token_id(&custody.key())?;
+    let is_junior = ctx.accounts.junior_lp_token_mint.is_some();
+    if is_junior && !pool.tranches_enabled {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // queued redemptions are filled first
+    let queued_lp_amount = if Perpetuals::is_empty_account(&ctx.accounts.redemption_queue)? {
+        0
+    } else {
+        Account::<RedemptionQueue>::try_from(&ctx.accounts.redemption_queue)?.get_lp_amount()?
+    };
+    let token_id = pool.get_token_id(&ctx.accounts.custody.key())?;
 
     // compute assets under management
     msg!("Compute assets under management");
-    let curtime = perpetuals.get_time()?;
-
-    // Refresh pool.aum_usm to adapt to token price change
-    pool.aum_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
 
-    let token_price = OraclePrice::new_from_oracle(
+    let token_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         false,
     )?;
 
-    let token_ema_price = OraclePrice::new_from_oracle(
+    let token_ema_price = custody.get_oracle_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
-        &custody.oracle,
         curtime,
         custody.pricing.use_ema,
     )?;
 
+    // Refresh pool.aum_usd to adapt to token price change
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+
     let max_price = if token_price > token_ema_price {
         token_price
     } else {
         token_ema_price
     };
 
-    let pool_amount_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::Min, ctx.remaining_accounts, curtime)?;
+    // tranches are tracked at the EMA value, shares of the pool value
+    // in other AUM modes are split pro rata
+    let aum_usd = pool.get_cached_aum_usd(AumCalcMode::EMA, curtime)?;
+    pool.update_tranches(aum_usd)?;
+    let pool_aum_usd = pool.get_cached_aum_usd(pool.remove_liquidity_aum_mode, curtime)?;
+    let (senior_amount_usd, junior_amount_usd) = pool.get_tranche_shares_usd(pool_aum_usd)?;
+
+    let (lp_token_mint, pool_amount_usd) =
+        if let Some(junior_lp_token_mint) = ctx.accounts.junior_lp_token_mint.as_ref() {
+            (junior_lp_token_mint, junior_amount_usd)
+        } else {
+            (&ctx.accounts.lp_token_mint, senior_amount_usd)
+        };
 
     // compute amount of tokens to return
     let remove_amount_usd = math::checked_as_u64(math::checked_div(
         math::checked_mul(pool_amount_usd, params.lp_amount_in as u128)?,
-        ctx.accounts.lp_token_mint.supply as u128,
+        lp_token_mint.supply as u128,
     )?)?;
 
     let remove_amount = max_price.get_token_amount(remove_amount_usd, custody.decimals)?;
 
     // calculate fee
     let fee_amount =
-        pool.get_remove_liquidity_fee(token_id, remove_amount, custody, &token_ema_price)?;
+        pool.get_remove_liquidity_fee(token_id, remove_amount, &custody, &token_ema_price)?;
     msg!("Collected fee: {}", fee_amount);
 
     let transfer_amount = math::checked_sub(remove_amount, fee_amount)?;
@@ -180,16 +244,38 @@ pub fn remove_liquidity(
     msg!("Check pool constraints");
     let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
     let withdrawal_amount = math::checked_add(transfer_amount, protocol_fee)?;
+    // settled custodies can be drained regardless of the target ratio
     require!(
-        pool.check_token_ratio(token_id, 0, withdrawal_amount, custody, &token_ema_price)?,
+        custody.status == MarketStatus::Settled
+            || pool.check_token_ratio(
+                token_id,
+                0,
+                withdrawal_amount,
+                &custody,
+                &token_ema_price
+            )?,
         PerpetualsError::TokenRatioOutOfRange
     );
 
+    let free_amount = math::checked_sub(custody.assets.owned, custody.assets.locked)?;
     require!(
-        math::checked_sub(custody.assets.owned, custody.assets.locked)? >= withdrawal_amount,
+        free_amount >= withdrawal_amount,
         PerpetualsError::CustodyAmountLimit
     );
 
+    // free liquidity is reserved for queued redemptions first
+    if queued_lp_amount > 0 {
+        let queued_amount_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(senior_amount_usd, queued_lp_amount as u128)?,
+            ctx.accounts.lp_token_mint.supply as u128,
+        )?)?;
+        let queued_amount = max_price.get_token_amount(queued_amount_usd, custody.decimals)?;
+        require!(
+            free_amount.saturating_sub(queued_amount) >= withdrawal_amount,
+            PerpetualsError::RedemptionQueueNotEmpty
+        );
+    }
+
     // transfer tokens
     msg!("Transfer tokens");
     perpetuals.transfer_tokens(
@@ -203,36 +289,55 @@ pub fn remove_liquidity(
     // burn lp tokens
     msg!("Burn LP tokens");
     perpetuals.burn_tokens(
-        ctx.accounts.lp_token_mint.to_account_info(),
+        lp_token_mint.to_account_info(),
         ctx.accounts.lp_token_account.to_account_info(),
         ctx.accounts.owner.to_account_info(),
         ctx.accounts.token_program.to_account_info(),
         params.lp_amount_in,
     )?;
+    if let Some(user_deposit) = ctx.accounts.user_deposit.as_mut() {
+        user_deposit.remove_lp_amount(params.lp_amount_in);
+    }
 
     // update custody stats
     msg!("Update custody stats");
+    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
     custody.collected_fees.remove_liquidity_usd = custody
         .collected_fees
         .remove_liquidity_usd
-        .wrapping_add(token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?);
+        .saturating_add(fee_amount_usd as u128);
 
     custody.volume_stats.remove_liquidity_usd = custody
         .volume_stats
         .remove_liquidity_usd
-        .wrapping_add(remove_amount_usd);
+        .saturating_add(remove_amount_usd as u128);
 
     custody.assets.protocol_fees = math::checked_add(custody.assets.protocol_fees, protocol_fee)?;
 
     custody.assets.owned = math::checked_sub(custody.assets.owned, withdrawal_amount)?;
 
+    custody.update_stats_history(curtime, 0, fee_amount_usd);
     custody.update_borrow_rate(curtime)?;
 
     // update pool stats
     msg!("Update pool stats");
-    custody.exit(&crate::ID)?;
-    pool.aum_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    *ctx.accounts.custody.load_mut()? = *custody;
+
+    if pool.tranches_enabled {
+        if !is_junior {
+            pool.senior_aum_usd = pool.senior_aum_usd.saturating_sub(
+                pool.get_tranche_value_usd(remove_amount_usd as u128, pool_aum_usd)?,
+            );
+        }
+        pool.tranche_aum_usd = pool.aum_usd;
+    }
 
     Ok(())
 }
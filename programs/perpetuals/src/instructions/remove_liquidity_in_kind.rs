@@ -0,0 +1,304 @@
+//! RemoveLiquidityInKind instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            perpetuals::{MarketStatus, Perpetuals},
+            pool::Pool,
+            redemption_queue::RedemptionQueue,
+            user_deposit::UserDeposit,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: RemoveLiquidityInKindParams)]
+pub struct RemoveLiquidityInKind<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = lp_token_account.mint == lp_token_mint.key(),
+        has_one = owner
+    )]
+    pub lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    // if provided, burned LP tokens are released from the user's LP cap
+    #[account(
+        mut,
+        seeds = [b"user_deposit",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = user_deposit.bump
+    )]
+    pub user_deposit: Option<Box<Account<'info, UserDeposit>>>,
+
+    token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   pool.tokens.len() custody accounts (writable, unsigned)
+    //   pool.tokens.len() custody oracles (read-only, unsigned)
+    //   pool.tokens.len() custody redemption queues (read-only, unsigned, can be uninitialized)
+    //   for each non-virtual custody, in pool order:
+    //     receiving account (writable, unsigned)
+    //     custody token account (writable, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveLiquidityInKindParams {
+    pub lp_amount_in: u64,
+    // one per pool token, ignored for virtual custodies
+    pub min_amounts_out: Vec<u64>,
+}
+
+pub fn remove_liquidity_in_kind<'info>(
+    ctx: Context<'_, '_, '_, 'info, RemoveLiquidityInKind<'info>>,
+    params: &RemoveLiquidityInKindParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    require!(
+        perpetuals.permissions.allow_remove_liquidity
+            && pool.permissions.allow_remove_liquidity
+            && !pool.tranches_enabled,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        pool.status.allows_reduce(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // validate inputs
+    msg!("Validate inputs");
+    let pool_tokens = pool.get_custodies().len();
+    if params.lp_amount_in == 0 || params.min_amounts_out.len() != pool_tokens {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    if ctx.remaining_accounts.len() < pool_tokens * 3 {
+        return Err(ProgramError::NotEnoughAccountKeys.into());
+    }
+    let curtime = perpetuals.get_time()?;
+
+    // custody accounts must follow the pool order,
+    // their queued redemptions are filled first
+    let pool_key = ctx.accounts.pool.key();
+    let custodies = pool.get_custodies().to_vec();
+    let mut queued_lp_amounts = vec![0u64; pool_tokens];
+    for (token_id, custody_key) in custodies.iter().enumerate() {
+        let custody_info = &ctx.remaining_accounts[token_id];
+        require_keys_eq!(custody_info.key(), *custody_key);
+        require!(custody_info.is_writable, ErrorCode::ConstraintMut);
+
+        let redemption_queue_info = &ctx.remaining_accounts[token_id + pool_tokens * 2];
+        let (redemption_queue_key, _) = Pubkey::find_program_address(
+            &[b"redemption_queue", pool_key.as_ref(), custody_key.as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(redemption_queue_info.key(), redemption_queue_key);
+        if !Perpetuals::is_empty_account(redemption_queue_info)? {
+            queued_lp_amounts[token_id] =
+                Account::<RedemptionQueue>::try_from(redemption_queue_info)?.get_lp_amount()?;
+        }
+    }
+
+    // compute assets under management
+    msg!("Compute assets under management");
+    pool.update_aum_from_accounts(&ctx.remaining_accounts[..pool_tokens * 2], curtime)?;
+
+    let aum_calc_mode = pool.remove_liquidity_aum_mode;
+    let pool_amount_usd = pool.get_cached_aum_usd(aum_calc_mode, curtime)?;
+    let remove_amount_usd = math::checked_div(
+        math::checked_mul(pool_amount_usd, params.lp_amount_in as u128)?,
+        ctx.accounts.lp_token_mint.supply as u128,
+    )?;
+
+    // each custody pays its share of the pool value, unrealized pnl included,
+    // value of virtual custodies is split between the others
+    let mut tokens_aum_usd: u128 = 0;
+    for custody_info in &ctx.remaining_accounts[..pool_tokens] {
+        let custody = AccountLoader::<Custody>::try_from(custody_info)?;
+        let custody = custody.load()?;
+        if !custody.is_virtual {
+            tokens_aum_usd =
+                math::checked_add(tokens_aum_usd, custody.aum_usd[aum_calc_mode as usize])?;
+        }
+    }
+
+    let mut token_accounts_idx = pool_tokens * 3;
+    for (token_id, custody_key) in custodies.iter().enumerate() {
+        let custody_info = &ctx.remaining_accounts[token_id];
+        let custody_account = AccountLoader::<Custody>::try_from(custody_info)?;
+        if custody_account.load()?.is_virtual {
+            continue;
+        }
+        let mut custody = custody_account.load_mut()?;
+        require!(
+            custody.permissions.allow_remove_liquidity,
+            PerpetualsError::InstructionNotAllowed
+        );
+        require!(
+            custody.status.allows_withdrawal(),
+            PerpetualsError::MarketStatusRestricted
+        );
+
+        if ctx.remaining_accounts.len() < token_accounts_idx + 2 {
+            return Err(ProgramError::NotEnoughAccountKeys.into());
+        }
+        let receiving_account_info = &ctx.remaining_accounts[token_accounts_idx];
+        let custody_token_account = &ctx.remaining_accounts[token_accounts_idx + 1];
+        token_accounts_idx += 2;
+
+        let receiving_account = Account::<TokenAccount>::try_from(receiving_account_info)?;
+        require_keys_eq!(receiving_account.mint, custody.mint);
+        require_keys_eq!(receiving_account.owner, ctx.accounts.owner.key());
+        require_keys_eq!(custody_token_account.key(), custody.token_account);
+
+        let oracle_account = &ctx.remaining_accounts[token_id + pool_tokens];
+        require_keys_eq!(oracle_account.key(), custody.oracle.oracle_account);
+        let token_price = custody.get_oracle_price(oracle_account, curtime, false)?;
+        let token_ema_price =
+            custody.get_oracle_price(oracle_account, curtime, custody.pricing.use_ema)?;
+
+        let max_price = if token_price > token_ema_price {
+            token_price
+        } else {
+            token_ema_price
+        };
+
+        // compute pro-rata share of the custody value
+        let custody_amount_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(remove_amount_usd, custody.aum_usd[aum_calc_mode as usize])?,
+            tokens_aum_usd,
+        )?)?;
+        let remove_amount = max_price.get_token_amount(custody_amount_usd, custody.decimals)?;
+
+        // pool composition doesn't change, so only the base fee is charged
+        let fee_amount = if custody.status == MarketStatus::Settled {
+            0
+        } else {
+            Pool::get_fee_amount(custody.fees.remove_liquidity, remove_amount)?
+        };
+        msg!("Collected fee: {}", fee_amount);
+
+        let transfer_amount = math::checked_sub(remove_amount, fee_amount)?;
+        msg!("Amount out: {}", transfer_amount);
+
+        require!(
+            transfer_amount >= params.min_amounts_out[token_id],
+            PerpetualsError::MaxPriceSlippage
+        );
+
+        let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+        let withdrawal_amount = math::checked_add(transfer_amount, protocol_fee)?;
+        let free_amount = math::checked_sub(custody.assets.owned, custody.assets.locked)?;
+        require!(
+            free_amount >= withdrawal_amount,
+            PerpetualsError::CustodyAmountLimit
+        );
+
+        // free liquidity is reserved for queued redemptions first
+        if queued_lp_amounts[token_id] > 0 {
+            let queued_amount_usd = math::checked_as_u64(math::checked_div(
+                math::checked_mul(pool_amount_usd, queued_lp_amounts[token_id] as u128)?,
+                ctx.accounts.lp_token_mint.supply as u128,
+            )?)?;
+            let queued_amount = max_price.get_token_amount(queued_amount_usd, custody.decimals)?;
+            require!(
+                free_amount.saturating_sub(queued_amount) >= withdrawal_amount,
+                PerpetualsError::RedemptionQueueNotEmpty
+            );
+        }
+
+        // transfer tokens
+        msg!("Transfer tokens");
+        perpetuals.transfer_tokens(
+            custody_token_account.clone(),
+            receiving_account_info.clone(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            transfer_amount,
+        )?;
+
+        // update custody stats
+        msg!("Update custody stats");
+        let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+        custody.collected_fees.remove_liquidity_usd = custody
+            .collected_fees
+            .remove_liquidity_usd
+            .saturating_add(fee_amount_usd as u128);
+
+        custody.volume_stats.remove_liquidity_usd = custody
+            .volume_stats
+            .remove_liquidity_usd
+            .saturating_add(custody_amount_usd as u128);
+
+        custody.assets.protocol_fees =
+            math::checked_add(custody.assets.protocol_fees, protocol_fee)?;
+
+        custody.assets.owned = math::checked_sub(custody.assets.owned, withdrawal_amount)?;
+
+        custody.update_stats_history(curtime, 0, fee_amount_usd);
+        custody.update_borrow_rate(curtime)?;
+
+        // update pool stats
+        pool.update_custody_aum(
+            custody_key,
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            curtime,
+        )?;
+    }
+
+    // burn lp tokens
+    msg!("Burn LP tokens");
+    perpetuals.burn_tokens(
+        ctx.accounts.lp_token_mint.to_account_info(),
+        ctx.accounts.lp_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.lp_amount_in,
+    )?;
+    if let Some(user_deposit) = ctx.accounts.user_deposit.as_mut() {
+        user_deposit.remove_lp_amount(params.lp_amount_in);
+    }
+
+    Ok(())
+}
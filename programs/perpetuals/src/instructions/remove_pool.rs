@@ -5,7 +5,7 @@ use {
         error::PerpetualsError,
         state::{
             multisig::{AdminInstruction, Multisig},
-            perpetuals::Perpetuals,
+            perpetuals::{FeeTier, Perpetuals},
             pool::Pool,
         },
     },
@@ -18,12 +18,15 @@ pub struct RemovePool<'info> {
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
         seeds = [b"multisig"],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     /// CHECK: empty PDA, authority for token accounts
     #[account(
         mut,
@@ -34,7 +37,8 @@ pub struct RemovePool<'info> {
 
     #[account(
         mut,
-        realloc = Perpetuals::LEN + (perpetuals.pools.len() - 1) * 32,
+        realloc = Perpetuals::LEN + (perpetuals.pools.len() - 1) * 32 +
+                                    perpetuals.fee_tiers.len() * std::mem::size_of::<FeeTier>(),
         realloc::payer = admin,
         realloc::zero = false,
         seeds = [b"perpetuals"],
@@ -45,11 +49,11 @@ pub struct RemovePool<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump,
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump,
         close = transfer_authority
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     system_program: Program<'info, System>,
 }
@@ -62,10 +66,12 @@ pub fn remove_pool<'info>(
     params: &RemovePoolParams,
 ) -> Result<u8> {
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::RemovePool, params)?,
     )?;
@@ -78,7 +84,7 @@ pub fn remove_pool<'info>(
     }
 
     require!(
-        ctx.accounts.pool.custodies.is_empty(),
+        ctx.accounts.pool.load()?.get_custodies().is_empty(),
         PerpetualsError::InvalidPoolState
     );
 
@@ -0,0 +1,222 @@
+//! RequestRedemption instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            migration::Versioned,
+            perpetuals::Perpetuals,
+            pool::Pool,
+            redemption_queue::{RedemptionQueue, RedemptionRequest},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: RequestRedemptionParams)]
+pub struct RequestRedemption<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = lp_token_account.mint == lp_token_mint.key(),
+        has_one = owner
+    )]
+    pub lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    /// CHECK: oracle account for the returned token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = RedemptionQueue::LEN,
+        seeds = [b"redemption_queue",
+                 pool.key().as_ref(),
+                 custody.key().as_ref()],
+        bump
+    )]
+    pub redemption_queue: Box<Account<'info, RedemptionQueue>>,
+
+    // holds LP tokens of queued redemptions
+    #[account(
+        init_if_needed,
+        payer = owner,
+        token::mint = lp_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"redemption_lp_account",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub redemption_lp_account: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+    // optional remaining accounts, AUM values of these custodies are refreshed:
+    //   custody accounts (write, unsigned)
+    //   custody oracles (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RequestRedemptionParams {
+    pub lp_amount_in: u64,
+    pub min_amount_out: u64,
+}
+
+pub fn request_redemption(
+    ctx: Context<RequestRedemption>,
+    params: &RequestRedemptionParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    // LP cooldown is counted from the request time, escrowed tokens
+    // can't be moved to a wallet with an older deposit
+    let curtime = perpetuals.get_time()?;
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.update_aum_from_accounts(ctx.remaining_accounts, curtime)?;
+    // custody can be passed in remaining accounts too, work on
+    // a copy loaded after the refresh and write it back at the end
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    require!(
+        perpetuals.permissions.allow_remove_liquidity
+            && pool.permissions.allow_remove_liquidity
+            && !pool.tranches_enabled
+            && custody.permissions.allow_remove_liquidity
+            && !custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        pool.status.allows_reduce() && custody.status.allows_withdrawal(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // validate inputs
+    msg!("Validate inputs");
+    if params.lp_amount_in == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // compute assets under management
+    msg!("Compute assets under management");
+    let token_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        false,
+    )?;
+
+    let token_ema_price = custody.get_oracle_price(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        curtime,
+        custody.pricing.use_ema,
+    )?;
+
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_ema_price,
+        curtime,
+    )?;
+    *ctx.accounts.custody.load_mut()? = *custody;
+
+    let max_price = if token_price > token_ema_price {
+        token_price
+    } else {
+        token_ema_price
+    };
+
+    let redemption_queue = ctx.accounts.redemption_queue.as_mut();
+    if redemption_queue.pool == Pubkey::default() {
+        redemption_queue.pool = ctx.accounts.pool.key();
+        redemption_queue.custody = ctx.accounts.custody.key();
+        redemption_queue.bump = *ctx
+            .bumps
+            .get("redemption_queue")
+            .ok_or(ProgramError::InvalidSeeds)?;
+        redemption_queue.version = RedemptionQueue::VERSION;
+    }
+
+    // requests are only queued if free liquidity, after the requests
+    // ahead in the queue, can't cover them
+    let pool_amount_usd = pool.get_cached_aum_usd(pool.remove_liquidity_aum_mode, curtime)?;
+    let queued_lp_amount =
+        math::checked_add(redemption_queue.get_lp_amount()?, params.lp_amount_in)?;
+    let queued_amount_usd = math::checked_as_u64(math::checked_div(
+        math::checked_mul(pool_amount_usd, queued_lp_amount as u128)?,
+        ctx.accounts.lp_token_mint.supply as u128,
+    )?)?;
+    let queued_amount = max_price.get_token_amount(queued_amount_usd, custody.decimals)?;
+    require!(
+        math::checked_sub(custody.assets.owned, custody.assets.locked)? < queued_amount,
+        PerpetualsError::LiquidityAvailable
+    );
+
+    // record new request
+    redemption_queue.push(RedemptionRequest {
+        owner: ctx.accounts.owner.key(),
+        lp_amount: params.lp_amount_in,
+        min_amount_out: params.min_amount_out,
+        request_time: curtime,
+    })?;
+    msg!("Queue position: {}", redemption_queue.requests.len());
+
+    // move lp tokens to escrow
+    msg!("Transfer LP tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.lp_token_account.to_account_info(),
+        ctx.accounts.redemption_lp_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.lp_amount_in,
+    )?;
+
+    Ok(())
+}
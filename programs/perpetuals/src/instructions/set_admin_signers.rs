@@ -7,21 +7,29 @@ use {
 
 #[derive(Accounts)]
 pub struct SetAdminSigners<'info> {
-    #[account()]
+    #[account(mut)]
     pub admin: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"multisig"], 
+        seeds = [b"multisig"],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
     // remaining accounts: 1 to Multisig::MAX_SIGNERS admin signers (read-only, unsigned)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct SetAdminSignersParams {
-    pub min_signatures: u8,
+    // signer weights in the order of remaining accounts, 1 for all signers if empty
+    pub weights: Vec<u8>,
+    pub min_weight: u8,
 }
 
 pub fn set_admin_signers<'info>(
@@ -33,6 +41,8 @@ pub fn set_admin_signers<'info>(
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::SetAdminSigners, params)?,
     )?;
@@ -45,7 +55,7 @@ pub fn set_admin_signers<'info>(
     }
 
     // set new admin signers
-    multisig.set_signers(ctx.remaining_accounts, params.min_signatures)?;
+    multisig.set_signers(ctx.remaining_accounts, &params.weights, params.min_weight)?;
 
     Ok(0)
 }
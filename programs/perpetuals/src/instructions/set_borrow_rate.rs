@@ -0,0 +1,133 @@
+//! SetBorrowRate instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::CustodyConfigUpdated,
+        state::{
+            config_bounds::ConfigBounds,
+            custody::{BorrowRateParams, Custody},
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetBorrowRate<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::RiskManager,
+            &Pubkey::default()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"config_bounds"],
+        bump = config_bounds.bump
+    )]
+    pub config_bounds: Box<Account<'info, ConfigBounds>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SetBorrowRateParams {
+    pub borrow_rate: BorrowRateParams,
+}
+
+pub fn set_borrow_rate<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetBorrowRate<'info>>,
+    params: &SetBorrowRateParams,
+) -> Result<u8> {
+    // validate inputs
+    if !params.borrow_rate.validate() {
+        return err!(PerpetualsError::InvalidCustodyConfig);
+    }
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetBorrowRate, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // check governance bounds
+    if !ctx
+        .accounts
+        .config_bounds
+        .custody
+        .borrow_rate
+        .contains(&params.borrow_rate)
+    {
+        return err!(PerpetualsError::ConfigOutOfBounds);
+    }
+
+    // settle interest accrued under the current borrow rate params
+    let mut custody = ctx.accounts.custody.load_mut()?;
+    let old_config = custody.get_config();
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    custody.update_borrow_rate(curtime)?;
+
+    // update borrow rate params and recompute the current rate
+    msg!("Set borrow rate params");
+    custody.borrow_rate = params.borrow_rate;
+    custody.update_borrow_rate(curtime)?;
+    let new_config = custody.get_config();
+    let ratios = ctx.accounts.pool.load()?.get_ratios();
+
+    emit!(CustodyConfigUpdated {
+        pool: ctx.accounts.pool.key(),
+        custody: ctx.accounts.custody.key(),
+        old_config,
+        new_config,
+        old_ratios: ratios.clone(),
+        new_ratios: ratios,
+    });
+
+    Ok(0)
+}
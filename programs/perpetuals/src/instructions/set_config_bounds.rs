@@ -0,0 +1,90 @@
+//! SetConfigBounds instruction handler
+
+use {
+    crate::{
+        events::ConfigBoundsUpdated,
+        state::{
+            config_bounds::{ConfigBounds, CustodyBounds},
+            migration::Versioned,
+            multisig::{AdminInstruction, Multisig},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetConfigBounds<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = ConfigBounds::LEN,
+        seeds = [b"config_bounds"],
+        bump
+    )]
+    pub config_bounds: Box<Account<'info, ConfigBounds>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SetConfigBoundsParams {
+    pub custody: CustodyBounds,
+}
+
+pub fn set_config_bounds<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetConfigBounds<'info>>,
+    params: &SetConfigBoundsParams,
+) -> Result<u8> {
+    // validate inputs
+    if !params.custody.validate() {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetConfigBounds, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // update config bounds
+    let config_bounds = ctx.accounts.config_bounds.as_mut();
+
+    emit!(ConfigBoundsUpdated {
+        old_bounds: config_bounds.custody,
+        new_bounds: params.custody,
+    });
+
+    config_bounds.custody = params.custody;
+    config_bounds.bump = *ctx
+        .bumps
+        .get("config_bounds")
+        .ok_or(ProgramError::InvalidSeeds)?;
+    config_bounds.version = ConfigBounds::VERSION;
+
+    Ok(0)
+}
@@ -3,6 +3,7 @@
 use {
     crate::{
         error::PerpetualsError,
+        events::CustodyConfigUpdated,
         state::{
             custody::{BorrowRateParams, Custody, Fees, PricingParams},
             multisig::{AdminInstruction, Multisig},
@@ -16,32 +17,37 @@ use {
 
 #[derive(Accounts)]
 pub struct SetCustodyConfig<'info> {
-    #[account()]
+    #[account(mut)]
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
         seeds = [b"multisig"],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
+                 custody.load()?.mint.as_ref()],
         bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
+
+    system_program: Program<'info, System>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
@@ -61,15 +67,17 @@ pub fn set_custody_config<'info>(
     params: &SetCustodyConfigParams,
 ) -> Result<u8> {
     // validate inputs
-    if params.ratios.len() != ctx.accounts.pool.ratios.len() {
+    if params.ratios.len() != ctx.accounts.pool.load()?.get_custodies().len() {
         return Err(ProgramError::InvalidArgument.into());
     }
 
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::SetCustodyConfig, params)?,
     )?;
@@ -82,14 +90,16 @@ pub fn set_custody_config<'info>(
     }
 
     // update pool data
-    let pool = ctx.accounts.pool.as_mut();
-    pool.ratios = params.ratios.clone();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let old_ratios = pool.get_ratios();
+    pool.set_ratios(&params.ratios)?;
     if !pool.validate() {
         return err!(PerpetualsError::InvalidPoolConfig);
     }
 
     // update custody data
-    let custody = ctx.accounts.custody.as_mut();
+    let mut custody = ctx.accounts.custody.load_mut()?;
+    let old_config = custody.get_config();
     custody.is_stable = params.is_stable;
     custody.is_virtual = params.is_virtual;
     custody.oracle = params.oracle;
@@ -99,8 +109,18 @@ pub fn set_custody_config<'info>(
     custody.borrow_rate = params.borrow_rate;
 
     if !custody.validate() {
-        err!(PerpetualsError::InvalidCustodyConfig)
-    } else {
-        Ok(0)
+        return err!(PerpetualsError::InvalidCustodyConfig);
     }
+    let new_config = custody.get_config();
+
+    emit!(CustodyConfigUpdated {
+        pool: ctx.accounts.pool.key(),
+        custody: ctx.accounts.custody.key(),
+        old_config,
+        new_config,
+        old_ratios,
+        new_ratios: params.ratios.clone(),
+    });
+
+    Ok(0)
 }
@@ -1,12 +1,15 @@
 //! SetCustomOraclePrice instruction handler
 
 use {
-    crate::state::{
-        custody::Custody,
-        multisig::{AdminInstruction, Multisig},
-        oracle::CustomOracle,
-        perpetuals::Perpetuals,
-        pool::Pool,
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            oracle::CustomOracle,
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
     },
     anchor_lang::prelude::*,
 };
@@ -17,12 +20,18 @@ pub struct SetCustomOraclePrice<'info> {
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"multisig"],
-        bump = multisig.load()?.bump
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::PoolAdmin,
+            &pool.key()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     #[account(
         seeds = [b"perpetuals"],
         bump = perpetuals.perpetuals_bump
@@ -31,27 +40,27 @@ pub struct SetCustomOraclePrice<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     #[account(
         init_if_needed,
         payer = admin,
         space = CustomOracle::LEN,
-        //constraint = oracle_account.key() == custody.oracle.oracle_account,
+        //constraint = oracle_account.key() == custody.load()?.oracle.oracle_account,
         seeds = [b"oracle_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
+                 custody.load()?.mint.as_ref()],
         bump
     )]
     pub oracle_account: Box<Account<'info, CustomOracle>>,
@@ -73,10 +82,12 @@ pub fn set_custom_oracle_price<'info>(
     params: &SetCustomOraclePriceParams,
 ) -> Result<u8> {
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::SetCustomOraclePrice, params)?,
     )?;
@@ -20,26 +20,26 @@ pub struct SetCustomOraclePricePermissionless<'info> {
 
     #[account(
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
+                 custody.load()?.mint.as_ref()],
         constraint = custody.key() == params.custody_account,
-        bump = custody.bump
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     #[account(
         // Custom oracle must first be initialized by authority before permissionless updates.
         mut,
         seeds = [b"oracle_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
+                 custody.load()?.mint.as_ref()],
         bump
     )]
     pub oracle_account: Box<Account<'info, CustomOracle>>,
@@ -73,7 +73,7 @@ pub fn set_custom_oracle_price_permissionless(
 
     validate_ed25519_signature_instruction(
         &signature_ix,
-        &ctx.accounts.custody.oracle.oracle_authority,
+        &{ ctx.accounts.custody.load()?.oracle.oracle_authority },
         params,
     )?;
 
@@ -0,0 +1,80 @@
+//! SetFeeTiers instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::{FeeTier, Perpetuals},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+#[instruction(params: SetFeeTiersParams)]
+pub struct SetFeeTiers<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        realloc = Perpetuals::LEN + perpetuals.pools.len() * std::mem::size_of::<Pubkey>() +
+                                    params.fee_tiers.len() * std::mem::size_of::<FeeTier>(),
+        realloc::payer = admin,
+        realloc::zero = false,
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetFeeTiersParams {
+    pub fee_tiers: Vec<FeeTier>,
+}
+
+pub fn set_fee_tiers<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetFeeTiers<'info>>,
+    params: &SetFeeTiersParams,
+) -> Result<u8> {
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetFeeTiers, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // update fee tiers
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    perpetuals.fee_tiers = params.fee_tiers.clone();
+
+    if !perpetuals.validate() {
+        err!(PerpetualsError::InvalidPerpetualsConfig)
+    } else {
+        Ok(0)
+    }
+}
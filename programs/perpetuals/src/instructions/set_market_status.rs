@@ -0,0 +1,111 @@
+//! SetMarketStatus instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            perpetuals::MarketStatus,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetMarketStatus<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::Guardian,
+            &Pubkey::default()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    // if not provided, the status is set for the whole pool
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: Option<AccountLoader<'info, Custody>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetMarketStatusParams {
+    pub status: MarketStatus,
+}
+
+pub fn set_market_status<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetMarketStatus<'info>>,
+    params: &SetMarketStatusParams,
+) -> Result<u8> {
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetMarketStatus, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // update market status
+    let mut custody = ctx
+        .accounts
+        .custody
+        .as_ref()
+        .map(|x| x.load_mut())
+        .transpose()?;
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let status = if let Some(custody) = custody.as_mut() {
+        &mut custody.status
+    } else {
+        &mut pool.status
+    };
+
+    // custodies are settled with a frozen price by settle_custody, and can't be reopened
+    if params.status == MarketStatus::Settled || *status == MarketStatus::Settled {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // guardian can only make the market more restrictive
+    if !multisig.is_root(&ctx.accounts.multisig.key()) && params.status < *status {
+        msg!("Error: Only root multisig can relax market status");
+        return err!(PerpetualsError::MultisigAccountNotAuthorized);
+    }
+
+    msg!("Set market status: {:?} -> {:?}", *status, params.status);
+    *status = params.status;
+
+    Ok(0)
+}
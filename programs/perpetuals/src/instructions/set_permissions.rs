@@ -4,8 +4,8 @@ use {
     crate::{
         error::PerpetualsError,
         state::{
-            multisig::{AdminInstruction, Multisig},
-            perpetuals::Perpetuals,
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            perpetuals::{Permissions, Perpetuals},
         },
     },
     anchor_lang::prelude::*,
@@ -13,22 +13,30 @@ use {
 
 #[derive(Accounts)]
 pub struct SetPermissions<'info> {
-    #[account()]
+    #[account(mut)]
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"multisig"],
-        bump = multisig.load()?.bump
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::Guardian,
+            &Pubkey::default()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     #[account(
         mut,
         seeds = [b"perpetuals"],
         bump = perpetuals.perpetuals_bump
     )]
     pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    system_program: Program<'info, System>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -48,10 +56,12 @@ pub fn set_permissions<'info>(
     params: &SetPermissionsParams,
 ) -> Result<u8> {
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::SetPermissions, params)?,
     )?;
@@ -63,16 +73,28 @@ pub fn set_permissions<'info>(
         return Ok(signatures_left);
     }
 
-    // update permissions
+    // role multisigs are only allowed to pause, i.e. disable permissions
+    let permissions = Permissions {
+        allow_swap: params.allow_swap,
+        allow_add_liquidity: params.allow_add_liquidity,
+        allow_remove_liquidity: params.allow_remove_liquidity,
+        allow_open_position: params.allow_open_position,
+        allow_close_position: params.allow_close_position,
+        allow_pnl_withdrawal: params.allow_pnl_withdrawal,
+        allow_collateral_withdrawal: params.allow_collateral_withdrawal,
+        allow_size_change: params.allow_size_change,
+    };
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    perpetuals.permissions.allow_swap = params.allow_swap;
-    perpetuals.permissions.allow_add_liquidity = params.allow_add_liquidity;
-    perpetuals.permissions.allow_remove_liquidity = params.allow_remove_liquidity;
-    perpetuals.permissions.allow_open_position = params.allow_open_position;
-    perpetuals.permissions.allow_close_position = params.allow_close_position;
-    perpetuals.permissions.allow_pnl_withdrawal = params.allow_pnl_withdrawal;
-    perpetuals.permissions.allow_collateral_withdrawal = params.allow_collateral_withdrawal;
-    perpetuals.permissions.allow_size_change = params.allow_size_change;
+
+    if !multisig.is_root(&ctx.accounts.multisig.key())
+        && !perpetuals.permissions.is_restricted_by(&permissions)
+    {
+        msg!("Error: Only root multisig can enable permissions");
+        return err!(PerpetualsError::MultisigAccountNotAuthorized);
+    }
+
+    // update permissions
+    perpetuals.permissions = permissions;
 
     if !perpetuals.validate() {
         err!(PerpetualsError::InvalidPerpetualsConfig)
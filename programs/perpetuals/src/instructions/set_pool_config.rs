@@ -0,0 +1,92 @@
+//! SetPoolConfig instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::PoolConfigUpdated,
+        state::{
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            pool::{Pool, PoolConfig},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetPoolConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::PoolAdmin,
+            &pool.key()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPoolConfigParams {
+    pub config: PoolConfig,
+}
+
+pub fn set_pool_config<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetPoolConfig<'info>>,
+    params: &SetPoolConfigParams,
+) -> Result<u8> {
+    // validate inputs
+    if !params.config.validate() {
+        return err!(PerpetualsError::InvalidPoolConfig);
+    }
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetPoolConfig, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // update pool config
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let old_config = pool.get_config();
+    pool.set_config(&params.config)?;
+
+    if !pool.validate() {
+        return err!(PerpetualsError::InvalidPoolConfig);
+    }
+
+    emit!(PoolConfigUpdated {
+        pool: ctx.accounts.pool.key(),
+        old_config,
+        new_config: params.config.clone(),
+    });
+
+    Ok(0)
+}
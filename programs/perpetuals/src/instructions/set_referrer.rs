@@ -0,0 +1,69 @@
+//! SetReferrer instruction handler
+
+use {
+    crate::state::{
+        migration::Versioned,
+        pool::Pool,
+        referral::{Referral, Referrer},
+    },
+    anchor_lang::prelude::*,
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct SetReferrer<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        seeds = [b"referrer",
+                 pool.key().as_ref(),
+                 referrer.code.as_bytes()],
+        bump = referrer.bump
+    )]
+    pub referrer: Box<Account<'info, Referrer>>,
+
+    // traders can link to a referrer only once
+    #[account(
+        init,
+        payer = owner,
+        space = Referral::LEN,
+        seeds = [b"referral",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub referral: Box<Account<'info, Referral>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetReferrerParams {}
+
+pub fn set_referrer(ctx: Context<SetReferrer>, _params: &SetReferrerParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    require_keys_neq!(ctx.accounts.referrer.owner, ctx.accounts.owner.key());
+
+    // record referral
+    msg!("Link to referrer: {}", ctx.accounts.referrer.code);
+    let referral = ctx.accounts.referral.as_mut();
+    referral.owner = ctx.accounts.owner.key();
+    referral.pool = ctx.accounts.pool.key();
+    referral.referrer = ctx.accounts.referrer.key();
+    referral.bump = *ctx
+        .bumps
+        .get("referral")
+        .ok_or(ProgramError::InvalidSeeds)?;
+    referral.version = Referral::VERSION;
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! SetRoleSigners instruction handler
+
+use {
+    crate::state::multisig::{AdminInstruction, AdminRole, Multisig},
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+#[instruction(params: SetRoleSignersParams)]
+pub struct SetRoleSigners<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    // role multisig is created on the first call and filled out when all
+    // signatures are collected, hence init_if_needed
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = Multisig::LEN,
+        seeds = [b"multisig".as_ref(),
+                 &[params.role as u8],
+                 params.scope.as_ref()],
+        bump
+    )]
+    pub role_multisig: AccountLoader<'info, Multisig>,
+
+    system_program: Program<'info, System>,
+    // remaining accounts: 1 to Multisig::MAX_SIGNERS role signers (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetRoleSignersParams {
+    pub role: AdminRole,
+    // pool address for AdminRole::PoolAdmin, Pubkey::default() otherwise
+    pub scope: Pubkey,
+    // signer weights in the order of remaining accounts, 1 for all signers if empty
+    pub weights: Vec<u8>,
+    pub min_weight: u8,
+}
+
+pub fn set_role_signers<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetRoleSigners<'info>>,
+    params: &SetRoleSignersParams,
+) -> Result<u8> {
+    // validate inputs
+    if (params.role == AdminRole::PoolAdmin) == (params.scope == Pubkey::default()) {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetRoleSigners, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // set new role signers
+    let mut role_multisig = match ctx.accounts.role_multisig.load_mut() {
+        Ok(role_multisig) => role_multisig,
+        Err(_) => ctx.accounts.role_multisig.load_init()?,
+    };
+    role_multisig.bump = *ctx
+        .bumps
+        .get("role_multisig")
+        .ok_or(ProgramError::InvalidSeeds)?;
+    role_multisig.version = Multisig::VERSION;
+    role_multisig.set_signers(ctx.remaining_accounts, &params.weights, params.min_weight)?;
+
+    Ok(0)
+}
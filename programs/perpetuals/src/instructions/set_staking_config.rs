@@ -0,0 +1,185 @@
+//! SetStakingConfig instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::StakingConfigUpdated,
+        math,
+        state::{
+            custody::Custody,
+            migration::Versioned,
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            staking::{Staking, StakingConfig},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct SetStakingConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::PoolAdmin,
+            &pool.key()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    // instruction can be called multiple times due to multisig use, hence init_if_needed
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = Staking::LEN,
+        seeds = [b"staking",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub staking: Box<Account<'info, Staking>>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = lp_token_mint,
+        token::authority = transfer_authority,
+        seeds = [b"staking_lp_vault",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub staking_lp_vault: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+    // remaining accounts:
+    //   pool.tokens.len() custody accounts (writable, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetStakingConfigParams {
+    pub config: StakingConfig,
+}
+
+pub fn set_staking_config<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetStakingConfig<'info>>,
+    params: &SetStakingConfigParams,
+) -> Result<u8> {
+    // validate inputs
+    if !params.config.validate() {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let token_id = ctx
+        .accounts
+        .pool
+        .load()?
+        .get_token_id(&params.config.reward_custody)?;
+    let reward_custody = AccountLoader::<Custody>::try_from(
+        ctx.remaining_accounts
+            .get(token_id)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?,
+    )?;
+    if reward_custody.key() != params.config.reward_custody || reward_custody.load()?.is_virtual {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetStakingConfig, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // distribute fees collected under the current config
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let staking = ctx.accounts.staking.as_mut();
+    if staking.pool == Pubkey::default() {
+        staking.pool = ctx.accounts.pool.key();
+        staking.last_fees_usd = Staking::get_pool_fees_usd(&pool, ctx.remaining_accounts)?;
+        staking.bump = *ctx.bumps.get("staking").ok_or(ProgramError::InvalidSeeds)?;
+        staking.lp_vault_bump = *ctx
+            .bumps
+            .get("staking_lp_vault")
+            .ok_or(ProgramError::InvalidSeeds)?;
+        staking.version = Staking::VERSION;
+    } else {
+        staking.update_pool_rewards(&mut pool, ctx.remaining_accounts)?;
+
+        // rewards owed by the previous reward custody are paid by the new one
+        if let Ok(prev_token_id) = pool.get_token_id(&staking.reward_custody) {
+            if prev_token_id != token_id {
+                let prev_custody =
+                    AccountLoader::<Custody>::try_from(&ctx.remaining_accounts[prev_token_id])?;
+                let mut prev_custody = prev_custody.load_mut()?;
+                let rewards_usd = prev_custody.staking_rewards_usd;
+                pool.set_staking_rewards(&mut prev_custody, 0)?;
+
+                let mut reward_custody = reward_custody.load_mut()?;
+                let staking_rewards_usd =
+                    math::checked_add(reward_custody.staking_rewards_usd, rewards_usd)?;
+                pool.set_staking_rewards(&mut reward_custody, staking_rewards_usd)?;
+            }
+        }
+    }
+    let old_config = staking.get_config();
+
+    msg!("Set staking config");
+    staking.reward_custody = params.config.reward_custody;
+    staking.fee_share = params.config.fee_share;
+
+    emit!(StakingConfigUpdated {
+        pool: ctx.accounts.pool.key(),
+        old_config,
+        new_config: params.config,
+    });
+
+    Ok(0)
+}
@@ -0,0 +1,109 @@
+//! SetTradingSchedule instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::TradingScheduleUpdated,
+        state::{
+            custody::{Custody, TradingSchedule},
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetTradingSchedule<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::RiskManager,
+            &Pubkey::default()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SetTradingScheduleParams {
+    pub trading_schedule: TradingSchedule,
+}
+
+pub fn set_trading_schedule<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetTradingSchedule<'info>>,
+    params: &SetTradingScheduleParams,
+) -> Result<u8> {
+    // validate inputs
+    if !params.trading_schedule.validate() {
+        return err!(PerpetualsError::InvalidCustodyConfig);
+    }
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetTradingSchedule, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // settle interest accrued under the current schedule
+    let mut custody = ctx.accounts.custody.load_mut()?;
+    let old_schedule = custody.trading_schedule;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    custody.update_borrow_rate(curtime)?;
+
+    msg!("Set trading schedule");
+    custody.trading_schedule = params.trading_schedule;
+
+    emit!(TradingScheduleUpdated {
+        pool: ctx.accounts.pool.key(),
+        custody: ctx.accounts.custody.key(),
+        old_schedule,
+        new_schedule: params.trading_schedule,
+    });
+
+    Ok(0)
+}
@@ -0,0 +1,127 @@
+//! SettleCustody instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            oracle::OraclePrice,
+            perpetuals::{MarketStatus, Perpetuals},
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SettleCustody<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::PoolAdmin,
+            &pool.key()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    /// CHECK: oracle account for the custody token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.load()?.oracle.oracle_account
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SettleCustodyParams {}
+
+pub fn settle_custody<'info>(
+    ctx: Context<'_, '_, '_, 'info, SettleCustody<'info>>,
+    params: &SettleCustodyParams,
+) -> Result<u8> {
+    // validate inputs
+    require!(
+        ctx.accounts.custody.load()?.status != MarketStatus::Settled,
+        PerpetualsError::InvalidCustodyState
+    );
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SettleCustody, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // freeze settlement price
+    let mut custody = ctx.accounts.custody.load_mut()?;
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+
+    let settlement_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &{ custody.oracle },
+        curtime,
+        false,
+    )?;
+
+    msg!(
+        "Settlement price: {} * 10^{}",
+        settlement_price.price,
+        settlement_price.exponent
+    );
+    custody.settlement_price = settlement_price;
+    custody.status = MarketStatus::Settled;
+
+    // custody is valued at the settlement price from now on
+    ctx.accounts.pool.load_mut()?.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &settlement_price,
+        &settlement_price,
+        curtime,
+    )?;
+
+    Ok(0)
+}
@@ -0,0 +1,299 @@
+//! SettlePosition instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            custody::Custody,
+            perpetuals::{MarketStatus, Perpetuals},
+            pool::Pool,
+            position::{Position, Side},
+            user_stats::{Fill, FillType, UserStats},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct SettlePosition<'info> {
+    pub signer: Signer<'info>,
+
+    /// CHECK: position owner, receives position account rent
+    #[account(
+        mut,
+        constraint = owner.key() == position.owner
+    )]
+    pub owner: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == collateral_custody.load()?.mint,
+        constraint = receiving_account.owner == position.owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"position",
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8]],
+        bump = position.bump,
+        close = owner
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        constraint = position.custody == custody.key()
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    #[account(
+        mut,
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: AccountLoader<'info, Custody>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.load()?.oracle.oracle_account
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"custody_token_account",
+                 pool.key().as_ref(),
+                 collateral_custody.load()?.mint.as_ref()],
+        bump = collateral_custody.load()?.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    // if the position owner has stats, the settlement is recorded as a close
+    #[account(
+        mut,
+        seeds = [b"user_stats",
+                 position.owner.as_ref()],
+        bump = user_stats.bump
+    )]
+    pub user_stats: Option<Box<Account<'info, UserStats>>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SettlePositionParams {}
+
+pub fn settle_position(ctx: Context<SettlePosition>, _params: &SettlePositionParams) -> Result<()> {
+    // check market status
+    msg!("Check market status");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    // custody and collateral_custody can be the same account, work on copies
+    // and write them back when the position is updated
+    let mut custody = Box::new(*ctx.accounts.custody.load()?);
+    let mut collateral_custody = Box::new(*ctx.accounts.collateral_custody.load()?);
+    require!(
+        custody.status == MarketStatus::Settled
+            && pool.status.allows_reduce()
+            && collateral_custody.status.allows_withdrawal(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    let position = ctx.accounts.position.as_mut();
+
+    // settled positions are closed at the frozen price
+    let curtime = perpetuals.get_time()?;
+
+    let token_price = custody.settlement_price;
+
+    let collateral_token_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        false,
+    )?;
+
+    let collateral_token_ema_price = collateral_custody.get_oracle_price(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        curtime,
+        collateral_custody.pricing.use_ema,
+    )?;
+
+    msg!("Settle position");
+    let (transfer_amount, _, profit_usd, loss_usd) = pool.get_close_amount(
+        position,
+        &token_price,
+        &token_price,
+        &custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        &collateral_custody,
+        curtime,
+        false,
+        Perpetuals::BPS_POWER as u64,
+    )?;
+
+    msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
+    msg!("Amount out: {}", transfer_amount);
+
+    // record user stats, settlements are closes without fees
+    if let Some(user_stats) = ctx.accounts.user_stats.as_mut() {
+        let exit_price =
+            pool.get_exit_price(&token_price, &token_price, position.side, &custody)?;
+        user_stats.add_volume(position.size_usd, curtime);
+        user_stats.record_fill(Fill {
+            fill_type: FillType::Close,
+            side: position.side,
+            custody: position.custody,
+            time: curtime,
+            price: exit_price,
+            size_usd: position.size_usd,
+            fee_usd: 0,
+            profit_usd,
+            loss_usd,
+        });
+    }
+
+    // unlock pool funds
+    collateral_custody.unlock_funds(position.locked_amount)?;
+
+    // check pool constraints
+    msg!("Check pool constraints");
+    require!(
+        pool.check_available_amount(transfer_amount, &collateral_custody)?,
+        PerpetualsError::CustodyAmountLimit
+    );
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        transfer_amount,
+    )?;
+
+    // update custody stats
+    msg!("Update custody stats");
+    if transfer_amount > position.collateral_amount {
+        let amount_lost = transfer_amount.saturating_sub(position.collateral_amount);
+        collateral_custody.assets.owned =
+            math::checked_sub(collateral_custody.assets.owned, amount_lost)?;
+    } else {
+        let amount_gained = position.collateral_amount.saturating_sub(transfer_amount);
+        collateral_custody.assets.owned =
+            math::checked_add(collateral_custody.assets.owned, amount_gained)?;
+    }
+    collateral_custody.assets.collateral = math::checked_sub(
+        collateral_custody.assets.collateral,
+        position.collateral_amount,
+    )?;
+
+    // if custody and collateral_custody accounts are the same, ensure that data is in sync
+    if position.side == Side::Long && !custody.is_virtual {
+        collateral_custody.volume_stats.close_position_usd = collateral_custody
+            .volume_stats
+            .close_position_usd
+            .saturating_add(position.size_usd as u128);
+
+        collateral_custody.trade_stats.oi_long_usd = collateral_custody
+            .trade_stats
+            .oi_long_usd
+            .saturating_sub(position.size_usd);
+
+        collateral_custody.trade_stats.profit_usd = collateral_custody
+            .trade_stats
+            .profit_usd
+            .wrapping_add(profit_usd);
+        collateral_custody.trade_stats.loss_usd = collateral_custody
+            .trade_stats
+            .loss_usd
+            .wrapping_add(loss_usd);
+
+        collateral_custody.update_stats_history(curtime, position.size_usd, 0);
+        collateral_custody.remove_position(position, curtime, None)?;
+        collateral_custody.update_borrow_rate(curtime)?;
+        custody = collateral_custody.clone();
+    } else {
+        custody.volume_stats.close_position_usd = custody
+            .volume_stats
+            .close_position_usd
+            .saturating_add(position.size_usd as u128);
+
+        if position.side == Side::Long {
+            custody.trade_stats.oi_long_usd = custody
+                .trade_stats
+                .oi_long_usd
+                .saturating_sub(position.size_usd);
+        } else {
+            custody.trade_stats.oi_short_usd = custody
+                .trade_stats
+                .oi_short_usd
+                .saturating_sub(position.size_usd);
+        }
+
+        custody.trade_stats.profit_usd = custody.trade_stats.profit_usd.wrapping_add(profit_usd);
+        custody.trade_stats.loss_usd = custody.trade_stats.loss_usd.wrapping_add(loss_usd);
+
+        custody.update_stats_history(curtime, position.size_usd, 0);
+        custody.remove_position(position, curtime, Some(collateral_custody.as_mut()))?;
+        collateral_custody.update_borrow_rate(curtime)?;
+    }
+
+    // update pool stats
+    pool.update_custody_aum(
+        &ctx.accounts.custody.key(),
+        &mut custody,
+        &token_price,
+        &token_price,
+        curtime,
+    )?;
+    if ctx.accounts.custody.key() == ctx.accounts.collateral_custody.key() {
+        collateral_custody = custody.clone();
+    } else {
+        pool.update_custody_aum(
+            &ctx.accounts.collateral_custody.key(),
+            &mut collateral_custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            curtime,
+        )?;
+    }
+
+    *ctx.accounts.custody.load_mut()? = *custody;
+    *ctx.accounts.collateral_custody.load_mut()? = *collateral_custody;
+
+    Ok(())
+}
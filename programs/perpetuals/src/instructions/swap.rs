@@ -4,7 +4,16 @@ use {
     crate::{
         error::PerpetualsError,
         math,
-        state::{custody::Custody, oracle::OraclePrice, perpetuals::Perpetuals, pool::Pool},
+        state::{
+            custody::Custody,
+            migration::Versioned,
+            oracle::OraclePrice,
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::Side,
+            referral::{Referral, Referrer},
+            user_stats::{Fill, FillType, UserStats},
+        },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Token, TokenAccount},
@@ -14,19 +23,19 @@ use {
 #[derive(Accounts)]
 #[instruction(params: SwapParams)]
 pub struct Swap<'info> {
-    #[account()]
+    #[account(mut)]
     pub owner: Signer<'info>,
 
     #[account(
         mut,
-        constraint = funding_account.mint == receiving_custody.mint,
+        constraint = funding_account.mint == receiving_custody.load()?.mint,
         has_one = owner
     )]
     pub funding_account: Box<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = receiving_account.mint == dispensing_custody.mint,
+        constraint = receiving_account.mint == dispensing_custody.load()?.mint,
         has_one = owner
     )]
     pub receiving_account: Box<Account<'info, TokenAccount>>,
@@ -47,23 +56,23 @@ pub struct Swap<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 receiving_custody.mint.as_ref()],
-        bump = receiving_custody.bump
+                 receiving_custody.load()?.mint.as_ref()],
+        bump = receiving_custody.load()?.bump
     )]
-    pub receiving_custody: Box<Account<'info, Custody>>,
+    pub receiving_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the received token
     #[account(
-        constraint = receiving_custody_oracle_account.key() == receiving_custody.oracle.oracle_account
+        constraint = receiving_custody_oracle_account.key() == receiving_custody.load()?.oracle.oracle_account
     )]
     pub receiving_custody_oracle_account: AccountInfo<'info>,
 
@@ -71,8 +80,8 @@ pub struct Swap<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 receiving_custody.mint.as_ref()],
-        bump = receiving_custody.token_account_bump
+                 receiving_custody.load()?.mint.as_ref()],
+        bump = receiving_custody.load()?.token_account_bump
     )]
     pub receiving_custody_token_account: Box<Account<'info, TokenAccount>>,
 
@@ -80,14 +89,14 @@ pub struct Swap<'info> {
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 dispensing_custody.mint.as_ref()],
-        bump = dispensing_custody.bump
+                 dispensing_custody.load()?.mint.as_ref()],
+        bump = dispensing_custody.load()?.bump
     )]
-    pub dispensing_custody: Box<Account<'info, Custody>>,
+    pub dispensing_custody: AccountLoader<'info, Custody>,
 
     /// CHECK: oracle account for the returned token
     #[account(
-        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.oracle.oracle_account
+        constraint = dispensing_custody_oracle_account.key() == dispensing_custody.load()?.oracle.oracle_account
     )]
     pub dispensing_custody_oracle_account: AccountInfo<'info>,
 
@@ -95,11 +104,37 @@ pub struct Swap<'info> {
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 dispensing_custody.mint.as_ref()],
-        bump = dispensing_custody.token_account_bump
+                 dispensing_custody.load()?.mint.as_ref()],
+        bump = dispensing_custody.load()?.token_account_bump
     )]
     pub dispensing_custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    // if provided, the trader gets a fee discount and the referrer is credited a fee share
+    #[account(
+        seeds = [b"referral",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = referral.bump
+    )]
+    pub referral: Option<Box<Account<'info, Referral>>>,
+
+    #[account(
+        mut,
+        constraint = referral.as_ref().map(|x| x.referrer) == Some(referrer.key())
+    )]
+    pub referrer: Option<Box<Account<'info, Referrer>>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = UserStats::LEN,
+        seeds = [b"user_stats",
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+
+    system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
 }
 
@@ -112,36 +147,59 @@ pub struct SwapParams {
 pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
     // check permissions
     msg!("Check permissions");
+    require_keys_neq!(
+        ctx.accounts.receiving_custody.key(),
+        ctx.accounts.dispensing_custody.key()
+    );
     let perpetuals = ctx.accounts.perpetuals.as_mut();
-    let receiving_custody = ctx.accounts.receiving_custody.as_mut();
-    let dispensing_custody = ctx.accounts.dispensing_custody.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let mut receiving_custody = ctx.accounts.receiving_custody.load_mut()?;
+    let mut dispensing_custody = ctx.accounts.dispensing_custody.load_mut()?;
     require!(
         perpetuals.permissions.allow_swap
+            && pool.permissions.allow_swap
             && receiving_custody.permissions.allow_swap
             && dispensing_custody.permissions.allow_swap
             && !receiving_custody.is_virtual
             && !dispensing_custody.is_virtual,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        pool.status.allows_increase()
+            && receiving_custody.status.allows_increase()
+            && dispensing_custody.status.allows_increase(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // validate inputs
     msg!("Validate inputs");
     if params.amount_in == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
-    require_keys_neq!(receiving_custody.key(), dispensing_custody.key());
 
-    // compute token amount returned to the user
-    let pool = ctx.accounts.pool.as_mut();
     let curtime = perpetuals.get_time()?;
-    let token_id_in = pool.get_token_id(&receiving_custody.key())?;
-    let token_id_out = pool.get_token_id(&dispensing_custody.key())?;
+
+    // record user stats, trade volume selects the fee tier
+    let user_stats = ctx.accounts.user_stats.as_mut();
+    if user_stats.owner == Pubkey::default() {
+        user_stats.owner = ctx.accounts.owner.key();
+        user_stats.bump = *ctx
+            .bumps
+            .get("user_stats")
+            .ok_or(ProgramError::InvalidSeeds)?;
+        user_stats.version = UserStats::VERSION;
+    }
+    let fee_mult = perpetuals.get_fee_mult(user_stats.get_volume_usd(curtime));
+
+    // compute token amount returned to the user
+    let token_id_in = pool.get_token_id(&ctx.accounts.receiving_custody.key())?;
+    let token_id_out = pool.get_token_id(&ctx.accounts.dispensing_custody.key())?;
 
     let received_token_price = OraclePrice::new_from_oracle(
         &ctx.accounts
             .receiving_custody_oracle_account
             .to_account_info(),
-        &receiving_custody.oracle,
+        &{ receiving_custody.oracle },
         curtime,
         false,
     )?;
@@ -150,7 +208,7 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
         &ctx.accounts
             .receiving_custody_oracle_account
             .to_account_info(),
-        &receiving_custody.oracle,
+        &{ receiving_custody.oracle },
         curtime,
         receiving_custody.pricing.use_ema,
     )?;
@@ -159,7 +217,7 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
         &ctx.accounts
             .dispensing_custody_oracle_account
             .to_account_info(),
-        &dispensing_custody.oracle,
+        &{ dispensing_custody.oracle },
         curtime,
         false,
     )?;
@@ -168,7 +226,7 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
         &ctx.accounts
             .dispensing_custody_oracle_account
             .to_account_info(),
-        &dispensing_custody.oracle,
+        &{ dispensing_custody.oracle },
         curtime,
         dispensing_custody.pricing.use_ema,
     )?;
@@ -179,22 +237,31 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
         &received_token_ema_price,
         &dispensed_token_price,
         &dispensed_token_ema_price,
-        receiving_custody,
-        dispensing_custody,
+        &receiving_custody,
+        &dispensing_custody,
         params.amount_in,
     )?;
 
     // calculate fee
-    let fees = pool.get_swap_fees(
+    let mut fees = pool.get_swap_fees(
         token_id_in,
         token_id_out,
         params.amount_in,
         amount_out,
-        receiving_custody,
+        &receiving_custody,
         &received_token_price,
-        dispensing_custody,
+        &dispensing_custody,
         &dispensed_token_price,
+        fee_mult,
     )?;
+    // fee_in is not charged to the trader, so referrals only apply to fee_out
+    let mut referrer_fee_usd = 0;
+    if ctx.accounts.referrer.is_some() {
+        let (discount, referrer_fee) = pool.get_referral_fees(fees.1)?;
+        fees.1 = math::checked_sub(fees.1, discount)?;
+        referrer_fee_usd = dispensed_token_price
+            .get_asset_amount_usd(referrer_fee, dispensing_custody.decimals)?;
+    }
     msg!("Collected fees: {} {}", fees.0, fees.1);
 
     // check returned amount
@@ -218,13 +285,13 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
             token_id_in,
             deposit_amount,
             0,
-            receiving_custody,
+            &receiving_custody,
             &received_token_price
         )? && pool.check_token_ratio(
             token_id_out,
             0,
             withdrawal_amount,
-            dispensing_custody,
+            &dispensing_custody,
             &dispensed_token_price
         )?,
         PerpetualsError::TokenRatioOutOfRange
@@ -261,14 +328,19 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
 
     // update custody stats
     msg!("Update custody stats");
-    receiving_custody.volume_stats.swap_usd = receiving_custody.volume_stats.swap_usd.wrapping_add(
-        received_token_price.get_asset_amount_usd(params.amount_in, receiving_custody.decimals)?,
-    );
-
-    receiving_custody.collected_fees.swap_usd =
-        receiving_custody.collected_fees.swap_usd.wrapping_add(
-            received_token_price.get_asset_amount_usd(fees.0, receiving_custody.decimals)?,
-        );
+    let volume_usd =
+        received_token_price.get_asset_amount_usd(params.amount_in, receiving_custody.decimals)?;
+    let fee_in_usd =
+        received_token_price.get_asset_amount_usd(fees.0, receiving_custody.decimals)?;
+    receiving_custody.volume_stats.swap_usd = receiving_custody
+        .volume_stats
+        .swap_usd
+        .saturating_add(volume_usd as u128);
+
+    receiving_custody.collected_fees.swap_usd = receiving_custody
+        .collected_fees
+        .swap_usd
+        .saturating_add(fee_in_usd as u128);
 
     receiving_custody.assets.owned =
         math::checked_add(receiving_custody.assets.owned, deposit_amount)?;
@@ -276,15 +348,19 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
     receiving_custody.assets.protocol_fees =
         math::checked_add(receiving_custody.assets.protocol_fees, protocol_fee_in)?;
 
-    dispensing_custody.collected_fees.swap_usd =
-        dispensing_custody.collected_fees.swap_usd.wrapping_add(
-            dispensed_token_price.get_asset_amount_usd(fees.1, dispensing_custody.decimals)?,
-        );
+    let amount_out_usd =
+        dispensed_token_price.get_asset_amount_usd(amount_out, dispensing_custody.decimals)?;
+    let fee_out_usd =
+        dispensed_token_price.get_asset_amount_usd(fees.1, dispensing_custody.decimals)?;
+    dispensing_custody.collected_fees.swap_usd = dispensing_custody
+        .collected_fees
+        .swap_usd
+        .saturating_add(fee_out_usd as u128);
 
-    dispensing_custody.volume_stats.swap_usd =
-        dispensing_custody.volume_stats.swap_usd.wrapping_add(
-            dispensed_token_price.get_asset_amount_usd(amount_out, dispensing_custody.decimals)?,
-        );
+    dispensing_custody.volume_stats.swap_usd = dispensing_custody
+        .volume_stats
+        .swap_usd
+        .saturating_add(amount_out_usd as u128);
 
     dispensing_custody.assets.protocol_fees =
         math::checked_add(dispensing_custody.assets.protocol_fees, protocol_fee_out)?;
@@ -292,8 +368,49 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
     dispensing_custody.assets.owned =
         math::checked_sub(dispensing_custody.assets.owned, withdrawal_amount)?;
 
+    if let Some(referrer) = ctx.accounts.referrer.as_mut() {
+        referrer.add_trade(volume_usd, referrer_fee_usd)?;
+        dispensing_custody.referral_fees_usd = math::checked_add(
+            dispensing_custody.referral_fees_usd,
+            referrer_fee_usd as u128,
+        )?;
+    }
+    user_stats.add_volume(volume_usd, curtime);
+    user_stats.record_fill(Fill {
+        fill_type: FillType::Swap,
+        side: Side::None,
+        custody: ctx.accounts.receiving_custody.key(),
+        time: curtime,
+        price: received_token_price
+            .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+            .price,
+        size_usd: volume_usd,
+        fee_usd: fee_out_usd,
+        ..Fill::default()
+    });
+
+    receiving_custody.update_stats_history(curtime, volume_usd, fee_in_usd);
+    dispensing_custody.update_stats_history(curtime, amount_out_usd, fee_out_usd);
+
     receiving_custody.update_borrow_rate(curtime)?;
     dispensing_custody.update_borrow_rate(curtime)?;
 
+    // update pool stats
+    msg!("Update pool stats");
+    pool.update_custody_aum(
+        &ctx.accounts.receiving_custody.key(),
+        &mut receiving_custody,
+        &received_token_price,
+        &received_token_ema_price,
+        curtime,
+    )?;
+    pool.update_custody_aum(
+        &ctx.accounts.dispensing_custody.key(),
+        &mut dispensing_custody,
+        &dispensed_token_price,
+        &dispensed_token_ema_price,
+        curtime,
+    )?;
+
     Ok(())
 }
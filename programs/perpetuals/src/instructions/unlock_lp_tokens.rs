@@ -0,0 +1,133 @@
+//! UnlockLpTokens instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{perpetuals::Perpetuals, pool::Pool, user_deposit::UserDeposit},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct UnlockLpTokens<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = lp_token_account.mint == junior_lp_token_mint
+            .as_ref()
+            .map_or(lp_token_mint.key(), |x| x.key()),
+        has_one = owner
+    )]
+    pub lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    // if provided, junior LP tokens are unlocked
+    #[account(
+        seeds = [b"junior_lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.junior_lp_token_bump
+    )]
+    pub junior_lp_token_mint: Option<Box<Account<'info, Mint>>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"user_deposit",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = user_deposit.bump
+    )]
+    pub user_deposit: Box<Account<'info, UserDeposit>>,
+
+    // escrow of the unlocked tranche is required
+    #[account(
+        mut,
+        seeds = [b"locked_lp_account",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub locked_lp_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [b"locked_junior_lp_account",
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub locked_junior_lp_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UnlockLpTokensParams {}
+
+pub fn unlock_lp_tokens(
+    ctx: Context<UnlockLpTokens>,
+    _params: &UnlockLpTokensParams,
+) -> Result<()> {
+    // check cooldown
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let curtime = perpetuals.get_time()?;
+    let pool = ctx.accounts.pool.load()?;
+    let user_deposit = ctx.accounts.user_deposit.as_mut();
+    require!(
+        user_deposit.is_unlocked(pool.lp_cooldown_sec, curtime)?,
+        PerpetualsError::LpCooldown
+    );
+
+    let is_junior = ctx.accounts.junior_lp_token_mint.is_some();
+    let locked_lp_account = if is_junior {
+        ctx.accounts.locked_junior_lp_account.as_ref()
+    } else {
+        ctx.accounts.locked_lp_account.as_ref()
+    }
+    .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    let lp_amount = user_deposit.unlock_lp_amount(is_junior);
+    if lp_amount == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // release lp tokens
+    msg!("Unlock LP tokens: {}", lp_amount);
+    perpetuals.transfer_tokens(
+        locked_lp_account.to_account_info(),
+        ctx.accounts.lp_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        lp_amount,
+    )?;
+
+    Ok(())
+}
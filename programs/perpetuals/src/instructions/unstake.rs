@@ -0,0 +1,122 @@
+//! Unstake instruction handler
+
+use {
+    crate::{
+        math,
+        state::{
+            perpetuals::Perpetuals,
+            pool::Pool,
+            staking::{Staking, UserStake},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: UnstakeParams)]
+pub struct Unstake<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = lp_token_account.mint == lp_token_mint.key(),
+        has_one = owner
+    )]
+    pub lp_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        seeds = [b"lp_token_mint",
+                 pool.key().as_ref()],
+        bump = pool.load()?.lp_token_bump
+    )]
+    pub lp_token_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"staking",
+                 pool.key().as_ref()],
+        bump = staking.bump
+    )]
+    pub staking: Box<Account<'info, Staking>>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_lp_vault",
+                 pool.key().as_ref()],
+        bump = staking.lp_vault_bump
+    )]
+    pub staking_lp_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stake",
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = user_stake.bump
+    )]
+    pub user_stake: Box<Account<'info, UserStake>>,
+
+    token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   pool.tokens.len() custody accounts (writable, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UnstakeParams {
+    pub amount: u64,
+}
+
+pub fn unstake(ctx: Context<Unstake>, params: &UnstakeParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    let user_stake = ctx.accounts.user_stake.as_mut();
+    if params.amount == 0 || params.amount > user_stake.staked_amount {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // settle rewards
+    msg!("Update rewards");
+    let staking = ctx.accounts.staking.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    staking.update_pool_rewards(&mut pool, ctx.remaining_accounts)?;
+    user_stake.update_rewards(staking)?;
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    ctx.accounts.perpetuals.transfer_tokens(
+        ctx.accounts.staking_lp_vault.to_account_info(),
+        ctx.accounts.lp_token_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount,
+    )?;
+
+    user_stake.staked_amount = math::checked_sub(user_stake.staked_amount, params.amount)?;
+    staking.staked_amount = math::checked_sub(staking.staked_amount, params.amount)?;
+
+    Ok(())
+}
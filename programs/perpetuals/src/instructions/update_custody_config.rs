@@ -0,0 +1,194 @@
+//! UpdateCustodyConfig instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        events::CustodyConfigUpdated,
+        state::{
+            config_bounds::ConfigBounds,
+            custody::{BorrowRateParams, Custody, Fees, PricingParams},
+            multisig::{AdminInstruction, AdminRole, Multisig},
+            oracle::OracleParams,
+            perpetuals::{Permissions, Perpetuals},
+            pool::{Pool, TokenRatios},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct UpdateCustodyConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::RiskManager,
+            &Pubkey::default()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"perpetuals"],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [b"config_bounds"],
+        bump = config_bounds.bump
+    )]
+    pub config_bounds: Box<Account<'info, ConfigBounds>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool",
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"custody",
+                 pool.key().as_ref(),
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.bump
+    )]
+    pub custody: AccountLoader<'info, Custody>,
+
+    system_program: Program<'info, System>,
+}
+
+// only provided sections are updated
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct UpdateCustodyConfigParams {
+    pub oracle: Option<OracleParams>,
+    pub pricing: Option<PricingParams>,
+    pub permissions: Option<Permissions>,
+    pub fees: Option<Fees>,
+    pub borrow_rate: Option<BorrowRateParams>,
+    pub ratios: Option<Vec<TokenRatios>>,
+    pub flash_loan_fee: Option<u64>,
+}
+
+pub fn update_custody_config<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpdateCustodyConfig<'info>>,
+    params: &UpdateCustodyConfigParams,
+) -> Result<u8> {
+    // validate inputs
+    if params.oracle.is_none()
+        && params.pricing.is_none()
+        && params.permissions.is_none()
+        && params.fees.is_none()
+        && params.borrow_rate.is_none()
+        && params.ratios.is_none()
+        && params.flash_loan_fee.is_none()
+    {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    if let Some(ratios) = &params.ratios {
+        if ratios.len() != ctx.accounts.pool.load()?.get_custodies().len() {
+            return Err(ProgramError::InvalidArgument.into());
+        }
+    }
+
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::UpdateCustodyConfig, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // update pool data
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let old_ratios = pool.get_ratios();
+    if let Some(ratios) = &params.ratios {
+        msg!("Update token ratios");
+        pool.set_ratios(ratios)?;
+        if !pool.validate() {
+            return err!(PerpetualsError::InvalidPoolConfig);
+        }
+    }
+
+    // update custody data within governance bounds
+    let bounds = ctx.accounts.config_bounds.custody;
+    let mut custody = ctx.accounts.custody.load_mut()?;
+    let old_config = custody.get_config();
+    if let Some(oracle) = params.oracle {
+        msg!("Update oracle params");
+        if !bounds.oracle.contains(&oracle) {
+            return err!(PerpetualsError::ConfigOutOfBounds);
+        }
+        custody.oracle = oracle;
+    }
+    if let Some(pricing) = params.pricing {
+        msg!("Update pricing params");
+        if !bounds.pricing.contains(&pricing) {
+            return err!(PerpetualsError::ConfigOutOfBounds);
+        }
+        custody.pricing = pricing;
+    }
+    if let Some(permissions) = params.permissions {
+        msg!("Update permissions");
+        custody.permissions = permissions;
+    }
+    if let Some(fees) = params.fees {
+        msg!("Update fees");
+        if !bounds.fees.contains(&fees) {
+            return err!(PerpetualsError::ConfigOutOfBounds);
+        }
+        custody.fees = fees;
+    }
+    if let Some(borrow_rate) = params.borrow_rate {
+        msg!("Update borrow rate params");
+        if !bounds.borrow_rate.contains(&borrow_rate) {
+            return err!(PerpetualsError::ConfigOutOfBounds);
+        }
+        // settle interest accrued under the current borrow rate params
+        let curtime = ctx.accounts.perpetuals.get_time()?;
+        custody.update_borrow_rate(curtime)?;
+        custody.borrow_rate = borrow_rate;
+        custody.update_borrow_rate(curtime)?;
+    }
+    if let Some(flash_loan_fee) = params.flash_loan_fee {
+        msg!("Update flash loan fee");
+        if !bounds.flash_loan_fee.contains(flash_loan_fee) {
+            return err!(PerpetualsError::ConfigOutOfBounds);
+        }
+        custody.flash_loan_fee = flash_loan_fee;
+    }
+
+    if !custody.validate() {
+        return err!(PerpetualsError::InvalidCustodyConfig);
+    }
+    let new_config = custody.get_config();
+
+    emit!(CustodyConfigUpdated {
+        pool: ctx.accounts.pool.key(),
+        custody: ctx.accounts.custody.key(),
+        old_config,
+        new_config,
+        old_ratios,
+        new_ratios: pool.get_ratios(),
+    });
+
+    Ok(0)
+}
@@ -1,10 +1,7 @@
 //! UpdatePoolAum instruction handler
 
 use {
-    crate::state::{
-        perpetuals::Perpetuals,
-        pool::{AumCalcMode, Pool},
-    },
+    crate::state::{perpetuals::Perpetuals, pool::Pool},
     anchor_lang::prelude::*,
 };
 
@@ -22,30 +19,29 @@ pub struct UpdatePoolAum<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
-    // remaining accounts:
-    //   pool.tokens.len() custody accounts (read-only, unsigned)
-    //   pool.tokens.len() custody oracles (read-only, unsigned)
+    pub pool: AccountLoader<'info, Pool>,
+    // remaining accounts, any page of pool custodies:
+    //   custody accounts (write, unsigned)
+    //   custody oracles (read-only, unsigned)
 }
 
 pub fn update_pool_aum(ctx: Context<UpdatePoolAum>) -> Result<u128> {
     let perpetuals: &Account<'_, Perpetuals> = ctx.accounts.perpetuals.as_ref();
-    let pool = ctx.accounts.pool.as_mut();
+    let mut pool = ctx.accounts.pool.load_mut()?;
 
     let curtime: i64 = perpetuals.get_time()?;
 
     // update pool stats
     msg!("Update pool asset under management");
 
-    msg!("Previous value: {}", pool.aum_usd);
+    msg!("Previous value: {}", { pool.aum_usd });
 
-    pool.aum_usd =
-        pool.get_assets_under_management_usd(AumCalcMode::EMA, ctx.remaining_accounts, curtime)?;
+    let aum_usd = pool.refresh_aum(ctx.remaining_accounts, curtime)?;
 
-    msg!("Updated value: {}", pool.aum_usd);
+    msg!("Updated value: {}", aum_usd);
 
-    Ok(pool.aum_usd)
+    Ok(aum_usd)
 }
@@ -4,85 +4,45 @@ use {
     crate::{
         error::PerpetualsError,
         state::{
-            custody::{Custody, DeprecatedCustody},
+            custody::{
+                Custody, DeprecatedCustody, DeprecatedCustodyV2, DeprecatedCustodyV3,
+                DeprecatedCustodyV5, StatsHistory, TradingSchedule,
+            },
+            migration,
             multisig::{AdminInstruction, Multisig},
-            perpetuals::Perpetuals,
+            oracle::OraclePrice,
+            perpetuals::MarketStatus,
             pool::Pool,
         },
     },
     anchor_lang::prelude::*,
-    solana_program::program_memory::sol_memcpy,
-    std::{
-        cmp,
-        io::{self, Write},
-    },
 };
 
-#[derive(Debug, Default)]
-pub struct BpfWriter<T> {
-    inner: T,
-    pos: u64,
-}
-
-impl<T> BpfWriter<T> {
-    pub fn new(inner: T) -> Self {
-        Self { inner, pos: 0 }
-    }
-}
-
-impl Write for BpfWriter<&mut [u8]> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        if self.pos >= self.inner.len() as u64 {
-            return Ok(0);
-        }
-
-        let amt = cmp::min(
-            self.inner.len().saturating_sub(self.pos as usize),
-            buf.len(),
-        );
-        sol_memcpy(&mut self.inner[(self.pos as usize)..], buf, amt);
-        self.pos += amt as u64;
-        Ok(amt)
-    }
-
-    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        if self.write(buf)? == buf.len() {
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::WriteZero,
-                "failed to write whole buffer",
-            ))
-        }
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
-    }
-}
-
 #[derive(Accounts)]
 pub struct UpgradeCustody<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
         seeds = [b"multisig"],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(mut)]
-    /// CHECK: Deprecated custody account
+    /// CHECK: custody account with a previous layout, validated in the instruction handler
     pub custody: AccountInfo<'info>,
 
     system_program: Program<'info, System>,
@@ -96,10 +56,12 @@ pub fn upgrade_custody<'info>(
     params: &UpgradeCustodyParams,
 ) -> Result<u8> {
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::UpgradeCustody, params)?,
     )?;
@@ -111,62 +73,92 @@ pub fn upgrade_custody<'info>(
         return Ok(signatures_left);
     }
 
-    // load deprecated custody data
-    msg!("Load deprecated custody");
     let custody_account = &ctx.accounts.custody;
     if custody_account.owner != &crate::ID {
         return Err(ProgramError::IllegalOwner.into());
     }
-    if custody_account.try_data_len()? != DeprecatedCustody::LEN {
-        return Err(ProgramError::InvalidAccountData.into());
+
+    let data_len = custody_account.try_data_len()?;
+    if Custody::is_current_version(&custody_account.try_borrow_data()?) {
+        msg!("Error: Custody is already upgraded");
+        return Err(ProgramError::AccountAlreadyInitialized.into());
     }
-    let deprecated_custody = Account::<DeprecatedCustody>::try_from_unchecked(custody_account)?;
-
-    // update custody data
-    let custody_data = Custody {
-        pool: deprecated_custody.pool,
-        mint: deprecated_custody.mint,
-        token_account: deprecated_custody.token_account,
-        decimals: deprecated_custody.decimals,
-        is_stable: deprecated_custody.is_stable,
-        is_virtual: false,
-        oracle: deprecated_custody.oracle,
-        pricing: deprecated_custody.pricing,
-        permissions: deprecated_custody.permissions,
-        fees: deprecated_custody.fees,
-        borrow_rate: deprecated_custody.borrow_rate,
-        assets: deprecated_custody.assets,
-        collected_fees: deprecated_custody.collected_fees,
-        volume_stats: deprecated_custody.volume_stats,
-        trade_stats: deprecated_custody.trade_stats,
-        long_positions: deprecated_custody.long_positions,
-        short_positions: deprecated_custody.short_positions,
-        borrow_rate_state: deprecated_custody.borrow_rate_state,
-        bump: deprecated_custody.bump,
-        token_account_bump: deprecated_custody.token_account_bump,
+
+    let custody_data = if Custody::is_version_6(&custody_account.try_borrow_data()?) {
+        // referral fees credited before the upgrade are not reserved
+        msg!("Load custody data");
+        let mut custody =
+            migration::load_deprecated_zero_copy::<Custody>(&custody_account.try_borrow_data()?)?;
+        custody.version = Custody::VERSION;
+        custody
+    } else if data_len == Custody::LEN_V2
+        || data_len == Custody::LEN_V3
+        || data_len == Custody::LEN_V4
+        || data_len == Custody::LEN_V5
+    {
+        // cached AUM value starts zeroed, the same as in the upgraded pool,
+        // flash loans are disabled until the fee is set, staking rewards
+        // accrued before the upgrade are not reserved and collected flash
+        // loan fees are moved into collected fees
+        msg!("Load custody data");
+        Custody::from(migration::load_deprecated_zero_copy::<DeprecatedCustodyV5>(
+            &custody_account.try_borrow_data()?,
+        )?)
+    } else if data_len == DeprecatedCustody::LEN {
+        // custodies created before the stats were widened can't be padded in place
+        // and are converted field by field first
+        msg!("Load deprecated custody");
+        let deprecated_custody = Account::<DeprecatedCustody>::try_from_unchecked(custody_account)?;
+        Custody::from(DeprecatedCustodyV3 {
+            pool: deprecated_custody.pool,
+            mint: deprecated_custody.mint,
+            token_account: deprecated_custody.token_account,
+            decimals: deprecated_custody.decimals,
+            is_stable: deprecated_custody.is_stable,
+            is_virtual: false,
+            oracle: deprecated_custody.oracle,
+            pricing: deprecated_custody.pricing,
+            permissions: deprecated_custody.permissions,
+            fees: deprecated_custody.fees,
+            borrow_rate: deprecated_custody.borrow_rate,
+            assets: deprecated_custody.assets,
+            collected_fees: deprecated_custody.collected_fees.into(),
+            volume_stats: deprecated_custody.volume_stats.into(),
+            trade_stats: deprecated_custody.trade_stats,
+            long_positions: deprecated_custody.long_positions,
+            short_positions: deprecated_custody.short_positions,
+            borrow_rate_state: deprecated_custody.borrow_rate_state,
+            bump: deprecated_custody.bump,
+            token_account_bump: deprecated_custody.token_account_bump,
+            status: MarketStatus::Active,
+            settlement_price: OraclePrice::default(),
+            trading_schedule: TradingSchedule::default(),
+            stats_history: StatsHistory::default(),
+            version: 0,
+        })
+    } else if data_len <= DeprecatedCustodyV2::LEN {
+        msg!("Load deprecated custody");
+        Custody::from(DeprecatedCustodyV3::from(
+            Account::<DeprecatedCustodyV2>::try_from_unchecked(custody_account)?.into_inner(),
+        ))
+    } else {
+        // trading schedule and stats history of version 0 accounts start empty
+        msg!("Load custody data");
+        Custody::from(migration::load_deprecated::<DeprecatedCustodyV3>(
+            &custody_account.try_borrow_data()?,
+        )?)
     };
 
-    if !custody_data.validate() {
+    if custody_data.pool != ctx.accounts.pool.key() || !custody_data.validate() {
         return err!(PerpetualsError::InvalidCustodyConfig);
     }
 
-    msg!("Resize custody account");
-    Perpetuals::realloc(
+    migration::write_zero_copy_account(
         ctx.accounts.admin.to_account_info(),
         ctx.accounts.custody.clone(),
         ctx.accounts.system_program.to_account_info(),
-        Custody::LEN,
-        true,
+        &custody_data,
     )?;
 
-    msg!("Re-initialize the custody");
-    if custody_account.try_data_len()? != Custody::LEN {
-        return Err(ProgramError::InvalidAccountData.into());
-    }
-    let mut data = custody_account.try_borrow_mut_data()?;
-    let dst: &mut [u8] = &mut data;
-    let mut writer = BpfWriter::new(dst);
-    custody_data.try_serialize(&mut writer)?;
-
     Ok(0)
 }
@@ -0,0 +1,76 @@
+//! UpgradeMultisig instruction handler
+
+use {
+    crate::state::{
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct UpgradeMultisig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// CHECK: root or role multisig account with a previous layout, validated
+    /// in the instruction handler
+    #[account(mut)]
+    pub multisig: AccountInfo<'info>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpgradeMultisigParams {}
+
+pub fn upgrade_multisig<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpgradeMultisig<'info>>,
+    params: &UpgradeMultisigParams,
+) -> Result<u8> {
+    // load multisig data
+    msg!("Load multisig data");
+    let multisig_account = &ctx.accounts.multisig;
+    if multisig_account.owner != &crate::ID {
+        return Err(ProgramError::IllegalOwner.into());
+    }
+    let multisig_data = Multisig::migrate_data(&multisig_account.try_borrow_data()?)?;
+
+    // validate signatures, deprecated signers are migrated with equal weights
+    let signatures_left = multisig_data.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::UpgradeMultisig, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    msg!("Resize multisig account");
+    Perpetuals::realloc(
+        ctx.accounts.admin.to_account_info(),
+        ctx.accounts.multisig.clone(),
+        ctx.accounts.system_program.to_account_info(),
+        Multisig::LEN,
+        true,
+    )?;
+
+    msg!("Re-initialize the multisig");
+    if multisig_account.try_data_len()? != Multisig::LEN {
+        return Err(ProgramError::InvalidAccountData.into());
+    }
+    let mut data = multisig_account.try_borrow_mut_data()?;
+    data[8..].copy_from_slice(bytemuck::bytes_of(&multisig_data));
+
+    Ok(0)
+}
@@ -0,0 +1,70 @@
+//! UpgradePerpetuals instruction handler
+
+use {
+    crate::state::{
+        migration,
+        multisig::{AdminInstruction, Multisig},
+        perpetuals::Perpetuals,
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct UpgradePerpetuals<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    /// CHECK: perpetuals account with a previous layout, validated in the instruction handler
+    #[account(
+        mut,
+        seeds = [b"perpetuals"],
+        bump
+    )]
+    pub perpetuals: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpgradePerpetualsParams {}
+
+pub fn upgrade_perpetuals<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpgradePerpetuals<'info>>,
+    params: &UpgradePerpetualsParams,
+) -> Result<u8> {
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::UpgradePerpetuals, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    migration::upgrade_account::<Perpetuals>(
+        ctx.accounts.admin.to_account_info(),
+        ctx.accounts.perpetuals.clone(),
+        ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    Ok(0)
+}
@@ -0,0 +1,112 @@
+//! UpgradePool instruction handler
+
+use {
+    crate::{
+        error::PerpetualsError,
+        state::{
+            migration,
+            multisig::{AdminInstruction, Multisig},
+            pool::{DeprecatedPool, Pool},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct UpgradePool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"multisig"],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
+    /// CHECK: pool account with a previous layout, validated in the instruction handler
+    #[account(mut)]
+    pub pool: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpgradePoolParams {}
+
+pub fn upgrade_pool<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpgradePool<'info>>,
+    params: &UpgradePoolParams,
+) -> Result<u8> {
+    // validate signatures
+    let multisig = ctx.accounts.multisig.load()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::UpgradePool, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    let pool_account = &ctx.accounts.pool;
+    if pool_account.owner != &crate::ID {
+        return Err(ProgramError::IllegalOwner.into());
+    }
+    let data_len = pool_account.try_data_len()?;
+    if data_len == Pool::LEN {
+        msg!("Error: Pool is already upgraded");
+        return Err(ProgramError::AccountAlreadyInitialized.into());
+    }
+
+    let pool = if data_len == Pool::LEN_V2 || data_len == Pool::LEN_V3 {
+        // fields appended after the loaded layout start zeroed, so the keeper
+        // reward is disabled until it is set in the pool config
+        msg!("Load pool data");
+        let mut pool = migration::load_zero_copy::<Pool>(&pool_account.try_borrow_data()?)?;
+        if data_len == Pool::LEN_V2 {
+            // AUM values of pool custodies are cached from now on, they
+            // start zeroed as custodies are upgraded with zero values too
+            pool.max_aum_age_sec = Pool::get_default_config("").max_aum_age_sec;
+        }
+        pool.version = Pool::VERSION;
+        pool
+    } else {
+        // pools created before the pool config was added get it initialized
+        // with the values that were used before it was introduced
+        msg!("Load deprecated pool");
+        Pool::try_from(migration::load_deprecated::<DeprecatedPool>(
+            &pool_account.try_borrow_data()?,
+        )?)?
+    };
+
+    let expected_address =
+        Pubkey::create_program_address(&[b"pool", pool.get_name(), &[pool.bump]], &crate::ID)
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+    if expected_address != ctx.accounts.pool.key() {
+        return Err(ProgramError::InvalidSeeds.into());
+    }
+
+    if !pool.validate() {
+        return err!(PerpetualsError::InvalidPoolConfig);
+    }
+
+    migration::write_zero_copy_account(
+        ctx.accounts.admin.to_account_info(),
+        ctx.accounts.pool.clone(),
+        ctx.accounts.system_program.to_account_info(),
+        &pool,
+    )?;
+
+    Ok(0)
+}
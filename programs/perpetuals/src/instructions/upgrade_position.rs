@@ -0,0 +1,51 @@
+//! UpgradePosition instruction handler
+
+use {
+    crate::state::{migration, position::Position},
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct UpgradePosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: position account with a previous layout, validated in the instruction handler
+    #[account(mut)]
+    pub position: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpgradePositionParams {}
+
+pub fn upgrade_position(
+    ctx: Context<UpgradePosition>,
+    _params: &UpgradePositionParams,
+) -> Result<()> {
+    // upgrade is permissionless, payer only covers rent for the extra space
+    let position = migration::upgrade_account::<Position>(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.position.clone(),
+        ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let expected_address = Pubkey::create_program_address(
+        &[
+            b"position",
+            position.owner.as_ref(),
+            position.pool.as_ref(),
+            position.custody.as_ref(),
+            &[position.side as u8],
+            &[position.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| ProgramError::InvalidSeeds)?;
+    if expected_address != ctx.accounts.position.key() {
+        return Err(ProgramError::InvalidSeeds.into());
+    }
+
+    Ok(())
+}
@@ -2,10 +2,11 @@
 
 use {
     crate::{
+        error::PerpetualsError,
         math,
         state::{
             custody::Custody,
-            multisig::{AdminInstruction, Multisig},
+            multisig::{AdminInstruction, AdminRole, Multisig},
             perpetuals::Perpetuals,
             pool::Pool,
         },
@@ -16,16 +17,22 @@ use {
 
 #[derive(Accounts)]
 pub struct WithdrawFees<'info> {
-    #[account()]
+    #[account(mut)]
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"multisig"],
-        bump = multisig.load()?.bump
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::Treasurer,
+            &Pubkey::default()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     /// CHECK: empty PDA, authority for token accounts
     #[account(
         seeds = [b"transfer_authority"],
@@ -42,26 +49,26 @@ pub struct WithdrawFees<'info> {
     #[account(
         mut,
         seeds = [b"pool",
-                 pool.name.as_bytes()],
-        bump = pool.bump
+                 pool.load()?.get_name()],
+        bump = pool.load()?.bump
     )]
-    pub pool: Box<Account<'info, Pool>>,
+    pub pool: AccountLoader<'info, Pool>,
 
     #[account(
         mut,
         seeds = [b"custody",
                  pool.key().as_ref(),
-                 custody.mint.key().as_ref()],
-        bump = custody.bump
+                 custody.load()?.mint.key().as_ref()],
+        bump = custody.load()?.bump
     )]
-    pub custody: Box<Account<'info, Custody>>,
+    pub custody: AccountLoader<'info, Custody>,
 
     #[account(
         mut,
         seeds = [b"custody_token_account",
                  pool.key().as_ref(),
-                 custody.mint.as_ref()],
-        bump = custody.token_account_bump
+                 custody.load()?.mint.as_ref()],
+        bump = custody.load()?.token_account_bump
     )]
     pub custody_token_account: Box<Account<'info, TokenAccount>>,
 
@@ -72,6 +79,7 @@ pub struct WithdrawFees<'info> {
     pub receiving_token_account: Box<Account<'info, TokenAccount>>,
 
     token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -89,10 +97,12 @@ pub fn withdraw_fees<'info>(
     }
 
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::WithdrawFees, params)?,
     )?;
@@ -105,13 +115,11 @@ pub fn withdraw_fees<'info>(
     }
 
     // transfer token fees from the custody to the receiver
-    let custody = ctx.accounts.custody.as_mut();
+    let mut custody = ctx.accounts.custody.load_mut()?;
 
-    msg!(
-        "Withdraw token fees: {} / {}",
-        params.amount,
+    msg!("Withdraw token fees: {} / {}", params.amount, {
         custody.assets.protocol_fees
-    );
+    });
 
     if custody.assets.protocol_fees < params.amount {
         return Err(ProgramError::InsufficientFunds.into());
@@ -2,9 +2,10 @@
 
 use {
     crate::{
+        error::PerpetualsError,
         math,
         state::{
-            multisig::{AdminInstruction, Multisig},
+            multisig::{AdminInstruction, AdminRole, Multisig},
             perpetuals::Perpetuals,
         },
     },
@@ -14,16 +15,22 @@ use {
 
 #[derive(Accounts)]
 pub struct WithdrawSolFees<'info> {
-    #[account()]
+    #[account(mut)]
     pub admin: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"multisig"],
-        bump = multisig.load()?.bump
+        constraint = multisig.load()?.is_authorized(
+            &multisig.key(),
+            AdminRole::Treasurer,
+            &Pubkey::default()
+        ) @ PerpetualsError::MultisigAccountNotAuthorized
     )]
     pub multisig: AccountLoader<'info, Multisig>,
 
+    /// CHECK: multisig proposal PDA, created and validated in the instruction handler
+    #[account(mut)]
+    pub proposal: AccountInfo<'info>,
+
     /// CHECK: empty PDA, authority for token accounts
     #[account(
         seeds = [b"transfer_authority"],
@@ -43,6 +50,8 @@ pub struct WithdrawSolFees<'info> {
         constraint = receiving_account.data_is_empty()
     )]
     pub receiving_account: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -60,10 +69,12 @@ pub fn withdraw_sol_fees<'info>(
     }
 
     // validate signatures
-    let mut multisig = ctx.accounts.multisig.load_mut()?;
+    let multisig = ctx.accounts.multisig.load()?;
 
     let signatures_left = multisig.sign_multisig(
         &ctx.accounts.admin,
+        &ctx.accounts.proposal,
+        &ctx.accounts.system_program,
         &Multisig::get_account_infos(&ctx)[1..],
         &Multisig::get_instruction_data(AdminInstruction::WithdrawSolFees, params)?,
     )?;
@@ -3,6 +3,7 @@
 #![allow(clippy::result_large_err)]
 
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod math;
 pub mod state;
@@ -11,7 +12,8 @@ use {
     anchor_lang::prelude::*,
     instructions::*,
     state::perpetuals::{
-        AmountAndFee, NewPositionPricesAndFee, PriceAndFee, ProfitAndLoss, SwapAmountAndFees,
+        AmountAndFee, CustodyStats, NewPositionPricesAndFee, PriceAndFee, ProfitAndLoss,
+        SwapAmountAndFees,
     },
 };
 
@@ -77,6 +79,55 @@ pub mod perpetuals {
         instructions::set_custody_config(ctx, &params)
     }
 
+    pub fn set_borrow_rate<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetBorrowRate<'info>>,
+        params: SetBorrowRateParams,
+    ) -> Result<u8> {
+        instructions::set_borrow_rate(ctx, &params)
+    }
+
+    pub fn update_custody_config<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdateCustodyConfig<'info>>,
+        params: UpdateCustodyConfigParams,
+    ) -> Result<u8> {
+        instructions::update_custody_config(ctx, &params)
+    }
+
+    pub fn set_config_bounds<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetConfigBounds<'info>>,
+        params: SetConfigBoundsParams,
+    ) -> Result<u8> {
+        instructions::set_config_bounds(ctx, &params)
+    }
+
+    pub fn set_market_status<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetMarketStatus<'info>>,
+        params: SetMarketStatusParams,
+    ) -> Result<u8> {
+        instructions::set_market_status(ctx, &params)
+    }
+
+    pub fn set_pool_config<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetPoolConfig<'info>>,
+        params: SetPoolConfigParams,
+    ) -> Result<u8> {
+        instructions::set_pool_config(ctx, &params)
+    }
+
+    pub fn set_trading_schedule<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetTradingSchedule<'info>>,
+        params: SetTradingScheduleParams,
+    ) -> Result<u8> {
+        instructions::set_trading_schedule(ctx, &params)
+    }
+
+    pub fn settle_custody<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleCustody<'info>>,
+        params: SettleCustodyParams,
+    ) -> Result<u8> {
+        instructions::settle_custody(ctx, &params)
+    }
+
     pub fn set_permissions<'info>(
         ctx: Context<'_, '_, '_, 'info, SetPermissions<'info>>,
         params: SetPermissionsParams,
@@ -84,6 +135,13 @@ pub mod perpetuals {
         instructions::set_permissions(ctx, &params)
     }
 
+    pub fn set_role_signers<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetRoleSigners<'info>>,
+        params: SetRoleSignersParams,
+    ) -> Result<u8> {
+        instructions::set_role_signers(ctx, &params)
+    }
+
     pub fn withdraw_fees<'info>(
         ctx: Context<'_, '_, '_, 'info, WithdrawFees<'info>>,
         params: WithdrawFeesParams,
@@ -105,6 +163,48 @@ pub mod perpetuals {
         instructions::upgrade_custody(ctx, &params)
     }
 
+    pub fn upgrade_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpgradePool<'info>>,
+        params: UpgradePoolParams,
+    ) -> Result<u8> {
+        instructions::upgrade_pool(ctx, &params)
+    }
+
+    pub fn set_staking_config<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetStakingConfig<'info>>,
+        params: SetStakingConfigParams,
+    ) -> Result<u8> {
+        instructions::set_staking_config(ctx, &params)
+    }
+
+    pub fn enable_tranches<'info>(
+        ctx: Context<'_, '_, '_, 'info, EnableTranches<'info>>,
+        params: EnableTranchesParams,
+    ) -> Result<u8> {
+        instructions::enable_tranches(ctx, &params)
+    }
+
+    pub fn set_fee_tiers<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetFeeTiers<'info>>,
+        params: SetFeeTiersParams,
+    ) -> Result<u8> {
+        instructions::set_fee_tiers(ctx, &params)
+    }
+
+    pub fn upgrade_perpetuals<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpgradePerpetuals<'info>>,
+        params: UpgradePerpetualsParams,
+    ) -> Result<u8> {
+        instructions::upgrade_perpetuals(ctx, &params)
+    }
+
+    pub fn upgrade_multisig<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpgradeMultisig<'info>>,
+        params: UpgradeMultisigParams,
+    ) -> Result<u8> {
+        instructions::upgrade_multisig(ctx, &params)
+    }
+
     pub fn set_custom_oracle_price<'info>(
         ctx: Context<'_, '_, '_, 'info, SetCustomOraclePrice<'info>>,
         params: SetCustomOraclePriceParams,
@@ -112,6 +212,10 @@ pub mod perpetuals {
         instructions::set_custom_oracle_price(ctx, &params)
     }
 
+    pub fn close_proposal(ctx: Context<CloseProposal>, params: CloseProposalParams) -> Result<()> {
+        instructions::close_proposal(ctx, &params)
+    }
+
     // test instructions
 
     pub fn set_test_time<'info>(
@@ -131,6 +235,13 @@ pub mod perpetuals {
         instructions::add_liquidity(ctx, &params)
     }
 
+    pub fn add_liquidity_multi<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddLiquidityMulti<'info>>,
+        params: AddLiquidityMultiParams,
+    ) -> Result<()> {
+        instructions::add_liquidity_multi(ctx, &params)
+    }
+
     pub fn remove_liquidity(
         ctx: Context<RemoveLiquidity>,
         params: RemoveLiquidityParams,
@@ -138,6 +249,71 @@ pub mod perpetuals {
         instructions::remove_liquidity(ctx, &params)
     }
 
+    pub fn remove_liquidity_in_kind<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveLiquidityInKind<'info>>,
+        params: RemoveLiquidityInKindParams,
+    ) -> Result<()> {
+        instructions::remove_liquidity_in_kind(ctx, &params)
+    }
+
+    pub fn unlock_lp_tokens(
+        ctx: Context<UnlockLpTokens>,
+        params: UnlockLpTokensParams,
+    ) -> Result<()> {
+        instructions::unlock_lp_tokens(ctx, &params)
+    }
+
+    pub fn request_redemption(
+        ctx: Context<RequestRedemption>,
+        params: RequestRedemptionParams,
+    ) -> Result<()> {
+        instructions::request_redemption(ctx, &params)
+    }
+
+    pub fn cancel_redemption(
+        ctx: Context<CancelRedemption>,
+        params: CancelRedemptionParams,
+    ) -> Result<()> {
+        instructions::cancel_redemption(ctx, &params)
+    }
+
+    pub fn process_redemption_queue(
+        ctx: Context<ProcessRedemptionQueue>,
+        params: ProcessRedemptionQueueParams,
+    ) -> Result<()> {
+        instructions::process_redemption_queue(ctx, &params)
+    }
+
+    pub fn stake(ctx: Context<Stake>, params: StakeParams) -> Result<()> {
+        instructions::stake(ctx, &params)
+    }
+
+    pub fn unstake(ctx: Context<Unstake>, params: UnstakeParams) -> Result<()> {
+        instructions::unstake(ctx, &params)
+    }
+
+    pub fn claim_rewards(ctx: Context<ClaimRewards>, params: ClaimRewardsParams) -> Result<()> {
+        instructions::claim_rewards(ctx, &params)
+    }
+
+    pub fn create_referrer(
+        ctx: Context<CreateReferrer>,
+        params: CreateReferrerParams,
+    ) -> Result<()> {
+        instructions::create_referrer(ctx, &params)
+    }
+
+    pub fn set_referrer(ctx: Context<SetReferrer>, params: SetReferrerParams) -> Result<()> {
+        instructions::set_referrer(ctx, &params)
+    }
+
+    pub fn claim_referral_fees(
+        ctx: Context<ClaimReferralFees>,
+        params: ClaimReferralFeesParams,
+    ) -> Result<()> {
+        instructions::claim_referral_fees(ctx, &params)
+    }
+
     pub fn open_position(ctx: Context<OpenPosition>, params: OpenPositionParams) -> Result<()> {
         instructions::open_position(ctx, &params)
     }
@@ -161,10 +337,36 @@ pub mod perpetuals {
         instructions::liquidate(ctx, &params)
     }
 
+    pub fn settle_position(
+        ctx: Context<SettlePosition>,
+        params: SettlePositionParams,
+    ) -> Result<()> {
+        instructions::settle_position(ctx, &params)
+    }
+
     pub fn update_pool_aum(ctx: Context<UpdatePoolAum>) -> Result<u128> {
         instructions::update_pool_aum(ctx)
     }
 
+    pub fn crank_pool(ctx: Context<CrankPool>) -> Result<u64> {
+        instructions::crank_pool(ctx)
+    }
+
+    pub fn flash_borrow(ctx: Context<FlashBorrow>, params: FlashBorrowParams) -> Result<()> {
+        instructions::flash_borrow(ctx, &params)
+    }
+
+    pub fn flash_repay(ctx: Context<FlashRepay>, params: FlashRepayParams) -> Result<()> {
+        instructions::flash_repay(ctx, &params)
+    }
+
+    pub fn upgrade_position(
+        ctx: Context<UpgradePosition>,
+        params: UpgradePositionParams,
+    ) -> Result<()> {
+        instructions::upgrade_position(ctx, &params)
+    }
+
     pub fn get_add_liquidity_amount_and_fee(
         ctx: Context<GetAddLiquidityAmountAndFee>,
         params: GetAddLiquidityAmountAndFeeParams,
@@ -232,6 +434,13 @@ pub mod perpetuals {
         instructions::get_assets_under_management(ctx, &params)
     }
 
+    pub fn get_custody_stats(
+        ctx: Context<GetCustodyStats>,
+        params: GetCustodyStatsParams,
+    ) -> Result<CustodyStats> {
+        instructions::get_custody_stats(ctx, &params)
+    }
+
     pub fn get_lp_token_price(
         ctx: Context<GetLpTokenPrice>,
         params: GetLpTokenPriceParams,
@@ -1,8 +1,15 @@
 // Program state handling.
 
+pub mod config_bounds;
 pub mod custody;
+pub mod migration;
 pub mod multisig;
 pub mod oracle;
 pub mod perpetuals;
 pub mod pool;
 pub mod position;
+pub mod redemption_queue;
+pub mod referral;
+pub mod staking;
+pub mod user_deposit;
+pub mod user_stats;
@@ -0,0 +1,274 @@
+use {
+    crate::state::{
+        custody::{BorrowRateParams, Fees, PricingParams},
+        migration::Versioned,
+        oracle::OracleParams,
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct Bounds {
+    pub min: u64,
+    pub max: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct FeesBounds {
+    pub ratio_mult: Bounds,
+    pub utilization_mult: Bounds,
+    pub swap_in: Bounds,
+    pub swap_out: Bounds,
+    pub stable_swap_in: Bounds,
+    pub stable_swap_out: Bounds,
+    pub add_liquidity: Bounds,
+    pub remove_liquidity: Bounds,
+    pub open_position: Bounds,
+    pub close_position: Bounds,
+    pub liquidation: Bounds,
+    pub protocol_share: Bounds,
+    pub fee_max: Bounds,
+    pub fee_optimal: Bounds,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PricingBounds {
+    pub trade_spread_long: Bounds,
+    pub trade_spread_short: Bounds,
+    pub swap_spread: Bounds,
+    pub min_initial_leverage: Bounds,
+    pub max_initial_leverage: Bounds,
+    pub max_leverage: Bounds,
+    pub max_payoff_mult: Bounds,
+    pub max_utilization: Bounds,
+    pub max_position_locked_usd: Bounds,
+    pub max_total_locked_usd: Bounds,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct BorrowRateBounds {
+    pub base_rate: Bounds,
+    pub slope1: Bounds,
+    pub slope2: Bounds,
+    pub optimal_utilization: Bounds,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct OracleBounds {
+    pub max_price_error: Bounds,
+    pub max_price_age_sec: Bounds,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CustodyBounds {
+    pub oracle: OracleBounds,
+    pub pricing: PricingBounds,
+    pub fees: FeesBounds,
+    pub borrow_rate: BorrowRateBounds,
+    pub flash_loan_fee: Bounds,
+}
+
+/// Governance-set limits for custody params that can be changed by the risk manager
+#[account]
+#[derive(Default, Debug)]
+pub struct ConfigBounds {
+    pub custody: CustodyBounds,
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+impl Bounds {
+    pub fn validate(&self) -> bool {
+        self.min <= self.max
+    }
+
+    pub fn contains(&self, value: u64) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+
+impl FeesBounds {
+    pub fn validate(&self) -> bool {
+        self.ratio_mult.validate()
+            && self.utilization_mult.validate()
+            && self.swap_in.validate()
+            && self.swap_out.validate()
+            && self.stable_swap_in.validate()
+            && self.stable_swap_out.validate()
+            && self.add_liquidity.validate()
+            && self.remove_liquidity.validate()
+            && self.open_position.validate()
+            && self.close_position.validate()
+            && self.liquidation.validate()
+            && self.protocol_share.validate()
+            && self.fee_max.validate()
+            && self.fee_optimal.validate()
+    }
+
+    pub fn contains(&self, fees: &Fees) -> bool {
+        self.ratio_mult.contains(fees.ratio_mult)
+            && self.utilization_mult.contains(fees.utilization_mult)
+            && self.swap_in.contains(fees.swap_in)
+            && self.swap_out.contains(fees.swap_out)
+            && self.stable_swap_in.contains(fees.stable_swap_in)
+            && self.stable_swap_out.contains(fees.stable_swap_out)
+            && self.add_liquidity.contains(fees.add_liquidity)
+            && self.remove_liquidity.contains(fees.remove_liquidity)
+            && self.open_position.contains(fees.open_position)
+            && self.close_position.contains(fees.close_position)
+            && self.liquidation.contains(fees.liquidation)
+            && self.protocol_share.contains(fees.protocol_share)
+            && self.fee_max.contains(fees.fee_max)
+            && self.fee_optimal.contains(fees.fee_optimal)
+    }
+}
+
+impl PricingBounds {
+    pub fn validate(&self) -> bool {
+        self.trade_spread_long.validate()
+            && self.trade_spread_short.validate()
+            && self.swap_spread.validate()
+            && self.min_initial_leverage.validate()
+            && self.max_initial_leverage.validate()
+            && self.max_leverage.validate()
+            && self.max_payoff_mult.validate()
+            && self.max_utilization.validate()
+            && self.max_position_locked_usd.validate()
+            && self.max_total_locked_usd.validate()
+    }
+
+    pub fn contains(&self, pricing: &PricingParams) -> bool {
+        self.trade_spread_long.contains(pricing.trade_spread_long)
+            && self.trade_spread_short.contains(pricing.trade_spread_short)
+            && self.swap_spread.contains(pricing.swap_spread)
+            && self
+                .min_initial_leverage
+                .contains(pricing.min_initial_leverage)
+            && self
+                .max_initial_leverage
+                .contains(pricing.max_initial_leverage)
+            && self.max_leverage.contains(pricing.max_leverage)
+            && self.max_payoff_mult.contains(pricing.max_payoff_mult)
+            && self.max_utilization.contains(pricing.max_utilization)
+            && self
+                .max_position_locked_usd
+                .contains(pricing.max_position_locked_usd)
+            && self
+                .max_total_locked_usd
+                .contains(pricing.max_total_locked_usd)
+    }
+}
+
+impl BorrowRateBounds {
+    pub fn validate(&self) -> bool {
+        self.base_rate.validate()
+            && self.slope1.validate()
+            && self.slope2.validate()
+            && self.optimal_utilization.validate()
+    }
+
+    pub fn contains(&self, borrow_rate: &BorrowRateParams) -> bool {
+        self.base_rate.contains(borrow_rate.base_rate)
+            && self.slope1.contains(borrow_rate.slope1)
+            && self.slope2.contains(borrow_rate.slope2)
+            && self
+                .optimal_utilization
+                .contains(borrow_rate.optimal_utilization)
+    }
+}
+
+impl OracleBounds {
+    pub fn validate(&self) -> bool {
+        self.max_price_error.validate() && self.max_price_age_sec.validate()
+    }
+
+    pub fn contains(&self, oracle: &OracleParams) -> bool {
+        self.max_price_error.contains(oracle.max_price_error)
+            && self
+                .max_price_age_sec
+                .contains(oracle.max_price_age_sec as u64)
+    }
+}
+
+impl CustodyBounds {
+    pub fn validate(&self) -> bool {
+        self.oracle.validate()
+            && self.pricing.validate()
+            && self.fees.validate()
+            && self.borrow_rate.validate()
+            && self.flash_loan_fee.validate()
+    }
+}
+
+impl Versioned for ConfigBounds {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        ConfigBounds::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ConfigBounds {
+    pub const LEN: usize = 8 + std::mem::size_of::<ConfigBounds>();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bounds() {
+        let bounds = Bounds { min: 10, max: 20 };
+        assert!(bounds.validate());
+        assert!(!bounds.contains(9));
+        assert!(bounds.contains(10));
+        assert!(bounds.contains(20));
+        assert!(!bounds.contains(21));
+
+        assert!(!Bounds { min: 20, max: 10 }.validate());
+    }
+
+    #[test]
+    fn test_borrow_rate_bounds() {
+        let bounds = BorrowRateBounds {
+            base_rate: Bounds { min: 0, max: 0 },
+            slope1: Bounds {
+                min: 50_000,
+                max: 100_000,
+            },
+            slope2: Bounds {
+                min: 100_000,
+                max: 200_000,
+            },
+            optimal_utilization: Bounds {
+                min: 500_000_000,
+                max: 900_000_000,
+            },
+        };
+        assert!(bounds.validate());
+
+        let mut borrow_rate = BorrowRateParams {
+            base_rate: 0,
+            slope1: 80_000,
+            slope2: 120_000,
+            optimal_utilization: 800_000_000,
+        };
+        assert!(bounds.contains(&borrow_rate));
+
+        borrow_rate.slope1 = 120_000;
+        assert!(!bounds.contains(&borrow_rate));
+    }
+}
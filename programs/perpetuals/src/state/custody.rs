@@ -3,8 +3,9 @@ use {
         error::PerpetualsError,
         math,
         state::{
+            migration::EnumFields,
             oracle::{OracleParams, OraclePrice, OracleType},
-            perpetuals::{Permissions, Perpetuals},
+            perpetuals::{MarketStatus, Permissions, Perpetuals},
             position::{Position, Side},
         },
     },
@@ -12,12 +13,17 @@ use {
 };
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[repr(u8)]
 pub enum FeesMode {
     Fixed,
     Linear,
     Optimal,
 }
 
+impl FeesMode {
+    pub const VARIANTS: u8 = FeesMode::Optimal as u8 + 1;
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct Fees {
     pub mode: FeesMode,
@@ -40,23 +46,26 @@ pub struct Fees {
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+// cumulative counters, incremented with saturating_add so they never wrap or halt trading
 pub struct FeesStats {
-    pub swap_usd: u64,
-    pub add_liquidity_usd: u64,
-    pub remove_liquidity_usd: u64,
-    pub open_position_usd: u64,
-    pub close_position_usd: u64,
-    pub liquidation_usd: u64,
+    pub swap_usd: u128,
+    pub add_liquidity_usd: u128,
+    pub remove_liquidity_usd: u128,
+    pub open_position_usd: u128,
+    pub close_position_usd: u128,
+    pub liquidation_usd: u128,
+    // the whole flash loan fee is paid to LPs
+    pub flash_loan_usd: u128,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct VolumeStats {
-    pub swap_usd: u64,
-    pub add_liquidity_usd: u64,
-    pub remove_liquidity_usd: u64,
-    pub open_position_usd: u64,
-    pub close_position_usd: u64,
-    pub liquidation_usd: u64,
+    pub swap_usd: u128,
+    pub add_liquidity_usd: u128,
+    pub remove_liquidity_usd: u128,
+    pub open_position_usd: u128,
+    pub close_position_usd: u128,
+    pub liquidation_usd: u128,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -109,6 +118,53 @@ pub struct BorrowRateParams {
     pub optimal_utilization: u64,
 }
 
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct TradingSchedule {
+    // closes the market regardless of weekly hours
+    pub is_closed: bool,
+    // if not set, the market is open around the clock
+    pub use_weekly_hours: bool,
+    // bit N of the day is set if the market is open during UTC hour N, days start on Monday
+    pub weekly_hours: [u32; 7],
+    // extra spread for closing positions outside trading hours, implied BPS_DECIMALS decimals
+    pub closed_spread: u64,
+    // whether borrow interest accrues outside trading hours
+    pub accrue_borrow_when_closed: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct StatsBucket {
+    // swap and position volume
+    pub volume_usd: u64,
+    // all collected fees, including add and remove liquidity fees
+    pub fees_usd: u64,
+    // peak open interest, long and short combined
+    pub max_oi_usd: u64,
+}
+
+// rolling history of trading activity, buckets are indexed by hour % HOURLY_BUCKETS
+// and day % DAILY_BUCKETS and cleared lazily when the next trade moves past them
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct StatsHistory {
+    pub hourly: [StatsBucket; 24],
+    pub daily: [StatsBucket; 7],
+    // hour of the last recorded trade since unix epoch
+    pub last_hour: i64,
+}
+
+// custody params that can be changed by admins
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CustodyConfig {
+    pub is_stable: bool,
+    pub is_virtual: bool,
+    pub oracle: OracleParams,
+    pub pricing: PricingParams,
+    pub permissions: Permissions,
+    pub fees: Fees,
+    pub borrow_rate: BorrowRateParams,
+    pub flash_loan_fee: u64,
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct BorrowRateState {
     // borrow rates have implied RATE_DECIMALS decimals
@@ -130,7 +186,13 @@ pub struct PositionStats {
     pub cumulative_interest_snapshot: u128,
 }
 
-#[account]
+// Custody is loaded with zero-copy to save compute on the many instructions that
+// read it. Nested config types are shared with instruction params and hold bool
+// and enum fields, hence Pod is implemented unchecked. Custody data is only ever
+// written by the program, so these fields always hold valid values. Enums are
+// single bytes, data of older layouts is checked with EnumFields before the cast.
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
 #[derive(Default, Debug, PartialEq)]
 pub struct Custody {
     // static parameters
@@ -158,6 +220,112 @@ pub struct Custody {
     // bumps for address validation
     pub bump: u8,
     pub token_account_bump: u8,
+
+    pub status: MarketStatus,
+    // oracle price frozen when the custody is settled
+    pub settlement_price: OraclePrice,
+    pub trading_schedule: TradingSchedule,
+    pub stats_history: StatsHistory,
+
+    // layout version, upgrade_custody converts accounts with older layouts
+    pub version: u8,
+
+    // USD value of the custody in pool AUM by AumCalcMode, refreshed whenever
+    // the custody oracle is read. Pool keeps the sum over its custodies.
+    pub aum_usd: [u128; 4],
+
+    // flash loans of free liquidity, the fee has implied BPS_DECIMALS decimals
+    // and zero disables them
+    pub flash_loan_fee: u64,
+    // amount lent by flash_borrow, due by flash_repay in the same transaction
+    pub flash_loan_amount: u64,
+
+    // staking rewards accrued but not yet claimed from the custody,
+    // deducted from its AUM value
+    pub staking_rewards_usd: u128,
+
+    // referrer shares of fees collected by the custody and not yet claimed
+    // from it, deducted from its AUM value
+    pub referral_fees_usd: u128,
+}
+
+// zero-copy custody layout before flash loan fees were moved into FeesStats,
+// accounts of versions 2 to 5 are loaded with it
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
+#[derive(Default, Debug)]
+pub struct DeprecatedCustodyV5 {
+    // static parameters
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub decimals: u8,
+    pub is_stable: bool,
+    pub is_virtual: bool,
+    pub oracle: OracleParams,
+    pub pricing: PricingParams,
+    pub permissions: Permissions,
+    pub fees: Fees,
+    pub borrow_rate: BorrowRateParams,
+
+    // dynamic variables
+    pub assets: Assets,
+    pub collected_fees: DeprecatedFeesStatsV2,
+    pub volume_stats: VolumeStats,
+    pub trade_stats: TradeStats,
+    pub long_positions: PositionStats,
+    pub short_positions: PositionStats,
+    pub borrow_rate_state: BorrowRateState,
+
+    // bumps for address validation
+    pub bump: u8,
+    pub token_account_bump: u8,
+
+    pub status: MarketStatus,
+    pub settlement_price: OraclePrice,
+    pub trading_schedule: TradingSchedule,
+    pub stats_history: StatsHistory,
+
+    pub version: u8,
+
+    pub aum_usd: [u128; 4],
+
+    pub flash_loan_fee: u64,
+    pub flash_loan_amount: u64,
+    pub flash_loan_fees_usd: u128,
+
+    pub staking_rewards_usd: u128,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct DeprecatedFeesStats {
+    pub swap_usd: u64,
+    pub add_liquidity_usd: u64,
+    pub remove_liquidity_usd: u64,
+    pub open_position_usd: u64,
+    pub close_position_usd: u64,
+    pub liquidation_usd: u64,
+}
+
+// fees stats before flash loan fees were added
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct DeprecatedFeesStatsV2 {
+    pub swap_usd: u128,
+    pub add_liquidity_usd: u128,
+    pub remove_liquidity_usd: u128,
+    pub open_position_usd: u128,
+    pub close_position_usd: u128,
+    pub liquidation_usd: u128,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct DeprecatedVolumeStats {
+    pub swap_usd: u64,
+    pub add_liquidity_usd: u64,
+    pub remove_liquidity_usd: u64,
+    pub open_position_usd: u64,
+    pub close_position_usd: u64,
+    pub liquidation_usd: u64,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -192,7 +360,73 @@ pub struct DeprecatedCustody {
 
     // dynamic variables
     pub assets: Assets,
-    pub collected_fees: FeesStats,
+    pub collected_fees: DeprecatedFeesStats,
+    pub volume_stats: DeprecatedVolumeStats,
+    pub trade_stats: TradeStats,
+    pub long_positions: PositionStats,
+    pub short_positions: PositionStats,
+    pub borrow_rate_state: BorrowRateState,
+
+    // bumps for address validation
+    pub bump: u8,
+    pub token_account_bump: u8,
+}
+
+// custody layout before FeesStats and VolumeStats were widened to u128
+#[account]
+#[derive(Default, Debug)]
+pub struct DeprecatedCustodyV2 {
+    // static parameters
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub decimals: u8,
+    pub is_stable: bool,
+    pub is_virtual: bool,
+    pub oracle: OracleParams,
+    pub pricing: PricingParams,
+    pub permissions: Permissions,
+    pub fees: Fees,
+    pub borrow_rate: BorrowRateParams,
+
+    // dynamic variables
+    pub assets: Assets,
+    pub collected_fees: DeprecatedFeesStats,
+    pub volume_stats: DeprecatedVolumeStats,
+    pub trade_stats: TradeStats,
+    pub long_positions: PositionStats,
+    pub short_positions: PositionStats,
+    pub borrow_rate_state: BorrowRateState,
+
+    // bumps for address validation
+    pub bump: u8,
+    pub token_account_bump: u8,
+
+    pub status: MarketStatus,
+    pub settlement_price: OraclePrice,
+    pub trading_schedule: TradingSchedule,
+}
+
+// borsh-serialized custody layout before it was moved to zero-copy
+#[account]
+#[derive(Default, Debug)]
+pub struct DeprecatedCustodyV3 {
+    // static parameters
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub decimals: u8,
+    pub is_stable: bool,
+    pub is_virtual: bool,
+    pub oracle: OracleParams,
+    pub pricing: PricingParams,
+    pub permissions: Permissions,
+    pub fees: Fees,
+    pub borrow_rate: BorrowRateParams,
+
+    // dynamic variables
+    pub assets: Assets,
+    pub collected_fees: DeprecatedFeesStatsV2,
     pub volume_stats: VolumeStats,
     pub trade_stats: TradeStats,
     pub long_positions: PositionStats,
@@ -202,6 +436,16 @@ pub struct DeprecatedCustody {
     // bumps for address validation
     pub bump: u8,
     pub token_account_bump: u8,
+
+    // appended to the end of the account, accounts created before
+    // have zero padding in its place which deserializes as Active
+    pub status: MarketStatus,
+    // oracle price frozen when the custody is settled
+    pub settlement_price: OraclePrice,
+    pub trading_schedule: TradingSchedule,
+    pub stats_history: StatsHistory,
+
+    pub version: u8,
 }
 
 impl Default for FeesMode {
@@ -227,6 +471,62 @@ impl Fees {
     }
 }
 
+impl FeesStats {
+    pub fn get_total_usd(&self) -> Result<u128> {
+        [
+            self.swap_usd,
+            self.add_liquidity_usd,
+            self.remove_liquidity_usd,
+            self.open_position_usd,
+            self.close_position_usd,
+            self.liquidation_usd,
+            self.flash_loan_usd,
+        ]
+        .iter()
+        .try_fold(0u128, |acc, &x| math::checked_add(acc, x))
+    }
+}
+
+impl From<DeprecatedFeesStats> for DeprecatedFeesStatsV2 {
+    fn from(deprecated: DeprecatedFeesStats) -> Self {
+        Self {
+            swap_usd: deprecated.swap_usd as u128,
+            add_liquidity_usd: deprecated.add_liquidity_usd as u128,
+            remove_liquidity_usd: deprecated.remove_liquidity_usd as u128,
+            open_position_usd: deprecated.open_position_usd as u128,
+            close_position_usd: deprecated.close_position_usd as u128,
+            liquidation_usd: deprecated.liquidation_usd as u128,
+        }
+    }
+}
+
+impl DeprecatedFeesStatsV2 {
+    fn upgrade(self, flash_loan_usd: u128) -> FeesStats {
+        FeesStats {
+            swap_usd: self.swap_usd,
+            add_liquidity_usd: self.add_liquidity_usd,
+            remove_liquidity_usd: self.remove_liquidity_usd,
+            open_position_usd: self.open_position_usd,
+            close_position_usd: self.close_position_usd,
+            liquidation_usd: self.liquidation_usd,
+            flash_loan_usd,
+        }
+    }
+}
+
+impl From<DeprecatedVolumeStats> for VolumeStats {
+    fn from(deprecated: DeprecatedVolumeStats) -> Self {
+        Self {
+            swap_usd: deprecated.swap_usd as u128,
+            add_liquidity_usd: deprecated.add_liquidity_usd as u128,
+            remove_liquidity_usd: deprecated.remove_liquidity_usd as u128,
+            open_position_usd: deprecated.open_position_usd as u128,
+            close_position_usd: deprecated.close_position_usd as u128,
+            liquidation_usd: deprecated.liquidation_usd as u128,
+        }
+    }
+}
+
 impl OracleParams {
     pub fn validate(&self) -> bool {
         self.oracle_type == OracleType::None || self.oracle_account != Pubkey::default()
@@ -252,17 +552,246 @@ impl BorrowRateParams {
     }
 }
 
+impl TradingSchedule {
+    const SECONDS_PER_HOUR: i64 = 3_600;
+    const SECONDS_PER_DAY: i64 = 86_400;
+    const SECONDS_PER_WEEK: i64 = 604_800;
+    const ALL_HOURS: u32 = (1 << 24) - 1;
+
+    pub fn validate(&self) -> bool {
+        (self.closed_spread as u128) < Perpetuals::BPS_POWER
+            && self.weekly_hours.iter().all(|&x| x <= Self::ALL_HOURS)
+    }
+
+    pub fn is_open(&self, curtime: i64) -> bool {
+        if self.is_closed {
+            return false;
+        }
+        if !self.use_weekly_hours {
+            return true;
+        }
+        // unix epoch started on Thursday
+        let day = (curtime.div_euclid(Self::SECONDS_PER_DAY) + 3).rem_euclid(7) as usize;
+        let hour = curtime.rem_euclid(Self::SECONDS_PER_DAY) / Self::SECONDS_PER_HOUR;
+        self.weekly_hours[day] & (1 << hour) != 0
+    }
+
+    pub fn get_closed_spread(&self, curtime: i64) -> u64 {
+        if self.is_open(curtime) {
+            0
+        } else {
+            self.closed_spread
+        }
+    }
+
+    /// Returns the number of seconds between start_time and end_time during which
+    /// borrow interest accrues
+    pub fn get_accrual_time(&self, start_time: i64, end_time: i64) -> Result<i64> {
+        if end_time <= start_time {
+            return Ok(0);
+        }
+        if self.accrue_borrow_when_closed || (!self.is_closed && !self.use_weekly_hours) {
+            return math::checked_sub(end_time, start_time);
+        }
+        if self.is_closed {
+            return Ok(0);
+        }
+
+        // every full week has the same number of open hours
+        let elapsed = math::checked_sub(end_time, start_time)?;
+        let full_weeks = elapsed / Self::SECONDS_PER_WEEK;
+        let open_hours_per_week: u32 = self.weekly_hours.iter().map(|x| x.count_ones()).sum();
+        let mut accrual_time = math::checked_mul(
+            full_weeks,
+            math::checked_mul(open_hours_per_week as i64, Self::SECONDS_PER_HOUR)?,
+        )?;
+
+        // walk the remaining time hour by hour
+        let mut time = math::checked_add(start_time, full_weeks * Self::SECONDS_PER_WEEK)?;
+        while time < end_time {
+            let next_hour = std::cmp::min(
+                math::checked_mul(
+                    time.div_euclid(Self::SECONDS_PER_HOUR) + 1,
+                    Self::SECONDS_PER_HOUR,
+                )?,
+                end_time,
+            );
+            if self.is_open(time) {
+                accrual_time = math::checked_add(accrual_time, next_hour - time)?;
+            }
+            time = next_hour;
+        }
+
+        Ok(accrual_time)
+    }
+}
+
+impl StatsBucket {
+    fn add(&mut self, other: &StatsBucket) {
+        self.volume_usd = self.volume_usd.saturating_add(other.volume_usd);
+        self.fees_usd = self.fees_usd.saturating_add(other.fees_usd);
+        self.max_oi_usd = std::cmp::max(self.max_oi_usd, other.max_oi_usd);
+    }
+}
+
+impl StatsHistory {
+    pub const HOURLY_BUCKETS: i64 = 24;
+    pub const DAILY_BUCKETS: i64 = 7;
+    const SECONDS_PER_HOUR: i64 = 3_600;
+    const HOURS_PER_DAY: i64 = 24;
+
+    /// Adds a trade to the current buckets, clearing buckets that fell out of the window
+    pub fn record(&mut self, curtime: i64, volume_usd: u64, fees_usd: u64, oi_usd: u64) {
+        let hour = curtime.div_euclid(Self::SECONDS_PER_HOUR);
+        if hour > self.last_hour {
+            Self::clear_expired(&mut self.hourly, self.last_hour, hour);
+            Self::clear_expired(
+                &mut self.daily,
+                self.last_hour.div_euclid(Self::HOURS_PER_DAY),
+                hour.div_euclid(Self::HOURS_PER_DAY),
+            );
+            self.last_hour = hour;
+        }
+
+        let trade = StatsBucket {
+            volume_usd,
+            fees_usd,
+            max_oi_usd: oi_usd,
+        };
+        let day = self.last_hour.div_euclid(Self::HOURS_PER_DAY);
+        self.hourly[self.last_hour.rem_euclid(Self::HOURLY_BUCKETS) as usize].add(&trade);
+        self.daily[day.rem_euclid(Self::DAILY_BUCKETS) as usize].add(&trade);
+    }
+
+    /// Returns totals over the last 24 hours, including the current hour
+    pub fn get_hourly_total(&self, curtime: i64) -> StatsBucket {
+        Self::get_total(
+            &self.hourly,
+            self.last_hour,
+            curtime.div_euclid(Self::SECONDS_PER_HOUR),
+        )
+    }
+
+    /// Returns totals over the last 7 days, including the current day
+    pub fn get_daily_total(&self, curtime: i64) -> StatsBucket {
+        Self::get_total(
+            &self.daily,
+            self.last_hour.div_euclid(Self::HOURS_PER_DAY),
+            curtime
+                .div_euclid(Self::SECONDS_PER_HOUR)
+                .div_euclid(Self::HOURS_PER_DAY),
+        )
+    }
+
+    fn clear_expired(buckets: &mut [StatsBucket], last_period: i64, period: i64) {
+        let len = buckets.len() as i64;
+        let elapsed = std::cmp::min(period - last_period, len);
+        for p in (period - elapsed + 1)..=period {
+            buckets[p.rem_euclid(len) as usize] = StatsBucket::default();
+        }
+    }
+
+    fn get_total(buckets: &[StatsBucket], last_period: i64, period: i64) -> StatsBucket {
+        let len = buckets.len() as i64;
+        let first_period = std::cmp::max(period, last_period) - len + 1;
+        let mut total = StatsBucket::default();
+        for p in first_period..=last_period {
+            total.add(&buckets[p.rem_euclid(len) as usize]);
+        }
+        total
+    }
+}
+
+impl EnumFields for Custody {
+    fn enum_fields() -> Vec<(usize, u8)> {
+        let custody: Custody = bytemuck::Zeroable::zeroed();
+        let base = std::ptr::addr_of!(custody) as usize;
+        vec![
+            (
+                std::ptr::addr_of!(custody.oracle.oracle_type) as usize - base,
+                OracleType::VARIANTS,
+            ),
+            (
+                std::ptr::addr_of!(custody.fees.mode) as usize - base,
+                FeesMode::VARIANTS,
+            ),
+            (
+                std::ptr::addr_of!(custody.status) as usize - base,
+                MarketStatus::VARIANTS,
+            ),
+        ]
+    }
+}
+
 impl Custody {
     pub const LEN: usize = 8 + std::mem::size_of::<Custody>();
+    // zero-copy layout before referral fees were reserved
+    pub const LEN_V6: usize = Custody::LEN - std::mem::size_of::<u128>();
+    // zero-copy layout before flash loan fees were moved into FeesStats, of the same size
+    pub const LEN_V5: usize = 8 + std::mem::size_of::<DeprecatedCustodyV5>();
+    // zero-copy layout before staking rewards were reserved
+    pub const LEN_V4: usize = Custody::LEN_V5 - std::mem::size_of::<u128>();
+    // zero-copy layout before flash loans were added
+    pub const LEN_V3: usize =
+        Custody::LEN_V4 - 2 * std::mem::size_of::<u64>() - std::mem::size_of::<u128>();
+    // zero-copy layout before the AUM value was cached
+    pub const LEN_V2: usize = Custody::LEN_V3 - std::mem::size_of::<[u128; 4]>();
+    pub const VERSION: u8 = 7;
+
+    /// Returns true if the data holds a custody of the current version
+    pub fn is_current_version(data: &[u8]) -> bool {
+        data.len() == Custody::LEN && data[Custody::get_version_offset()] == Custody::VERSION
+    }
+
+    /// Returns true if the data holds a version 6 custody. Version 5 accounts
+    /// have the same size, the byte read as the version from them is the
+    /// high byte of the cached AUM value which never reaches it.
+    pub fn is_version_6(data: &[u8]) -> bool {
+        data.len() == Custody::LEN_V6 && data[Custody::get_version_offset()] == 6
+    }
+
+    fn get_version_offset() -> usize {
+        let custody: Custody = bytemuck::Zeroable::zeroed();
+        8 + std::ptr::addr_of!(custody.version) as usize - std::ptr::addr_of!(custody) as usize
+    }
 
     pub fn validate(&self) -> bool {
         (!self.is_virtual || !self.is_stable)
             && self.token_account != Pubkey::default()
             && self.mint != Pubkey::default()
-            && self.oracle.validate()
-            && self.pricing.validate()
-            && self.fees.validate()
-            && self.borrow_rate.validate()
+            && { self.oracle }.validate()
+            && { self.pricing }.validate()
+            && { self.fees }.validate()
+            && { self.borrow_rate }.validate()
+            && { self.trading_schedule }.validate()
+            && (self.flash_loan_fee as u128) <= Perpetuals::BPS_POWER
+    }
+
+    pub fn get_config(&self) -> CustodyConfig {
+        CustodyConfig {
+            is_stable: self.is_stable,
+            is_virtual: self.is_virtual,
+            oracle: self.oracle,
+            pricing: self.pricing,
+            permissions: self.permissions,
+            fees: self.fees,
+            borrow_rate: self.borrow_rate,
+            flash_loan_fee: self.flash_loan_fee,
+        }
+    }
+
+    /// Returns the frozen settlement price if the custody is settled, otherwise the oracle price
+    pub fn get_oracle_price(
+        &self,
+        oracle_account: &AccountInfo,
+        curtime: i64,
+        use_ema: bool,
+    ) -> Result<OraclePrice> {
+        if self.status == MarketStatus::Settled {
+            Ok(self.settlement_price)
+        } else {
+            OraclePrice::new_from_oracle(oracle_account, &{ self.oracle }, curtime, use_ema)
+        }
     }
 
     pub fn lock_funds(&mut self, amount: u64) -> Result<()> {
@@ -337,9 +866,11 @@ impl Custody {
 
     pub fn get_cumulative_interest(&self, curtime: i64) -> Result<u128> {
         if curtime > self.borrow_rate_state.last_update {
+            let accrual_time = { self.trading_schedule }
+                .get_accrual_time(self.borrow_rate_state.last_update, curtime)?;
             let cumulative_interest = math::checked_ceil_div(
                 math::checked_mul(
-                    math::checked_sub(curtime, self.borrow_rate_state.last_update)? as u128,
+                    accrual_time as u128,
                     self.borrow_rate_state.current_rate as u128,
                 )?,
                 3600,
@@ -353,6 +884,17 @@ impl Custody {
         }
     }
 
+    /// Records a trade in the stats history, open interest is taken after the trade
+    pub fn update_stats_history(&mut self, curtime: i64, volume_usd: u64, fees_usd: u64) {
+        let oi_usd = self
+            .trade_stats
+            .oi_long_usd
+            .saturating_add(self.trade_stats.oi_short_usd);
+        let mut stats_history = self.stats_history;
+        stats_history.record(curtime, volume_usd, fees_usd, oi_usd);
+        self.stats_history = stats_history;
+    }
+
     pub fn update_borrow_rate(&mut self, curtime: i64) -> Result<()> {
         // if current_utilization < optimal_utilization:
         //   rate = base_rate + (current_utilization / optimal_utilization) * slope1
@@ -411,12 +953,24 @@ impl Custody {
         Ok(())
     }
 
-    pub fn get_collective_position(&self, side: Side) -> Result<Position> {
-        let stats = if side == Side::Long {
-            &self.long_positions
+    pub fn get_position_stats(&self, side: Side) -> PositionStats {
+        if side == Side::Long {
+            self.long_positions
         } else {
-            &self.short_positions
-        };
+            self.short_positions
+        }
+    }
+
+    pub fn set_position_stats(&mut self, side: Side, stats: PositionStats) {
+        if side == Side::Long {
+            self.long_positions = stats;
+        } else {
+            self.short_positions = stats;
+        }
+    }
+
+    pub fn get_collective_position(&self, side: Side) -> Result<Position> {
+        let stats = self.get_position_stats(side);
         if stats.open_positions > 0 {
             Ok(Position {
                 side,
@@ -452,11 +1006,7 @@ impl Custody {
         let interest_usd = self.get_interest_amount_usd(&collective_position, curtime)?;
 
         // update positions
-        let stats = if position.side == Side::Long {
-            &mut self.long_positions
-        } else {
-            &mut self.short_positions
-        };
+        let mut stats = self.get_position_stats(position.side);
 
         stats.open_positions = math::checked_add(stats.open_positions, 1)?;
         stats.size_usd = math::checked_add(stats.size_usd, position.size_usd)?;
@@ -485,6 +1035,7 @@ impl Custody {
             math::checked_mul(position.price as u128, quantity)?,
         )?;
         stats.total_quantity = math::checked_add(stats.total_quantity, quantity)?;
+        self.set_position_stats(position.side, stats);
 
         // check limits
         if self.pricing.max_position_locked_usd > 0 {
@@ -510,11 +1061,7 @@ impl Custody {
             let collective_position = custody.get_collective_position(position.side)?;
             let interest_usd = custody.get_interest_amount_usd(&collective_position, curtime)?;
 
-            let stats = if position.side == Side::Long {
-                &mut custody.long_positions
-            } else {
-                &mut custody.short_positions
-            };
+            let mut stats = custody.get_position_stats(position.side);
 
             stats.cumulative_interest_usd =
                 math::checked_add(stats.cumulative_interest_usd, interest_usd)?;
@@ -523,6 +1070,7 @@ impl Custody {
             stats.open_positions = math::checked_add(stats.open_positions, 1)?;
             stats.borrow_size_usd =
                 math::checked_add(stats.borrow_size_usd, position.borrow_size_usd)?;
+            custody.set_position_stats(position.side, stats);
         }
 
         Ok(())
@@ -541,14 +1089,10 @@ impl Custody {
         let position_interest_usd = self.get_interest_amount_usd(position, curtime)?;
 
         // update stats
-        let stats = if position.side == Side::Long {
-            &mut self.long_positions
-        } else {
-            &mut self.short_positions
-        };
+        let mut stats = self.get_position_stats(position.side);
 
         if stats.open_positions == 1 {
-            *stats = PositionStats::default();
+            self.set_position_stats(position.side, PositionStats::default());
             return Ok(());
         }
 
@@ -582,6 +1126,7 @@ impl Custody {
             math::checked_mul(position.price as u128, quantity)?,
         )?;
         stats.total_quantity = math::checked_sub(stats.total_quantity, quantity)?;
+        self.set_position_stats(position.side, stats);
 
         // update collateral custody for interest tracking
         if let Some(custody) = collateral_custody {
@@ -589,14 +1134,10 @@ impl Custody {
             let collective_position = custody.get_collective_position(position.side)?;
             let interest_usd = custody.get_interest_amount_usd(&collective_position, curtime)?;
 
-            let stats = if position.side == Side::Long {
-                &mut custody.long_positions
-            } else {
-                &mut custody.short_positions
-            };
+            let mut stats = custody.get_position_stats(position.side);
 
             if stats.open_positions == 1 {
-                *stats = PositionStats::default();
+                custody.set_position_stats(position.side, PositionStats::default());
                 return Ok(());
             }
 
@@ -610,6 +1151,7 @@ impl Custody {
             stats.open_positions = math::checked_sub(stats.open_positions, 1)?;
             stats.borrow_size_usd =
                 math::checked_sub(stats.borrow_size_usd, position.borrow_size_usd)?;
+            custody.set_position_stats(position.side, stats);
         }
 
         Ok(())
@@ -620,6 +1162,141 @@ impl DeprecatedCustody {
     pub const LEN: usize = 8 + std::mem::size_of::<DeprecatedCustody>();
 }
 
+impl DeprecatedCustodyV2 {
+    pub const LEN: usize = 8 + std::mem::size_of::<DeprecatedCustodyV2>();
+}
+
+impl From<DeprecatedCustodyV2> for DeprecatedCustodyV3 {
+    fn from(deprecated: DeprecatedCustodyV2) -> Self {
+        Self {
+            pool: deprecated.pool,
+            mint: deprecated.mint,
+            token_account: deprecated.token_account,
+            decimals: deprecated.decimals,
+            is_stable: deprecated.is_stable,
+            is_virtual: deprecated.is_virtual,
+            oracle: deprecated.oracle,
+            pricing: deprecated.pricing,
+            permissions: deprecated.permissions,
+            fees: deprecated.fees,
+            borrow_rate: deprecated.borrow_rate,
+            assets: deprecated.assets,
+            collected_fees: deprecated.collected_fees.into(),
+            volume_stats: deprecated.volume_stats.into(),
+            trade_stats: deprecated.trade_stats,
+            long_positions: deprecated.long_positions,
+            short_positions: deprecated.short_positions,
+            borrow_rate_state: deprecated.borrow_rate_state,
+            bump: deprecated.bump,
+            token_account_bump: deprecated.token_account_bump,
+            status: deprecated.status,
+            settlement_price: deprecated.settlement_price,
+            trading_schedule: deprecated.trading_schedule,
+            stats_history: StatsHistory::default(),
+            version: 0,
+        }
+    }
+}
+
+impl DeprecatedCustodyV3 {
+    pub const LEN: usize = 8 + std::mem::size_of::<DeprecatedCustodyV3>();
+}
+
+impl From<DeprecatedCustodyV3> for Custody {
+    fn from(deprecated: DeprecatedCustodyV3) -> Self {
+        Self {
+            pool: deprecated.pool,
+            mint: deprecated.mint,
+            token_account: deprecated.token_account,
+            decimals: deprecated.decimals,
+            is_stable: deprecated.is_stable,
+            is_virtual: deprecated.is_virtual,
+            oracle: deprecated.oracle,
+            pricing: deprecated.pricing,
+            permissions: deprecated.permissions,
+            fees: deprecated.fees,
+            borrow_rate: deprecated.borrow_rate,
+            assets: deprecated.assets,
+            collected_fees: deprecated.collected_fees.upgrade(0),
+            volume_stats: deprecated.volume_stats,
+            trade_stats: deprecated.trade_stats,
+            long_positions: deprecated.long_positions,
+            short_positions: deprecated.short_positions,
+            borrow_rate_state: deprecated.borrow_rate_state,
+            bump: deprecated.bump,
+            token_account_bump: deprecated.token_account_bump,
+            status: deprecated.status,
+            settlement_price: deprecated.settlement_price,
+            trading_schedule: deprecated.trading_schedule,
+            stats_history: deprecated.stats_history,
+            version: Custody::VERSION,
+            aum_usd: [0; 4],
+            flash_loan_fee: 0,
+            flash_loan_amount: 0,
+            staking_rewards_usd: 0,
+            referral_fees_usd: 0,
+        }
+    }
+}
+
+impl EnumFields for DeprecatedCustodyV5 {
+    fn enum_fields() -> Vec<(usize, u8)> {
+        let custody: DeprecatedCustodyV5 = bytemuck::Zeroable::zeroed();
+        let base = std::ptr::addr_of!(custody) as usize;
+        vec![
+            (
+                std::ptr::addr_of!(custody.oracle.oracle_type) as usize - base,
+                OracleType::VARIANTS,
+            ),
+            (
+                std::ptr::addr_of!(custody.fees.mode) as usize - base,
+                FeesMode::VARIANTS,
+            ),
+            (
+                std::ptr::addr_of!(custody.status) as usize - base,
+                MarketStatus::VARIANTS,
+            ),
+        ]
+    }
+}
+
+impl From<DeprecatedCustodyV5> for Custody {
+    fn from(deprecated: DeprecatedCustodyV5) -> Self {
+        Self {
+            pool: deprecated.pool,
+            mint: deprecated.mint,
+            token_account: deprecated.token_account,
+            decimals: deprecated.decimals,
+            is_stable: deprecated.is_stable,
+            is_virtual: deprecated.is_virtual,
+            oracle: deprecated.oracle,
+            pricing: deprecated.pricing,
+            permissions: deprecated.permissions,
+            fees: deprecated.fees,
+            borrow_rate: deprecated.borrow_rate,
+            assets: deprecated.assets,
+            collected_fees: { deprecated.collected_fees }.upgrade(deprecated.flash_loan_fees_usd),
+            volume_stats: deprecated.volume_stats,
+            trade_stats: deprecated.trade_stats,
+            long_positions: deprecated.long_positions,
+            short_positions: deprecated.short_positions,
+            borrow_rate_state: deprecated.borrow_rate_state,
+            bump: deprecated.bump,
+            token_account_bump: deprecated.token_account_bump,
+            status: deprecated.status,
+            settlement_price: deprecated.settlement_price,
+            trading_schedule: deprecated.trading_schedule,
+            stats_history: deprecated.stats_history,
+            version: Custody::VERSION,
+            aum_usd: deprecated.aum_usd,
+            flash_loan_fee: deprecated.flash_loan_fee,
+            flash_loan_amount: deprecated.flash_loan_amount,
+            staking_rewards_usd: deprecated.staking_rewards_usd,
+            referral_fees_usd: 0,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -651,7 +1328,7 @@ mod test {
         let mut custody = get_fixture();
         custody.update_borrow_rate(3600).unwrap();
         assert_eq!(
-            custody.borrow_rate_state,
+            { custody.borrow_rate_state },
             BorrowRateState {
                 current_rate: 50000,
                 cumulative_interest: 0,
@@ -660,7 +1337,7 @@ mod test {
         );
         custody.update_borrow_rate(5400).unwrap();
         assert_eq!(
-            custody.borrow_rate_state,
+            { custody.borrow_rate_state },
             BorrowRateState {
                 current_rate: 50000,
                 cumulative_interest: 25000,
@@ -669,7 +1346,7 @@ mod test {
         );
         custody.update_borrow_rate(7200).unwrap();
         assert_eq!(
-            custody.borrow_rate_state,
+            { custody.borrow_rate_state },
             BorrowRateState {
                 current_rate: 50000,
                 cumulative_interest: 50000,
@@ -681,7 +1358,7 @@ mod test {
         custody.update_borrow_rate(3600).unwrap();
         custody.update_borrow_rate(7200).unwrap();
         assert_eq!(
-            custody.borrow_rate_state,
+            { custody.borrow_rate_state },
             BorrowRateState {
                 current_rate: 50000,
                 cumulative_interest: 50000,
@@ -693,7 +1370,7 @@ mod test {
         custody.assets.locked = 0;
         custody.update_borrow_rate(3600).unwrap();
         assert_eq!(
-            custody.borrow_rate_state,
+            { custody.borrow_rate_state },
             BorrowRateState {
                 current_rate: 0,
                 cumulative_interest: 0,
@@ -704,40 +1381,230 @@ mod test {
         let mut custody = get_fixture();
         custody.assets.locked = 800;
         custody.update_borrow_rate(3600).unwrap();
-        assert_eq!(custody.borrow_rate_state.current_rate, 80000);
+        assert_eq!({ custody.borrow_rate_state.current_rate }, 80000);
 
         let mut custody = get_fixture();
         custody.assets.locked = 900;
         custody.update_borrow_rate(3600).unwrap();
-        assert_eq!(custody.borrow_rate_state.current_rate, 140000);
+        assert_eq!({ custody.borrow_rate_state.current_rate }, 140000);
 
         custody.update_borrow_rate(5400).unwrap();
-        assert_eq!(custody.borrow_rate_state.cumulative_interest, 70000);
+        assert_eq!({ custody.borrow_rate_state.cumulative_interest }, 70000);
 
         custody.update_borrow_rate(7200).unwrap();
-        assert_eq!(custody.borrow_rate_state.cumulative_interest, 140000);
+        assert_eq!({ custody.borrow_rate_state.cumulative_interest }, 140000);
 
         custody.assets.locked = 500;
         custody.update_borrow_rate(10800).unwrap();
-        assert_eq!(custody.borrow_rate_state.current_rate, 50000);
-        assert_eq!(custody.borrow_rate_state.cumulative_interest, 280000);
+        assert_eq!({ custody.borrow_rate_state.current_rate }, 50000);
+        assert_eq!({ custody.borrow_rate_state.cumulative_interest }, 280000);
         custody.update_borrow_rate(14400).unwrap();
-        assert_eq!(custody.borrow_rate_state.current_rate, 50000);
-        assert_eq!(custody.borrow_rate_state.cumulative_interest, 330000);
+        assert_eq!({ custody.borrow_rate_state.current_rate }, 50000);
+        assert_eq!({ custody.borrow_rate_state.cumulative_interest }, 330000);
 
         let mut custody = get_fixture();
         custody.assets.locked = 1000;
         custody.update_borrow_rate(3600).unwrap();
-        assert_eq!(custody.borrow_rate_state.current_rate, 200000);
+        assert_eq!({ custody.borrow_rate_state.current_rate }, 200000);
 
         let mut custody = get_fixture();
         custody.assets.locked = 1;
         custody.update_borrow_rate(3600).unwrap();
-        assert_eq!(custody.borrow_rate_state.current_rate, 100);
+        assert_eq!({ custody.borrow_rate_state.current_rate }, 100);
 
         let mut custody = get_fixture();
         custody.assets.locked = 999;
         custody.update_borrow_rate(3600).unwrap();
-        assert_eq!(custody.borrow_rate_state.current_rate, 199400);
+        assert_eq!({ custody.borrow_rate_state.current_rate }, 199400);
+    }
+
+    #[test]
+    fn test_trading_schedule() {
+        // open on weekdays around the clock, unix epoch started on Thursday
+        let mut schedule = TradingSchedule {
+            use_weekly_hours: true,
+            weekly_hours: [0xffffff, 0xffffff, 0xffffff, 0xffffff, 0xffffff, 0, 0],
+            closed_spread: 50,
+            ..TradingSchedule::default()
+        };
+        assert!(schedule.validate());
+        assert!(schedule.is_open(0));
+        assert!(schedule.is_open(172_799));
+        assert!(!schedule.is_open(172_800));
+        assert!(!schedule.is_open(345_599));
+        assert!(schedule.is_open(345_600));
+        assert_eq!(schedule.get_closed_spread(0), 0);
+        assert_eq!(schedule.get_closed_spread(172_800), 50);
+
+        // friday to monday, only friday accrues
+        assert_eq!(schedule.get_accrual_time(86_400, 345_600).unwrap(), 86_400);
+        // two weeks from thursday, plus a partial saturday hour
+        assert_eq!(
+            schedule.get_accrual_time(0, 1_209_600 + 174_600).unwrap(),
+            2 * 432_000 + 172_800
+        );
+
+        schedule.accrue_borrow_when_closed = true;
+        assert_eq!(schedule.get_accrual_time(86_400, 345_600).unwrap(), 259_200);
+
+        schedule.is_closed = true;
+        assert!(!schedule.is_open(0));
+        schedule.accrue_borrow_when_closed = false;
+        assert_eq!(schedule.get_accrual_time(86_400, 345_600).unwrap(), 0);
+
+        schedule.weekly_hours[0] = 1 << 24;
+        assert!(!schedule.validate());
+
+        // interest doesn't accrue over the weekend
+        let mut custody = get_fixture();
+        custody.trading_schedule = TradingSchedule {
+            use_weekly_hours: true,
+            weekly_hours: [0xffffff, 0xffffff, 0xffffff, 0xffffff, 0xffffff, 0, 0],
+            ..TradingSchedule::default()
+        };
+        custody.update_borrow_rate(169_200).unwrap();
+        custody.update_borrow_rate(349_200).unwrap();
+        assert_eq!({ custody.borrow_rate_state.cumulative_interest }, 100000);
+    }
+
+    #[test]
+    fn test_upgrade_stats() {
+        let deprecated = DeprecatedCustodyV2 {
+            decimals: 6,
+            collected_fees: DeprecatedFeesStats {
+                swap_usd: u64::MAX,
+                liquidation_usd: 100,
+                ..DeprecatedFeesStats::default()
+            },
+            volume_stats: DeprecatedVolumeStats {
+                open_position_usd: 200,
+                ..DeprecatedVolumeStats::default()
+            },
+            status: MarketStatus::ReduceOnly,
+            ..DeprecatedCustodyV2::default()
+        };
+
+        // account data is resized before it is read, trailing zero padding is ignored
+        let mut data = Vec::new();
+        deprecated.try_serialize(&mut data).unwrap();
+        data.resize(DeprecatedCustodyV3::LEN, 0);
+        let upgraded = Custody::from(DeprecatedCustodyV3::from(
+            DeprecatedCustodyV2::try_deserialize(&mut data.as_slice()).unwrap(),
+        ));
+
+        assert_eq!({ upgraded.decimals }, 6);
+        assert_eq!({ upgraded.status }, MarketStatus::ReduceOnly);
+        assert_eq!({ upgraded.collected_fees.swap_usd }, u64::MAX as u128);
+        assert_eq!({ upgraded.collected_fees.liquidation_usd }, 100);
+        assert_eq!({ upgraded.volume_stats.open_position_usd }, 200);
+
+        // counters no longer wrap at u64::MAX
+        let mut collected_fees = upgraded.collected_fees;
+        collected_fees.swap_usd = collected_fees.swap_usd.saturating_add(1);
+        assert_eq!(collected_fees.swap_usd, u64::MAX as u128 + 1);
+        assert_eq!(
+            collected_fees.get_total_usd().unwrap(),
+            u64::MAX as u128 + 101
+        );
+    }
+
+    #[test]
+    fn test_stats_history() {
+        let hour = 3_600;
+        let day = 86_400;
+        let mut custody = get_fixture();
+        assert_eq!(
+            { custody.stats_history }.get_hourly_total(0),
+            StatsBucket::default()
+        );
+
+        custody.trade_stats.oi_long_usd = 300;
+        custody.update_stats_history(10 * day, 1_000, 10);
+        custody.trade_stats.oi_long_usd = 100;
+        custody.update_stats_history(10 * day + 5 * hour, 2_000, 20);
+        assert_eq!(
+            { custody.stats_history }.get_hourly_total(10 * day + 5 * hour),
+            StatsBucket {
+                volume_usd: 3_000,
+                fees_usd: 30,
+                max_oi_usd: 300,
+            }
+        );
+
+        // the first trade falls out of the hourly window, but not the daily one
+        assert_eq!(
+            { custody.stats_history }
+                .get_hourly_total(11 * day)
+                .volume_usd,
+            2_000
+        );
+        assert_eq!(
+            { custody.stats_history }
+                .get_daily_total(11 * day)
+                .volume_usd,
+            3_000
+        );
+
+        custody.update_stats_history(12 * day, 500, 5);
+        let hourly = { custody.stats_history }.get_hourly_total(12 * day);
+        assert_eq!(hourly.volume_usd, 500);
+        assert_eq!(hourly.max_oi_usd, 100);
+        assert_eq!(
+            { custody.stats_history }
+                .get_daily_total(16 * day)
+                .volume_usd,
+            3_500
+        );
+        assert_eq!(
+            { custody.stats_history }
+                .get_daily_total(17 * day)
+                .volume_usd,
+            500
+        );
+
+        // expired buckets are cleared before they are reused
+        custody.update_stats_history(17 * day, 100, 1);
+        let daily = { custody.stats_history }.get_daily_total(17 * day);
+        assert_eq!(daily.volume_usd, 600);
+        assert_eq!(daily.fees_usd, 6);
+        assert_eq!(
+            { custody.stats_history }.get_daily_total(30 * day),
+            StatsBucket::default()
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        // layout changes need a new version and a LEN_V constant for upgrade_custody
+        assert_eq!(Custody::LEN, 1855);
+        assert_eq!(Custody::LEN_V6, 1839);
+        assert_eq!(Custody::LEN_V5, 1839);
+        assert_eq!(Custody::LEN_V4, 1823);
+        assert_eq!(Custody::LEN_V3, 1791);
+        assert_eq!(Custody::LEN_V2, 1727);
+
+        // enums are stored as single bytes
+        assert_eq!(std::mem::size_of::<OracleType>(), 1);
+        assert_eq!(std::mem::size_of::<FeesMode>(), 1);
+        assert_eq!(std::mem::size_of::<MarketStatus>(), 1);
+        assert_eq!(
+            Custody::enum_fields(),
+            vec![
+                (175, OracleType::VARIANTS),
+                (387, FeesMode::VARIANTS),
+                (925, MarketStatus::VARIANTS)
+            ]
+        );
+        assert_eq!(
+            DeprecatedCustodyV5::enum_fields(),
+            vec![
+                (175, OracleType::VARIANTS),
+                (387, FeesMode::VARIANTS),
+                (909, MarketStatus::VARIANTS)
+            ]
+        );
+        assert_eq!(OracleType::VARIANTS, 3);
+        assert_eq!(FeesMode::VARIANTS, 3);
+        assert_eq!(MarketStatus::VARIANTS, 4);
     }
 }
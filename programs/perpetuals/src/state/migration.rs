@@ -0,0 +1,523 @@
+//! In-place account migration routines
+//!
+//! Accounts carry a layout version in their last field. New fields are always appended
+//! after it, so accounts created with an older layout deserialize with zero padding in
+//! place of the missing fields, including the version itself. Migration pads the data,
+//! upgrades it from the stored version, resizes the account and writes it back.
+//!
+//! Borsh accounts that were moved to zero-copy are loaded with their deprecated layout,
+//! converted and written back with the zero-copy layout. Zero-copy accounts follow the
+//! same rule, fields appended after the version start zeroed.
+
+use {
+    crate::state::perpetuals::Perpetuals,
+    anchor_lang::{prelude::*, ZeroCopy},
+    solana_program::program_memory::sol_memcpy,
+    std::{
+        cmp,
+        io::{self, Write},
+    },
+};
+
+#[derive(Debug, Default)]
+pub struct BpfWriter<T> {
+    inner: T,
+    pos: u64,
+}
+
+impl<T> BpfWriter<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner, pos: 0 }
+    }
+}
+
+impl Write for BpfWriter<&mut [u8]> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.pos >= self.inner.len() as u64 {
+            return Ok(0);
+        }
+
+        let amt = cmp::min(
+            self.inner.len().saturating_sub(self.pos as usize),
+            buf.len(),
+        );
+        sol_memcpy(&mut self.inner[(self.pos as usize)..], buf, amt);
+        self.pos += amt as u64;
+        Ok(amt)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.write(buf)? == buf.len() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to write whole buffer",
+            ))
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub trait Versioned: AccountSerialize + AccountDeserialize + Owner {
+    /// Layout version of accounts created by the current program
+    const VERSION: u8;
+
+    fn get_version(&self) -> u8;
+
+    fn set_version(&mut self, version: u8);
+
+    /// Returns the account size required by the current layout
+    fn get_size(&self) -> usize;
+
+    /// Upgrades fields from the stored version, fields appended since then
+    /// hold their default values at this point
+    fn migrate(&mut self) -> Result<()>;
+}
+
+/// Deserializes account data stored with any previous layout and upgrades it
+pub fn migrate_data<T: Versioned>(data: &[u8]) -> Result<T> {
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + std::mem::size_of::<T>(), 0);
+    let mut account = T::try_deserialize(&mut padded.as_slice())?;
+
+    let version = account.get_version();
+    if version == T::VERSION {
+        msg!("Error: Account is already upgraded");
+        return Err(ProgramError::AccountAlreadyInitialized.into());
+    }
+    if version > T::VERSION {
+        msg!("Error: Unknown account version {}", version);
+        return Err(ProgramError::InvalidAccountData.into());
+    }
+
+    account.migrate()?;
+    account.set_version(T::VERSION);
+
+    Ok(account)
+}
+
+/// Serializes the account into the beginning of its data
+pub fn write_account<T: AccountSerialize>(account_info: &AccountInfo, account: &T) -> Result<()> {
+    let mut data = account_info.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    let mut writer = BpfWriter::new(dst);
+    account.try_serialize(&mut writer)
+}
+
+/// Upgrades the account to the current layout in place, resizing it if needed
+pub fn upgrade_account<'a, T: Versioned>(
+    funding_account: AccountInfo<'a>,
+    target_account: AccountInfo<'a>,
+    system_program: AccountInfo<'a>,
+) -> Result<T> {
+    if target_account.owner != &T::owner() {
+        return Err(ProgramError::IllegalOwner.into());
+    }
+
+    msg!("Load account data");
+    let account = migrate_data::<T>(&target_account.try_borrow_data()?)?;
+
+    let data_len = target_account.try_data_len()?;
+    let new_len = account.get_size();
+    if new_len > data_len {
+        msg!("Resize account");
+        Perpetuals::realloc(
+            funding_account,
+            target_account.clone(),
+            system_program,
+            new_len,
+            true,
+        )?;
+    }
+
+    msg!("Re-initialize the account");
+    write_account(&target_account, &account)?;
+
+    Ok(account)
+}
+
+/// Deserializes borsh account data stored with a deprecated layout. The data is padded
+/// the same way as in migrate_data, the discriminator is not checked since deprecated
+/// types are stored under the name of the current type.
+pub fn load_deprecated<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + std::mem::size_of::<T>(), 0);
+    T::try_deserialize_unchecked(&mut padded.as_slice())
+}
+
+/// Zero-copy accounts are cast from raw data, where a `#[repr(u8)]` enum field can only
+/// hold one of its variants. Raw data is validated before the cast when it wasn't written
+/// with the current layout.
+pub trait EnumFields {
+    /// Offsets of enum fields in account data after the discriminator,
+    /// with the number of variants of each enum
+    fn enum_fields() -> Vec<(usize, u8)>;
+
+    fn validate_enum_fields(data: &[u8]) -> Result<()> {
+        for (offset, variants) in Self::enum_fields() {
+            // fields beyond the data are zero padded and hold the first variant
+            if let Some(&value) = data.get(offset) {
+                if value >= variants {
+                    msg!("Invalid enum value {} at offset {}", value, offset);
+                    return err!(ErrorCode::AccountDidNotDeserialize);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Loads zero-copy account data stored with a previous, shorter layout
+pub fn load_zero_copy<T: ZeroCopy + EnumFields>(data: &[u8]) -> Result<T> {
+    if data.len() < 8 || data[..8] != T::discriminator() {
+        return err!(ErrorCode::AccountDiscriminatorMismatch);
+    }
+    load_deprecated_zero_copy(data)
+}
+
+/// Loads zero-copy account data stored with a deprecated layout, the discriminator
+/// is not checked for the same reason as in load_deprecated
+pub fn load_deprecated_zero_copy<T: ZeroCopy + EnumFields>(data: &[u8]) -> Result<T> {
+    if data.len() < 8 {
+        return err!(ErrorCode::AccountDiscriminatorNotFound);
+    }
+    T::validate_enum_fields(&data[8..])?;
+    let mut account = T::zeroed();
+    let dst = bytemuck::bytes_of_mut(&mut account);
+    let len = cmp::min(dst.len(), data.len() - 8);
+    dst[..len].copy_from_slice(&data[8..8 + len]);
+    Ok(account)
+}
+
+/// Resizes the account to the zero-copy layout and writes the account into it
+pub fn write_zero_copy_account<'a, T: ZeroCopy + Owner>(
+    funding_account: AccountInfo<'a>,
+    target_account: AccountInfo<'a>,
+    system_program: AccountInfo<'a>,
+    account: &T,
+) -> Result<()> {
+    if target_account.owner != &T::owner() {
+        return Err(ProgramError::IllegalOwner.into());
+    }
+
+    let new_len = 8 + std::mem::size_of::<T>();
+    if target_account.try_data_len()? != new_len {
+        msg!("Resize account");
+        Perpetuals::realloc(
+            funding_account,
+            target_account.clone(),
+            system_program,
+            new_len,
+            true,
+        )?;
+    }
+
+    msg!("Re-initialize the account");
+    let mut data = target_account.try_borrow_mut_data()?;
+    data[..8].copy_from_slice(&T::discriminator());
+    data[8..new_len].copy_from_slice(bytemuck::bytes_of(account));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::state::{
+            custody::{Custody, DeprecatedCustodyV3, DeprecatedCustodyV5},
+            multisig::{Proposal, ProposalStatus},
+            perpetuals::{FeeTier, MarketStatus},
+            pool::{AumCalcMode, DeprecatedPool, Pool, TokenRatios},
+            position::{Position, Side},
+            referral::Referrer,
+        },
+        anchor_lang::Discriminator,
+        bytemuck::Zeroable,
+    };
+
+    // serializes the account with its trailing version byte removed, the way
+    // accounts created before the version field was added are stored
+    fn get_fixture<T: Versioned>(account: &T, space: usize) -> Vec<u8> {
+        let mut data = vec![];
+        account.try_serialize(&mut data).unwrap();
+        data.pop();
+        data.resize(space, 0);
+        data
+    }
+
+    #[test]
+    fn test_migrate_perpetuals() {
+        let perpetuals = Perpetuals {
+            pools: vec![Pubkey::new_unique()],
+            perpetuals_bump: 255,
+            inception_time: 1_000,
+            fee_tiers: vec![FeeTier {
+                min_volume_usd: 1_000_000,
+                fee_mult: 9_000,
+            }],
+            ..Perpetuals::default()
+        };
+        let data = get_fixture(&perpetuals, Perpetuals::LEN);
+
+        let upgraded = migrate_data::<Perpetuals>(&data).unwrap();
+        assert_eq!(upgraded.pools, perpetuals.pools);
+        assert_eq!(upgraded.perpetuals_bump, 255);
+        assert_eq!(upgraded.inception_time, 1_000);
+        assert_eq!(upgraded.fee_tiers, perpetuals.fee_tiers);
+        assert_eq!(upgraded.version, Perpetuals::VERSION);
+        assert_eq!(upgraded.get_size(), perpetuals.get_size());
+
+        // already upgraded
+        let mut data = vec![];
+        upgraded.try_serialize(&mut data).unwrap();
+        assert!(migrate_data::<Perpetuals>(&data).is_err());
+    }
+
+    #[test]
+    fn test_migrate_pool() {
+        // pool created before the pool config was added
+        let pool = DeprecatedPool {
+            name: "test pool".to_string(),
+            custodies: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            ratios: vec![TokenRatios::default(); 2],
+            aum_usd: 1_000_000,
+            bump: 254,
+            ..DeprecatedPool::default()
+        };
+        let mut data = vec![];
+        pool.try_serialize(&mut data).unwrap();
+        data.pop();
+
+        let upgraded = Pool::try_from(load_deprecated::<DeprecatedPool>(&data).unwrap()).unwrap();
+        assert_eq!(upgraded.get_name(), b"test pool");
+        assert_eq!(upgraded.get_custodies(), pool.custodies.as_slice());
+        assert_eq!(upgraded.get_ratios(), pool.ratios);
+        assert_eq!({ upgraded.aum_usd }, 1_000_000);
+        assert_eq!(upgraded.bump, 254);
+        assert_eq!(upgraded.get_display_name(), "test pool");
+        assert!(upgraded.permissions.allow_swap);
+        assert_eq!(upgraded.add_liquidity_aum_mode, AumCalcMode::Max);
+        assert_eq!(upgraded.remove_liquidity_aum_mode, AumCalcMode::Min);
+        assert_eq!(upgraded.version, Pool::VERSION);
+
+        // existing pool config is preserved
+        let pool = DeprecatedPool {
+            display_name: "custom".to_string(),
+            version: 1,
+            ..pool
+        };
+        let mut data = vec![];
+        pool.try_serialize(&mut data).unwrap();
+
+        let upgraded = Pool::try_from(load_deprecated::<DeprecatedPool>(&data).unwrap()).unwrap();
+        assert_eq!(upgraded.get_display_name(), "custom");
+        assert!(!upgraded.permissions.allow_swap);
+        assert_eq!(upgraded.version, Pool::VERSION);
+        assert_ne!(data.len(), Pool::LEN);
+    }
+
+    #[test]
+    fn test_migrate_custody() {
+        let mut custody = DeprecatedCustodyV3 {
+            pool: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            decimals: 9,
+            bump: 253,
+            version: 1,
+            ..DeprecatedCustodyV3::default()
+        };
+        custody.assets.owned = 1_000;
+        custody.collected_fees.swap_usd = 2_000;
+        let mut data = vec![];
+        custody.try_serialize(&mut data).unwrap();
+        data.resize(DeprecatedCustodyV3::LEN, 0);
+
+        let upgraded = Custody::from(load_deprecated::<DeprecatedCustodyV3>(&data).unwrap());
+        assert_eq!(upgraded.pool, custody.pool);
+        assert_eq!(upgraded.mint, custody.mint);
+        assert_eq!(upgraded.decimals, 9);
+        assert_eq!(upgraded.bump, 253);
+        assert_eq!({ upgraded.assets.owned }, 1_000);
+        assert_eq!({ upgraded.collected_fees.swap_usd }, 2_000);
+        assert_eq!(upgraded.version, Custody::VERSION);
+        assert_ne!(DeprecatedCustodyV3::LEN, Custody::LEN);
+        assert_ne!(DeprecatedCustodyV3::LEN, Custody::LEN_V4);
+        assert_ne!(DeprecatedCustodyV3::LEN, Custody::LEN_V3);
+        assert_ne!(DeprecatedCustodyV3::LEN, Custody::LEN_V2);
+    }
+
+    #[test]
+    fn test_load_zero_copy() {
+        let mut custody = Custody::zeroed();
+        custody.decimals = 9;
+        custody.assets.owned = 1_000;
+        custody.version = 2;
+        custody.aum_usd = [5_000; 4];
+        let mut data = Custody::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&custody));
+        let version_offset =
+            std::ptr::addr_of!(custody.version) as usize - std::ptr::addr_of!(custody) as usize;
+        data.truncate(8 + version_offset + 1);
+
+        // fields appended after the version start zeroed
+        let upgraded = load_zero_copy::<Custody>(&data).unwrap();
+        assert_eq!(upgraded.decimals, 9);
+        assert_eq!({ upgraded.assets.owned }, 1_000);
+        assert_eq!(upgraded.version, 2);
+        assert_eq!({ upgraded.aum_usd }, [0; 4]);
+
+        data[0] ^= 1;
+        assert!(load_zero_copy::<Custody>(&data).is_err());
+        data[0] ^= 1;
+
+        // enum fields must hold one of the variants
+        for (offset, variants) in Custody::enum_fields() {
+            data[8 + offset] = variants - 1;
+            assert!(load_zero_copy::<Custody>(&data).is_ok());
+            data[8 + offset] = variants;
+            assert!(load_zero_copy::<Custody>(&data).is_err());
+            data[8 + offset] = 0;
+        }
+    }
+
+    #[test]
+    fn test_load_deprecated_zero_copy() {
+        let mut custody = DeprecatedCustodyV5::zeroed();
+        custody.decimals = 6;
+        custody.collected_fees.swap_usd = 2_000;
+        custody.status = MarketStatus::ReduceOnly;
+        custody.version = 5;
+        custody.aum_usd = [5_000; 4];
+        custody.flash_loan_fee = 10;
+        custody.flash_loan_fees_usd = 300;
+        custody.staking_rewards_usd = 40;
+        let mut data = Custody::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&custody));
+        assert_eq!(data.len(), Custody::LEN_V5);
+        assert!(!Custody::is_current_version(&data));
+        assert!(!Custody::is_version_6(&data));
+
+        // flash loan fees are moved into collected fees
+        let upgraded =
+            Custody::from(load_deprecated_zero_copy::<DeprecatedCustodyV5>(&data).unwrap());
+        assert_eq!(upgraded.decimals, 6);
+        assert_eq!({ upgraded.collected_fees.swap_usd }, 2_000);
+        assert_eq!({ upgraded.collected_fees.flash_loan_usd }, 300);
+        assert_eq!({ upgraded.collected_fees }.get_total_usd().unwrap(), 2_300);
+        assert_eq!(upgraded.status, MarketStatus::ReduceOnly);
+        assert_eq!({ upgraded.aum_usd }, [5_000; 4]);
+        assert_eq!({ upgraded.flash_loan_fee }, 10);
+        assert_eq!({ upgraded.staking_rewards_usd }, 40);
+        assert_eq!(upgraded.version, Custody::VERSION);
+
+        let mut data = Custody::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&upgraded));
+        assert!(Custody::is_current_version(&data));
+
+        // version 6 accounts are the current layout without reserved referral fees
+        let mut custody_v6 = upgraded;
+        custody_v6.version = 6;
+        let mut data = Custody::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&custody_v6));
+        data.truncate(Custody::LEN_V6);
+        assert!(!Custody::is_current_version(&data));
+        assert!(Custody::is_version_6(&data));
+        let upgraded = load_deprecated_zero_copy::<Custody>(&data).unwrap();
+        assert_eq!({ upgraded.staking_rewards_usd }, 40);
+        assert_eq!({ upgraded.referral_fees_usd }, 0);
+
+        // older zero-copy layouts are loaded the same way
+        let mut data = Custody::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&custody));
+        data.truncate(Custody::LEN_V2);
+        let upgraded =
+            Custody::from(load_deprecated_zero_copy::<DeprecatedCustodyV5>(&data).unwrap());
+        assert_eq!({ upgraded.collected_fees.swap_usd }, 2_000);
+        assert_eq!({ upgraded.collected_fees.flash_loan_usd }, 0);
+        assert_eq!({ upgraded.aum_usd }, [0; 4]);
+    }
+
+    #[test]
+    fn test_migrate_position() {
+        let position = Position {
+            owner: Pubkey::new_unique(),
+            side: Side::Short,
+            size_usd: 5_000,
+            collateral_amount: 100,
+            bump: 252,
+            ..Position::default()
+        };
+        let data = get_fixture(&position, Position::LEN);
+
+        let upgraded = migrate_data::<Position>(&data).unwrap();
+        assert_eq!(upgraded.owner, position.owner);
+        assert_eq!(upgraded.side, Side::Short);
+        assert_eq!(upgraded.size_usd, 5_000);
+        assert_eq!(upgraded.collateral_amount, 100);
+        assert_eq!(upgraded.bump, 252);
+        assert_eq!(upgraded.version, Position::VERSION);
+
+        // unknown version
+        let position = Position {
+            version: Position::VERSION + 1,
+            ..upgraded
+        };
+        let mut data = vec![];
+        position.try_serialize(&mut data).unwrap();
+        assert!(migrate_data::<Position>(&data).is_err());
+    }
+
+    #[test]
+    fn test_migrate_proposal() {
+        let proposal = Proposal {
+            proposer: Pubkey::new_unique(),
+            instruction_accounts: vec![Pubkey::new_unique(); 3],
+            instruction_data: vec![7; 20],
+            signed: 0b101,
+            status: ProposalStatus::Pending,
+            bump: 254,
+            ..Proposal::default()
+        };
+        let data = get_fixture(&proposal, Proposal::get_size(3, 20));
+
+        let upgraded = migrate_data::<Proposal>(&data).unwrap();
+        assert_eq!(upgraded.proposer, proposal.proposer);
+        assert_eq!(upgraded.instruction_accounts, proposal.instruction_accounts);
+        assert_eq!(upgraded.instruction_data, proposal.instruction_data);
+        assert_eq!(upgraded.signed, 0b101);
+        assert_eq!(upgraded.bump, 254);
+        assert_eq!(upgraded.version, Proposal::VERSION);
+        assert_eq!(upgraded.get_size(), Proposal::get_size(3, 20));
+    }
+
+    #[test]
+    fn test_migrate_referrer() {
+        let referrer = Referrer {
+            owner: Pubkey::new_unique(),
+            code: "code".to_string(),
+            fees_usd: 500,
+            unclaimed_fees_usd: 100,
+            bump: 253,
+            ..Referrer::default()
+        };
+        let data = get_fixture(&referrer, Referrer::LEN);
+
+        let upgraded = migrate_data::<Referrer>(&data).unwrap();
+        assert_eq!(upgraded.owner, referrer.owner);
+        assert_eq!(upgraded.code, "code");
+        assert_eq!(upgraded.fees_usd, 500);
+        assert_eq!(upgraded.unclaimed_fees_usd, 100);
+        assert_eq!(upgraded.bump, 253);
+        assert_eq!(upgraded.version, Referrer::VERSION);
+
+        // already upgraded
+        let mut data = vec![];
+        upgraded.try_serialize(&mut data).unwrap();
+        assert!(migrate_data::<Referrer>(&data).is_err());
+    }
+}
@@ -1,27 +1,94 @@
 //! Multisig state and routines
 
 use {
-    crate::{error::PerpetualsError, math},
-    ahash::AHasher,
-    anchor_lang::prelude::*,
-    std::hash::Hasher,
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{migration::Versioned, perpetuals::Perpetuals},
+    },
+    anchor_lang::{prelude::*, Discriminator},
+    solana_program::hash::hashv,
 };
 
 #[repr(C, packed)]
 #[account(zero_copy)]
 #[derive(Default)]
 pub struct Multisig {
+    pub num_signers: u8,
+    // min total weight of signatures required to execute an instruction
+    pub min_weight: u8,
+    pub signers: [Pubkey; 16], // Multisig::MAX_SIGNERS
+    pub weights: [u8; 16],     // Multisig::MAX_SIGNERS
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+// multisig layout before weighted signers were introduced, used for migration only
+#[repr(C, packed)]
+#[zero_copy]
+#[derive(Default)]
+pub struct DeprecatedMultisig {
     pub num_signers: u8,
     pub num_signed: u8,
     pub min_signatures: u8,
     pub instruction_accounts_len: u8,
     pub instruction_data_len: u16,
     pub instruction_hash: u64,
-    pub signers: [Pubkey; 6], // Multisig::MAX_SIGNERS
-    pub signed: [u8; 6],      // Multisig::MAX_SIGNERS
+    pub signers: [Pubkey; 6],
+    pub signed: [u8; 6],
     pub bump: u8,
 }
 
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum ProposalStatus {
+    Pending,
+    Executed,
+}
+
+impl Default for ProposalStatus {
+    fn default() -> Self {
+        Self::Pending
+    }
+}
+
+/// Pending admin instruction, one account per distinct set of instruction accounts and data.
+/// PDA address is derived from the instruction hash, so admins signing the same instruction
+/// always land on the same proposal and different instructions never interfere.
+#[account]
+#[derive(Default, Debug)]
+pub struct Proposal {
+    pub proposer: Pubkey,
+    // sha256 of instruction accounts and data, also used as PDA seed
+    pub instruction_hash: [u8; 32],
+    // sha256 of the multisig signers at the time of creation
+    pub signers_hash: [u8; 32],
+    pub instruction_accounts: Vec<Pubkey>,
+    pub instruction_data: Vec<u8>,
+    // bit i is set if Multisig::signers[i] has signed the proposal
+    pub signed: u64,
+    pub signed_weight: u16,
+    pub status: ProposalStatus,
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+/// Admin roles with a limited set of allowed instructions. Each role is controlled by
+/// a separate multisig account with its own signers and threshold. The root multisig
+/// can execute any admin instruction.
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum AdminRole {
+    // can only disable permissions with set_permissions
+    Guardian,
+    // set_custody_config
+    RiskManager,
+    // withdraw_fees and withdraw_sol_fees
+    Treasurer,
+    // add_custody, remove_custody and set_custom_oracle_price for a single pool
+    PoolAdmin,
+}
+
 pub enum AdminInstruction {
     AddPool,
     RemovePool,
@@ -36,27 +103,97 @@ pub enum AdminInstruction {
     SetCustomOraclePrice,
     SetTestTime,
     UpgradeCustody,
+    SetRoleSigners,
+    UpgradeMultisig,
+    SetConfigBounds,
+    UpdateCustodyConfig,
+    SetMarketStatus,
+    SettleCustody,
+    SetTradingSchedule,
+    SetPoolConfig,
+    UpgradePool,
+    SetStakingConfig,
+    EnableTranches,
+    SetFeeTiers,
+    UpgradePerpetuals,
 }
 
 impl Multisig {
-    pub const MAX_SIGNERS: usize = 6;
+    pub const MAX_SIGNERS: usize = 16;
     pub const LEN: usize = 8 + std::mem::size_of::<Multisig>();
+    pub const VERSION: u8 = 1;
 
-    /// Returns instruction accounts and data hash.
-    /// Hash is not cryptographic and is meant to perform a fast check that admins are signing
-    /// the same instruction.
-    pub fn get_instruction_hash(
-        instruction_accounts: &[AccountInfo],
-        instruction_data: &[u8],
-    ) -> u64 {
-        let mut hasher = AHasher::new_with_keys(697533735114380, 537268678243635);
-        for account in instruction_accounts {
-            hasher.write(account.key.as_ref());
+    /// Loads multisig data stored with any previous layout and upgrades it
+    pub fn migrate_data(data: &[u8]) -> Result<Multisig> {
+        if data.len() < 8 || data[..8] != Multisig::discriminator() {
+            return err!(ErrorCode::AccountDiscriminatorMismatch);
         }
-        if !instruction_data.is_empty() {
-            hasher.write(instruction_data);
+
+        let mut multisig: Multisig = if data.len() == DeprecatedMultisig::LEN {
+            bytemuck::pod_read_unaligned::<DeprecatedMultisig>(&data[8..]).into()
+        } else {
+            // zero padding in place of appended fields reads as their default values
+            let mut padded = data[8..].to_vec();
+            padded.resize(std::mem::size_of::<Multisig>(), 0);
+            bytemuck::pod_read_unaligned::<Multisig>(&padded)
+        };
+
+        let version = multisig.version;
+        if version == Multisig::VERSION {
+            msg!("Error: Account is already upgraded");
+            return Err(ProgramError::AccountAlreadyInitialized.into());
+        }
+        if version > Multisig::VERSION {
+            msg!("Error: Unknown account version {}", version);
+            return Err(ProgramError::InvalidAccountData.into());
         }
-        hasher.finish()
+        multisig.version = Multisig::VERSION;
+
+        Ok(multisig)
+    }
+
+    /// Returns true if the given key is the root multisig address
+    pub fn is_root(&self, multisig_key: &Pubkey) -> bool {
+        Pubkey::create_program_address(&[b"multisig", &[self.bump]], &crate::ID)
+            == Ok(*multisig_key)
+    }
+
+    /// Returns true if the given key is the root multisig or a multisig of the given role.
+    /// Pool admins are scoped with the pool key, other roles with Pubkey::default().
+    pub fn is_authorized(&self, multisig_key: &Pubkey, role: AdminRole, scope: &Pubkey) -> bool {
+        self.is_root(multisig_key)
+            || Pubkey::create_program_address(
+                &[b"multisig", &[role as u8], scope.as_ref(), &[self.bump]],
+                &crate::ID,
+            ) == Ok(*multisig_key)
+    }
+
+    /// Returns sha256 hash of instruction accounts and data
+    pub fn get_instruction_hash(
+        instruction_accounts: &[Pubkey],
+        instruction_data: &[u8],
+    ) -> [u8; 32] {
+        let mut data: Vec<&[u8]> = instruction_accounts
+            .iter()
+            .map(|key| key.as_ref())
+            .collect();
+        data.push(instruction_data);
+        hashv(&data).to_bytes()
+    }
+
+    /// Returns sha256 hash of the current set of signers, weights and threshold
+    pub fn get_signers_hash(&self) -> [u8; 32] {
+        let signers = self.signers;
+        let weights = self.weights;
+        let num_signers = self.num_signers as usize;
+        let mut data: Vec<&[u8]> = signers[..num_signers]
+            .iter()
+            .map(|key| key.as_ref())
+            .collect();
+        let min_weight = [self.min_weight];
+        data.push(&weights[..num_signers]);
+        data.push(&min_weight);
+        hashv(&data).to_bytes()
     }
 
     /// Returns all accounts for the given context
@@ -79,61 +216,84 @@ impl Multisig {
         Ok(res)
     }
 
-    /// Initializes multisig PDA with a new set of signers
-    pub fn set_signers(&mut self, admin_signers: &[AccountInfo], min_signatures: u8) -> Result<()> {
-        if admin_signers.is_empty() || min_signatures == 0 {
+    /// Initializes multisig PDA with a new set of signers.
+    /// Signer weights are given in the same order as signers, empty weights mean 1 for all.
+    pub fn set_signers(
+        &mut self,
+        admin_signers: &[AccountInfo],
+        weights: &[u8],
+        min_weight: u8,
+    ) -> Result<()> {
+        if admin_signers.is_empty() || min_weight == 0 {
             msg!("Error: At least one signer is required");
             return Err(ProgramError::MissingRequiredSignature.into());
         }
-        if (min_signatures as usize) > admin_signers.len() {
+        if admin_signers.len() > Multisig::MAX_SIGNERS {
             msg!(
-                "Error: Number of min signatures ({}) exceeded number of signers ({})",
-                min_signatures,
+                "Error: Number of signers ({}) exceeded max ({})",
                 admin_signers.len(),
+                Multisig::MAX_SIGNERS
             );
             return Err(ProgramError::InvalidArgument.into());
         }
-        if admin_signers.len() > Multisig::MAX_SIGNERS {
+        if !weights.is_empty() && weights.len() != admin_signers.len() {
             msg!(
-                "Error: Number of signers ({}) exceeded max ({})",
+                "Error: Number of weights ({}) doesn't match number of signers ({})",
+                weights.len(),
                 admin_signers.len(),
-                Multisig::MAX_SIGNERS
             );
             return Err(ProgramError::InvalidArgument.into());
         }
 
         let mut signers: [Pubkey; Multisig::MAX_SIGNERS] = Default::default();
-        let mut signed: [u8; Multisig::MAX_SIGNERS] = Default::default();
+        let mut signer_weights: [u8; Multisig::MAX_SIGNERS] = Default::default();
+        let mut total_weight: u16 = 0;
 
         for idx in 0..admin_signers.len() {
             if signers.contains(admin_signers[idx].key) {
                 msg!("Error: Duplicate signer {}", admin_signers[idx].key);
                 return Err(ProgramError::InvalidArgument.into());
             }
+            let weight = if weights.is_empty() { 1 } else { weights[idx] };
+            if weight == 0 {
+                msg!("Error: Zero weight for signer {}", admin_signers[idx].key);
+                return Err(ProgramError::InvalidArgument.into());
+            }
             signers[idx] = *admin_signers[idx].key;
-            signed[idx] = 0;
+            signer_weights[idx] = weight;
+            total_weight = math::checked_add(total_weight, weight as u16)?;
+        }
+
+        if min_weight as u16 > total_weight {
+            msg!(
+                "Error: Min weight ({}) exceeded total weight of signers ({})",
+                min_weight,
+                total_weight,
+            );
+            return Err(ProgramError::InvalidArgument.into());
         }
 
         *self = Multisig {
             num_signers: admin_signers.len() as u8,
-            num_signed: 0,
-            min_signatures,
-            instruction_accounts_len: 0,
-            instruction_data_len: 0,
-            instruction_hash: 0,
+            min_weight,
             signers,
-            signed,
+            weights: signer_weights,
             bump: self.bump,
+            version: self.version,
         };
 
         Ok(())
     }
 
-    /// Signs multisig and returns Ok(0) if there are enough signatures to continue or Ok(signatures_left) otherwise.
+    /// Signs the proposal for the given instruction and returns Ok(0) if there are enough
+    /// signatures to continue or Ok(weight_left) otherwise. Proposal account is created
+    /// on the first signature.
     /// If Err() is returned then signature was not recognized and transaction must be aborted.
-    pub fn sign_multisig(
-        &mut self,
-        signer_account: &AccountInfo,
+    pub fn sign_multisig<'info>(
+        &self,
+        signer_account: &AccountInfo<'info>,
+        proposal_account: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
         instruction_accounts: &[AccountInfo],
         instruction_data: &[u8],
     ) -> Result<u8> {
@@ -149,56 +309,79 @@ impl Multisig {
             return err!(PerpetualsError::MultisigAccountNotAuthorized);
         };
 
-        // if single signer return Ok to continue
-        if self.num_signers <= 1 {
+        // if single signature is enough return Ok to continue
+        let signer_weight = self.weights[signer_idx];
+        if signer_weight >= self.min_weight {
             return Ok(0);
         }
 
+        // proposal address is derived from the instruction hence it is excluded from it
+        let instruction_accounts: Vec<Pubkey> = instruction_accounts
+            .iter()
+            .map(|account| account.key())
+            .filter(|key| key != proposal_account.key)
+            .collect();
         let instruction_hash =
-            Multisig::get_instruction_hash(instruction_accounts, instruction_data);
-        if instruction_hash != self.instruction_hash
-            || instruction_accounts.len() != self.instruction_accounts_len as usize
-            || instruction_data.len() != self.instruction_data_len as usize
-        {
-            // if this is a new instruction reset the data
-            self.num_signed = 1;
-            self.instruction_accounts_len = instruction_accounts.len() as u8;
-            self.instruction_data_len = instruction_data.len() as u16;
-            self.instruction_hash = instruction_hash;
-            self.signed.fill(0);
-            self.signed[signer_idx] = 1;
-            //multisig.pack(*multisig_account.try_borrow_mut_data()?)?;
-
-            math::checked_sub(self.min_signatures, 1)
-        } else if self.signed[signer_idx] == 1 {
-            err!(PerpetualsError::MultisigAlreadySigned)
-        } else if self.num_signed < self.min_signatures {
-            // count the signature in
-            self.num_signed = math::checked_add(self.num_signed, 1)?;
-            self.signed[signer_idx] = 1;
-
-            if self.num_signed == self.min_signatures {
-                Ok(0)
-            } else {
-                math::checked_sub(self.min_signatures, self.num_signed)
-            }
+            Multisig::get_instruction_hash(&instruction_accounts, instruction_data);
+
+        let mut proposal = if Perpetuals::is_empty_account(proposal_account)? {
+            msg!("Create new proposal");
+            Proposal::create(
+                signer_account,
+                proposal_account,
+                system_program,
+                instruction_hash,
+                self.get_signers_hash(),
+                instruction_accounts,
+                instruction_data,
+            )?
         } else {
-            err!(PerpetualsError::MultisigAlreadyExecuted)
+            let proposal = Account::<Proposal>::try_from(proposal_account)?;
+            proposal.validate_address(proposal_account.key)?;
+            require!(
+                proposal.instruction_hash == instruction_hash
+                    && proposal.instruction_accounts == instruction_accounts
+                    && proposal.instruction_data == instruction_data,
+                PerpetualsError::MultisigProposalMismatch
+            );
+            require!(
+                proposal.signers_hash == self.get_signers_hash(),
+                PerpetualsError::MultisigProposalStale
+            );
+            proposal
+        };
+
+        if proposal.status == ProposalStatus::Executed {
+            return err!(PerpetualsError::MultisigAlreadyExecuted);
+        }
+        if proposal.is_signed_by(signer_idx) {
+            return err!(PerpetualsError::MultisigAlreadySigned);
         }
+
+        // count the signature in
+        proposal.signed |= 1u64 << signer_idx;
+        proposal.signed_weight = math::checked_add(proposal.signed_weight, signer_weight as u16)?;
+
+        let weight_left = (self.min_weight as u16).saturating_sub(proposal.signed_weight) as u8;
+        if weight_left == 0 {
+            proposal.status = ProposalStatus::Executed;
+        }
+        proposal.exit(&crate::ID)?;
+
+        Ok(weight_left)
     }
 
-    /// Removes admin signature from the multisig
-    pub fn unsign_multisig(&mut self, signer_account: &AccountInfo) -> Result<()> {
+    /// Removes admin signature from the proposal
+    pub fn unsign_multisig(
+        &self,
+        signer_account: &AccountInfo,
+        proposal: &mut Proposal,
+    ) -> Result<()> {
         // return early if not a signer
         if !signer_account.is_signer {
             return Err(ProgramError::MissingRequiredSignature.into());
         }
 
-        // if single signer return
-        if self.num_signers <= 1 || self.num_signed == 0 {
-            return Ok(());
-        }
-
         // find index of current signer or return error if not found
         let signer_idx = if let Ok(idx) = self.get_signer_index(signer_account.key) {
             idx
@@ -206,14 +389,19 @@ impl Multisig {
             return err!(PerpetualsError::MultisigAccountNotAuthorized);
         };
 
+        if proposal.status == ProposalStatus::Executed {
+            return err!(PerpetualsError::MultisigAlreadyExecuted);
+        }
+
         // if not signed by this account return
-        if self.signed[signer_idx] == 0 {
+        if !proposal.is_signed_by(signer_idx) {
             return Ok(());
         }
 
         // remove signature
-        self.num_signed = math::checked_sub(self.num_signed, 1)?;
-        self.signed[signer_idx] = 0;
+        proposal.signed_weight =
+            math::checked_sub(proposal.signed_weight, self.weights[signer_idx] as u16)?;
+        proposal.signed &= !(1u64 << signer_idx);
 
         Ok(())
     }
@@ -233,3 +421,269 @@ impl Multisig {
         Ok(self.get_signer_index(key).is_ok())
     }
 }
+
+impl Versioned for Proposal {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        Proposal::get_size(self.instruction_accounts.len(), self.instruction_data.len())
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Proposal {
+    pub const LEN: usize = 8 + std::mem::size_of::<Proposal>();
+
+    /// Returns account size required to store the given instruction
+    pub fn get_size(instruction_accounts_len: usize, instruction_data_len: usize) -> usize {
+        Proposal::LEN
+            + instruction_accounts_len * std::mem::size_of::<Pubkey>()
+            + instruction_data_len
+    }
+
+    pub fn validate_address(&self, address: &Pubkey) -> Result<()> {
+        let expected_address = Pubkey::create_program_address(
+            &[b"proposal", &self.instruction_hash, &[self.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+        require_keys_eq!(expected_address, *address, ErrorCode::ConstraintSeeds);
+        Ok(())
+    }
+
+    pub fn is_signed_by(&self, signer_idx: usize) -> bool {
+        self.signed & (1u64 << signer_idx) != 0
+    }
+
+    fn create<'info>(
+        payer: &AccountInfo<'info>,
+        proposal_account: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+        instruction_hash: [u8; 32],
+        signers_hash: [u8; 32],
+        instruction_accounts: Vec<Pubkey>,
+        instruction_data: &[u8],
+    ) -> Result<Account<'info, Proposal>> {
+        let (expected_address, bump) =
+            Pubkey::find_program_address(&[b"proposal", &instruction_hash], &crate::ID);
+        require_keys_eq!(
+            expected_address,
+            proposal_account.key(),
+            ErrorCode::ConstraintSeeds
+        );
+
+        Perpetuals::create_account(
+            payer.clone(),
+            proposal_account.clone(),
+            system_program.clone(),
+            Proposal::get_size(instruction_accounts.len(), instruction_data.len()),
+            &[&[b"proposal", &instruction_hash, &[bump]]],
+        )?;
+
+        let mut proposal = Account::<Proposal>::try_from_unchecked(proposal_account)?;
+        proposal.proposer = payer.key();
+        proposal.instruction_hash = instruction_hash;
+        proposal.signers_hash = signers_hash;
+        proposal.instruction_accounts = instruction_accounts;
+        proposal.instruction_data = instruction_data.to_vec();
+        proposal.signed = 0;
+        proposal.signed_weight = 0;
+        proposal.status = ProposalStatus::Pending;
+        proposal.bump = bump;
+        proposal.version = Proposal::VERSION;
+
+        Ok(proposal)
+    }
+}
+
+impl DeprecatedMultisig {
+    pub const LEN: usize = 8 + std::mem::size_of::<DeprecatedMultisig>();
+}
+
+impl From<DeprecatedMultisig> for Multisig {
+    fn from(deprecated: DeprecatedMultisig) -> Self {
+        let mut signers: [Pubkey; Multisig::MAX_SIGNERS] = Default::default();
+        let mut weights: [u8; Multisig::MAX_SIGNERS] = Default::default();
+        let num_signers = deprecated.num_signers as usize;
+        let deprecated_signers = deprecated.signers;
+
+        signers[..num_signers].copy_from_slice(&deprecated_signers[..num_signers]);
+        weights[..num_signers].fill(1);
+
+        Multisig {
+            num_signers: deprecated.num_signers,
+            min_weight: deprecated.min_signatures,
+            signers,
+            weights,
+            bump: deprecated.bump,
+            version: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_signer_infos<'a>(
+        keys: &'a [Pubkey],
+        lamports: &'a mut [u64],
+        data: &'a mut [Vec<u8>],
+    ) -> Vec<AccountInfo<'a>> {
+        keys.iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, false, false, lamports, data, &crate::ID, false, 0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_set_signers() {
+        let keys: Vec<Pubkey> = (0..9).map(|_| Pubkey::new_unique()).collect();
+        let mut lamports = vec![0u64; keys.len()];
+        let mut data = vec![vec![]; keys.len()];
+        let signers = get_signer_infos(&keys, &mut lamports, &mut data);
+
+        let mut multisig = Multisig {
+            bump: 255,
+            ..Multisig::default()
+        };
+
+        // equal weights
+        assert!(multisig.set_signers(&signers, &[], 5).is_ok());
+        assert_eq!({ multisig.num_signers }, 9);
+        assert_eq!({ multisig.min_weight }, 5);
+        assert_eq!({ multisig.bump }, 255);
+        assert_eq!(multisig.weights[..9], [1; 9]);
+        assert!(multisig.set_signers(&signers, &[], 10).is_err());
+
+        // first signer counts double
+        let weights = [2, 1, 1, 1, 1, 1, 1, 1, 1];
+        assert!(multisig.set_signers(&signers, &weights, 10).is_ok());
+        assert_eq!(multisig.weights[..9], weights);
+        assert_eq!(multisig.get_signer_index(&keys[8]).unwrap(), 8);
+        assert!(multisig.set_signers(&signers, &weights, 11).is_err());
+        assert!(multisig.set_signers(&signers, &weights[..8], 5).is_err());
+        assert!(multisig
+            .set_signers(&signers, &[0, 1, 1, 1, 1, 1, 1, 1, 1], 5)
+            .is_err());
+
+        // duplicate signers
+        let duplicate_keys = [keys[0], keys[0]];
+        let mut lamports = vec![0u64; 2];
+        let mut data = vec![vec![]; 2];
+        let duplicate_signers = get_signer_infos(&duplicate_keys, &mut lamports, &mut data);
+        assert!(multisig.set_signers(&duplicate_signers, &[], 1).is_err());
+    }
+
+    #[test]
+    fn test_signers_hash() {
+        let keys: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let mut lamports = vec![0u64; keys.len()];
+        let mut data = vec![vec![]; keys.len()];
+        let signers = get_signer_infos(&keys, &mut lamports, &mut data);
+
+        let mut multisig = Multisig::default();
+        multisig.set_signers(&signers, &[], 2).unwrap();
+        let hash = multisig.get_signers_hash();
+
+        multisig.set_signers(&signers, &[], 2).unwrap();
+        assert_eq!(hash, multisig.get_signers_hash());
+
+        multisig.set_signers(&signers, &[2, 1, 1], 2).unwrap();
+        assert_ne!(hash, multisig.get_signers_hash());
+
+        multisig.set_signers(&signers, &[], 3).unwrap();
+        assert_ne!(hash, multisig.get_signers_hash());
+    }
+
+    #[test]
+    fn test_upgrade_deprecated() {
+        let mut deprecated = DeprecatedMultisig {
+            num_signers: 3,
+            num_signed: 1,
+            min_signatures: 2,
+            instruction_hash: 12345,
+            bump: 254,
+            ..DeprecatedMultisig::default()
+        };
+        let mut signers = deprecated.signers;
+        for signer in signers.iter_mut().take(3) {
+            *signer = Pubkey::new_unique();
+        }
+        deprecated.signers = signers;
+
+        let multisig: Multisig = deprecated.into();
+
+        assert_eq!({ multisig.num_signers }, 3);
+        assert_eq!({ multisig.min_weight }, 2);
+        assert_eq!({ multisig.bump }, 254);
+        assert_eq!(multisig.signers[..3], signers[..3]);
+        assert_eq!(multisig.signers[3..], [Pubkey::default(); 13]);
+        assert_eq!(multisig.weights[..3], [1; 3]);
+        assert_eq!(multisig.weights[3..], [0; 13]);
+        assert_eq!(DeprecatedMultisig::LEN, 221);
+    }
+
+    #[test]
+    fn test_migrate_data() {
+        let mut multisig = Multisig {
+            num_signers: 2,
+            min_weight: 3,
+            bump: 253,
+            ..Multisig::default()
+        };
+        multisig.signers[0] = Pubkey::new_unique();
+        multisig.signers[1] = Pubkey::new_unique();
+        multisig.weights[..2].copy_from_slice(&[2, 1]);
+
+        // multisig fixture without the version field
+        let mut data = Multisig::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&multisig));
+        data.pop();
+        assert_eq!(data.len(), Multisig::LEN - 1);
+
+        let upgraded = Multisig::migrate_data(&data).unwrap();
+        assert_eq!({ upgraded.num_signers }, 2);
+        assert_eq!({ upgraded.min_weight }, 3);
+        assert_eq!({ upgraded.bump }, 253);
+        assert_eq!({ upgraded.signers }, { multisig.signers });
+        assert_eq!({ upgraded.weights }, { multisig.weights });
+        assert_eq!({ upgraded.version }, Multisig::VERSION);
+
+        // deprecated multisig fixture
+        let deprecated = DeprecatedMultisig {
+            num_signers: 1,
+            min_signatures: 1,
+            bump: 252,
+            ..DeprecatedMultisig::default()
+        };
+        let mut data = Multisig::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&deprecated));
+
+        let upgraded = Multisig::migrate_data(&data).unwrap();
+        assert_eq!({ upgraded.num_signers }, 1);
+        assert_eq!({ upgraded.weights[0] }, 1);
+        assert_eq!({ upgraded.bump }, 252);
+        assert_eq!({ upgraded.version }, Multisig::VERSION);
+
+        // current layout
+        let mut data = Multisig::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&upgraded));
+        assert!(Multisig::migrate_data(&data).is_err());
+        assert!(Multisig::migrate_data(&data[8..]).is_err());
+    }
+}
@@ -11,12 +11,17 @@ const ORACLE_PRICE_SCALE: u64 = 1_000_000_000;
 const ORACLE_MAX_PRICE: u64 = (1 << 28) - 1;
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[repr(u8)]
 pub enum OracleType {
     None,
     Custom,
     Pyth,
 }
 
+impl OracleType {
+    pub const VARIANTS: u8 = OracleType::Pyth as u8 + 1;
+}
+
 impl Default for OracleType {
     fn default() -> Self {
         Self::None
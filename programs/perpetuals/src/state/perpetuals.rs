@@ -1,4 +1,5 @@
 use {
+    crate::state::migration::Versioned,
     anchor_lang::prelude::*,
     anchor_spl::token::{Burn, MintTo, Transfer},
 };
@@ -35,6 +36,17 @@ pub struct ProfitAndLoss {
     pub loss: u64,
 }
 
+// custody activity over the last 24 hours and 7 days, including the current hour and day
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct CustodyStats {
+    pub volume_24h_usd: u64,
+    pub fees_24h_usd: u64,
+    pub max_oi_24h_usd: u64,
+    pub volume_7d_usd: u64,
+    pub fees_7d_usd: u64,
+    pub max_oi_7d_usd: u64,
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct Permissions {
     pub allow_swap: bool,
@@ -47,6 +59,69 @@ pub struct Permissions {
     pub allow_size_change: bool,
 }
 
+impl Permissions {
+    /// Returns true if new permissions don't allow anything that is currently disallowed
+    pub fn is_restricted_by(&self, new_permissions: &Permissions) -> bool {
+        (self.allow_swap || !new_permissions.allow_swap)
+            && (self.allow_add_liquidity || !new_permissions.allow_add_liquidity)
+            && (self.allow_remove_liquidity || !new_permissions.allow_remove_liquidity)
+            && (self.allow_open_position || !new_permissions.allow_open_position)
+            && (self.allow_close_position || !new_permissions.allow_close_position)
+            && (self.allow_pnl_withdrawal || !new_permissions.allow_pnl_withdrawal)
+            && (self.allow_collateral_withdrawal || !new_permissions.allow_collateral_withdrawal)
+            && (self.allow_size_change || !new_permissions.allow_size_change)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct FeeTier {
+    // min rolling trade volume of the user to qualify for the tier, USD denominated
+    pub min_volume_usd: u64,
+    // multiplier applied to trade fees, in BPS
+    pub fee_mult: u64,
+}
+
+// statuses are ordered from the least to the most restrictive
+#[derive(
+    Copy, Clone, PartialEq, Eq, PartialOrd, Ord, AnchorSerialize, AnchorDeserialize, Debug,
+)]
+#[repr(u8)]
+pub enum MarketStatus {
+    // all trading instructions are allowed
+    Active,
+    // only instructions that reduce exposure are allowed
+    ReduceOnly,
+    // all trading instructions are disabled
+    Paused,
+    // market has been wound down
+    Settled,
+}
+
+impl Default for MarketStatus {
+    fn default() -> Self {
+        Self::Active
+    }
+}
+
+impl MarketStatus {
+    pub const VARIANTS: u8 = MarketStatus::Settled as u8 + 1;
+
+    /// Returns true if new positions, swaps and deposits are allowed
+    pub fn allows_increase(&self) -> bool {
+        *self == MarketStatus::Active
+    }
+
+    /// Returns true if closes, collateral additions, liquidations and withdrawals are allowed
+    pub fn allows_reduce(&self) -> bool {
+        *self == MarketStatus::Active || *self == MarketStatus::ReduceOnly
+    }
+
+    /// Returns true if liquidity withdrawals are allowed
+    pub fn allows_withdrawal(&self) -> bool {
+        self.allows_reduce() || *self == MarketStatus::Settled
+    }
+}
+
 #[account]
 #[derive(Default, Debug)]
 pub struct Perpetuals {
@@ -57,6 +132,12 @@ pub struct Perpetuals {
     pub perpetuals_bump: u8,
     // time of inception, also used as current wall clock time for testing
     pub inception_time: i64,
+    // volume-based trade fee tiers sorted by min_volume_usd, appended to the end
+    // of the account, accounts created before deserialize it as empty
+    pub fee_tiers: Vec<FeeTier>,
+
+    // layout version, new fields are appended after it
+    pub version: u8,
 }
 
 impl anchor_lang::Id for Perpetuals {
@@ -65,6 +146,29 @@ impl anchor_lang::Id for Perpetuals {
     }
 }
 
+impl Versioned for Perpetuals {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        Perpetuals::LEN
+            + self.pools.len() * std::mem::size_of::<Pubkey>()
+            + self.fee_tiers.len() * std::mem::size_of::<FeeTier>()
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        // fee tiers of version 0 accounts deserialize as empty
+        Ok(())
+    }
+}
+
 impl Perpetuals {
     pub const LEN: usize = 8 + std::mem::size_of::<Perpetuals>();
     pub const BPS_DECIMALS: u8 = 4;
@@ -74,9 +178,27 @@ impl Perpetuals {
     pub const LP_DECIMALS: u8 = Self::USD_DECIMALS;
     pub const RATE_DECIMALS: u8 = 9;
     pub const RATE_POWER: u128 = 10u64.pow(Self::RATE_DECIMALS as u32) as u128;
+    pub const MAX_FEE_TIERS: usize = 8;
 
     pub fn validate(&self) -> bool {
-        true
+        self.fee_tiers.len() <= Self::MAX_FEE_TIERS
+            && self
+                .fee_tiers
+                .iter()
+                .all(|tier| tier.fee_mult as u128 <= Self::BPS_POWER)
+            && self
+                .fee_tiers
+                .windows(2)
+                .all(|w| w[0].min_volume_usd < w[1].min_volume_usd)
+    }
+
+    /// Returns trade fee multiplier of the highest tier the volume qualifies for
+    pub fn get_fee_mult(&self, volume_usd: u64) -> u64 {
+        self.fee_tiers
+            .iter()
+            .rev()
+            .find(|tier| volume_usd >= tier.min_volume_usd)
+            .map_or(Self::BPS_POWER as u64, |tier| tier.fee_mult)
     }
 
     #[cfg(feature = "test")]
@@ -207,6 +329,30 @@ impl Perpetuals {
         anchor_spl::token::burn(context, amount)
     }
 
+    pub fn burn_owned_tokens<'info>(
+        &self,
+        mint: AccountInfo<'info>,
+        from: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        let authority_seeds: &[&[&[u8]]] =
+            &[&[b"transfer_authority", &[self.transfer_authority_bump]]];
+
+        let context = CpiContext::new(
+            token_program,
+            Burn {
+                mint,
+                from,
+                authority,
+            },
+        )
+        .with_signer(authority_seeds);
+
+        anchor_spl::token::burn(context, amount)
+    }
+
     pub fn is_empty_account(account_info: &AccountInfo) -> Result<bool> {
         Ok(account_info.try_data_is_empty()? || account_info.try_lamports()? == 0)
     }
@@ -261,6 +407,26 @@ impl Perpetuals {
         anchor_lang::system_program::transfer(cpi_context, amount)
     }
 
+    pub fn transfer_sol_signed<'a>(
+        source_account: AccountInfo<'a>,
+        destination_account: AccountInfo<'a>,
+        system_program: AccountInfo<'a>,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: source_account,
+            to: destination_account,
+        };
+        let cpi_context = anchor_lang::context::CpiContext::new_with_signer(
+            system_program,
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        anchor_lang::system_program::transfer(cpi_context, amount)
+    }
+
     pub fn realloc<'a>(
         funding_account: AccountInfo<'a>,
         target_account: AccountInfo<'a>,
@@ -282,4 +448,87 @@ impl Perpetuals {
             .realloc(new_len, zero_init)
             .map_err(|_| ProgramError::InvalidRealloc.into())
     }
+
+    pub fn create_account<'a>(
+        funding_account: AccountInfo<'a>,
+        target_account: AccountInfo<'a>,
+        system_program: AccountInfo<'a>,
+        space: usize,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let minimum_balance = Rent::get()?.minimum_balance(space);
+        let current_lamports = target_account.try_lamports()?;
+
+        if current_lamports == 0 {
+            let cpi_accounts = anchor_lang::system_program::CreateAccount {
+                from: funding_account,
+                to: target_account,
+            };
+            let cpi_context = anchor_lang::context::CpiContext::new(system_program, cpi_accounts)
+                .with_signer(signer_seeds);
+
+            anchor_lang::system_program::create_account(
+                cpi_context,
+                minimum_balance,
+                space as u64,
+                &crate::ID,
+            )
+        } else {
+            // account has been prefunded, create_account would fail
+            Perpetuals::transfer_sol(
+                funding_account,
+                target_account.clone(),
+                system_program.clone(),
+                minimum_balance.saturating_sub(current_lamports),
+            )?;
+
+            let cpi_accounts = anchor_lang::system_program::Allocate {
+                account_to_allocate: target_account.clone(),
+            };
+            let cpi_context =
+                anchor_lang::context::CpiContext::new(system_program.clone(), cpi_accounts)
+                    .with_signer(signer_seeds);
+            anchor_lang::system_program::allocate(cpi_context, space as u64)?;
+
+            let cpi_accounts = anchor_lang::system_program::Assign {
+                account_to_assign: target_account,
+            };
+            let cpi_context = anchor_lang::context::CpiContext::new(system_program, cpi_accounts)
+                .with_signer(signer_seeds);
+            anchor_lang::system_program::assign(cpi_context, &crate::ID)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fee_tiers() {
+        let mut perpetuals = Perpetuals::default();
+        assert!(perpetuals.validate());
+        assert_eq!(perpetuals.get_fee_mult(1_000), 10_000);
+
+        perpetuals.fee_tiers = vec![
+            FeeTier {
+                min_volume_usd: 1_000,
+                fee_mult: 9_000,
+            },
+            FeeTier {
+                min_volume_usd: 10_000,
+                fee_mult: 5_000,
+            },
+        ];
+        assert!(perpetuals.validate());
+        assert_eq!(perpetuals.get_fee_mult(999), 10_000);
+        assert_eq!(perpetuals.get_fee_mult(1_000), 9_000);
+        assert_eq!(perpetuals.get_fee_mult(20_000), 5_000);
+
+        perpetuals.fee_tiers[1].min_volume_usd = 1_000;
+        assert!(!perpetuals.validate());
+        perpetuals.fee_tiers[1].min_volume_usd = 10_000;
+        perpetuals.fee_tiers[1].fee_mult = 10_001;
+        assert!(!perpetuals.validate());
+    }
 }
@@ -4,8 +4,9 @@ use {
         math,
         state::{
             custody::{Custody, FeesMode},
+            migration::EnumFields,
             oracle::OraclePrice,
-            perpetuals::Perpetuals,
+            perpetuals::{MarketStatus, Permissions, Perpetuals},
             position::{Position, Side},
         },
     },
@@ -14,6 +15,7 @@ use {
 };
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[repr(u8)]
 pub enum AumCalcMode {
     Min,
     Max,
@@ -21,6 +23,16 @@ pub enum AumCalcMode {
     EMA,
 }
 
+impl AumCalcMode {
+    pub const VARIANTS: u8 = AumCalcMode::EMA as u8 + 1;
+}
+
+impl Default for AumCalcMode {
+    fn default() -> Self {
+        Self::EMA
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct TokenRatios {
     pub target: u64,
@@ -28,9 +40,93 @@ pub struct TokenRatios {
     pub max: u64,
 }
 
+// pool params that can be changed by admins
+#[derive(Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct PoolConfig {
+    pub display_name: String,
+    pub permissions: Permissions,
+    pub add_liquidity_aum_mode: AumCalcMode,
+    pub remove_liquidity_aum_mode: AumCalcMode,
+    pub max_aum_usd: u128,
+    pub max_user_lp_amount: u64,
+    pub lp_cooldown_sec: u64,
+    pub junior_premium: u64,
+    pub referrer_share: u64,
+    pub trader_discount: u64,
+    pub max_aum_age_sec: u64,
+    pub keeper_reward: u64,
+}
+
+// Pool is loaded with zero-copy, see Custody for why Pod is implemented unchecked
+// and how enum fields are validated.
+// Name and custodies are stored in fixed size arrays, use get_name() and
+// get_custodies() to read them.
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
+#[derive(Debug)]
+pub struct Pool {
+    // zero padded
+    pub name: [u8; 64],
+    pub num_custodies: u8,
+    pub custodies: [Pubkey; 16],   // Pool::MAX_CUSTODIES
+    pub ratios: [TokenRatios; 16], // Pool::MAX_CUSTODIES
+    pub aum_usd: u128,
+
+    pub bump: u8,
+    pub lp_token_bump: u8,
+    pub inception_time: i64,
+    pub status: MarketStatus,
+
+    // pool config, zero padded display name
+    pub display_name: [u8; 64],
+    pub permissions: Permissions,
+    // aum calculation modes used to price lp tokens on deposits and withdrawals
+    pub add_liquidity_aum_mode: AumCalcMode,
+    pub remove_liquidity_aum_mode: AumCalcMode,
+    // USD denominated, zero means no limit
+    pub max_aum_usd: u128,
+    // max LP tokens minted per user, zero means no limit
+    pub max_user_lp_amount: u64,
+    // LP tokens can't be redeemed until this period passes since the last deposit
+    pub lp_cooldown_sec: u64,
+    // share of senior tranche gains paid to the junior tranche, in BPS
+    pub junior_premium: u64,
+
+    // senior/junior tranches, enabled with enable_tranches. Senior LPs hold
+    // lp_token_mint, junior LPs hold junior_lp_token_mint and take losses first.
+    pub tranches_enabled: bool,
+    pub junior_lp_token_bump: u8,
+    // senior tranche value and pool AUM at the last tranche update
+    pub senior_aum_usd: u128,
+    pub tranche_aum_usd: u128,
+
+    // shares of referred trade fees, in BPS. The trader discount is deducted
+    // from the fee, the referrer share is paid from fees accrued to the pool.
+    pub referrer_share: u64,
+    pub trader_discount: u64,
+
+    // layout version, upgrade_pool converts accounts with older layouts
+    pub version: u8,
+
+    // sums of the AUM values cached in pool custodies by AumCalcMode, and the time
+    // each custody value was refreshed, in the same order as custodies
+    pub cached_aum_usd: [u128; 4],
+    pub aum_update_times: [i64; 16], // Pool::MAX_CUSTODIES
+    // liquidity is priced with the cached AUM only if all custody values are
+    // more recent than this, zero means no limit
+    pub max_aum_age_sec: u64,
+
+    // oldest of the custody AUM update times, cached AUM is up to date as of then
+    pub last_aum_update: i64,
+    // lamports paid from SOL fees to keepers that refresh the pool AUM
+    // before it goes stale, see crank_pool
+    pub keeper_reward: u64,
+}
+
+// borsh-serialized pool layout before it was moved to zero-copy
 #[account]
 #[derive(Default, Debug)]
-pub struct Pool {
+pub struct DeprecatedPool {
     pub name: String,
     pub custodies: Vec<Pubkey>,
     pub ratios: Vec<TokenRatios>,
@@ -39,6 +135,48 @@ pub struct Pool {
     pub bump: u8,
     pub lp_token_bump: u8,
     pub inception_time: i64,
+    // appended to the end of the account, accounts created before
+    // have zero padding in its place which deserializes as Active
+    pub status: MarketStatus,
+
+    // pool config, accounts created before are initialized with upgrade_pool
+    pub display_name: String,
+    pub permissions: Permissions,
+    // aum calculation modes used to price lp tokens on deposits and withdrawals
+    pub add_liquidity_aum_mode: AumCalcMode,
+    pub remove_liquidity_aum_mode: AumCalcMode,
+    // USD denominated, zero means no limit
+    pub max_aum_usd: u128,
+    // max LP tokens minted per user, zero means no limit
+    pub max_user_lp_amount: u64,
+    // LP tokens can't be redeemed until this period passes since the last deposit
+    pub lp_cooldown_sec: u64,
+    // share of senior tranche gains paid to the junior tranche, in BPS
+    pub junior_premium: u64,
+
+    // senior/junior tranches, enabled with enable_tranches. Senior LPs hold
+    // lp_token_mint, junior LPs hold junior_lp_token_mint and take losses first.
+    pub tranches_enabled: bool,
+    pub junior_lp_token_bump: u8,
+    // senior tranche value and pool AUM at the last tranche update
+    pub senior_aum_usd: u128,
+    pub tranche_aum_usd: u128,
+
+    // shares of referred trade fees, in BPS. The trader discount is deducted
+    // from the fee, the referrer share is paid from fees accrued to the pool.
+    pub referrer_share: u64,
+    pub trader_discount: u64,
+
+    pub version: u8,
+}
+
+impl PoolConfig {
+    pub fn validate(&self) -> bool {
+        !self.display_name.is_empty()
+            && self.display_name.len() <= 64
+            && (self.junior_premium as u128) <= Perpetuals::BPS_POWER
+            && (self.referrer_share as u128 + self.trader_discount as u128) <= Perpetuals::BPS_POWER
+    }
 }
 
 impl TokenRatios {
@@ -55,40 +193,300 @@ impl TokenRatios {
 /// All returned prices are scaled to PRICE_DECIMALS.
 /// All returned amounts are scaled to corresponding custody decimals.
 ///
+impl Default for Pool {
+    fn default() -> Self {
+        Self {
+            add_liquidity_aum_mode: AumCalcMode::default(),
+            remove_liquidity_aum_mode: AumCalcMode::default(),
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+}
+
+impl EnumFields for Pool {
+    fn enum_fields() -> Vec<(usize, u8)> {
+        let pool: Pool = bytemuck::Zeroable::zeroed();
+        let base = std::ptr::addr_of!(pool) as usize;
+        vec![
+            (
+                std::ptr::addr_of!(pool.status) as usize - base,
+                MarketStatus::VARIANTS,
+            ),
+            (
+                std::ptr::addr_of!(pool.add_liquidity_aum_mode) as usize - base,
+                AumCalcMode::VARIANTS,
+            ),
+            (
+                std::ptr::addr_of!(pool.remove_liquidity_aum_mode) as usize - base,
+                AumCalcMode::VARIANTS,
+            ),
+        ]
+    }
+}
+
 impl Pool {
-    pub const LEN: usize = 8 + 64 + std::mem::size_of::<Pool>();
+    pub const LEN: usize = 8 + std::mem::size_of::<Pool>();
+    // zero-copy layout before the keeper crank was added
+    pub const LEN_V3: usize = Pool::LEN - std::mem::size_of::<i64>() - std::mem::size_of::<u64>();
+    // zero-copy layout before the AUM was cached
+    pub const LEN_V2: usize = Pool::LEN_V3
+        - std::mem::size_of::<[u128; 4]>()
+        - std::mem::size_of::<[i64; Pool::MAX_CUSTODIES]>()
+        - std::mem::size_of::<u64>();
+    pub const MAX_CUSTODIES: usize = 16;
+    pub const MAX_NAME_LEN: usize = 64;
+    pub const VERSION: u8 = 4;
 
     pub fn validate(&self) -> bool {
-        for ratio in &self.ratios {
+        let ratios = self.get_ratios();
+        for ratio in &ratios {
             if !ratio.validate() {
                 return false;
             }
         }
 
         // check target ratios add up to 1
-        if !self.ratios.is_empty()
-            && self
-                .ratios
-                .iter()
-                .map(|&x| (x.target as u128))
-                .sum::<u128>()
-                != Perpetuals::BPS_POWER
+        if !ratios.is_empty()
+            && ratios.iter().map(|&x| (x.target as u128)).sum::<u128>() != Perpetuals::BPS_POWER
         {
             return false;
         }
 
         // check custodies are unique
-        for i in 1..self.custodies.len() {
-            if self.custodies[i..].contains(&self.custodies[i - 1]) {
+        let custodies = self.get_custodies();
+        for i in 1..custodies.len() {
+            if custodies[i..].contains(&custodies[i - 1]) {
                 return false;
             }
         }
 
-        !self.name.is_empty() && self.name.len() <= 64 && self.custodies.len() == self.ratios.len()
+        !self.get_name().is_empty() && (self.num_custodies as usize) <= Pool::MAX_CUSTODIES
+    }
+
+    /// Returns pool name as used in the pool PDA seeds
+    pub fn get_name(&self) -> &[u8] {
+        Self::trim_zeros(&self.name)
+    }
+
+    pub fn set_name(&mut self, name: &str) -> Result<()> {
+        self.name = Self::pad_zeros(name)?;
+        Ok(())
+    }
+
+    pub fn get_display_name(&self) -> String {
+        String::from_utf8_lossy(Self::trim_zeros(&self.display_name)).into_owned()
+    }
+
+    pub fn get_custodies(&self) -> &[Pubkey] {
+        &self.custodies[..std::cmp::min(self.num_custodies as usize, Pool::MAX_CUSTODIES)]
+    }
+
+    pub fn get_ratios(&self) -> Vec<TokenRatios> {
+        let ratios = self.ratios;
+        ratios[..std::cmp::min(self.num_custodies as usize, Pool::MAX_CUSTODIES)].to_vec()
+    }
+
+    /// Replaces pool custodies and their token ratios
+    pub fn set_custodies(&mut self, custodies: &[Pubkey], ratios: &[TokenRatios]) -> Result<()> {
+        if custodies.len() > Pool::MAX_CUSTODIES {
+            msg!(
+                "Error: Number of custodies ({}) exceeded max ({})",
+                custodies.len(),
+                Pool::MAX_CUSTODIES
+            );
+            return err!(PerpetualsError::InvalidPoolConfig);
+        }
+        if ratios.len() != custodies.len() {
+            return err!(PerpetualsError::InvalidPoolConfig);
+        }
+        let mut pool_custodies = [Pubkey::default(); Pool::MAX_CUSTODIES];
+        let mut pool_ratios = [TokenRatios::default(); Pool::MAX_CUSTODIES];
+        let mut aum_update_times = [0i64; Pool::MAX_CUSTODIES];
+        pool_custodies[..custodies.len()].copy_from_slice(custodies);
+        pool_ratios[..ratios.len()].copy_from_slice(ratios);
+        // existing custodies keep the time their AUM value was refreshed
+        let prev_times = self.aum_update_times;
+        for (idx, custody) in custodies.iter().enumerate() {
+            if let Ok(prev_idx) = self.get_token_id(custody) {
+                aum_update_times[idx] = prev_times[prev_idx];
+            }
+        }
+        self.custodies = pool_custodies;
+        self.ratios = pool_ratios;
+        self.aum_update_times = aum_update_times;
+        self.num_custodies = custodies.len() as u8;
+        self.update_last_aum_update();
+        Ok(())
+    }
+
+    /// Replaces token ratios of existing custodies
+    pub fn set_ratios(&mut self, ratios: &[TokenRatios]) -> Result<()> {
+        let custodies = self.get_custodies().to_vec();
+        self.set_custodies(&custodies, ratios)
+    }
+
+    fn trim_zeros(data: &[u8]) -> &[u8] {
+        let len = data.iter().rposition(|&x| x != 0).map_or(0, |pos| pos + 1);
+        &data[..len]
+    }
+
+    fn pad_zeros(value: &str) -> Result<[u8; 64]> {
+        if value.len() > Pool::MAX_NAME_LEN {
+            return err!(PerpetualsError::InvalidPoolConfig);
+        }
+        let mut res = [0u8; 64];
+        res[..value.len()].copy_from_slice(value.as_bytes());
+        Ok(res)
+    }
+
+    pub fn get_config(&self) -> PoolConfig {
+        PoolConfig {
+            display_name: self.get_display_name(),
+            permissions: self.permissions,
+            add_liquidity_aum_mode: self.add_liquidity_aum_mode,
+            remove_liquidity_aum_mode: self.remove_liquidity_aum_mode,
+            max_aum_usd: self.max_aum_usd,
+            max_user_lp_amount: self.max_user_lp_amount,
+            lp_cooldown_sec: self.lp_cooldown_sec,
+            junior_premium: self.junior_premium,
+            referrer_share: self.referrer_share,
+            trader_discount: self.trader_discount,
+            max_aum_age_sec: self.max_aum_age_sec,
+            keeper_reward: self.keeper_reward,
+        }
+    }
+
+    pub fn set_config(&mut self, config: &PoolConfig) -> Result<()> {
+        self.display_name = Self::pad_zeros(&config.display_name)?;
+        self.permissions = config.permissions;
+        self.add_liquidity_aum_mode = config.add_liquidity_aum_mode;
+        self.remove_liquidity_aum_mode = config.remove_liquidity_aum_mode;
+        self.max_aum_usd = config.max_aum_usd;
+        self.max_user_lp_amount = config.max_user_lp_amount;
+        self.lp_cooldown_sec = config.lp_cooldown_sec;
+        self.junior_premium = config.junior_premium;
+        self.referrer_share = config.referrer_share;
+        self.trader_discount = config.trader_discount;
+        self.max_aum_age_sec = config.max_aum_age_sec;
+        self.keeper_reward = config.keeper_reward;
+        Ok(())
+    }
+
+    /// Returns config that new pools are created with
+    pub fn get_default_config(name: &str) -> PoolConfig {
+        PoolConfig {
+            display_name: name.to_string(),
+            permissions: Permissions {
+                allow_swap: true,
+                allow_add_liquidity: true,
+                allow_remove_liquidity: true,
+                allow_open_position: true,
+                allow_close_position: true,
+                allow_pnl_withdrawal: true,
+                allow_collateral_withdrawal: true,
+                allow_size_change: true,
+            },
+            add_liquidity_aum_mode: AumCalcMode::Max,
+            remove_liquidity_aum_mode: AumCalcMode::Min,
+            max_aum_usd: 0,
+            max_user_lp_amount: 0,
+            lp_cooldown_sec: 0,
+            junior_premium: 0,
+            referrer_share: 0,
+            trader_discount: 0,
+            max_aum_age_sec: 60,
+            keeper_reward: 0,
+        }
+    }
+
+    /// Splits pool AUM into senior and junior tranche values.
+    /// Gains since the last tranche update are shared pro-rata, with junior_premium
+    /// of the senior share paid to juniors. Losses are taken by juniors first.
+    pub fn get_tranche_aum_usd(&self, aum_usd: u128) -> Result<(u128, u128)> {
+        if !self.tranches_enabled {
+            return Ok((aum_usd, 0));
+        }
+
+        let mut senior_aum_usd = self.senior_aum_usd;
+        if aum_usd > self.tranche_aum_usd && self.tranche_aum_usd > 0 {
+            let senior_gain_usd = math::checked_div(
+                math::checked_mul(
+                    math::checked_sub(aum_usd, self.tranche_aum_usd)?,
+                    std::cmp::min(self.senior_aum_usd, self.tranche_aum_usd),
+                )?,
+                self.tranche_aum_usd,
+            )?;
+            let premium_usd = math::checked_div(
+                math::checked_mul(senior_gain_usd, self.junior_premium as u128)?,
+                Perpetuals::BPS_POWER,
+            )?;
+            senior_aum_usd = math::checked_add(
+                senior_aum_usd,
+                math::checked_sub(senior_gain_usd, premium_usd)?,
+            )?;
+        }
+        senior_aum_usd = std::cmp::min(senior_aum_usd, aum_usd);
+
+        Ok((senior_aum_usd, math::checked_sub(aum_usd, senior_aum_usd)?))
+    }
+
+    /// Splits the pool value in any AUM mode into senior and junior shares.
+    /// Tranches are tracked at the EMA value, update_tranches records it first.
+    pub fn get_tranche_shares_usd(&self, pool_aum_usd: u128) -> Result<(u128, u128)> {
+        if !self.tranches_enabled || self.tranche_aum_usd == 0 {
+            return self.get_tranche_aum_usd(pool_aum_usd);
+        }
+
+        let senior_amount_usd = std::cmp::min(
+            math::checked_div(
+                math::checked_mul(self.senior_aum_usd, pool_aum_usd)?,
+                self.tranche_aum_usd,
+            )?,
+            pool_aum_usd,
+        );
+
+        Ok((
+            senior_amount_usd,
+            math::checked_sub(pool_aum_usd, senior_amount_usd)?,
+        ))
+    }
+
+    /// Converts a value priced from the pool value in any AUM mode
+    /// to the EMA value tranches are tracked at
+    pub fn get_tranche_value_usd(&self, amount_usd: u128, pool_aum_usd: u128) -> Result<u128> {
+        if pool_aum_usd == 0 || self.tranche_aum_usd == 0 {
+            return Ok(amount_usd);
+        }
+        math::checked_div(
+            math::checked_mul(amount_usd, self.tranche_aum_usd)?,
+            pool_aum_usd,
+        )
+    }
+
+    /// Records tranche values at the current AUM
+    pub fn update_tranches(&mut self, aum_usd: u128) -> Result<()> {
+        if self.tranches_enabled {
+            self.senior_aum_usd = self.get_tranche_aum_usd(aum_usd)?.0;
+            self.tranche_aum_usd = aum_usd;
+        }
+        Ok(())
+    }
+
+    /// Returns trader discount and referrer share of a referred trade fee
+    pub fn get_referral_fees(&self, fee_amount: u64) -> Result<(u64, u64)> {
+        let discount = math::checked_as_u64(math::checked_div(
+            math::checked_mul(fee_amount as u128, self.trader_discount as u128)?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+        let referrer_fee = math::checked_as_u64(math::checked_div(
+            math::checked_mul(fee_amount as u128, self.referrer_share as u128)?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+        Ok((discount, referrer_fee))
     }
 
     pub fn get_token_id(&self, custody: &Pubkey) -> Result<usize> {
-        self.custodies
+        self.get_custodies()
             .iter()
             .position(|&k| k == *custody)
             .ok_or_else(|| PerpetualsError::UnsupportedToken.into())
@@ -124,6 +522,7 @@ impl Pool {
         size: u64,
         locked_amount: u64,
         collateral_custody: &Custody,
+        fee_mult: u64,
     ) -> Result<u64> {
         // The "optimal" algorithm is always used to compute the fee for entering a position.
         // entry_fee = custody.fees.open_position * utilization_fee * size
@@ -170,7 +569,7 @@ impl Pool {
             )?)?;
         }
 
-        Ok(size_fee)
+        Self::get_fee_amount(fee_mult, size_fee)
     }
 
     pub fn get_exit_price(
@@ -180,6 +579,51 @@ impl Pool {
         side: Side,
         custody: &Custody,
     ) -> Result<u64> {
+        self.get_exit_price_with_spread(token_price, token_ema_price, side, custody, 0)
+    }
+
+    /// Returns the price a position is closed at by its owner, positions closed
+    /// outside trading hours pay an extra spread. Liquidations, settlements and
+    /// PnL of open positions use the regular exit price.
+    pub fn get_close_price(
+        &self,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        side: Side,
+        custody: &Custody,
+        curtime: i64,
+    ) -> Result<u64> {
+        self.get_exit_price_with_spread(
+            token_price,
+            token_ema_price,
+            side,
+            custody,
+            { custody.trading_schedule }.get_closed_spread(curtime),
+        )
+    }
+
+    fn get_exit_price_with_spread(
+        &self,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        side: Side,
+        custody: &Custody,
+        closed_spread: u64,
+    ) -> Result<u64> {
+        // settled positions are closed at the settlement price without spread
+        let spread = if custody.status == MarketStatus::Settled {
+            0
+        } else {
+            math::checked_add(
+                if side == Side::Long {
+                    custody.pricing.trade_spread_short
+                } else {
+                    custody.pricing.trade_spread_long
+                },
+                closed_spread,
+            )?
+        };
+
         let price = self.get_price(
             token_price,
             token_ema_price,
@@ -188,11 +632,7 @@ impl Pool {
             } else {
                 Side::Long
             },
-            if side == Side::Long {
-                custody.pricing.trade_spread_short
-            } else {
-                custody.pricing.trade_spread_long
-            },
+            spread,
         )?;
 
         Ok(price
@@ -200,8 +640,14 @@ impl Pool {
             .price)
     }
 
-    pub fn get_exit_fee(&self, size: u64, custody: &Custody) -> Result<u64> {
-        Self::get_fee_amount(custody.fees.close_position, size)
+    pub fn get_exit_fee(&self, size: u64, custody: &Custody, fee_mult: u64) -> Result<u64> {
+        if custody.status == MarketStatus::Settled {
+            return Ok(0);
+        }
+        Self::get_fee_amount(
+            fee_mult,
+            Self::get_fee_amount(custody.fees.close_position, size)?,
+        )
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -216,8 +662,15 @@ impl Pool {
         collateral_custody: &Custody,
         curtime: i64,
         liquidation: bool,
+        fee_mult: u64,
     ) -> Result<(u64, u64, u64, u64)> {
-        let (profit_usd, loss_usd, fee_amount) = self.get_pnl_usd(
+        // owners closing outside trading hours pay the closed spread, liquidations don't
+        let closed_spread = if liquidation {
+            0
+        } else {
+            { custody.trading_schedule }.get_closed_spread(curtime)
+        };
+        let (profit_usd, loss_usd, fee_amount) = self.get_pnl_usd_with_spread(
             position,
             token_price,
             token_ema_price,
@@ -227,6 +680,8 @@ impl Pool {
             collateral_custody,
             curtime,
             liquidation,
+            fee_mult,
+            closed_spread,
         )?;
 
         let available_amount_usd = if profit_usd > 0 {
@@ -326,6 +781,7 @@ impl Pool {
         token_price_in: &OraclePrice,
         custody_out: &Custody,
         token_price_out: &OraclePrice,
+        fee_mult: u64,
     ) -> Result<(u64, u64)> {
         let stable_swap = custody_in.is_stable && custody_out.is_stable;
 
@@ -355,7 +811,10 @@ impl Pool {
             token_price_out,
         )?;
 
-        Ok((swap_in_fee, swap_out_fee))
+        Ok((
+            Self::get_fee_amount(fee_mult, swap_in_fee)?,
+            Self::get_fee_amount(fee_mult, swap_out_fee)?,
+        ))
     }
 
     pub fn get_add_liquidity_fee(
@@ -382,6 +841,10 @@ impl Pool {
         custody: &Custody,
         token_price: &OraclePrice,
     ) -> Result<u64> {
+        // settled custodies are drained without fees
+        if custody.status == MarketStatus::Settled {
+            return Ok(0);
+        }
         self.get_fee(
             token_id,
             custody.fees.remove_liquidity,
@@ -445,6 +908,7 @@ impl Pool {
             collateral_custody,
             curtime,
             false,
+            Perpetuals::BPS_POWER as u64,
         )?;
 
         let current_margin_usd = if profit_usd > 0 {
@@ -510,7 +974,7 @@ impl Pool {
         }
 
         let size = token_ema_price.get_token_amount(position.size_usd, custody.decimals)?;
-        let exit_fee_tokens = self.get_exit_fee(size, custody)?;
+        let exit_fee_tokens = self.get_exit_fee(size, custody, Perpetuals::BPS_POWER as u64)?;
         let exit_fee_usd =
             token_ema_price.get_asset_amount_usd(exit_fee_tokens, custody.decimals)?;
         let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
@@ -583,20 +1047,56 @@ impl Pool {
         collateral_custody: &Custody,
         curtime: i64,
         liquidation: bool,
+        fee_mult: u64,
+    ) -> Result<(u64, u64, u64)> {
+        self.get_pnl_usd_with_spread(
+            position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            liquidation,
+            fee_mult,
+            0,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn get_pnl_usd_with_spread(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+        liquidation: bool,
+        fee_mult: u64,
+        closed_spread: u64,
     ) -> Result<(u64, u64, u64)> {
         if position.size_usd == 0 || position.price == 0 {
             return Ok((0, 0, 0));
         }
 
-        let exit_price =
-            self.get_exit_price(token_price, token_ema_price, position.side, custody)?;
+        let exit_price = self.get_exit_price_with_spread(
+            token_price,
+            token_ema_price,
+            position.side,
+            custody,
+            closed_spread,
+        )?;
 
         let size = token_ema_price.get_token_amount(position.size_usd, custody.decimals)?;
 
         let exit_fee = if liquidation {
             self.get_liquidation_fee(size, custody)?
         } else {
-            self.get_exit_fee(size, custody)?
+            self.get_exit_fee(size, custody, fee_mult)?
         };
 
         let exit_fee_usd = token_ema_price.get_asset_amount_usd(exit_fee, custody.decimals)?;
@@ -706,6 +1206,7 @@ impl Pool {
         }
     }
 
+    /// Computes pool AUM from all pool custodies and their oracles
     pub fn get_assets_under_management_usd(
         &self,
         aum_calc_mode: AumCalcMode,
@@ -713,46 +1214,107 @@ impl Pool {
         curtime: i64,
     ) -> Result<u128> {
         let mut pool_amount_usd: u128 = 0;
-        for (idx, &custody) in self.custodies.iter().enumerate() {
-            let oracle_idx = idx + self.custodies.len();
+        let custodies = self.get_custodies();
+        for (idx, &custody) in custodies.iter().enumerate() {
+            let oracle_idx = idx + custodies.len();
             if oracle_idx >= accounts.len() {
                 return Err(ProgramError::NotEnoughAccountKeys.into());
             }
 
             require_keys_eq!(accounts[idx].key(), custody);
-            let custody = Account::<Custody>::try_from(&accounts[idx])?;
+            let custody = AccountLoader::<Custody>::try_from(&accounts[idx])?;
+            let custody = custody.load()?;
 
             require_keys_eq!(accounts[oracle_idx].key(), custody.oracle.oracle_account);
 
-            let token_price = OraclePrice::new_from_oracle(
-                &accounts[oracle_idx],
-                &custody.oracle,
-                curtime,
-                false,
-            )?;
+            let token_price = custody.get_oracle_price(&accounts[oracle_idx], curtime, false)?;
 
-            let token_ema_price = OraclePrice::new_from_oracle(
+            let token_ema_price = custody.get_oracle_price(
                 &accounts[oracle_idx],
-                &custody.oracle,
                 curtime,
                 custody.pricing.use_ema,
             )?;
 
+            let custody_aum_usd =
+                self.get_custody_aum_usd(&custody, &token_price, &token_ema_price, curtime)?;
+
+            pool_amount_usd =
+                math::checked_add(pool_amount_usd, custody_aum_usd[aum_calc_mode as usize])?;
+        }
+
+        Ok(pool_amount_usd)
+    }
+
+    /// Returns USD value of the custody in pool AUM for each AumCalcMode.
+    /// Unrealized trader profits are deducted from the value of their own custody,
+    /// which is not less than zero.
+    pub fn get_custody_aum_usd(
+        &self,
+        custody: &Custody,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        curtime: i64,
+    ) -> Result<[u128; 4]> {
+        let mut added_usd: u128 = 0;
+        let mut deducted_usd: u128 = 0;
+
+        if custody.pricing.use_unrealized_pnl_in_aum {
+            if custody.is_stable {
+                // compute accumulated interest
+                let collective_position = custody.get_collective_position(Side::Long)?;
+                let interest_usd =
+                    custody.get_interest_amount_usd(&collective_position, curtime)?;
+                added_usd = math::checked_add(added_usd, interest_usd as u128)?;
+
+                let collective_position = custody.get_collective_position(Side::Short)?;
+                let interest_usd =
+                    custody.get_interest_amount_usd(&collective_position, curtime)?;
+                added_usd = math::checked_add(added_usd, interest_usd as u128)?;
+            } else {
+                // compute aggregate unrealized pnl
+                for side in [Side::Long, Side::Short] {
+                    let (profit, loss, _) = self.get_pnl_usd(
+                        &custody.get_collective_position(side)?,
+                        token_price,
+                        token_ema_price,
+                        custody,
+                        token_price,
+                        token_ema_price,
+                        custody,
+                        curtime,
+                        false,
+                        Perpetuals::BPS_POWER as u64,
+                    )?;
+
+                    // adjust custody amount by collective profit/loss
+                    added_usd = math::checked_add(added_usd, loss as u128)?;
+                    deducted_usd = math::checked_add(deducted_usd, profit as u128)?;
+                }
+            }
+        }
+
+        let mut res = [0u128; 4];
+        for aum_calc_mode in [
+            AumCalcMode::Min,
+            AumCalcMode::Max,
+            AumCalcMode::Last,
+            AumCalcMode::EMA,
+        ] {
             let aum_token_price = match aum_calc_mode {
-                AumCalcMode::Last => token_price,
-                AumCalcMode::EMA => token_ema_price,
+                AumCalcMode::Last => *token_price,
+                AumCalcMode::EMA => *token_ema_price,
                 AumCalcMode::Min => {
                     if token_price < token_ema_price {
-                        token_price
+                        *token_price
                     } else {
-                        token_ema_price
+                        *token_ema_price
                     }
                 }
                 AumCalcMode::Max => {
                     if token_price > token_ema_price {
-                        token_price
+                        *token_price
                     } else {
-                        token_ema_price
+                        *token_ema_price
                     }
                 }
             };
@@ -760,55 +1322,180 @@ impl Pool {
             let token_amount_usd =
                 aum_token_price.get_asset_amount_usd(custody.assets.owned, custody.decimals)?;
 
-            pool_amount_usd = math::checked_add(pool_amount_usd, token_amount_usd as u128)?;
+            res[aum_calc_mode as usize] = math::checked_add(token_amount_usd as u128, added_usd)?
+                .saturating_sub(math::checked_add(
+                    math::checked_add(deducted_usd, custody.staking_rewards_usd)?,
+                    custody.referral_fees_usd,
+                )?);
+        }
 
-            if custody.pricing.use_unrealized_pnl_in_aum {
-                if custody.is_stable {
-                    // compute accumulated interest
-                    let collective_position = custody.get_collective_position(Side::Long)?;
-                    let interest_usd =
-                        custody.get_interest_amount_usd(&collective_position, curtime)?;
-                    pool_amount_usd = math::checked_add(pool_amount_usd, interest_usd as u128)?;
+        Ok(res)
+    }
 
-                    let collective_position = custody.get_collective_position(Side::Short)?;
-                    let interest_usd =
-                        custody.get_interest_amount_usd(&collective_position, curtime)?;
-                    pool_amount_usd = math::checked_add(pool_amount_usd, interest_usd as u128)?;
-                } else {
-                    // compute aggregate unrealized pnl
-                    let (long_profit, long_loss, _) = self.get_pnl_usd(
-                        &custody.get_collective_position(Side::Long)?,
-                        &token_price,
-                        &token_ema_price,
-                        &custody,
-                        &token_price,
-                        &token_ema_price,
-                        &custody,
-                        curtime,
-                        false,
-                    )?;
-                    let (short_profit, short_loss, _) = self.get_pnl_usd(
-                        &custody.get_collective_position(Side::Short)?,
-                        &token_price,
-                        &token_ema_price,
-                        &custody,
-                        &token_price,
-                        &token_ema_price,
-                        &custody,
-                        curtime,
-                        false,
-                    )?;
+    /// Recomputes the AUM value cached in the custody and updates pool totals
+    pub fn update_custody_aum(
+        &mut self,
+        custody_key: &Pubkey,
+        custody: &mut Custody,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        curtime: i64,
+    ) -> Result<()> {
+        let token_id = self.get_token_id(custody_key)?;
+        let custody_aum_usd =
+            self.get_custody_aum_usd(custody, token_price, token_ema_price, curtime)?;
+
+        let prev_aum_usd = custody.aum_usd;
+        let mut cached_aum_usd = self.cached_aum_usd;
+        for (idx, aum_usd) in cached_aum_usd.iter_mut().enumerate() {
+            *aum_usd = math::checked_add(
+                aum_usd.saturating_sub(prev_aum_usd[idx]),
+                custody_aum_usd[idx],
+            )?;
+        }
 
-                    // adjust pool amount by collective profit/loss
-                    pool_amount_usd = math::checked_add(pool_amount_usd, long_loss as u128)?;
-                    pool_amount_usd = math::checked_add(pool_amount_usd, short_loss as u128)?;
-                    pool_amount_usd = pool_amount_usd.saturating_sub(long_profit as u128);
-                    pool_amount_usd = pool_amount_usd.saturating_sub(short_profit as u128);
-                }
-            }
+        self.cached_aum_usd = cached_aum_usd;
+        self.set_aum_update_time(token_id, curtime);
+        self.aum_usd = cached_aum_usd[AumCalcMode::EMA as usize];
+        custody.aum_usd = custody_aum_usd;
+
+        Ok(())
+    }
+
+    /// Sets staking rewards owed by the custody and adjusts cached AUM values
+    /// without a price refresh, as rewards are valued in USD
+    pub fn set_staking_rewards(&mut self, custody: &mut Custody, rewards_usd: u128) -> Result<()> {
+        let prev_rewards_usd = custody.staking_rewards_usd;
+        let mut custody_aum_usd = custody.aum_usd;
+        let mut cached_aum_usd = self.cached_aum_usd;
+        for (idx, aum_usd) in cached_aum_usd.iter_mut().enumerate() {
+            let new_custody_aum_usd = math::checked_add(custody_aum_usd[idx], prev_rewards_usd)?
+                .saturating_sub(rewards_usd);
+            *aum_usd = math::checked_add(
+                aum_usd.saturating_sub(custody_aum_usd[idx]),
+                new_custody_aum_usd,
+            )?;
+            custody_aum_usd[idx] = new_custody_aum_usd;
         }
 
-        Ok(pool_amount_usd)
+        self.cached_aum_usd = cached_aum_usd;
+        self.aum_usd = cached_aum_usd[AumCalcMode::EMA as usize];
+        custody.aum_usd = custody_aum_usd;
+        custody.staking_rewards_usd = rewards_usd;
+
+        Ok(())
+    }
+
+    /// Deducts the AUM value cached in the custody from pool totals
+    pub fn remove_custody_aum(&mut self, custody: &Custody) {
+        let prev_aum_usd = custody.aum_usd;
+        let mut cached_aum_usd = self.cached_aum_usd;
+        for (idx, aum_usd) in cached_aum_usd.iter_mut().enumerate() {
+            *aum_usd = aum_usd.saturating_sub(prev_aum_usd[idx]);
+        }
+        self.cached_aum_usd = cached_aum_usd;
+        self.aum_usd = cached_aum_usd[AumCalcMode::EMA as usize];
+    }
+
+    /// Refreshes borrow rates and AUM values of a page of pool custodies. Accounts
+    /// hold custodies (writable) followed by their oracles, in the same order.
+    pub fn update_aum_from_accounts(
+        &mut self,
+        accounts: &[AccountInfo],
+        curtime: i64,
+    ) -> Result<()> {
+        let num_custodies = accounts.len() / 2;
+        if accounts.len() != num_custodies * 2 {
+            return Err(ProgramError::NotEnoughAccountKeys.into());
+        }
+        for idx in 0..num_custodies {
+            let oracle_idx = idx + num_custodies;
+            let custody_account = AccountLoader::<Custody>::try_from(&accounts[idx])?;
+            let mut custody = custody_account.load_mut()?;
+
+            require_keys_eq!(accounts[oracle_idx].key(), custody.oracle.oracle_account);
+
+            // idle custodies would otherwise report a stale borrow rate
+            custody.update_borrow_rate(curtime)?;
+
+            let token_price = custody.get_oracle_price(&accounts[oracle_idx], curtime, false)?;
+
+            let token_ema_price = custody.get_oracle_price(
+                &accounts[oracle_idx],
+                curtime,
+                custody.pricing.use_ema,
+            )?;
+
+            self.update_custody_aum(
+                &accounts[idx].key(),
+                &mut custody,
+                &token_price,
+                &token_ema_price,
+                curtime,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns pool AUM from the values cached in pool custodies, fails
+    /// if any of them is older than max_aum_age_sec
+    pub fn get_cached_aum_usd(&self, aum_calc_mode: AumCalcMode, curtime: i64) -> Result<u128> {
+        if self.max_aum_age_sec > 0
+            && curtime.saturating_sub(self.last_aum_update) > self.max_aum_age_sec as i64
+        {
+            msg!("Error: Pool AUM is stale, updated at {}", {
+                self.last_aum_update
+            });
+            return err!(PerpetualsError::StaleAum);
+        }
+
+        Ok(self.cached_aum_usd[aum_calc_mode as usize])
+    }
+
+    /// Records the time AUM value of the custody was refreshed
+    pub fn set_aum_update_time(&mut self, token_id: usize, curtime: i64) {
+        self.aum_update_times[token_id] = curtime;
+        self.update_last_aum_update();
+    }
+
+    fn update_last_aum_update(&mut self) {
+        let aum_update_times = self.aum_update_times;
+        let num_custodies = std::cmp::min(self.num_custodies as usize, Pool::MAX_CUSTODIES);
+        if let Some(&update_time) = aum_update_times[..num_custodies].iter().min() {
+            self.last_aum_update = update_time;
+        }
+    }
+
+    /// Refreshes AUM values of the custodies in accounts and records tranche
+    /// values at the updated pool AUM, which is returned
+    pub fn refresh_aum(&mut self, accounts: &[AccountInfo], curtime: i64) -> Result<u128> {
+        self.update_aum_from_accounts(accounts, curtime)?;
+
+        let aum_usd = self.aum_usd;
+        self.update_tranches(aum_usd)?;
+
+        Ok(aum_usd)
+    }
+
+    /// Returns true if at least half of max_aum_age_sec has passed since the pool
+    /// AUM update time, cranks are rejected before that
+    pub fn is_crank_due(&self, curtime: i64) -> bool {
+        curtime.saturating_sub(self.last_aum_update) >= (self.max_aum_age_sec / 2) as i64
+    }
+
+    /// Returns lamports owed to a keeper whose crank moved the pool AUM update
+    /// time from prev_aum_update. Keepers are paid only once at least half of
+    /// max_aum_age_sec has passed, so the reward can't be collected every slot.
+    pub fn get_keeper_reward(&self, prev_aum_update: i64, curtime: i64) -> u64 {
+        if self.keeper_reward == 0
+            || self.max_aum_age_sec == 0
+            || self.last_aum_update <= prev_aum_update
+            || curtime.saturating_sub(prev_aum_update) < (self.max_aum_age_sec / 2) as i64
+        {
+            return 0;
+        }
+        self.keeper_reward
     }
 
     pub fn get_fee_amount(fee: u64, amount: u64) -> Result<u64> {
@@ -999,7 +1686,7 @@ impl Pool {
         //   otherwise:
         //     ratio_fee = 1 + custody.fees.ratio_mult * (new_ratio - ratios.target) / (ratios.max - ratios.target);
 
-        let ratios = &self.ratios[token_id];
+        let ratios = self.ratios[token_id];
         let current_ratio = self.get_current_ratio(custody, token_price)?;
         let new_ratio = self.get_new_ratio(amount_add, amount_remove, custody, token_price)?;
 
@@ -1123,12 +1810,57 @@ impl Pool {
     }
 }
 
+impl TryFrom<DeprecatedPool> for Pool {
+    type Error = Error;
+
+    fn try_from(pool: DeprecatedPool) -> Result<Self> {
+        let mut res = Pool {
+            aum_usd: pool.aum_usd,
+            bump: pool.bump,
+            lp_token_bump: pool.lp_token_bump,
+            inception_time: pool.inception_time,
+            status: pool.status,
+            tranches_enabled: pool.tranches_enabled,
+            junior_lp_token_bump: pool.junior_lp_token_bump,
+            senior_aum_usd: pool.senior_aum_usd,
+            tranche_aum_usd: pool.tranche_aum_usd,
+            version: Pool::VERSION,
+            ..Default::default()
+        };
+        res.set_name(&pool.name)?;
+        res.set_custodies(&pool.custodies, &pool.ratios)?;
+
+        // pools created before the pool config was added get the values
+        // that were used before it was introduced
+        if pool.version == 0 && pool.display_name.is_empty() {
+            res.set_config(&Pool::get_default_config(&pool.name))?;
+        } else {
+            res.set_config(&PoolConfig {
+                display_name: pool.display_name,
+                permissions: pool.permissions,
+                add_liquidity_aum_mode: pool.add_liquidity_aum_mode,
+                remove_liquidity_aum_mode: pool.remove_liquidity_aum_mode,
+                max_aum_usd: pool.max_aum_usd,
+                max_user_lp_amount: pool.max_user_lp_amount,
+                lp_cooldown_sec: pool.lp_cooldown_sec,
+                junior_premium: pool.junior_premium,
+                referrer_share: pool.referrer_share,
+                trader_discount: pool.trader_discount,
+                max_aum_age_sec: Pool::get_default_config(&pool.name).max_aum_age_sec,
+                keeper_reward: 0,
+            })?;
+        }
+
+        Ok(res)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use {
         super::*,
         crate::state::{
-            custody::{BorrowRateParams, Fees, PricingParams},
+            custody::{BorrowRateParams, Fees, PricingParams, TradingSchedule},
             oracle::{OracleParams, OracleType},
             perpetuals::Permissions,
         },
@@ -1227,8 +1959,8 @@ mod test {
 
         (
             Pool {
-                name: "Test Pool".to_string(),
-                ratios: vec![ratios, ratios],
+                name: Pool::pad_zeros("Test Pool").unwrap(),
+                ratios: [ratios; Pool::MAX_CUSTODIES],
                 ..Default::default()
             },
             custody,
@@ -1354,7 +2086,8 @@ mod test {
                 custody.fees.open_position,
                 0,
                 custody.get_locked_amount(0, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1365,7 +2098,8 @@ mod test {
                 custody.fees.open_position,
                 100_000,
                 custody.get_locked_amount(100_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1376,7 +2110,8 @@ mod test {
                 custody.fees.open_position,
                 150_000,
                 custody.get_locked_amount(150_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1387,7 +2122,8 @@ mod test {
                 custody.fees.open_position,
                 200_000,
                 custody.get_locked_amount(200_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1398,7 +2134,8 @@ mod test {
                 custody.fees.open_position,
                 300_000,
                 custody.get_locked_amount(300_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1413,7 +2150,8 @@ mod test {
                 custody.fees.open_position,
                 100_000,
                 custody.get_locked_amount(100_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1424,7 +2162,8 @@ mod test {
                 custody.fees.open_position,
                 150_000,
                 custody.get_locked_amount(150_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1435,7 +2174,8 @@ mod test {
                 custody.fees.open_position,
                 200_000,
                 custody.get_locked_amount(200_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1446,7 +2186,8 @@ mod test {
                 custody.fees.open_position,
                 300_000,
                 custody.get_locked_amount(300_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1459,7 +2200,8 @@ mod test {
                 custody.fees.open_position,
                 100_000,
                 custody.get_locked_amount(100_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1470,7 +2212,8 @@ mod test {
                 custody.fees.open_position,
                 150_000,
                 custody.get_locked_amount(150_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1481,7 +2224,8 @@ mod test {
                 custody.fees.open_position,
                 200_000,
                 custody.get_locked_amount(200_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1492,7 +2236,8 @@ mod test {
                 custody.fees.open_position,
                 300_000,
                 custody.get_locked_amount(300_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1506,7 +2251,8 @@ mod test {
                 custody.fees.open_position,
                 100_000,
                 custody.get_locked_amount(100_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1517,7 +2263,8 @@ mod test {
                 custody.fees.open_position,
                 150_000,
                 custody.get_locked_amount(150_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1528,7 +2275,8 @@ mod test {
                 custody.fees.open_position,
                 200_000,
                 custody.get_locked_amount(200_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1539,7 +2287,8 @@ mod test {
                 custody.fees.open_position,
                 300_000,
                 custody.get_locked_amount(300_000, Side::Long).unwrap(),
-                &custody
+                &custody,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1754,7 +2503,8 @@ mod test {
                 &token_ema_price,
                 &custody,
                 1,
-                false
+                false,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1772,7 +2522,8 @@ mod test {
                 &token_ema_price,
                 &custody,
                 1,
-                false
+                false,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1790,7 +2541,8 @@ mod test {
                 &token_ema_price,
                 &custody,
                 1,
-                false
+                false,
+                Perpetuals::BPS_POWER as u64
             )
             .unwrap()
         );
@@ -1986,10 +2738,320 @@ mod test {
                 &token_ema_price,
                 &custody,
                 1,
-                false
+                false,
+                Perpetuals::BPS_POWER as u64
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pool_config() {
+        let (mut pool, _, _, _, _) = get_fixture();
+
+        let name = String::from_utf8(pool.get_name().to_vec()).unwrap();
+        let mut config = Pool::get_default_config(&name);
+        assert!(config.validate());
+        pool.set_config(&config).unwrap();
+        assert_eq!(pool.get_config(), config);
+        assert_eq!(pool.get_display_name(), name);
+
+        config.display_name = String::new();
+        assert!(!config.validate());
+        config.display_name = "x".repeat(65);
+        assert!(!config.validate());
+
+        config.display_name = name.clone();
+        config.referrer_share = 6_000;
+        config.trader_discount = 4_000;
+        assert!(config.validate());
+        config.trader_discount = 4_001;
+        assert!(!config.validate());
+    }
+
+    #[test]
+    fn test_get_referral_fees() {
+        let (mut pool, _, _, _, _) = get_fixture();
+        assert_eq!(pool.get_referral_fees(1_000).unwrap(), (0, 0));
+
+        pool.referrer_share = 2_000;
+        pool.trader_discount = 1_000;
+        assert_eq!(pool.get_referral_fees(1_000).unwrap(), (100, 200));
+        assert_eq!(pool.get_referral_fees(9).unwrap(), (0, 1));
+    }
+
+    #[test]
+    fn test_tranches() {
+        let (mut pool, _, _, _, _) = get_fixture();
+
+        // without tranches all AUM is senior
+        assert_eq!(pool.get_tranche_aum_usd(1_000).unwrap(), (1_000, 0));
+
+        pool.tranches_enabled = true;
+        pool.junior_premium = 2_000;
+        pool.senior_aum_usd = 600;
+        pool.tranche_aum_usd = 1_000;
+        assert_eq!(pool.get_tranche_aum_usd(1_000).unwrap(), (600, 400));
+
+        // senior gets 60% of gains minus 20% premium
+        assert_eq!(pool.get_tranche_aum_usd(1_100).unwrap(), (648, 452));
+
+        // juniors take losses first
+        assert_eq!(pool.get_tranche_aum_usd(700).unwrap(), (600, 100));
+        assert_eq!(pool.get_tranche_aum_usd(500).unwrap(), (500, 0));
+
+        // losses below junior capital persist after recovery
+        pool.update_tranches(500).unwrap();
+        assert_eq!((pool.senior_aum_usd, pool.tranche_aum_usd), (500, 500));
+        assert_eq!(pool.get_tranche_aum_usd(600).unwrap(), (580, 20));
+
+        // values in other AUM modes are split pro rata
+        pool.update_tranches(600).unwrap();
+        assert_eq!(pool.get_tranche_shares_usd(660).unwrap(), (638, 22));
+        assert_eq!(pool.get_tranche_value_usd(66, 660).unwrap(), 60);
+    }
+
+    #[test]
+    fn test_cached_aum() {
+        let (mut pool, mut custody, _, token_price, token_ema_price) = get_fixture();
+        let custody_key = Pubkey::new_unique();
+        pool.set_custodies(&[custody_key], &[TokenRatios::default()])
+            .unwrap();
+        pool.max_aum_age_sec = 60;
+        custody.assets.owned = scale(10, 9);
+
+        // custody value was never refreshed
+        assert!(pool.get_cached_aum_usd(AumCalcMode::EMA, 1_000).is_err());
+
+        pool.update_custody_aum(
+            &custody_key,
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(
+            pool.get_cached_aum_usd(AumCalcMode::Min, 1_000).unwrap(),
+            scale(250_000, Perpetuals::USD_DECIMALS) as u128
+        );
+        assert_eq!(
+            pool.get_cached_aum_usd(AumCalcMode::Max, 1_000).unwrap(),
+            scale(253_000, Perpetuals::USD_DECIMALS) as u128
+        );
+        assert_eq!(
+            { pool.aum_usd },
+            scale(253_000, Perpetuals::USD_DECIMALS) as u128
+        );
+
+        // refreshed value replaces the previous one
+        custody.assets.owned = scale(5, 9);
+        pool.update_custody_aum(
+            &custody_key,
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            1_030,
+        )
+        .unwrap();
+        assert_eq!(
+            pool.get_cached_aum_usd(AumCalcMode::Last, 1_090).unwrap(),
+            scale(125_000, Perpetuals::USD_DECIMALS) as u128
+        );
+        assert!(pool.get_cached_aum_usd(AumCalcMode::Last, 1_091).is_err());
+
+        // reserved staking rewards are deducted without a price refresh
+        let rewards_usd = scale(25_000, Perpetuals::USD_DECIMALS) as u128;
+        pool.set_staking_rewards(&mut custody, rewards_usd).unwrap();
+        assert_eq!(
+            pool.get_cached_aum_usd(AumCalcMode::Last, 1_030).unwrap(),
+            scale(100_000, Perpetuals::USD_DECIMALS) as u128
+        );
+        pool.update_custody_aum(
+            &custody_key,
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            1_030,
+        )
+        .unwrap();
+        assert_eq!(
+            pool.get_cached_aum_usd(AumCalcMode::Last, 1_030).unwrap(),
+            scale(100_000, Perpetuals::USD_DECIMALS) as u128
+        );
+        pool.set_staking_rewards(&mut custody, 0).unwrap();
+        assert_eq!(
+            pool.get_cached_aum_usd(AumCalcMode::Last, 1_030).unwrap(),
+            scale(125_000, Perpetuals::USD_DECIMALS) as u128
+        );
+
+        // reserved referral fees are deducted on refresh
+        custody.referral_fees_usd = scale(5_000, Perpetuals::USD_DECIMALS) as u128;
+        pool.update_custody_aum(
+            &custody_key,
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            1_030,
+        )
+        .unwrap();
+        assert_eq!(
+            pool.get_cached_aum_usd(AumCalcMode::Last, 1_030).unwrap(),
+            scale(120_000, Perpetuals::USD_DECIMALS) as u128
+        );
+
+        // update time follows the custody when pool custodies change
+        let new_custody_key = Pubkey::new_unique();
+        pool.set_custodies(
+            &[new_custody_key, custody_key],
+            &[TokenRatios::default(); 2],
+        )
+        .unwrap();
+        assert_eq!({ pool.aum_update_times[0] }, 0);
+        assert_eq!({ pool.aum_update_times[1] }, 1_030);
+        assert_eq!({ pool.last_aum_update }, 0);
+
+        pool.remove_custody_aum(&custody);
+        assert_eq!({ pool.aum_usd }, 0);
+        assert_eq!({ pool.cached_aum_usd }, [0; 4]);
+    }
+
+    #[test]
+    fn test_keeper_reward() {
+        let (mut pool, mut custody, _, token_price, token_ema_price) = get_fixture();
+        let custody_keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        pool.set_custodies(&custody_keys, &[TokenRatios::default(); 2])
+            .unwrap();
+        pool.max_aum_age_sec = 60;
+        pool.keeper_reward = 5_000;
+
+        pool.set_aum_update_time(0, 1_000);
+        pool.set_aum_update_time(1, 1_010);
+        assert_eq!({ pool.last_aum_update }, 1_000);
+        assert!(!pool.is_crank_due(1_029));
+        assert!(pool.is_crank_due(1_030));
+
+        // pool AUM update time doesn't move until the oldest custody is refreshed
+        pool.update_custody_aum(
+            &custody_keys[1],
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            1_040,
+        )
+        .unwrap();
+        assert_eq!({ pool.last_aum_update }, 1_000);
+        assert_eq!(pool.get_keeper_reward(1_000, 1_040), 0);
+
+        pool.update_custody_aum(
+            &custody_keys[0],
+            &mut custody,
+            &token_price,
+            &token_ema_price,
+            1_040,
+        )
+        .unwrap();
+        assert_eq!({ pool.last_aum_update }, 1_040);
+        assert_eq!(pool.get_keeper_reward(1_000, 1_040), 5_000);
+
+        // the next crank is due once half of the max age passes again
+        assert!(!pool.is_crank_due(1_040));
+        assert!(!pool.is_crank_due(1_069));
+        assert!(pool.is_crank_due(1_070));
+
+        // too early since the previous update
+        assert_eq!(pool.get_keeper_reward(1_020, 1_040), 0);
+
+        pool.keeper_reward = 0;
+        assert_eq!(pool.get_keeper_reward(1_000, 1_040), 0);
+    }
+
+    #[test]
+    fn test_settled_exit() {
+        let (pool, mut custody, _, token_price, _) = get_fixture();
+        let size = scale(1, custody.decimals);
+
+        assert_eq!(
+            Pool::get_fee_amount(custody.fees.close_position, size).unwrap(),
+            pool.get_exit_fee(size, &custody, Perpetuals::BPS_POWER as u64)
+                .unwrap()
+        );
+
+        custody.status = MarketStatus::Settled;
+        custody.settlement_price = token_price;
+
+        assert_eq!(
+            0,
+            pool.get_exit_fee(size, &custody, Perpetuals::BPS_POWER as u64)
+                .unwrap()
+        );
+        assert_eq!(
+            scale(25_000, Perpetuals::PRICE_DECIMALS),
+            pool.get_exit_price(&token_price, &token_price, Side::Long, &custody)
+                .unwrap()
+        );
+        assert_eq!(
+            scale(25_000, Perpetuals::PRICE_DECIMALS),
+            pool.get_exit_price(&token_price, &token_price, Side::Short, &custody)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_closed_spread() {
+        let (pool, mut custody, position, token_price, token_ema_price) = get_fixture();
+        let get_close_amount = |custody: &Custody, liquidation: bool| {
+            pool.get_close_amount(
+                &position,
+                &token_price,
+                &token_ema_price,
+                custody,
+                &token_price,
+                &token_ema_price,
+                custody,
+                1,
+                liquidation,
+                Perpetuals::BPS_POWER as u64,
+            )
+            .unwrap()
+        };
+        let get_pnl_usd = |custody: &Custody| {
+            pool.get_pnl_usd(
+                &position,
+                &token_price,
+                &token_ema_price,
+                custody,
+                &token_price,
+                &token_ema_price,
+                custody,
+                1,
+                false,
+                Perpetuals::BPS_POWER as u64,
             )
             .unwrap()
+        };
+        let open_close_amount = get_close_amount(&custody, false);
+        let open_liquidation_amount = get_close_amount(&custody, true);
+        let open_pnl = get_pnl_usd(&custody);
+
+        custody.trading_schedule = TradingSchedule {
+            is_closed: true,
+            closed_spread: 100,
+            ..TradingSchedule::default()
+        };
+
+        // only owners closing their positions pay the closed spread
+        let exit_price = pool
+            .get_exit_price(&token_price, &token_ema_price, Side::Long, &custody)
+            .unwrap();
+        assert!(
+            pool.get_close_price(&token_price, &token_ema_price, Side::Long, &custody, 1)
+                .unwrap()
+                < exit_price
         );
+        assert!(get_close_amount(&custody, false).0 < open_close_amount.0);
+        assert_eq!(get_close_amount(&custody, true), open_liquidation_amount);
+        assert_eq!(get_pnl_usd(&custody), open_pnl);
     }
 
     #[test]
@@ -2020,4 +3082,24 @@ mod test {
         let interest = custody.get_interest_amount_usd(&position, 7_200).unwrap();
         assert_eq!(interest, scale(7, Perpetuals::USD_DECIMALS));
     }
+
+    #[test]
+    fn test_layout() {
+        // layout changes need a new version and a LEN_V constant for upgrade_pool
+        assert_eq!(Pool::LEN, 1377);
+        assert_eq!(Pool::LEN_V3, 1361);
+        assert_eq!(Pool::LEN_V2, 1161);
+
+        // enums are stored as single bytes
+        assert_eq!(std::mem::size_of::<AumCalcMode>(), 1);
+        assert_eq!(
+            Pool::enum_fields(),
+            vec![
+                (987, MarketStatus::VARIANTS),
+                (1060, AumCalcMode::VARIANTS),
+                (1061, AumCalcMode::VARIANTS)
+            ]
+        );
+        assert_eq!(AumCalcMode::VARIANTS, 4);
+    }
 }
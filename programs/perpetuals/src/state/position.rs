@@ -1,9 +1,14 @@
 use {
-    crate::{math, state::perpetuals::Perpetuals},
+    crate::{
+        math,
+        state::{migration::Versioned, perpetuals::Perpetuals},
+    },
     anchor_lang::prelude::*,
 };
 
+// stored as a single byte, also used as a PDA seed
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[repr(u8)]
 pub enum Side {
     None,
     Long,
@@ -51,6 +56,29 @@ pub struct Position {
     pub collateral_amount: u64,
 
     pub bump: u8,
+
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+impl Versioned for Position {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        Position::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl Position {
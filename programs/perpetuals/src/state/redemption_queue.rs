@@ -0,0 +1,168 @@
+use {
+    crate::{error::PerpetualsError, math, state::migration::Versioned},
+    anchor_lang::prelude::*,
+};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct RedemptionRequest {
+    pub owner: Pubkey,
+    // LP tokens held in escrow until the request is filled
+    pub lp_amount: u64,
+    // request is cancelled if it would pay out less
+    pub min_amount_out: u64,
+    pub request_time: i64,
+}
+
+// FIFO queue of withdrawals from the custody that wait for locked funds to be released
+#[account]
+#[derive(Default, Debug)]
+pub struct RedemptionQueue {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub requests: Vec<RedemptionRequest>,
+
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+impl Versioned for RedemptionQueue {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        RedemptionQueue::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl RedemptionQueue {
+    pub const MAX_REQUESTS: usize = 32;
+    pub const MAX_OWNER_REQUESTS: usize = 4;
+    pub const LEN: usize = 8
+        + std::mem::size_of::<RedemptionQueue>()
+        + RedemptionQueue::MAX_REQUESTS * std::mem::size_of::<RedemptionRequest>();
+
+    pub fn push(&mut self, request: RedemptionRequest) -> Result<()> {
+        require!(
+            self.requests.len() < Self::MAX_REQUESTS,
+            PerpetualsError::RedemptionQueueFull
+        );
+        require!(
+            self.requests
+                .iter()
+                .filter(|x| x.owner == request.owner)
+                .count()
+                < Self::MAX_OWNER_REQUESTS,
+            PerpetualsError::TooManyRedemptionRequests
+        );
+        self.requests.push(request);
+        Ok(())
+    }
+
+    pub fn front(&self) -> Option<&RedemptionRequest> {
+        self.requests.first()
+    }
+
+    pub fn pop_front(&mut self) -> Option<RedemptionRequest> {
+        if self.requests.is_empty() {
+            None
+        } else {
+            Some(self.requests.remove(0))
+        }
+    }
+
+    /// Returns the total LP amount of queued requests
+    pub fn get_lp_amount(&self) -> Result<u64> {
+        self.requests
+            .iter()
+            .try_fold(0u64, |acc, x| math::checked_add(acc, x.lp_amount))
+    }
+
+    /// Removes all requests of the owner and returns the total LP amount
+    pub fn remove_owner_requests(&mut self, owner: &Pubkey) -> u64 {
+        let lp_amount = self
+            .requests
+            .iter()
+            .filter(|x| x.owner == *owner)
+            .fold(0u64, |acc, x| acc.saturating_add(x.lp_amount));
+        self.requests.retain(|x| x.owner != *owner);
+        lp_amount
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        let mut queue = RedemptionQueue::default();
+        let owner1 = Pubkey::new_unique();
+        let owner2 = Pubkey::new_unique();
+
+        let owners: Vec<Pubkey> = (0..RedemptionQueue::MAX_REQUESTS
+            / RedemptionQueue::MAX_OWNER_REQUESTS)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+
+        for i in 0..RedemptionQueue::MAX_REQUESTS {
+            queue
+                .push(RedemptionRequest {
+                    owner: owners[i % owners.len()],
+                    lp_amount: i as u64,
+                    ..RedemptionRequest::default()
+                })
+                .unwrap();
+        }
+        assert!(queue
+            .push(RedemptionRequest {
+                owner: Pubkey::new_unique(),
+                ..RedemptionRequest::default()
+            })
+            .is_err());
+
+        assert_eq!(queue.get_lp_amount().unwrap(), 496);
+        assert_eq!(queue.pop_front().unwrap().lp_amount, 0);
+        assert_eq!(queue.front().unwrap().lp_amount, 1);
+
+        // owners[0] requests 8, 16, 24
+        assert_eq!(queue.remove_owner_requests(&owners[0]), 48);
+        assert_eq!(queue.requests.len(), 28);
+        assert!(queue.requests.iter().all(|x| x.owner != owners[0]));
+        assert_eq!(queue.remove_owner_requests(&owners[0]), 0);
+
+        // owner's slots are limited
+        assert_eq!(queue.remove_owner_requests(&owners[1]), 52);
+        for _ in 0..RedemptionQueue::MAX_OWNER_REQUESTS {
+            queue
+                .push(RedemptionRequest {
+                    owner: owner1,
+                    ..RedemptionRequest::default()
+                })
+                .unwrap();
+        }
+        assert!(queue
+            .push(RedemptionRequest {
+                owner: owner1,
+                ..RedemptionRequest::default()
+            })
+            .is_err());
+        queue
+            .push(RedemptionRequest {
+                owner: owner2,
+                ..RedemptionRequest::default()
+            })
+            .unwrap();
+    }
+}
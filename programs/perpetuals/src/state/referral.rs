@@ -0,0 +1,126 @@
+use {
+    crate::{math, state::migration::Versioned},
+    anchor_lang::prelude::*,
+};
+
+// referrer code registered with create_referrer, traders link to it with set_referrer
+#[account]
+#[derive(Default, Debug)]
+pub struct Referrer {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub code: String,
+    // referred trade volume and referrer share of fees, USD denominated,
+    // informational only and saturate instead of failing trades
+    pub volume_usd: u128,
+    pub fees_usd: u128,
+    // fees not yet paid with claim_referral_fees
+    pub unclaimed_fees_usd: u64,
+
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+// trader's link to a referrer
+#[account]
+#[derive(Default, Debug)]
+pub struct Referral {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub referrer: Pubkey,
+
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+impl Versioned for Referrer {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        Referrer::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Referrer {
+    // code is used as a PDA seed
+    pub const MAX_CODE_LEN: usize = 32;
+    pub const LEN: usize = 8 + std::mem::size_of::<Referrer>() + Self::MAX_CODE_LEN;
+
+    pub fn validate_code(code: &str) -> bool {
+        !code.is_empty() && code.len() <= Self::MAX_CODE_LEN
+    }
+
+    /// Records a referred trade and credits the referrer's share of the fee
+    pub fn add_trade(&mut self, volume_usd: u64, fee_usd: u64) -> Result<()> {
+        self.volume_usd = self.volume_usd.saturating_add(volume_usd as u128);
+        self.fees_usd = self.fees_usd.saturating_add(fee_usd as u128);
+        self.unclaimed_fees_usd = math::checked_add(self.unclaimed_fees_usd, fee_usd)?;
+        Ok(())
+    }
+}
+
+impl Versioned for Referral {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        Referral::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Referral {
+    pub const LEN: usize = 8 + std::mem::size_of::<Referral>();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_add_trade() {
+        let mut referrer = Referrer {
+            volume_usd: u128::MAX - 1_000,
+            fees_usd: 500,
+            ..Default::default()
+        };
+
+        referrer.add_trade(2_000, 10).unwrap();
+        assert_eq!(referrer.volume_usd, u128::MAX);
+        assert_eq!(referrer.fees_usd, 510);
+        assert_eq!(referrer.unclaimed_fees_usd, 10);
+
+        referrer.add_trade(u64::MAX, u64::MAX - 10).unwrap();
+        assert_eq!(referrer.volume_usd, u128::MAX);
+        assert_eq!(referrer.fees_usd, 510 + (u64::MAX - 10) as u128);
+        assert_eq!(referrer.unclaimed_fees_usd, u64::MAX);
+
+        // unclaimed fees are owed and must not saturate
+        assert!(referrer.add_trade(1, 1).is_err());
+        assert_eq!(referrer.unclaimed_fees_usd, u64::MAX);
+    }
+}
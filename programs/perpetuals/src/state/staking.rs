@@ -0,0 +1,248 @@
+use {
+    crate::{
+        math,
+        state::{custody::Custody, migration::Versioned, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct StakingConfig {
+    // custody that pays the rewards
+    pub reward_custody: Pubkey,
+    // share of collected fees paid to stakers, in BPS
+    pub fee_share: u64,
+}
+
+#[account]
+#[derive(Default, Debug)]
+pub struct Staking {
+    pub pool: Pubkey,
+    pub reward_custody: Pubkey,
+    pub fee_share: u64,
+    pub staked_amount: u64,
+    // cumulative rewards in USD per staked LP token, scaled by REWARD_INDEX_POWER
+    pub reward_index: u128,
+    // total fees collected by the pool at the last update
+    pub last_fees_usd: u128,
+
+    pub bump: u8,
+    pub lp_vault_bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+#[account]
+#[derive(Default, Debug)]
+pub struct UserStake {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub staked_amount: u64,
+    pub reward_index: u128,
+    pub unclaimed_rewards_usd: u64,
+
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+impl StakingConfig {
+    pub fn validate(&self) -> bool {
+        self.reward_custody != Pubkey::default() && self.fee_share as u128 <= Perpetuals::BPS_POWER
+    }
+}
+
+impl Versioned for Staking {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        Staking::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Staking {
+    pub const LEN: usize = 8 + std::mem::size_of::<Staking>();
+    pub const REWARD_INDEX_POWER: u128 = 10u64.pow(12) as u128;
+
+    pub fn get_config(&self) -> StakingConfig {
+        StakingConfig {
+            reward_custody: self.reward_custody,
+            fee_share: self.fee_share,
+        }
+    }
+
+    /// Returns LP share of fees collected by all pool custodies, protocol
+    /// share of fees is paid out to the protocol and not distributed
+    pub fn get_pool_fees_usd(pool: &Pool, accounts: &[AccountInfo]) -> Result<u128> {
+        let custodies = pool.get_custodies();
+        if accounts.len() < custodies.len() {
+            return Err(ProgramError::NotEnoughAccountKeys.into());
+        }
+        let mut fees_usd: u128 = 0;
+        for (idx, &custody) in custodies.iter().enumerate() {
+            require_keys_eq!(accounts[idx].key(), custody);
+            let custody = AccountLoader::<Custody>::try_from(&accounts[idx])?;
+            let custody = custody.load()?;
+            let lp_share =
+                math::checked_sub(Perpetuals::BPS_POWER, custody.fees.protocol_share as u128)?;
+            // flash loan fees are paid to LPs in full
+            let collected_fees = custody.collected_fees;
+            let shared_fees_usd = math::checked_sub(
+                collected_fees.get_total_usd()?,
+                collected_fees.flash_loan_usd,
+            )?;
+            fees_usd = math::checked_add(
+                fees_usd,
+                math::checked_add(
+                    math::checked_div(
+                        math::checked_mul(shared_fees_usd, lp_share)?,
+                        Perpetuals::BPS_POWER,
+                    )?,
+                    collected_fees.flash_loan_usd,
+                )?,
+            )?;
+        }
+        Ok(fees_usd)
+    }
+
+    /// Distributes the stakers' share of fees collected since the last update,
+    /// returns distributed rewards
+    pub fn update_rewards(&mut self, fees_usd: u128) -> Result<u128> {
+        let new_fees_usd = fees_usd.saturating_sub(self.last_fees_usd);
+        self.last_fees_usd = fees_usd;
+
+        // with nothing staked, fees stay with the pool
+        if self.staked_amount == 0 || new_fees_usd == 0 {
+            return Ok(0);
+        }
+
+        let rewards_usd = math::checked_div(
+            math::checked_mul(new_fees_usd, self.fee_share as u128)?,
+            Perpetuals::BPS_POWER,
+        )?;
+        self.reward_index = math::checked_add(
+            self.reward_index,
+            math::checked_div(
+                math::checked_mul(rewards_usd, Self::REWARD_INDEX_POWER)?,
+                self.staked_amount as u128,
+            )?,
+        )?;
+
+        Ok(rewards_usd)
+    }
+
+    /// Distributes fees collected by pool custodies since the last update and
+    /// reserves the rewards in the reward custody. Accounts hold all pool
+    /// custodies, the reward custody must be writable.
+    pub fn update_pool_rewards(&mut self, pool: &mut Pool, accounts: &[AccountInfo]) -> Result<()> {
+        let rewards_usd = self.update_rewards(Self::get_pool_fees_usd(pool, accounts)?)?;
+        if rewards_usd == 0 {
+            return Ok(());
+        }
+
+        let token_id = pool.get_token_id(&self.reward_custody)?;
+        let reward_custody = AccountLoader::<Custody>::try_from(&accounts[token_id])?;
+        let mut reward_custody = reward_custody.load_mut()?;
+        let staking_rewards_usd =
+            math::checked_add(reward_custody.staking_rewards_usd, rewards_usd)?;
+        pool.set_staking_rewards(&mut reward_custody, staking_rewards_usd)
+    }
+}
+
+impl Versioned for UserStake {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        UserStake::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl UserStake {
+    pub const LEN: usize = 8 + std::mem::size_of::<UserStake>();
+
+    /// Moves rewards accrued since the last update to unclaimed rewards
+    pub fn update_rewards(&mut self, staking: &Staking) -> Result<()> {
+        let accrued_usd = math::checked_div(
+            math::checked_mul(
+                self.staked_amount as u128,
+                math::checked_sub(staking.reward_index, self.reward_index)?,
+            )?,
+            Staking::REWARD_INDEX_POWER,
+        )?;
+        self.unclaimed_rewards_usd = math::checked_add(
+            self.unclaimed_rewards_usd,
+            math::checked_as_u64(accrued_usd)?,
+        )?;
+        self.reward_index = staking.reward_index;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rewards() {
+        let mut staking = Staking {
+            fee_share: 2_000,
+            ..Staking::default()
+        };
+
+        // fees collected before anything is staked are not distributed
+        staking.update_rewards(1_000_000).unwrap();
+        assert_eq!(staking.reward_index, 0);
+        assert_eq!(staking.last_fees_usd, 1_000_000);
+
+        let mut user1 = UserStake::default();
+        user1.update_rewards(&staking).unwrap();
+        user1.staked_amount = 2_000_000;
+        staking.staked_amount += user1.staked_amount;
+
+        // 20% of 5 USD goes to the only staker
+        assert_eq!(staking.update_rewards(6_000_000).unwrap(), 1_000_000);
+        user1.update_rewards(&staking).unwrap();
+        assert_eq!(user1.unclaimed_rewards_usd, 1_000_000);
+
+        let mut user2 = UserStake::default();
+        user2.update_rewards(&staking).unwrap();
+        user2.staked_amount = 2_000_000;
+        staking.staked_amount += user2.staked_amount;
+
+        // rewards are split by stake
+        staking.update_rewards(10_000_000).unwrap();
+        user1.update_rewards(&staking).unwrap();
+        user2.update_rewards(&staking).unwrap();
+        assert_eq!(user1.unclaimed_rewards_usd, 1_400_000);
+        assert_eq!(user2.unclaimed_rewards_usd, 400_000);
+
+        // repeated updates don't accrue more
+        user2.update_rewards(&staking).unwrap();
+        assert_eq!(user2.unclaimed_rewards_usd, 400_000);
+    }
+}
@@ -0,0 +1,113 @@
+use {
+    crate::{math, state::migration::Versioned},
+    anchor_lang::prelude::*,
+};
+
+// per-user liquidity record used to enforce LP mint caps
+#[account]
+#[derive(Default, Debug)]
+pub struct UserDeposit {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    // LP tokens minted to the owner less LP tokens burned by the owner
+    pub lp_amount: u64,
+    pub last_deposit_time: i64,
+    // LP tokens minted while the pool has LP cooldown enabled, held in escrow
+    // until the cooldown has passed since the last deposit
+    pub locked_lp_amount: u64,
+    pub locked_junior_lp_amount: u64,
+
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+impl Versioned for UserDeposit {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        UserDeposit::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl UserDeposit {
+    pub const LEN: usize = 8 + std::mem::size_of::<UserDeposit>();
+
+    /// Records minted LP tokens and checks the cap, zero cap means no limit
+    pub fn add_lp_amount(&mut self, amount: u64, max_lp_amount: u64) -> Result<bool> {
+        self.lp_amount = math::checked_add(self.lp_amount, amount)?;
+        Ok(max_lp_amount == 0 || self.lp_amount <= max_lp_amount)
+    }
+
+    /// Returns true if LP tokens are not locked by the cooldown
+    pub fn is_unlocked(&self, cooldown_sec: u64, curtime: i64) -> Result<bool> {
+        Ok(curtime >= math::checked_add(self.last_deposit_time, cooldown_sec as i64)?)
+    }
+
+    /// Records LP tokens moved to escrow, a new deposit extends the cooldown
+    /// of tokens locked before
+    pub fn lock_lp_amount(&mut self, amount: u64, junior: bool) -> Result<()> {
+        if junior {
+            self.locked_junior_lp_amount = math::checked_add(self.locked_junior_lp_amount, amount)?;
+        } else {
+            self.locked_lp_amount = math::checked_add(self.locked_lp_amount, amount)?;
+        }
+        Ok(())
+    }
+
+    /// Releases all LP tokens of the tranche held in escrow
+    pub fn unlock_lp_amount(&mut self, junior: bool) -> u64 {
+        if junior {
+            std::mem::take(&mut self.locked_junior_lp_amount)
+        } else {
+            std::mem::take(&mut self.locked_lp_amount)
+        }
+    }
+
+    pub fn remove_lp_amount(&mut self, amount: u64) {
+        // LP tokens are transferable, so more than minted can be burned
+        self.lp_amount = self.lp_amount.saturating_sub(amount);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lp_amount() {
+        let mut user_deposit = UserDeposit::default();
+        assert!(user_deposit.add_lp_amount(1_000, 0).unwrap());
+        assert!(user_deposit.add_lp_amount(1_000, 2_000).unwrap());
+        assert!(!user_deposit.add_lp_amount(1, 2_000).unwrap());
+
+        user_deposit.remove_lp_amount(1_500);
+        assert_eq!(user_deposit.lp_amount, 501);
+        user_deposit.remove_lp_amount(1_000);
+        assert_eq!(user_deposit.lp_amount, 0);
+
+        user_deposit.last_deposit_time = 100;
+        assert!(user_deposit.is_unlocked(0, 100).unwrap());
+        assert!(!user_deposit.is_unlocked(60, 159).unwrap());
+        assert!(user_deposit.is_unlocked(60, 160).unwrap());
+
+        user_deposit.lock_lp_amount(100, false).unwrap();
+        user_deposit.lock_lp_amount(50, false).unwrap();
+        user_deposit.lock_lp_amount(20, true).unwrap();
+        assert_eq!(user_deposit.unlock_lp_amount(false), 150);
+        assert_eq!(user_deposit.unlock_lp_amount(false), 0);
+        assert_eq!(user_deposit.unlock_lp_amount(true), 20);
+    }
+}
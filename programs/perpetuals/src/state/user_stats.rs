@@ -0,0 +1,208 @@
+use {
+    crate::state::{migration::Versioned, position::Side},
+    anchor_lang::prelude::*,
+};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[repr(u8)]
+pub enum FillType {
+    None,
+    Open,
+    Close,
+    Liquidation,
+    Swap,
+}
+
+impl Default for FillType {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct Fill {
+    pub fill_type: FillType,
+    // Side::None for swaps
+    pub side: Side,
+    // position custody, or receiving custody for swaps
+    pub custody: Pubkey,
+    pub time: i64,
+    // execution price for positions, token in oracle price for swaps
+    pub price: u64,
+    pub size_usd: u64,
+    pub fee_usd: u64,
+    // realized pnl, zero for open and swap fills
+    pub profit_usd: u64,
+    pub loss_usd: u64,
+}
+
+// per-user trading stats, trade volume is also used to select the fee tier
+#[account]
+#[derive(Default, Debug)]
+pub struct UserStats {
+    pub owner: Pubkey,
+    // daily trade volume, USD denominated, indexed by day % VOLUME_DAYS
+    pub daily_volume_usd: [u64; 30],
+    // day of the last recorded trade
+    pub last_day: i64,
+
+    // lifetime totals, USD denominated
+    pub volume_usd: u64,
+    pub fees_paid_usd: u64,
+    pub profit_usd: u64,
+    pub loss_usd: u64,
+    pub liquidations: u64,
+
+    // ring buffer of the most recent fills, the next one is written
+    // at fills_count % MAX_FILLS
+    pub fills_count: u64,
+    pub fills: [Fill; 16],
+
+    pub bump: u8,
+    // layout version, new fields are appended after it
+    pub version: u8,
+}
+
+impl Versioned for UserStats {
+    const VERSION: u8 = 1;
+
+    fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    fn get_size(&self) -> usize {
+        UserStats::LEN
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl UserStats {
+    pub const LEN: usize = 8 + std::mem::size_of::<UserStats>();
+    pub const VOLUME_DAYS: i64 = 30;
+    pub const MAX_FILLS: usize = 16;
+    const SECONDS_PER_DAY: i64 = 86_400;
+
+    /// Returns trade volume over the rolling window ending today
+    pub fn get_volume_usd(&self, curtime: i64) -> u64 {
+        let day = curtime.div_euclid(Self::SECONDS_PER_DAY);
+        let first_day = std::cmp::max(day, self.last_day) - Self::VOLUME_DAYS + 1;
+        (first_day..=self.last_day).fold(0u64, |acc, d| {
+            acc.saturating_add(self.daily_volume_usd[d.rem_euclid(Self::VOLUME_DAYS) as usize])
+        })
+    }
+
+    /// Records trade volume, discarding days that fell out of the window
+    pub fn add_volume(&mut self, volume_usd: u64, curtime: i64) {
+        let day = curtime.div_euclid(Self::SECONDS_PER_DAY);
+        if day > self.last_day {
+            let elapsed_days = std::cmp::min(day - self.last_day, Self::VOLUME_DAYS);
+            for d in (day - elapsed_days + 1)..=day {
+                self.daily_volume_usd[d.rem_euclid(Self::VOLUME_DAYS) as usize] = 0;
+            }
+            self.last_day = day;
+        }
+        let idx = self.last_day.rem_euclid(Self::VOLUME_DAYS) as usize;
+        self.daily_volume_usd[idx] = self.daily_volume_usd[idx].saturating_add(volume_usd);
+    }
+
+    /// Updates lifetime totals and stores the fill, overwriting the oldest one
+    pub fn record_fill(&mut self, fill: Fill) {
+        self.volume_usd = self.volume_usd.saturating_add(fill.size_usd);
+        self.fees_paid_usd = self.fees_paid_usd.saturating_add(fill.fee_usd);
+        self.profit_usd = self.profit_usd.saturating_add(fill.profit_usd);
+        self.loss_usd = self.loss_usd.saturating_add(fill.loss_usd);
+        if fill.fill_type == FillType::Liquidation {
+            self.liquidations = self.liquidations.saturating_add(1);
+        }
+        self.fills[(self.fills_count % Self::MAX_FILLS as u64) as usize] = fill;
+        self.fills_count = self.fills_count.wrapping_add(1);
+    }
+
+    /// Returns stored fills, the most recent first
+    pub fn get_recent_fills(&self) -> Vec<Fill> {
+        let len = std::cmp::min(self.fills_count, Self::MAX_FILLS as u64);
+        (1..=len)
+            .map(|i| {
+                self.fills[(self.fills_count.wrapping_sub(i) % Self::MAX_FILLS as u64) as usize]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_volume() {
+        let day = UserStats::SECONDS_PER_DAY;
+        let mut user_stats = UserStats::default();
+        assert_eq!(user_stats.get_volume_usd(100 * day), 0);
+
+        user_stats.add_volume(1_000, 100 * day);
+        user_stats.add_volume(500, 100 * day + 10);
+        user_stats.add_volume(2_000, 110 * day);
+        assert_eq!(user_stats.get_volume_usd(110 * day), 3_500);
+
+        // volume older than the window is not counted
+        assert_eq!(user_stats.get_volume_usd(129 * day), 3_500);
+        assert_eq!(user_stats.get_volume_usd(130 * day), 2_000);
+        assert_eq!(user_stats.get_volume_usd(140 * day), 0);
+
+        user_stats.add_volume(100, 130 * day);
+        assert_eq!(user_stats.get_volume_usd(130 * day), 2_100);
+        user_stats.add_volume(100, 200 * day);
+        assert_eq!(user_stats.get_volume_usd(200 * day), 100);
+    }
+
+    #[test]
+    fn test_record_fill() {
+        let mut user_stats = UserStats::default();
+        assert!(user_stats.get_recent_fills().is_empty());
+
+        let fill = |fill_type, time| Fill {
+            fill_type,
+            side: Side::Long,
+            time,
+            size_usd: 1_000,
+            fee_usd: 10,
+            profit_usd: if fill_type == FillType::Close { 50 } else { 0 },
+            loss_usd: if fill_type == FillType::Liquidation {
+                900
+            } else {
+                0
+            },
+            ..Fill::default()
+        };
+
+        user_stats.record_fill(fill(FillType::Close, 1));
+        user_stats.record_fill(fill(FillType::Liquidation, 2));
+        assert_eq!(user_stats.volume_usd, 2_000);
+        assert_eq!(user_stats.fees_paid_usd, 20);
+        assert_eq!(user_stats.profit_usd, 50);
+        assert_eq!(user_stats.loss_usd, 900);
+        assert_eq!(user_stats.liquidations, 1);
+        let fills = user_stats.get_recent_fills();
+        assert_eq!(fills.len(), 2);
+        assert_eq!(fills[0].time, 2);
+        assert_eq!(fills[1].time, 1);
+
+        // the oldest fills are overwritten once the buffer is full
+        for time in 3..=20 {
+            user_stats.record_fill(fill(FillType::Swap, time));
+        }
+        assert_eq!(user_stats.fills_count, 20);
+        assert_eq!(user_stats.volume_usd, 20_000);
+        let fills = user_stats.get_recent_fills();
+        assert_eq!(fills.len(), UserStats::MAX_FILLS);
+        assert_eq!(fills[0].time, 20);
+        assert_eq!(fills[UserStats::MAX_FILLS - 1].time, 5);
+    }
+}
@@ -18,6 +18,7 @@ describe("perpetuals", () => {
   let permissions;
   let fees;
   let borrowRate;
+  let custodyBounds;
   let ratios;
   let isStable;
   let isVirtual;
@@ -48,25 +49,20 @@ describe("perpetuals", () => {
       transferAuthorityBump: tc.authority.bump,
       perpetualsBump: tc.perpetuals.bump,
       inceptionTime: new BN(0),
+      feeTiers: [],
+      version: 1,
     };
 
     multisigExpected = {
       numSigners: 2,
-      numSigned: 0,
-      minSignatures: 2,
-      instructionAccountsLen: 0,
-      instructionDataLen: 0,
-      instructionHash: new anchor.BN(0),
+      minWeight: 2,
       signers: [
         tc.admins[0].publicKey,
         tc.admins[1].publicKey,
-        PublicKey.default,
-        PublicKey.default,
-        PublicKey.default,
-        PublicKey.default,
-      ],
-      signed: [0, 0, 0, 0, 0, 0],
+      ].concat(new Array(14).fill(PublicKey.default)),
+      weights: [1, 1].concat(new Array(14).fill(0)),
       bump: tc.multisig.bump,
+      version: 1,
     };
 
     let multisig = await tc.program.account.multisig.fetch(
@@ -88,7 +84,7 @@ describe("perpetuals", () => {
     let multisig = await tc.program.account.multisig.fetch(
       tc.multisig.publicKey
     );
-    multisigExpected.minSignatures = 1;
+    multisigExpected.minWeight = 1;
     expect(JSON.stringify(multisig)).to.equal(JSON.stringify(multisigExpected));
   });
 
@@ -118,13 +114,48 @@ describe("perpetuals", () => {
 
     let pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
     let poolExpected = {
-      name: "test pool",
-      custodies: [],
-      ratios: [],
+      name: tc.encodeName("test pool"),
+      numCustodies: 0,
+      custodies: new Array(16).fill(PublicKey.default),
+      ratios: new Array(16).fill({
+        target: new BN(0),
+        min: new BN(0),
+        max: new BN(0),
+      }),
       aumUsd: new BN(0),
       bump: tc.pool.bump,
       lpTokenBump: pool.lpTokenBump,
       inceptionTime: new BN(0),
+      status: { active: {} },
+      displayName: tc.encodeName("test pool"),
+      permissions: {
+        allowSwap: true,
+        allowAddLiquidity: true,
+        allowRemoveLiquidity: true,
+        allowOpenPosition: true,
+        allowClosePosition: true,
+        allowPnlWithdrawal: true,
+        allowCollateralWithdrawal: true,
+        allowSizeChange: true,
+      },
+      addLiquidityAumMode: { max: {} },
+      removeLiquidityAumMode: { min: {} },
+      maxAumUsd: new BN(0),
+      maxUserLpAmount: new BN(0),
+      lpCooldownSec: new BN(0),
+      juniorPremium: new BN(0),
+      tranchesEnabled: false,
+      juniorLpTokenBump: 0,
+      seniorAumUsd: new BN(0),
+      trancheAumUsd: new BN(0),
+      referrerShare: new BN(0),
+      traderDiscount: new BN(0),
+      version: 4,
+      cachedAumUsd: new Array(4).fill(new BN(0)),
+      aumUpdateTimes: new Array(16).fill(new BN(0)),
+      maxAumAgeSec: new BN(60),
+      lastAumUpdate: new BN(0),
+      keeperReward: new BN(0),
     };
     expect(JSON.stringify(pool)).to.equal(JSON.stringify(poolExpected));
 
@@ -297,6 +328,7 @@ describe("perpetuals", () => {
         openPositionUsd: "0",
         closePositionUsd: "0",
         liquidationUsd: "0",
+        flashLoanUsd: "0",
       },
       volumeStats: {
         swapUsd: "0",
@@ -341,6 +373,28 @@ describe("perpetuals", () => {
       },
       bump: token.bump,
       tokenAccountBump: token.tokenAccountBump,
+      status: { active: {} },
+      settlementPrice: {
+        price: "0",
+        exponent: 0,
+      },
+      tradingSchedule: {
+        isClosed: false,
+        useWeeklyHours: false,
+        weeklyHours: [0, 0, 0, 0, 0, 0, 0],
+        closedSpread: "0",
+        accrueBorrowWhenClosed: false,
+      },
+      statsHistory: {
+        hourly: new Array(24).fill({ volumeUsd: "0", feesUsd: "0", maxOiUsd: "0" }),
+        daily: new Array(7).fill({ volumeUsd: "0", feesUsd: "0", maxOiUsd: "0" }),
+        lastHour: "0",
+      },
+      version: 6,
+      aumUsd: new Array(4).fill("0"),
+      flashLoanFee: "0",
+      flashLoanAmount: "0",
+      stakingRewardsUsd: "0",
     };
     expect(JSON.stringify(token)).to.equal(JSON.stringify(tokenExpected));
 
@@ -400,6 +454,198 @@ describe("perpetuals", () => {
     expect(JSON.stringify(token)).to.equal(JSON.stringify(tokenExpected));
   });
 
+  it("setConfigBounds", async () => {
+    const bounds = (min: number, max: number) => ({
+      min: new BN(min),
+      max: new BN(max),
+    });
+    custodyBounds = {
+      oracle: {
+        maxPriceError: bounds(0, 100000),
+        maxPriceAgeSec: bounds(0, 3600),
+      },
+      pricing: {
+        tradeSpreadLong: bounds(0, 1000),
+        tradeSpreadShort: bounds(0, 1000),
+        swapSpread: bounds(0, 1000),
+        minInitialLeverage: bounds(10000, 2000000),
+        maxInitialLeverage: bounds(10000, 2000000),
+        maxLeverage: bounds(10000, 2000000),
+        maxPayoffMult: bounds(0, 100000),
+        maxUtilization: bounds(0, 10000),
+        maxPositionLockedUsd: bounds(0, 1000000000000),
+        maxTotalLockedUsd: bounds(0, 1000000000000),
+      },
+      fees: {
+        ratioMult: bounds(0, 100000),
+        utilizationMult: bounds(0, 100000),
+        swapIn: bounds(0, 1000),
+        swapOut: bounds(0, 1000),
+        stableSwapIn: bounds(0, 1000),
+        stableSwapOut: bounds(0, 1000),
+        addLiquidity: bounds(0, 1000),
+        removeLiquidity: bounds(0, 1000),
+        openPosition: bounds(0, 1000),
+        closePosition: bounds(0, 1000),
+        liquidation: bounds(0, 1000),
+        protocolShare: bounds(0, 1000),
+        feeMax: bounds(0, 1000),
+        feeOptimal: bounds(0, 1000),
+      },
+      borrowRate: {
+        baseRate: bounds(0, 100000),
+        slope1: bounds(0, 1000000),
+        slope2: bounds(0, 1000000),
+        optimalUtilization: bounds(1, 1000000000),
+      },
+      flashLoanFee: bounds(0, 100),
+    };
+    await tc.setConfigBounds(custodyBounds);
+
+    let configBounds = await tc.program.account.configBounds.fetch(
+      tc.configBounds.publicKey
+    );
+    expect(JSON.stringify(configBounds.custody)).to.equal(
+      JSON.stringify(custodyBounds)
+    );
+  });
+
+  it("setBorrowRate", async () => {
+    borrowRate.slope1 = new BN(90000);
+    await tc.setBorrowRate(tc.custodies[0], borrowRate);
+
+    let token = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    tokenExpected.borrowRate.slope1 = "90000";
+    expect(JSON.stringify(token)).to.equal(JSON.stringify(tokenExpected));
+  });
+
+  it("setTradingSchedule", async () => {
+    let tradingSchedule = {
+      isClosed: false,
+      useWeeklyHours: true,
+      weeklyHours: [
+        0xffffff, 0xffffff, 0xffffff, 0xffffff, 0xffffff, 0, 0,
+      ],
+      closedSpread: new BN(50),
+      accrueBorrowWhenClosed: false,
+    };
+    await tc.setTradingSchedule(tc.custodies[0], tradingSchedule);
+
+    let token = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    tokenExpected.tradingSchedule = {
+      isClosed: false,
+      useWeeklyHours: true,
+      weeklyHours: [
+        0xffffff, 0xffffff, 0xffffff, 0xffffff, 0xffffff, 0, 0,
+      ],
+      closedSpread: "50",
+      accrueBorrowWhenClosed: false,
+    };
+    expect(JSON.stringify(token)).to.equal(JSON.stringify(tokenExpected));
+
+    // restore round-the-clock trading for the rest of the tests
+    tradingSchedule.useWeeklyHours = false;
+    tradingSchedule.weeklyHours = [0, 0, 0, 0, 0, 0, 0];
+    tradingSchedule.closedSpread = new BN(0);
+    await tc.setTradingSchedule(tc.custodies[0], tradingSchedule);
+
+    token = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    tokenExpected.tradingSchedule = {
+      isClosed: false,
+      useWeeklyHours: false,
+      weeklyHours: [0, 0, 0, 0, 0, 0, 0],
+      closedSpread: "0",
+      accrueBorrowWhenClosed: false,
+    };
+    expect(JSON.stringify(token)).to.equal(JSON.stringify(tokenExpected));
+  });
+
+  it("updateCustodyConfig", async () => {
+    fees.openPosition = new BN(90);
+    await tc.updateCustodyConfig(tc.custodies[0], {
+      oracle: null,
+      pricing: null,
+      permissions: null,
+      fees,
+      borrowRate: null,
+      ratios: null,
+      flashLoanFee: null,
+    });
+
+    let token = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    tokenExpected.fees.openPosition = "90";
+    expect(JSON.stringify(token)).to.equal(JSON.stringify(tokenExpected));
+
+    // out of governance bounds
+    fees.openPosition = new BN(2000);
+    await tc.ensureFails(
+      tc.updateCustodyConfig(tc.custodies[0], {
+        oracle: null,
+        pricing: null,
+        permissions: null,
+        fees,
+        borrowRate: null,
+        ratios: null,
+        flashLoanFee: null,
+      })
+    );
+    fees.openPosition = new BN(90);
+  });
+
+  it("setMarketStatus", async () => {
+    await tc.setMarketStatus({ reduceOnly: {} }, tc.custodies[0]);
+
+    let token = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    tokenExpected.status = { reduceOnly: {} };
+    expect(JSON.stringify(token)).to.equal(JSON.stringify(tokenExpected));
+
+    await tc.setMarketStatus({ paused: {} }, null);
+    let pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
+    expect(JSON.stringify(pool.status)).to.equal(
+      JSON.stringify({ paused: {} })
+    );
+
+    await tc.setMarketStatus({ active: {} }, tc.custodies[0]);
+    await tc.setMarketStatus({ active: {} }, null);
+    token = await tc.program.account.custody.fetch(tc.custodies[0].custody);
+    tokenExpected.status = { active: {} };
+    expect(JSON.stringify(token)).to.equal(JSON.stringify(tokenExpected));
+  });
+
+  it("setPoolConfig", async () => {
+    let pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
+    let config = {
+      displayName: "Test Pool",
+      permissions: pool.permissions,
+      addLiquidityAumMode: { ema: {} },
+      removeLiquidityAumMode: { ema: {} },
+      maxAumUsd: new BN(1000000000000),
+      maxUserLpAmount: new BN(0),
+      lpCooldownSec: new BN(0),
+      juniorPremium: new BN(0),
+      referrerShare: new BN(0),
+      traderDiscount: new BN(0),
+      maxAumAgeSec: new BN(60),
+      keeperReward: new BN(0),
+    };
+    await tc.setPoolConfig(config);
+
+    pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
+    expect(tc.decodeName(pool.name)).to.equal("test pool");
+    expect(tc.decodeName(pool.displayName)).to.equal("Test Pool");
+    expect(JSON.stringify(pool.addLiquidityAumMode)).to.equal(
+      JSON.stringify({ ema: {} })
+    );
+    expect(pool.maxAumUsd.toString()).to.equal("1000000000000");
+
+    // restore defaults for the rest of the tests
+    config.displayName = "test pool";
+    config.addLiquidityAumMode = { max: {} };
+    config.removeLiquidityAumMode = { min: {} };
+    config.maxAumUsd = new BN(0);
+    await tc.setPoolConfig(config);
+  });
+
   it("setCustomOraclePrice", async () => {
     await tc.setCustomOraclePrice(123, tc.custodies[0]);
     await tc.setCustomOraclePrice(200, tc.custodies[1]);
@@ -539,6 +785,37 @@ describe("perpetuals", () => {
       tc.users[1].tokenAccounts[1],
       tc.custodies[1]
     );
+
+    let userDeposit = await tc.program.account.userDeposit.fetch(
+      tc.users[0].userDeposit
+    );
+    expect(userDeposit.owner.toBase58()).to.equal(
+      tc.users[0].wallet.publicKey.toBase58()
+    );
+    expect(userDeposit.lpAmount.gtn(0)).to.be.true;
+  });
+
+  it("addLiquidityMulti", async () => {
+    let userDeposit = await tc.program.account.userDeposit.fetch(
+      tc.users[0].userDeposit
+    );
+    let lpAmountBefore = userDeposit.lpAmount;
+
+    await tc.addLiquidityMulti(
+      [
+        tc.toTokenAmount(1, tc.custodies[0].decimals),
+        tc.toTokenAmount(1, tc.custodies[1].decimals),
+      ],
+      new BN(1),
+      tc.users[0],
+      [tc.users[0].tokenAccounts[0], tc.users[0].tokenAccounts[1]],
+      [tc.custodies[0], tc.custodies[1]]
+    );
+
+    userDeposit = await tc.program.account.userDeposit.fetch(
+      tc.users[0].userDeposit
+    );
+    expect(userDeposit.lpAmount.gt(lpAmountBefore)).to.be.true;
   });
 
   it("swap", async () => {
@@ -570,6 +847,144 @@ describe("perpetuals", () => {
     );
   });
 
+  it("removeLiquidityInKind", async () => {
+    let balancesBefore = await Promise.all(
+      tc.users[0].tokenAccounts.map((account) => tc.getBalance(account))
+    );
+
+    await tc.removeLiquidityInKind(
+      tc.toTokenAmount(1, 6),
+      tc.custodies.map(() => new BN(1)),
+      tc.users[0],
+      tc.users[0].tokenAccounts
+    );
+
+    for (let i = 0; i < tc.custodies.length; ++i) {
+      let balance = await tc.getBalance(tc.users[0].tokenAccounts[i]);
+      expect(balance > balancesBefore[i]).to.be.true;
+    }
+  });
+
+  it("crankPool", async () => {
+    // custodies were refreshed by the previous instruction, crank is not due
+    await tc.ensureFails(tc.crankPool(tc.users[1]));
+
+    let pool = await tc.program.account.pool.fetch(tc.pool.publicKey);
+    expect(pool.lastAumUpdate.toString()).to.equal("111");
+    for (let i = 0; i < tc.custodies.length; ++i) {
+      expect(pool.aumUpdateTimes[i].toString()).to.equal("111");
+    }
+  });
+
+  it("flashLoan", async () => {
+    let amount = tc.toTokenAmount(1, tc.custodies[0].decimals);
+
+    // disabled until the fee is set
+    await tc.ensureFails(
+      tc.flashLoan(
+        amount,
+        tc.users[0],
+        tc.users[0].tokenAccounts[0],
+        tc.custodies[0]
+      )
+    );
+
+    await tc.updateCustodyConfig(tc.custodies[0], {
+      oracle: null,
+      pricing: null,
+      permissions: null,
+      fees: null,
+      borrowRate: null,
+      ratios: null,
+      flashLoanFee: new BN(10),
+    });
+
+    let balanceBefore = await tc.getBalance(tc.users[0].tokenAccounts[0]);
+    await tc.flashLoan(
+      amount,
+      tc.users[0],
+      tc.users[0].tokenAccounts[0],
+      tc.custodies[0]
+    );
+    let balanceAfter = await tc.getBalance(tc.users[0].tokenAccounts[0]);
+    expect(balanceBefore - balanceAfter).to.equal(1000000);
+
+    let custody = await tc.program.account.custody.fetch(
+      tc.custodies[0].custody
+    );
+    expect(custody.flashLoanAmount.toString()).to.equal("0");
+    expect(custody.collectedFees.flashLoanUsd.gtn(0)).to.be.true;
+
+    // repaid without the fee
+    await tc.ensureFails(
+      tc.flashLoan(
+        amount,
+        tc.users[0],
+        tc.users[0].tokenAccounts[0],
+        tc.custodies[0],
+        true,
+        amount
+      )
+    );
+
+    // not repaid in the same transaction
+    await tc.ensureFails(
+      tc.flashLoan(
+        amount,
+        tc.users[0],
+        tc.users[0].tokenAccounts[0],
+        tc.custodies[0],
+        false
+      )
+    );
+  });
+
+  it("staking", async () => {
+    await tc.setStakingConfig({
+      rewardCustody: tc.custodies[1].custody,
+      feeShare: new BN(2000),
+    });
+    await tc.stake(tc.toTokenAmount(1, 6), tc.users[0]);
+
+    let userStake = await tc.program.account.userStake.fetch(
+      tc.getUserStakeKey(tc.users[0])
+    );
+    expect(userStake.stakedAmount.toString()).to.equal(
+      tc.toTokenAmount(1, 6).toString()
+    );
+
+    // collect fees to be shared with stakers
+    await tc.swap(
+      tc.toTokenAmount(1, tc.custodies[0].decimals),
+      new BN(1),
+      tc.users[1],
+      tc.users[1].tokenAccounts[0],
+      tc.users[1].tokenAccounts[1],
+      tc.custodies[0],
+      tc.custodies[1]
+    );
+
+    // rewards are reserved in the reward custody until claimed
+    await tc.stake(new BN(1), tc.users[0]);
+    let rewardCustody = await tc.program.account.custody.fetch(
+      tc.custodies[1].custody
+    );
+    expect(rewardCustody.stakingRewardsUsd.gtn(0)).to.be.true;
+
+    let balanceBefore = await tc.getBalance(tc.users[0].tokenAccounts[1]);
+    await tc.claimRewards(
+      tc.users[0],
+      tc.users[0].tokenAccounts[1],
+      tc.custodies[1]
+    );
+    let balance = await tc.getBalance(tc.users[0].tokenAccounts[1]);
+    expect(balance > balanceBefore).to.be.true;
+
+    await tc.unstake(tc.toTokenAmount(1, 6).addn(1), tc.users[0]);
+    let staking = await tc.program.account.staking.fetch(tc.staking.publicKey);
+    expect(staking.stakedAmount.toString()).to.equal("0");
+  });
+
   it("openPosition", async () => {
     await tc.openPosition(
       125,
@@ -603,6 +1018,7 @@ describe("perpetuals", () => {
       lockedAmount: "7000000000",
       collateralAmount: "1000000000",
       bump: position.bump,
+      version: 1,
     };
 
     expect(JSON.stringify(position)).to.equal(JSON.stringify(positionExpected));
@@ -8,6 +8,7 @@ import {
   SYSVAR_RENT_PUBKEY,
   SYSVAR_SLOT_HASHES_PUBKEY,
   SYSVAR_INSTRUCTIONS_PUBKEY,
+  TransactionInstruction,
   AddressLookupTableProgram,
   TransactionMessage,
   VersionedTransaction,
@@ -15,9 +16,40 @@ import {
 import * as nacl from "tweetnacl";
 import * as spl from "@solana/spl-token";
 import BN from "bn.js";
+import { createHash } from "crypto";
 
 export type PositionSide = "long" | "short";
 
+// must match AdminInstruction in programs/perpetuals/src/state/multisig.rs
+export enum AdminInstruction {
+  AddPool,
+  RemovePool,
+  AddCustody,
+  RemoveCustody,
+  SetAdminSigners,
+  SetCustodyConfig,
+  SetPermissions,
+  SetBorrowRate,
+  WithdrawFees,
+  WithdrawSolFees,
+  SetCustomOraclePrice,
+  SetTestTime,
+  UpgradeCustody,
+  SetRoleSigners,
+  UpgradeMultisig,
+  SetConfigBounds,
+  UpdateCustodyConfig,
+  SetMarketStatus,
+  SettleCustody,
+  SetTradingSchedule,
+  SetPoolConfig,
+  UpgradePool,
+  SetStakingConfig,
+  EnableTranches,
+  SetFeeTiers,
+  UpgradePerpetuals,
+}
+
 export class TestClient {
   provider: anchor.AnchorProvider;
   program: anchor.Program<Perpetuals>;
@@ -34,6 +66,9 @@ export class TestClient {
   perpetuals: { publicKey: PublicKey; bump: number };
   pool: { publicKey: PublicKey; bump: number };
   lpToken: { publicKey: PublicKey; bump: number };
+  configBounds: { publicKey: PublicKey; bump: number };
+  staking: { publicKey: PublicKey; bump: number };
+  stakingLpVault: { publicKey: PublicKey; bump: number };
 
   custodies: {
     mint: Keypair;
@@ -88,17 +123,23 @@ export class TestClient {
     this.lpToken = this.findProgramAddress("lp_token_mint", [
       this.pool.publicKey,
     ]);
+    this.configBounds = this.findProgramAddress("config_bounds");
+    this.staking = this.findProgramAddress("staking", [this.pool.publicKey]);
+    this.stakingLpVault = this.findProgramAddress("staking_lp_vault", [
+      this.pool.publicKey,
+    ]);
 
     // custodies
     this.custodies = [];
     this.custodies.push(this.generateCustody(9));
     this.custodies.push(this.generateCustody(6));
 
+    // custodies are writable so their cached AUM values can be refreshed
     this.custodyMetas = [];
     for (const custody of this.custodies) {
       this.custodyMetas.push({
         isSigner: false,
-        isWritable: false,
+        isWritable: true,
         pubkey: custody.custody,
       });
     }
@@ -174,10 +215,16 @@ export class TestClient {
         positionAccountsShort.push(positionAccount);
       }
 
+      let userDeposit = this.findProgramAddress("user_deposit", [
+        wallet.publicKey,
+        this.pool.publicKey,
+      ]).publicKey;
+
       this.users.push({
         wallet,
         tokenAccounts,
         lpTokenAccount: PublicKey.default,
+        userDeposit,
         positionAccountsLong,
         positionAccountsShort,
       });
@@ -247,6 +294,17 @@ export class TestClient {
     return { publicKey: res[0], bump: res[1] };
   };
 
+  // pool names are stored zero padded to 64 bytes
+  encodeName = (name: string): number[] => {
+    let res = new Array(64).fill(0);
+    Buffer.from(name).forEach((x, i) => (res[i] = x));
+    return res;
+  };
+
+  decodeName = (name: number[]): string => {
+    return Buffer.from(name).toString().replace(/\0+$/, "");
+  };
+
   confirmTx = async (txSignature: anchor.web3.TransactionSignature) => {
     const latestBlockHash = await this.provider.connection.getLatestBlockhash();
 
@@ -334,6 +392,46 @@ export class TestClient {
   ///////
   // instructions
 
+  // multisig proposal address is derived from the instruction accounts and data,
+  // admin (index 0) and the proposal itself (index 2) are not part of the hash
+  getProposalKey = (
+    ix: TransactionInstruction,
+    instructionType: AdminInstruction
+  ) => {
+    let keys = ix.keys
+      // skip admin, proposal and placeholders of omitted optional accounts
+      .filter(
+        (meta, idx) =>
+          idx != 0 && idx != 2 && !meta.pubkey.equals(this.program.programId)
+      )
+      .map((meta) => meta.pubkey.toBuffer());
+    let data = Buffer.concat([
+      ix.data.subarray(8),
+      Buffer.from([instructionType]),
+    ]);
+    let hash = createHash("sha256")
+      .update(Buffer.concat([...keys, data]))
+      .digest();
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("proposal"), hash],
+      this.program.programId
+    )[0];
+  };
+
+  signAdminInstruction = async (
+    method,
+    instructionType: AdminInstruction,
+    admin: Keypair
+  ) => {
+    let ix = await method
+      .accounts({ proposal: PublicKey.default })
+      .instruction();
+    await method
+      .accounts({ proposal: this.getProposalKey(ix, instructionType) })
+      .signers([admin])
+      .rpc();
+  };
+
   init = async () => {
     try {
       let programData = PublicKey.findProgramAddressSync(
@@ -373,23 +471,27 @@ export class TestClient {
     }
   };
 
-  setAdminSigners = async (minSignatures: number) => {
+  setAdminSigners = async (minWeight: number) => {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .setAdminSigners({
-            minSignatures,
-          })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-          })
-          .remainingAccounts(this.adminMetas)
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .setAdminSigners({
+              weights: [],
+              minWeight,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              systemProgram: SystemProgram.programId,
+            })
+            .remainingAccounts(this.adminMetas),
+          AdminInstruction.SetAdminSigners,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -403,17 +505,20 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .setPermissions(permissions)
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .setPermissions(permissions)
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetPermissions,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -427,23 +532,25 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .addPool({ name })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            transferAuthority: this.authority.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-            pool: this.pool.publicKey,
-            lpTokenMint: this.lpToken.publicKey,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: spl.TOKEN_PROGRAM_ID,
-            rent: SYSVAR_RENT_PUBKEY,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .addPool({ name })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              transferAuthority: this.authority.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              pool: this.pool.publicKey,
+              lpTokenMint: this.lpToken.publicKey,
+              systemProgram: SystemProgram.programId,
+              tokenProgram: spl.TOKEN_PROGRAM_ID,
+              rent: SYSVAR_RENT_PUBKEY,
+            }),
+          AdminInstruction.AddPool,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -468,20 +575,22 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .removePool({})
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            transferAuthority: this.authority.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-            pool: this.pool.publicKey,
-            systemProgram: SystemProgram.programId,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .removePool({})
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              transferAuthority: this.authority.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              pool: this.pool.publicKey,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.RemovePool,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -505,34 +614,36 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .addCustody({
-            isStable,
-            isVirtual,
-            oracle: oracleConfig,
-            pricing,
-            permissions,
-            fees,
-            borrowRate,
-            ratios,
-          })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            transferAuthority: this.authority.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-            pool: this.pool.publicKey,
-            custody: custody.custody,
-            custodyTokenAccount: custody.tokenAccount,
-            custodyTokenMint: custody.mint.publicKey,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: spl.TOKEN_PROGRAM_ID,
-            rent: SYSVAR_RENT_PUBKEY,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .addCustody({
+              isStable,
+              isVirtual,
+              oracle: oracleConfig,
+              pricing,
+              permissions,
+              fees,
+              borrowRate,
+              ratios,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              transferAuthority: this.authority.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody.custody,
+              custodyTokenAccount: custody.tokenAccount,
+              custodyTokenMint: custody.mint.publicKey,
+              systemProgram: SystemProgram.programId,
+              tokenProgram: spl.TOKEN_PROGRAM_ID,
+              rent: SYSVAR_RENT_PUBKEY,
+            }),
+          AdminInstruction.AddCustody,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -546,23 +657,25 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .removeCustody({ ratios })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            transferAuthority: this.authority.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-            pool: this.pool.publicKey,
-            custody: custody.custody,
-            custodyTokenAccount: custody.tokenAccount,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: spl.TOKEN_PROGRAM_ID,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .removeCustody({ ratios })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              transferAuthority: this.authority.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody.custody,
+              custodyTokenAccount: custody.tokenAccount,
+              systemProgram: SystemProgram.programId,
+              tokenProgram: spl.TOKEN_PROGRAM_ID,
+            }),
+          AdminInstruction.RemoveCustody,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -586,27 +699,250 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .setCustodyConfig({
-            isStable,
-            isVirtual,
-            oracle: oracleConfig,
-            pricing,
-            permissions,
-            fees,
-            borrowRate,
-            ratios,
-          })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            pool: this.pool.publicKey,
-            custody: custody.custody,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .setCustodyConfig({
+              isStable,
+              isVirtual,
+              oracle: oracleConfig,
+              pricing,
+              permissions,
+              fees,
+              borrowRate,
+              ratios,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody.custody,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetCustodyConfig,
+          this.admins[i]
+        );
+      } catch (err) {
+        if (this.printErrors) {
+          console.log(err);
+        }
+        throw err;
+      }
+    }
+  };
+
+  setConfigBounds = async (custodyBounds) => {
+    let multisig = await this.program.account.multisig.fetch(
+      this.multisig.publicKey
+    );
+    for (let i = 0; i < multisig.minWeight; ++i) {
+      try {
+        await this.signAdminInstruction(
+          this.program.methods
+            .setConfigBounds({
+              custody: custodyBounds,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              configBounds: this.configBounds.publicKey,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetConfigBounds,
+          this.admins[i]
+        );
+      } catch (err) {
+        if (this.printErrors) {
+          console.log(err);
+        }
+        throw err;
+      }
+    }
+  };
+
+  updateCustodyConfig = async (custody, params) => {
+    let multisig = await this.program.account.multisig.fetch(
+      this.multisig.publicKey
+    );
+    for (let i = 0; i < multisig.minWeight; ++i) {
+      try {
+        await this.signAdminInstruction(
+          this.program.methods
+            .updateCustodyConfig(params)
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              configBounds: this.configBounds.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody.custody,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.UpdateCustodyConfig,
+          this.admins[i]
+        );
+      } catch (err) {
+        if (this.printErrors) {
+          console.log(err);
+        }
+        throw err;
+      }
+    }
+  };
+
+  setMarketStatus = async (status, custody) => {
+    let multisig = await this.program.account.multisig.fetch(
+      this.multisig.publicKey
+    );
+    for (let i = 0; i < multisig.minWeight; ++i) {
+      try {
+        await this.signAdminInstruction(
+          this.program.methods
+            .setMarketStatus({
+              status,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody ? custody.custody : null,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetMarketStatus,
+          this.admins[i]
+        );
+      } catch (err) {
+        if (this.printErrors) {
+          console.log(err);
+        }
+        throw err;
+      }
+    }
+  };
+
+  setBorrowRate = async (custody, borrowRate) => {
+    let multisig = await this.program.account.multisig.fetch(
+      this.multisig.publicKey
+    );
+    for (let i = 0; i < multisig.minWeight; ++i) {
+      try {
+        await this.signAdminInstruction(
+          this.program.methods
+            .setBorrowRate({
+              borrowRate,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              configBounds: this.configBounds.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody.custody,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetBorrowRate,
+          this.admins[i]
+        );
+      } catch (err) {
+        if (this.printErrors) {
+          console.log(err);
+        }
+        throw err;
+      }
+    }
+  };
+
+  setPoolConfig = async (config) => {
+    let multisig = await this.program.account.multisig.fetch(
+      this.multisig.publicKey
+    );
+    for (let i = 0; i < multisig.minWeight; ++i) {
+      try {
+        await this.signAdminInstruction(
+          this.program.methods
+            .setPoolConfig({
+              config,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              pool: this.pool.publicKey,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetPoolConfig,
+          this.admins[i]
+        );
+      } catch (err) {
+        if (this.printErrors) {
+          console.log(err);
+        }
+        throw err;
+      }
+    }
+  };
+
+  setStakingConfig = async (config) => {
+    let multisig = await this.program.account.multisig.fetch(
+      this.multisig.publicKey
+    );
+    for (let i = 0; i < multisig.minWeight; ++i) {
+      try {
+        await this.signAdminInstruction(
+          this.program.methods
+            .setStakingConfig({
+              config,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              transferAuthority: this.authority.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              pool: this.pool.publicKey,
+              lpTokenMint: this.lpToken.publicKey,
+              staking: this.staking.publicKey,
+              stakingLpVault: this.stakingLpVault.publicKey,
+              systemProgram: SystemProgram.programId,
+              tokenProgram: spl.TOKEN_PROGRAM_ID,
+              rent: SYSVAR_RENT_PUBKEY,
+            })
+            .remainingAccounts(
+              this.custodyMetas.slice(0, this.custodies.length)
+            ),
+          AdminInstruction.SetStakingConfig,
+          this.admins[i]
+        );
+      } catch (err) {
+        if (this.printErrors) {
+          console.log(err);
+        }
+        throw err;
+      }
+    }
+  };
+
+  setTradingSchedule = async (custody, tradingSchedule) => {
+    let multisig = await this.program.account.multisig.fetch(
+      this.multisig.publicKey
+    );
+    for (let i = 0; i < multisig.minWeight; ++i) {
+      try {
+        await this.signAdminInstruction(
+          this.program.methods
+            .setTradingSchedule({
+              tradingSchedule,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody.custody,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetTradingSchedule,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -620,25 +956,28 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .withdrawFees({
-            amount,
-          })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            transferAuthority: this.authority.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-            pool: this.pool.publicKey,
-            custody: custody.custody,
-            custodyTokenAccount: custody.tokenAccount,
-            receivingTokenAccount: receivingTokenAccount,
-            tokenProgram: spl.TOKEN_PROGRAM_ID,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .withdrawFees({
+              amount,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              transferAuthority: this.authority.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody.custody,
+              custodyTokenAccount: custody.tokenAccount,
+              receivingTokenAccount: receivingTokenAccount,
+              tokenProgram: spl.TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.WithdrawFees,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -652,21 +991,24 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .withdrawSolFees({
-            amount,
-          })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            transferAuthority: this.authority.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-            receivingAccount: receivingAccount,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .withdrawSolFees({
+              amount,
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              transferAuthority: this.authority.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              receivingAccount: receivingAccount,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.WithdrawSolFees,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -680,27 +1022,29 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .setCustomOraclePrice({
-            price: new BN(price * 1000),
-            expo: -3,
-            conf: new BN(0),
-            ema: new BN(price * 1000),
-            publishTime: new BN(this.getTime()),
-          })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-            pool: this.pool.publicKey,
-            custody: custody.custody,
-            oracleAccount: custody.oracleAccount,
-            systemProgram: SystemProgram.programId,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .setCustomOraclePrice({
+              price: new BN(price * 1000),
+              expo: -3,
+              conf: new BN(0),
+              ema: new BN(price * 1000),
+              publishTime: new BN(this.getTime()),
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              pool: this.pool.publicKey,
+              custody: custody.custody,
+              oracleAccount: custody.oracleAccount,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetCustomOraclePrice,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -786,19 +1130,22 @@ export class TestClient {
     let multisig = await this.program.account.multisig.fetch(
       this.multisig.publicKey
     );
-    for (let i = 0; i < multisig.minSignatures; ++i) {
+    for (let i = 0; i < multisig.minWeight; ++i) {
       try {
-        await this.program.methods
-          .setTestTime({
-            time: new BN(time),
-          })
-          .accounts({
-            admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            perpetuals: this.perpetuals.publicKey,
-          })
-          .signers([this.admins[i]])
-          .rpc();
+        await this.signAdminInstruction(
+          this.program.methods
+            .setTestTime({
+              time: new BN(time),
+            })
+            .accounts({
+              admin: this.admins[i].publicKey,
+              multisig: this.multisig.publicKey,
+              perpetuals: this.perpetuals.publicKey,
+              systemProgram: SystemProgram.programId,
+            }),
+          AdminInstruction.SetTestTime,
+          this.admins[i]
+        );
       } catch (err) {
         if (this.printErrors) {
           console.log(err);
@@ -808,6 +1155,91 @@ export class TestClient {
     }
   };
 
+  flashLoan = async (
+    amount: BN,
+    user,
+    tokenAccount: PublicKey,
+    custody,
+    repay: boolean = true,
+    repayAmount: BN = null
+  ) => {
+    if (repayAmount === null) {
+      // the fee is rounded up
+      let flashLoanFee = (
+        await this.program.account.custody.fetch(custody.custody)
+      ).flashLoanFee;
+      repayAmount = amount.add(
+        amount.mul(flashLoanFee).addn(9999).divn(10000)
+      );
+    }
+
+    let tx = this.program.methods
+      .flashBorrow({ amount })
+      .accounts({
+        owner: user.wallet.publicKey,
+        receivingAccount: tokenAccount,
+        transferAuthority: this.authority.publicKey,
+        perpetuals: this.perpetuals.publicKey,
+        pool: this.pool.publicKey,
+        custody: custody.custody,
+        custodyTokenAccount: custody.tokenAccount,
+        ixSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      })
+      .signers([user.wallet]);
+
+    if (repay) {
+      tx = tx.postInstructions([
+        await this.program.methods
+          .flashRepay({ amount: repayAmount })
+          .accounts({
+            owner: user.wallet.publicKey,
+            fundingAccount: tokenAccount,
+            perpetuals: this.perpetuals.publicKey,
+            pool: this.pool.publicKey,
+            custody: custody.custody,
+            custodyOracleAccount: custody.oracleAccount,
+            custodyTokenAccount: custody.tokenAccount,
+            tokenProgram: spl.TOKEN_PROGRAM_ID,
+          })
+          .instruction(),
+      ]);
+    }
+
+    try {
+      await tx.rpc();
+    } catch (err) {
+      if (this.printErrors) {
+        console.log(err);
+      }
+      throw err;
+    }
+  };
+
+  crankPool = async (keeper) => {
+    try {
+      await this.program.methods
+        .crankPool()
+        .accounts({
+          keeper: keeper.wallet.publicKey,
+          keeperRewards: this.findProgramAddress("keeper_rewards", [
+            this.pool.publicKey,
+          ]).publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(this.custodyMetas)
+        .signers([keeper.wallet])
+        .rpc();
+    } catch (err) {
+      if (this.printErrors) {
+        console.log(err);
+      }
+      throw err;
+    }
+  };
+
   swap = async (
     amountIn: BN,
     minAmountOut: BN,
@@ -836,6 +1268,12 @@ export class TestClient {
           dispensingCustody: custodyOut.custody,
           dispensingCustodyOracleAccount: custodyOut.oracleAccount,
           dispensingCustodyTokenAccount: custodyOut.tokenAccount,
+          referral: null,
+          referrer: null,
+          userStats: this.findProgramAddress("user_stats", [
+            user.wallet.publicKey,
+          ]).publicKey,
+          systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
         })
         .signers([user.wallet])
@@ -872,6 +1310,11 @@ export class TestClient {
           custodyOracleAccount: custody.oracleAccount,
           custodyTokenAccount: custody.tokenAccount,
           lpTokenMint: this.lpToken.publicKey,
+          juniorLpTokenMint: null,
+          userDeposit: user.userDeposit,
+          lockedLpAccount: null,
+          lockedJuniorLpAccount: null,
+          systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
         })
         .remainingAccounts(this.custodyMetas)
@@ -885,6 +1328,63 @@ export class TestClient {
     }
   };
 
+  addLiquidityMulti = async (
+    amountsIn: BN[],
+    minLpAmountOut: BN,
+    user,
+    fundingAccounts: PublicKey[],
+    custodies
+  ) => {
+    let remainingAccounts = [];
+    for (let i = 0; i < custodies.length; ++i) {
+      remainingAccounts.push(
+        { isSigner: false, isWritable: true, pubkey: custodies[i].custody },
+        {
+          isSigner: false,
+          isWritable: false,
+          pubkey: custodies[i].oracleAccount,
+        },
+        { isSigner: false, isWritable: true, pubkey: fundingAccounts[i] },
+        {
+          isSigner: false,
+          isWritable: true,
+          pubkey: custodies[i].tokenAccount,
+        }
+      );
+    }
+
+    try {
+      await this.program.methods
+        .addLiquidityMulti({
+          deposits: custodies.map((custody, i) => ({
+            custody: custody.custody,
+            amountIn: amountsIn[i],
+          })),
+          minLpAmountOut,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          lpTokenAccount: user.lpTokenAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          lpTokenMint: this.lpToken.publicKey,
+          userDeposit: user.userDeposit,
+          lockedLpAccount: null,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      if (this.printErrors) {
+        console.log(err);
+      }
+      throw err;
+    }
+  };
+
   removeLiquidity = async (
     lpAmountIn: BN,
     minAmountOut: BN,
@@ -909,6 +1409,12 @@ export class TestClient {
           custodyOracleAccount: custody.oracleAccount,
           custodyTokenAccount: custody.tokenAccount,
           lpTokenMint: this.lpToken.publicKey,
+          juniorLpTokenMint: null,
+          redemptionQueue: this.findProgramAddress("redemption_queue", [
+            this.pool.publicKey,
+            custody.custody,
+          ]).publicKey,
+          userDeposit: user.userDeposit,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
         })
         .remainingAccounts(this.custodyMetas)
@@ -922,6 +1428,155 @@ export class TestClient {
     }
   };
 
+  removeLiquidityInKind = async (
+    lpAmountIn: BN,
+    minAmountsOut: BN[],
+    user,
+    receivingAccounts: PublicKey[]
+  ) => {
+    // all custodies are non-virtual
+    let remainingAccounts = [...this.custodyMetas];
+    for (const custody of this.custodies) {
+      remainingAccounts.push({
+        isSigner: false,
+        isWritable: false,
+        pubkey: this.findProgramAddress("redemption_queue", [
+          this.pool.publicKey,
+          custody.custody,
+        ]).publicKey,
+      });
+    }
+    for (let i = 0; i < this.custodies.length; ++i) {
+      remainingAccounts.push(
+        { isSigner: false, isWritable: true, pubkey: receivingAccounts[i] },
+        {
+          isSigner: false,
+          isWritable: true,
+          pubkey: this.custodies[i].tokenAccount,
+        }
+      );
+    }
+
+    try {
+      await this.program.methods
+        .removeLiquidityInKind({
+          lpAmountIn,
+          minAmountsOut,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          lpTokenAccount: user.lpTokenAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          lpTokenMint: this.lpToken.publicKey,
+          userDeposit: user.userDeposit,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      if (this.printErrors) {
+        console.log(err);
+      }
+      throw err;
+    }
+  };
+
+  getUserStakeKey = (user) => {
+    return this.findProgramAddress("user_stake", [
+      user.wallet.publicKey,
+      this.pool.publicKey,
+    ]).publicKey;
+  };
+
+  stake = async (amount: BN, user) => {
+    try {
+      await this.program.methods
+        .stake({
+          amount,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          lpTokenAccount: user.lpTokenAccount,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          lpTokenMint: this.lpToken.publicKey,
+          staking: this.staking.publicKey,
+          stakingLpVault: this.stakingLpVault.publicKey,
+          userStake: this.getUserStakeKey(user),
+          systemProgram: SystemProgram.programId,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(this.custodyMetas.slice(0, this.custodies.length))
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      if (this.printErrors) {
+        console.log(err);
+      }
+      throw err;
+    }
+  };
+
+  unstake = async (amount: BN, user) => {
+    try {
+      await this.program.methods
+        .unstake({
+          amount,
+        })
+        .accounts({
+          owner: user.wallet.publicKey,
+          lpTokenAccount: user.lpTokenAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          lpTokenMint: this.lpToken.publicKey,
+          staking: this.staking.publicKey,
+          stakingLpVault: this.stakingLpVault.publicKey,
+          userStake: this.getUserStakeKey(user),
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(this.custodyMetas.slice(0, this.custodies.length))
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      if (this.printErrors) {
+        console.log(err);
+      }
+      throw err;
+    }
+  };
+
+  claimRewards = async (user, receivingAccount: PublicKey, rewardCustody) => {
+    try {
+      await this.program.methods
+        .claimRewards({})
+        .accounts({
+          owner: user.wallet.publicKey,
+          receivingAccount,
+          transferAuthority: this.authority.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          pool: this.pool.publicKey,
+          staking: this.staking.publicKey,
+          userStake: this.getUserStakeKey(user),
+          rewardCustody: rewardCustody.custody,
+          rewardCustodyOracleAccount: rewardCustody.oracleAccount,
+          rewardCustodyTokenAccount: rewardCustody.tokenAccount,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(this.custodyMetas.slice(0, this.custodies.length))
+        .signers([user.wallet])
+        .rpc();
+    } catch (err) {
+      if (this.printErrors) {
+        console.log(err);
+      }
+      throw err;
+    }
+  };
+
   openPosition = async (
     price: number,
     collateral: BN,
@@ -952,6 +1607,11 @@ export class TestClient {
           collateralCustody: custody.custody,
           collateralCustodyOracleAccount: custody.oracleAccount,
           collateralCustodyTokenAccount: custody.tokenAccount,
+          referral: null,
+          referrer: null,
+          userStats: this.findProgramAddress("user_stats", [
+            user.wallet.publicKey,
+          ]).publicKey,
           systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
         })
@@ -1061,6 +1721,12 @@ export class TestClient {
           collateralCustody: custody.custody,
           collateralCustodyOracleAccount: custody.oracleAccount,
           collateralCustodyTokenAccount: custody.tokenAccount,
+          referral: null,
+          referrer: null,
+          userStats: this.findProgramAddress("user_stats", [
+            user.wallet.publicKey,
+          ]).publicKey,
+          systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
         })
         .signers([user.wallet])
@@ -1095,6 +1761,10 @@ export class TestClient {
           collateralCustody: custody.custody,
           collateralCustodyOracleAccount: custody.oracleAccount,
           collateralCustodyTokenAccount: custody.tokenAccount,
+          userStats: this.findProgramAddress("user_stats", [
+            user.wallet.publicKey,
+          ]).publicKey,
+          systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
         })
         .signers([user.wallet])
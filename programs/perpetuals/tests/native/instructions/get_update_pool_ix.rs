@@ -28,16 +28,16 @@ pub async fn get_update_pool_ix(
         let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
 
         // For each token, add custody account as remaining_account
-        for custody in &pool_account.custodies {
+        for custody in pool_account.get_custodies() {
             accounts_meta.push(AccountMeta {
                 pubkey: *custody,
                 is_signer: false,
-                is_writable: false,
+                is_writable: true,
             });
         }
 
         // For each token, add custody oracle account as remaining_account
-        for custody in &pool_account.custodies {
+        for custody in pool_account.get_custodies() {
             let custody_account = utils::get_account::<Custody>(program_test_ctx, *custody).await;
 
             accounts_meta.push(AccountMeta {
@@ -1,21 +1,27 @@
 pub mod get_update_pool_ix;
 pub mod test_add_custody;
+pub mod test_add_junior_liquidity;
 pub mod test_add_liquidity;
 pub mod test_add_pool;
 pub mod test_close_position;
+pub mod test_crank_pool;
+pub mod test_enable_tranches;
 pub mod test_get_lp_token_price;
 pub mod test_init;
 pub mod test_liquidate;
 pub mod test_open_position;
 pub mod test_remove_liquidity;
+pub mod test_remove_liquidity_in_kind;
 pub mod test_set_custody_config;
 pub mod test_set_custom_oracle_price;
 pub mod test_swap;
 pub mod test_update_pool_aum;
 
 pub use {
-    get_update_pool_ix::*, test_add_custody::*, test_add_liquidity::*, test_add_pool::*,
-    test_close_position::*, test_get_lp_token_price::*, test_init::*, test_liquidate::*,
-    test_open_position::*, test_remove_liquidity::*, test_set_custody_config::*,
-    test_set_custom_oracle_price::*, test_swap::*, test_update_pool_aum::*,
+    get_update_pool_ix::*, test_add_custody::*, test_add_junior_liquidity::*,
+    test_add_liquidity::*, test_add_pool::*, test_close_position::*, test_crank_pool::*,
+    test_enable_tranches::*, test_get_lp_token_price::*, test_init::*, test_liquidate::*,
+    test_open_position::*, test_remove_liquidity::*, test_remove_liquidity_in_kind::*,
+    test_set_custody_config::*, test_set_custom_oracle_price::*, test_swap::*,
+    test_update_pool_aum::*,
 };
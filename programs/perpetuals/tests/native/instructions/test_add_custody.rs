@@ -6,7 +6,11 @@ use {
     },
     perpetuals::{
         instructions::AddCustodyParams,
-        state::{custody::Custody, multisig::Multisig, pool::Pool},
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            pool::Pool,
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
@@ -35,13 +39,14 @@ pub async fn test_add_custody(
     let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
 
     // One Tx per multisig signer
-    for i in 0..multisig_account.min_signatures {
+    for i in 0..multisig_account.min_weight {
         let signer: &Keypair = multisig_signers[i as usize];
 
         let accounts_meta = {
             let accounts = perpetuals::accounts::AddCustody {
                 admin: admin.pubkey(),
                 multisig: multisig_pda,
+                proposal: Pubkey::default(),
                 transfer_authority: transfer_authority_pda,
                 perpetuals: perpetuals_pda,
                 pool: *pool_pda,
@@ -61,6 +66,10 @@ pub async fn test_add_custody(
                 is_writable: false,
             });
 
+            let instruction_data =
+                Multisig::get_instruction_data(AdminInstruction::AddCustody, &params).unwrap();
+            accounts_meta[2].pubkey = pda::get_proposal_pda(&accounts_meta, &instruction_data).0;
+
             accounts_meta
         };
 
@@ -88,11 +97,11 @@ pub async fn test_add_custody(
         assert_eq!(custody_account.token_account, custody_token_account_pda);
         assert_eq!(custody_account.decimals, custody_token_decimals);
         assert_eq!(custody_account.is_stable, params.is_stable);
-        assert_eq!(custody_account.oracle, params.oracle);
-        assert_eq!(custody_account.pricing, params.pricing);
-        assert_eq!(custody_account.permissions, params.permissions);
-        assert_eq!(custody_account.fees, params.fees);
-        assert_eq!(custody_account.borrow_rate, params.borrow_rate,);
+        assert_eq!({ custody_account.oracle }, params.oracle);
+        assert_eq!({ custody_account.pricing }, params.pricing);
+        assert_eq!({ custody_account.permissions }, params.permissions);
+        assert_eq!({ custody_account.fees }, params.fees);
+        assert_eq!({ custody_account.borrow_rate }, params.borrow_rate,);
         assert_eq!(custody_account.bump, custody_bump);
         assert_eq!(
             custody_account.token_account_bump,
@@ -0,0 +1,122 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::{
+        prelude::{AccountMeta, Pubkey},
+        ToAccountMetas,
+    },
+    perpetuals::{
+        instructions::AddLiquidityParams,
+        state::{custody::Custody, pool::Pool},
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_add_junior_liquidity(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    owner: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    custody_token_mint: &Pubkey,
+    params: AddLiquidityParams,
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+    // Prepare PDA and addresses
+    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
+    let custody_token_account_pda =
+        pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
+    let lp_token_mint_pda = pda::get_lp_token_mint_pda(pool_pda).0;
+    let junior_lp_token_mint_pda = pda::get_junior_lp_token_mint_pda(pool_pda).0;
+    let user_deposit_pda = pda::get_user_deposit_pda(&owner.pubkey(), pool_pda).0;
+
+    let funding_account_address =
+        utils::find_associated_token_account(&owner.pubkey(), custody_token_mint).0;
+    let lp_token_account_address =
+        utils::find_associated_token_account(&owner.pubkey(), &junior_lp_token_mint_pda).0;
+
+    let custody_account = utils::get_account::<Custody>(program_test_ctx, custody_pda).await;
+    let custody_oracle_account_address = custody_account.oracle.oracle_account;
+
+    // Save account state before tx execution
+    let owner_funding_account_before =
+        utils::get_token_account(program_test_ctx, funding_account_address).await;
+    let owner_lp_token_account_before =
+        utils::get_token_account(program_test_ctx, lp_token_account_address).await;
+    let custody_token_account_before =
+        utils::get_token_account(program_test_ctx, custody_token_account_pda).await;
+
+    let accounts_meta = {
+        let accounts = perpetuals::accounts::AddLiquidity {
+            owner: owner.pubkey(),
+            funding_account: funding_account_address,
+            lp_token_account: lp_token_account_address,
+            transfer_authority: transfer_authority_pda,
+            perpetuals: perpetuals_pda,
+            pool: *pool_pda,
+            custody: custody_pda,
+            custody_oracle_account: custody_oracle_account_address,
+            custody_token_account: custody_token_account_pda,
+            lp_token_mint: lp_token_mint_pda,
+            junior_lp_token_mint: Some(junior_lp_token_mint_pda),
+            user_deposit: user_deposit_pda,
+            locked_lp_account: None,
+            locked_junior_lp_account: None,
+            system_program: anchor_lang::system_program::ID,
+            token_program: anchor_spl::token::ID,
+        };
+
+        let mut accounts_meta = accounts.to_account_metas(None);
+
+        let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
+
+        // For each token, add custody account as remaining_account
+        for custody in pool_account.get_custodies() {
+            accounts_meta.push(AccountMeta {
+                pubkey: *custody,
+                is_signer: false,
+                is_writable: true,
+            });
+        }
+
+        // For each token, add custody oracle account as remaining_account
+        for custody in pool_account.get_custodies() {
+            let custody_account = utils::get_account::<Custody>(program_test_ctx, *custody).await;
+
+            accounts_meta.push(AccountMeta {
+                pubkey: custody_account.oracle.oracle_account,
+                is_signer: false,
+                is_writable: false,
+            });
+        }
+
+        accounts_meta
+    };
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        accounts_meta,
+        perpetuals::instruction::AddLiquidity { params },
+        Some(&payer.pubkey()),
+        &[owner, payer],
+        None,
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    let owner_funding_account_after =
+        utils::get_token_account(program_test_ctx, funding_account_address).await;
+    let owner_lp_token_account_after =
+        utils::get_token_account(program_test_ctx, lp_token_account_address).await;
+    let custody_token_account_after =
+        utils::get_token_account(program_test_ctx, custody_token_account_pda).await;
+
+    assert!(owner_funding_account_after.amount < owner_funding_account_before.amount);
+    assert!(owner_lp_token_account_after.amount > owner_lp_token_account_before.amount);
+    assert!(custody_token_account_after.amount > custody_token_account_before.amount);
+
+    Ok(())
+}
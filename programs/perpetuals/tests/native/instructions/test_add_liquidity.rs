@@ -29,6 +29,7 @@ pub async fn test_add_liquidity(
     let custody_token_account_pda =
         pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
     let lp_token_mint_pda = pda::get_lp_token_mint_pda(pool_pda).0;
+    let user_deposit_pda = pda::get_user_deposit_pda(&owner.pubkey(), pool_pda).0;
 
     let funding_account_address =
         utils::find_associated_token_account(&owner.pubkey(), custody_token_mint).0;
@@ -58,6 +59,11 @@ pub async fn test_add_liquidity(
             custody_oracle_account: custody_oracle_account_address,
             custody_token_account: custody_token_account_pda,
             lp_token_mint: lp_token_mint_pda,
+            junior_lp_token_mint: None,
+            user_deposit: user_deposit_pda,
+            locked_lp_account: None,
+            locked_junior_lp_account: None,
+            system_program: anchor_lang::system_program::ID,
             token_program: anchor_spl::token::ID,
         };
 
@@ -66,16 +72,16 @@ pub async fn test_add_liquidity(
         let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
 
         // For each token, add custody account as remaining_account
-        for custody in &pool_account.custodies {
+        for custody in pool_account.get_custodies() {
             accounts_meta.push(AccountMeta {
                 pubkey: *custody,
                 is_signer: false,
-                is_writable: false,
+                is_writable: true,
             });
         }
 
         // For each token, add custody oracle account as remaining_account
-        for custody in &pool_account.custodies {
+        for custody in pool_account.get_custodies() {
             let custody_account = utils::get_account::<Custody>(program_test_ctx, *custody).await;
 
             accounts_meta.push(AccountMeta {
@@ -1,9 +1,16 @@
 use {
     crate::utils::{self, pda},
-    anchor_lang::{prelude::AccountMeta, ToAccountMetas},
+    anchor_lang::{
+        prelude::{AccountMeta, Pubkey},
+        ToAccountMetas,
+    },
     perpetuals::{
         instructions::AddPoolParams,
-        state::{multisig::Multisig, perpetuals::Perpetuals, pool::Pool},
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
@@ -37,13 +44,14 @@ pub async fn test_add_pool(
     let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
 
     // One Tx per multisig signer
-    for i in 0..multisig_account.min_signatures {
+    for i in 0..multisig_account.min_weight {
         let signer: &Keypair = multisig_signers[i as usize];
 
         let accounts_meta = {
             let accounts = perpetuals::accounts::AddPool {
                 admin: admin.pubkey(),
                 multisig: multisig_pda,
+                proposal: Pubkey::default(),
                 transfer_authority: transfer_authority_pda,
                 perpetuals: perpetuals_pda,
                 pool: pool_pda,
@@ -61,6 +69,15 @@ pub async fn test_add_pool(
                 is_writable: false,
             });
 
+            let instruction_data = Multisig::get_instruction_data(
+                AdminInstruction::AddPool,
+                &AddPoolParams {
+                    name: String::from_str(pool_name).unwrap(),
+                },
+            )
+            .unwrap();
+            accounts_meta[2].pubkey = pda::get_proposal_pda(&accounts_meta, &instruction_data).0;
+
             accounts_meta
         };
 
@@ -83,7 +100,8 @@ pub async fn test_add_pool(
     // ==== THEN ==============================================================
     let pool_account = utils::get_account::<Pool>(program_test_ctx, pool_pda).await;
 
-    assert_eq!(pool_account.name.as_str(), pool_name);
+    assert_eq!(pool_account.get_name(), pool_name.as_bytes());
+    assert_eq!(pool_account.get_display_name(), pool_name);
     assert_eq!(pool_account.bump, pool_bump);
     assert_eq!(pool_account.lp_token_bump, lp_token_mint_bump);
 
@@ -53,6 +53,10 @@ pub async fn test_close_position(
             collateral_custody: custody_pda,
             collateral_custody_oracle_account: custody_oracle_account_address,
             collateral_custody_token_account: custody_token_account_pda,
+            referral: None,
+            referrer: None,
+            user_stats: pda::get_user_stats_pda(&owner.pubkey()).0,
+            system_program: anchor_lang::system_program::ID,
             token_program: anchor_spl::token::ID,
         }
         .to_account_metas(None),
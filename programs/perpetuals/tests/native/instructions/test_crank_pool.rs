@@ -0,0 +1,78 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::Pubkey, ToAccountMetas},
+    perpetuals::state::{custody::Custody, pool::Pool},
+    solana_program::instruction::AccountMeta,
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_crank_pool(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    keeper: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+
+    // Prepare PDA and addresses
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let keeper_rewards_pda = pda::get_keeper_rewards_pda(pool_pda).0;
+
+    let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
+
+    let accounts_meta = {
+        let accounts = perpetuals::accounts::CrankPool {
+            keeper: keeper.pubkey(),
+            keeper_rewards: keeper_rewards_pda,
+            perpetuals: perpetuals_pda,
+            pool: *pool_pda,
+            system_program: anchor_lang::system_program::ID,
+        };
+
+        let mut accounts_meta = accounts.to_account_metas(None);
+
+        // For each token, add custody account as remaining_account
+        for custody in pool_account.get_custodies() {
+            accounts_meta.push(AccountMeta {
+                pubkey: *custody,
+                is_signer: false,
+                is_writable: true,
+            });
+        }
+
+        // For each token, add custody oracle account as remaining_account
+        for custody in pool_account.get_custodies() {
+            let custody_account = utils::get_account::<Custody>(program_test_ctx, *custody).await;
+
+            accounts_meta.push(AccountMeta {
+                pubkey: custody_account.oracle.oracle_account,
+                is_signer: false,
+                is_writable: false,
+            });
+        }
+
+        accounts_meta
+    };
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        accounts_meta,
+        perpetuals::instruction::CrankPool {},
+        Some(&payer.pubkey()),
+        &[keeper, payer],
+        None,
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    let pool_account_after = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
+    let current_time = utils::get_current_unix_timestamp(program_test_ctx).await;
+
+    // All custodies are refreshed by the crank
+    assert_eq!({ pool_account_after.last_aum_update }, current_time);
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::{
+        prelude::{AccountMeta, Pubkey},
+        ToAccountMetas,
+    },
+    perpetuals::{
+        instructions::EnableTranchesParams,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            pool::Pool,
+        },
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_enable_tranches(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    admin: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    multisig_signers: &[&Keypair],
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+    let multisig_pda = pda::get_multisig_pda().0;
+    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let junior_lp_token_mint_pda = pda::get_junior_lp_token_mint_pda(pool_pda).0;
+
+    let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
+    let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
+
+    let params = EnableTranchesParams {};
+
+    // One Tx per multisig signer
+    for i in 0..multisig_account.min_weight {
+        let signer: &Keypair = multisig_signers[i as usize];
+
+        let accounts_meta = {
+            let accounts = perpetuals::accounts::EnableTranches {
+                admin: admin.pubkey(),
+                multisig: multisig_pda,
+                proposal: Pubkey::default(),
+                transfer_authority: transfer_authority_pda,
+                perpetuals: perpetuals_pda,
+                pool: *pool_pda,
+                junior_lp_token_mint: junior_lp_token_mint_pda,
+                system_program: anchor_lang::system_program::ID,
+                token_program: anchor_spl::token::ID,
+                rent: solana_program::sysvar::rent::ID,
+            };
+
+            let mut accounts_meta = accounts.to_account_metas(None);
+
+            accounts_meta.push(AccountMeta {
+                pubkey: signer.pubkey(),
+                is_signer: true,
+                is_writable: false,
+            });
+
+            // For each token, add custody account as remaining_account
+            for custody in pool_account.get_custodies() {
+                accounts_meta.push(AccountMeta {
+                    pubkey: *custody,
+                    is_signer: false,
+                    is_writable: true,
+                });
+            }
+
+            // For each token, add custody oracle account as remaining_account
+            for custody in pool_account.get_custodies() {
+                let custody_account =
+                    utils::get_account::<Custody>(program_test_ctx, *custody).await;
+
+                accounts_meta.push(AccountMeta {
+                    pubkey: custody_account.oracle.oracle_account,
+                    is_signer: false,
+                    is_writable: false,
+                });
+            }
+
+            let instruction_data =
+                Multisig::get_instruction_data(AdminInstruction::EnableTranches, &params).unwrap();
+            accounts_meta[2].pubkey = pda::get_proposal_pda(&accounts_meta, &instruction_data).0;
+
+            accounts_meta
+        };
+
+        utils::create_and_execute_perpetuals_ix(
+            program_test_ctx,
+            accounts_meta,
+            perpetuals::instruction::EnableTranches {
+                params: EnableTranchesParams {},
+            },
+            Some(&payer.pubkey()),
+            &[admin, payer, signer],
+            None,
+            None,
+        )
+        .await?;
+    }
+
+    // ==== THEN ==============================================================
+    let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
+
+    assert!(pool_account.tranches_enabled);
+    assert_eq!({ pool_account.senior_aum_usd }, {
+        pool_account.tranche_aum_usd
+    });
+
+    Ok(())
+}
@@ -33,7 +33,7 @@ pub async fn test_get_lp_token_price(
         let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
 
         // For each token, add custody account as remaining_account
-        for custody in &pool_account.custodies {
+        for custody in pool_account.get_custodies() {
             accounts_meta.push(AccountMeta {
                 pubkey: *custody,
                 is_signer: false,
@@ -42,7 +42,7 @@ pub async fn test_get_lp_token_price(
         }
 
         // For each token, add custody oracle account as remaining_account
-        for custody in &pool_account.custodies {
+        for custody in pool_account.get_custodies() {
             let custody_account = utils::get_account::<Custody>(program_test_ctx, *custody).await;
 
             accounts_meta.push(AccountMeta {
@@ -90,7 +90,7 @@ pub async fn test_init(
     // Assert multisig
     {
         assert_eq!(multisig_account.bump, multisig_bump);
-        assert_eq!(multisig_account.min_signatures, params.min_signatures);
+        assert_eq!(multisig_account.min_weight, params.min_signatures);
 
         // Check signers
         {
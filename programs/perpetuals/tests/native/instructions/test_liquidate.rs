@@ -65,6 +65,8 @@ pub async fn test_liquidate(
             collateral_custody: custody_pda,
             collateral_custody_oracle_account: custody_oracle_account_address,
             collateral_custody_token_account: custody_token_account_pda,
+            user_stats: Some(pda::get_user_stats_pda(&owner).0),
+            system_program: anchor_lang::system_program::ID,
             token_program: anchor_spl::token::ID,
         }
         .to_account_metas(None),
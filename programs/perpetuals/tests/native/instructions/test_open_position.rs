@@ -58,6 +58,9 @@ pub async fn test_open_position(
             collateral_custody_oracle_account: custody_oracle_account_address,
             collateral_custody_token_account: custody_token_account_pda,
             system_program: anchor_lang::system_program::ID,
+            referral: None,
+            referrer: None,
+            user_stats: pda::get_user_stats_pda(&owner.pubkey()).0,
             token_program: anchor_spl::token::ID,
         }
         .to_account_metas(None),
@@ -30,6 +30,8 @@ pub async fn test_remove_liquidity(
     let custody_token_account_pda =
         pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
     let lp_token_mint_pda = pda::get_lp_token_mint_pda(pool_pda).0;
+    let redemption_queue_pda = pda::get_redemption_queue_pda(pool_pda, &custody_pda).0;
+    let user_deposit_pda = pda::get_user_deposit_pda(&owner.pubkey(), pool_pda).0;
 
     let receiving_account_address =
         utils::find_associated_token_account(&owner.pubkey(), custody_token_mint).0;
@@ -59,6 +61,9 @@ pub async fn test_remove_liquidity(
             custody_oracle_account: custody_oracle_account_address,
             custody_token_account: custody_token_account_pda,
             lp_token_mint: lp_token_mint_pda,
+            junior_lp_token_mint: None,
+            redemption_queue: redemption_queue_pda,
+            user_deposit: Some(user_deposit_pda),
             token_program: anchor_spl::token::ID,
         };
 
@@ -67,16 +72,16 @@ pub async fn test_remove_liquidity(
         let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
 
         // For each token, add custody account as remaining_account
-        for custody in &pool_account.custodies {
+        for custody in pool_account.get_custodies() {
             accounts_meta.push(AccountMeta {
                 pubkey: *custody,
                 is_signer: false,
-                is_writable: false,
+                is_writable: true,
             });
         }
 
         // For each token, add custody oracle account as remaining_account
-        for custody in &pool_account.custodies {
+        for custody in pool_account.get_custodies() {
             let custody_account = utils::get_account::<Custody>(program_test_ctx, *custody).await;
 
             accounts_meta.push(AccountMeta {
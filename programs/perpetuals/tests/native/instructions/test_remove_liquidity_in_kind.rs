@@ -0,0 +1,128 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::{
+        prelude::{AccountMeta, Pubkey},
+        ToAccountMetas,
+    },
+    perpetuals::{
+        instructions::RemoveLiquidityInKindParams,
+        state::{custody::Custody, pool::Pool},
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_remove_liquidity_in_kind(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    owner: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    params: RemoveLiquidityInKindParams,
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+
+    // Prepare PDA and addresses
+    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let lp_token_mint_pda = pda::get_lp_token_mint_pda(pool_pda).0;
+    let user_deposit_pda = pda::get_user_deposit_pda(&owner.pubkey(), pool_pda).0;
+
+    let lp_token_account_address =
+        utils::find_associated_token_account(&owner.pubkey(), &lp_token_mint_pda).0;
+
+    let pool_account = utils::get_account::<Pool>(program_test_ctx, *pool_pda).await;
+    let mut custody_accounts = vec![];
+    for custody in pool_account.get_custodies() {
+        custody_accounts.push(utils::get_account::<Custody>(program_test_ctx, *custody).await);
+    }
+
+    // Save account state before tx execution
+    let owner_lp_token_account_before =
+        utils::get_token_account(program_test_ctx, lp_token_account_address).await;
+
+    let accounts_meta = {
+        let accounts = perpetuals::accounts::RemoveLiquidityInKind {
+            owner: owner.pubkey(),
+            lp_token_account: lp_token_account_address,
+            transfer_authority: transfer_authority_pda,
+            perpetuals: perpetuals_pda,
+            pool: *pool_pda,
+            lp_token_mint: lp_token_mint_pda,
+            user_deposit: Some(user_deposit_pda),
+            token_program: anchor_spl::token::ID,
+        };
+
+        let mut accounts_meta = accounts.to_account_metas(None);
+
+        // For each token, add custody account as remaining_account
+        for custody in pool_account.get_custodies() {
+            accounts_meta.push(AccountMeta {
+                pubkey: *custody,
+                is_signer: false,
+                is_writable: true,
+            });
+        }
+
+        // For each token, add custody oracle account as remaining_account
+        for custody_account in custody_accounts.iter() {
+            accounts_meta.push(AccountMeta {
+                pubkey: custody_account.oracle.oracle_account,
+                is_signer: false,
+                is_writable: false,
+            });
+        }
+
+        // For each token, add redemption queue as remaining_account
+        for custody in pool_account.get_custodies() {
+            accounts_meta.push(AccountMeta {
+                pubkey: pda::get_redemption_queue_pda(pool_pda, custody).0,
+                is_signer: false,
+                is_writable: false,
+            });
+        }
+
+        // For each non-virtual token, add receiving and custody token accounts
+        for custody_account in custody_accounts.iter() {
+            if custody_account.is_virtual {
+                continue;
+            }
+
+            accounts_meta.push(AccountMeta {
+                pubkey: utils::find_associated_token_account(
+                    &owner.pubkey(),
+                    &custody_account.mint,
+                )
+                .0,
+                is_signer: false,
+                is_writable: true,
+            });
+            accounts_meta.push(AccountMeta {
+                pubkey: custody_account.token_account,
+                is_signer: false,
+                is_writable: true,
+            });
+        }
+
+        accounts_meta
+    };
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        accounts_meta,
+        perpetuals::instruction::RemoveLiquidityInKind { params },
+        Some(&payer.pubkey()),
+        &[owner, payer],
+        None,
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    let owner_lp_token_account_after =
+        utils::get_token_account(program_test_ctx, lp_token_account_address).await;
+
+    assert!(owner_lp_token_account_after.amount < owner_lp_token_account_before.amount);
+
+    Ok(())
+}
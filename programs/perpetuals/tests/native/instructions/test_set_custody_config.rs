@@ -6,7 +6,10 @@ use {
     },
     perpetuals::{
         instructions::SetCustodyConfigParams,
-        state::{custody::Custody, multisig::Multisig},
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
@@ -27,15 +30,17 @@ pub async fn test_set_custody_config(
     let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
 
     // One Tx per multisig signer
-    for i in 0..multisig_account.min_signatures {
+    for i in 0..multisig_account.min_weight {
         let signer: &Keypair = multisig_signers[i as usize];
 
         let accounts_meta = {
             let accounts = perpetuals::accounts::SetCustodyConfig {
                 admin: admin.pubkey(),
                 multisig: multisig_pda,
+                proposal: Pubkey::default(),
                 pool: *pool_pda,
                 custody: *custody_pda,
+                system_program: anchor_lang::system_program::ID,
             };
 
             let mut accounts_meta = accounts.to_account_metas(None);
@@ -46,6 +51,11 @@ pub async fn test_set_custody_config(
                 is_writable: false,
             });
 
+            let instruction_data =
+                Multisig::get_instruction_data(AdminInstruction::SetCustodyConfig, &params)
+                    .unwrap();
+            accounts_meta[2].pubkey = pda::get_proposal_pda(&accounts_meta, &instruction_data).0;
+
             accounts_meta
         };
 
@@ -70,10 +80,10 @@ pub async fn test_set_custody_config(
     {
         assert_eq!(custody_account.pool, *pool_pda);
         assert_eq!(custody_account.is_stable, params.is_stable);
-        assert_eq!(custody_account.oracle, params.oracle);
-        assert_eq!(custody_account.pricing, params.pricing);
-        assert_eq!(custody_account.permissions, params.permissions);
-        assert_eq!(custody_account.fees, params.fees);
+        assert_eq!({ custody_account.oracle }, params.oracle);
+        assert_eq!({ custody_account.pricing }, params.pricing);
+        assert_eq!({ custody_account.permissions }, params.permissions);
+        assert_eq!({ custody_account.fees }, params.fees);
     }
 
     Ok(())
@@ -6,7 +6,10 @@ use {
     },
     perpetuals::{
         instructions::SetCustomOraclePriceParams,
-        state::{multisig::Multisig, oracle::CustomOracle},
+        state::{
+            multisig::{AdminInstruction, Multisig},
+            oracle::CustomOracle,
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
@@ -31,13 +34,14 @@ pub async fn test_set_custom_oracle_price(
     let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
 
     // One Tx per multisig signer
-    for i in 0..multisig_account.min_signatures {
+    for i in 0..multisig_account.min_weight {
         let signer: &Keypair = multisig_signers[i as usize];
 
         let accounts_meta = {
             let accounts = perpetuals::accounts::SetCustomOraclePrice {
                 admin: admin.pubkey(),
                 multisig: multisig_pda,
+                proposal: Pubkey::default(),
                 perpetuals: perpetuals_pda,
                 pool: *pool_pda,
                 custody: *custody_pda,
@@ -53,6 +57,11 @@ pub async fn test_set_custom_oracle_price(
                 is_writable: false,
             });
 
+            let instruction_data =
+                Multisig::get_instruction_data(AdminInstruction::SetCustomOraclePrice, &params)
+                    .unwrap();
+            accounts_meta[2].pubkey = pda::get_proposal_pda(&accounts_meta, &instruction_data).0;
+
             accounts_meta
         };
 
@@ -67,6 +67,10 @@ pub async fn test_swap(
             dispensing_custody: dispensing_custody_pda,
             dispensing_custody_oracle_account: dispensing_custody_oracle_account_address,
             dispensing_custody_token_account: dispensing_custody_token_account_pda,
+            referral: None,
+            referrer: None,
+            user_stats: pda::get_user_stats_pda(&owner.pubkey()).0,
+            system_program: anchor_lang::system_program::ID,
             token_program: anchor_spl::token::ID,
         }
         .to_account_metas(None),
@@ -11,10 +11,14 @@ pub async fn test_integration() {
     tests_suite::liquidity::fixed_fees().await;
     tests_suite::liquidity::insuffisient_fund().await;
     tests_suite::liquidity::min_max_ratio().await;
+    tests_suite::liquidity::remove_liquidity_in_kind().await;
+    tests_suite::liquidity::tranches().await;
 
     tests_suite::position::min_max_leverage().await;
     tests_suite::position::liquidate_position().await;
     tests_suite::position::max_user_profit().await;
 
     tests_suite::lp_token::lp_token_price().await;
+
+    tests_suite::pool::crank_pool().await;
 }
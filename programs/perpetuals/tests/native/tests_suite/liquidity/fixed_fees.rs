@@ -73,17 +73,17 @@ pub async fn fixed_fees() {
             .await;
 
             assert_eq!(
-                pool_account.aum_usd,
+                { pool_account.aum_usd },
                 utils::scale_f64(999.95, USDC_DECIMALS).into(),
             );
 
             assert_eq!(
-                custody_account.collected_fees.add_liquidity_usd,
-                utils::scale(20, USDC_DECIMALS),
+                { custody_account.collected_fees.add_liquidity_usd },
+                utils::scale(20, USDC_DECIMALS) as u128,
             );
 
             assert_eq!(
-                custody_account.assets.protocol_fees,
+                { custody_account.assets.protocol_fees },
                 utils::scale_f64(0.05, USDC_DECIMALS),
             );
         }
@@ -115,17 +115,17 @@ pub async fn fixed_fees() {
             .await;
 
             assert_eq!(
-                pool_account.aum_usd,
+                { pool_account.aum_usd },
                 utils::scale_f64(900.967705, USDC_DECIMALS).into(),
             );
 
             assert_eq!(
-                custody_account.collected_fees.remove_liquidity_usd,
-                utils::scale_f64(3.061072, USDC_DECIMALS),
+                { custody_account.collected_fees.remove_liquidity_usd },
+                utils::scale_f64(3.061072, USDC_DECIMALS) as u128,
             );
 
             assert_eq!(
-                custody_account.assets.protocol_fees,
+                { custody_account.assets.protocol_fees },
                 utils::scale_f64(0.057653, USDC_DECIMALS),
             );
         }
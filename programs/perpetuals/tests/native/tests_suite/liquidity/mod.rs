@@ -1,5 +1,9 @@
 pub mod fixed_fees;
 pub mod insuffisient_fund;
 pub mod min_max_ratio;
+pub mod remove_liquidity_in_kind;
+pub mod tranches;
 
-pub use {fixed_fees::*, insuffisient_fund::*, min_max_ratio::*};
+pub use {
+    fixed_fees::*, insuffisient_fund::*, min_max_ratio::*, remove_liquidity_in_kind::*, tranches::*,
+};
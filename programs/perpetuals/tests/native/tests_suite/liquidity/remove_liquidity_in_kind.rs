@@ -0,0 +1,221 @@
+use {
+    crate::{instructions, utils},
+    maplit::hashmap,
+    perpetuals::{
+        instructions::{
+            OpenPositionParams, RemoveLiquidityInKindParams, RemoveLiquidityParams,
+            SetCustomOraclePriceParams,
+        },
+        state::position::Side,
+    },
+    solana_sdk::signer::Signer,
+};
+
+const USDC_DECIMALS: u8 = 6;
+const ETH_DECIMALS: u8 = 9;
+
+pub async fn remove_liquidity_in_kind() {
+    let test_setup = utils::TestSetup::new(
+        vec![
+            utils::UserParam {
+                name: "alice",
+                token_balances: hashmap! {
+                    "usdc" => utils::scale(15_000, USDC_DECIMALS),
+                    "eth" => utils::scale(10, ETH_DECIMALS),
+                },
+            },
+            utils::UserParam {
+                name: "martin",
+                token_balances: hashmap! {
+                    "usdc" => utils::scale(1_000, USDC_DECIMALS),
+                    "eth" => utils::scale(2, ETH_DECIMALS),
+                },
+            },
+        ],
+        vec![
+            utils::MintParam {
+                name: "usdc",
+                decimals: USDC_DECIMALS,
+            },
+            utils::MintParam {
+                name: "eth",
+                decimals: ETH_DECIMALS,
+            },
+        ],
+        vec!["admin_a", "admin_b", "admin_c"],
+        "main_pool",
+        vec![
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "usdc",
+                    is_stable: true,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(50.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1, USDC_DECIMALS),
+                    initial_conf: utils::scale_f64(0.01, USDC_DECIMALS),
+                    pricing_params: None,
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(15_000, USDC_DECIMALS),
+                payer_user_name: "alice",
+            },
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "eth",
+                    is_stable: false,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(50.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1_500, ETH_DECIMALS),
+                    initial_conf: utils::scale(10, ETH_DECIMALS),
+                    pricing_params: None,
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(10, ETH_DECIMALS),
+                payer_user_name: "alice",
+            },
+        ],
+    )
+    .await;
+
+    let alice = test_setup.get_user_keypair_by_name("alice");
+    let martin = test_setup.get_user_keypair_by_name("martin");
+
+    let admin_a = test_setup.get_multisig_member_keypair_by_name("admin_a");
+
+    let multisig_signers = test_setup.get_multisig_signers();
+
+    let usdc_mint = &test_setup.get_mint_by_name("usdc");
+    let eth_mint = &test_setup.get_mint_by_name("eth");
+
+    // Martin: Open 1 ETH long position x5, locks 5 ETH of the pool
+    instructions::test_open_position(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        eth_mint,
+        OpenPositionParams {
+            // max price paid (slippage implied)
+            price: utils::scale(1_550, ETH_DECIMALS),
+            collateral: utils::scale(1, ETH_DECIMALS),
+            size: utils::scale(5, ETH_DECIMALS),
+            side: Side::Long,
+        },
+    )
+    .await
+    .unwrap();
+
+    // Makes ETH price to raise 20%, the position is in profit
+    {
+        let eth_test_oracle_pda = test_setup.custodies_info[1].custom_oracle_pda;
+        let eth_custody_pda = test_setup.custodies_info[1].custody_pda;
+
+        let publish_time = utils::get_current_unix_timestamp(&test_setup.program_test_ctx).await;
+
+        instructions::test_set_custom_oracle_price(
+            &test_setup.program_test_ctx,
+            admin_a,
+            &test_setup.payer_keypair,
+            &test_setup.pool_pda,
+            &eth_custody_pda,
+            &eth_test_oracle_pda,
+            SetCustomOraclePriceParams {
+                price: utils::scale(1_800, ETH_DECIMALS),
+                expo: -(ETH_DECIMALS as i32),
+                conf: utils::scale(10, ETH_DECIMALS),
+                ema: utils::scale(1_800, ETH_DECIMALS),
+                publish_time,
+            },
+            &multisig_signers,
+        )
+        .await
+        .unwrap();
+    }
+
+    utils::warp_forward(&test_setup.program_test_ctx, 1).await;
+
+    let alice_usdc_pda = utils::find_associated_token_account(&alice.pubkey(), usdc_mint).0;
+    let alice_eth_pda = utils::find_associated_token_account(&alice.pubkey(), eth_mint).0;
+    let alice_lp_pda =
+        utils::find_associated_token_account(&alice.pubkey(), &test_setup.lp_token_mint_pda).0;
+
+    let lp_amount_in =
+        utils::get_token_account_balance(&test_setup.program_test_ctx, alice_lp_pda).await / 10;
+
+    // USD value of the tokens, with USDC decimals
+    let get_value_usd =
+        |usdc_amount: u64, eth_amount: u64| -> u64 { usdc_amount + eth_amount * 1_800 / 1_000 };
+
+    // Alice: Remove 10% of LP tokens for ETH
+    let remove_value_usd = {
+        let eth_balance_before =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_eth_pda).await;
+
+        instructions::test_remove_liquidity(
+            &test_setup.program_test_ctx,
+            alice,
+            &test_setup.payer_keypair,
+            &test_setup.pool_pda,
+            eth_mint,
+            RemoveLiquidityParams {
+                lp_amount_in,
+                min_amount_out: 1,
+            },
+        )
+        .await
+        .unwrap();
+
+        let eth_balance_after =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_eth_pda).await;
+
+        get_value_usd(0, eth_balance_after - eth_balance_before)
+    };
+
+    utils::warp_forward(&test_setup.program_test_ctx, 1).await;
+
+    // Alice: Remove the same amount of LP tokens in kind
+    let in_kind_value_usd = {
+        let usdc_balance_before =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_usdc_pda).await;
+        let eth_balance_before =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_eth_pda).await;
+
+        instructions::test_remove_liquidity_in_kind(
+            &test_setup.program_test_ctx,
+            alice,
+            &test_setup.payer_keypair,
+            &test_setup.pool_pda,
+            RemoveLiquidityInKindParams {
+                lp_amount_in,
+                min_amounts_out: vec![1, 1],
+            },
+        )
+        .await
+        .unwrap();
+
+        let usdc_balance_after =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_usdc_pda).await;
+        let eth_balance_after =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_eth_pda).await;
+
+        assert!(usdc_balance_after > usdc_balance_before);
+        assert!(eth_balance_after > eth_balance_before);
+
+        get_value_usd(
+            usdc_balance_after - usdc_balance_before,
+            eth_balance_after - eth_balance_before,
+        )
+    };
+
+    // Both withdrawals are priced from the same pool value, unrealized pnl
+    // of the position included, and differ only by fees
+    assert!(in_kind_value_usd.abs_diff(remove_value_usd) * 100 < remove_value_usd * 3);
+}
@@ -0,0 +1,231 @@
+use {
+    crate::{instructions, utils},
+    maplit::hashmap,
+    perpetuals::{
+        instructions::{AddLiquidityParams, RemoveLiquidityParams, SetCustomOraclePriceParams},
+        state::pool::Pool,
+    },
+    solana_sdk::signer::Signer,
+};
+
+const USDC_DECIMALS: u8 = 6;
+const ETH_DECIMALS: u8 = 9;
+
+pub async fn tranches() {
+    let test_setup = utils::TestSetup::new(
+        vec![
+            utils::UserParam {
+                name: "alice",
+                token_balances: hashmap! {
+                    "usdc" => utils::scale(15_000, USDC_DECIMALS),
+                    "eth" => utils::scale(10, ETH_DECIMALS),
+                },
+            },
+            utils::UserParam {
+                name: "martin",
+                token_balances: hashmap! {
+                    "usdc" => utils::scale(3_000, USDC_DECIMALS),
+                    "eth" => utils::scale(1, ETH_DECIMALS),
+                },
+            },
+        ],
+        vec![
+            utils::MintParam {
+                name: "usdc",
+                decimals: USDC_DECIMALS,
+            },
+            utils::MintParam {
+                name: "eth",
+                decimals: ETH_DECIMALS,
+            },
+        ],
+        vec!["admin_a", "admin_b", "admin_c"],
+        "main_pool",
+        vec![
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "usdc",
+                    is_stable: true,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(50.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1, USDC_DECIMALS),
+                    initial_conf: utils::scale_f64(0.01, USDC_DECIMALS),
+                    pricing_params: None,
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(15_000, USDC_DECIMALS),
+                payer_user_name: "alice",
+            },
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "eth",
+                    is_stable: false,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(50.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1_500, ETH_DECIMALS),
+                    initial_conf: utils::scale(10, ETH_DECIMALS),
+                    pricing_params: None,
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(10, ETH_DECIMALS),
+                payer_user_name: "alice",
+            },
+        ],
+    )
+    .await;
+
+    let alice = test_setup.get_user_keypair_by_name("alice");
+    let martin = test_setup.get_user_keypair_by_name("martin");
+
+    let admin_a = test_setup.get_multisig_member_keypair_by_name("admin_a");
+
+    let multisig_signers = test_setup.get_multisig_signers();
+
+    let usdc_mint = &test_setup.get_mint_by_name("usdc");
+
+    // Existing liquidity becomes the senior tranche
+    instructions::test_enable_tranches(
+        &test_setup.program_test_ctx,
+        admin_a,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        &multisig_signers,
+    )
+    .await
+    .unwrap();
+
+    // Martin: Add 3k USDC of junior liquidity
+    {
+        let junior_lp_token_mint_pda =
+            utils::pda::get_junior_lp_token_mint_pda(&test_setup.pool_pda).0;
+
+        utils::initialize_token_account(
+            &test_setup.program_test_ctx,
+            &junior_lp_token_mint_pda,
+            &martin.pubkey(),
+        )
+        .await;
+
+        instructions::test_add_junior_liquidity(
+            &test_setup.program_test_ctx,
+            martin,
+            &test_setup.payer_keypair,
+            &test_setup.pool_pda,
+            usdc_mint,
+            AddLiquidityParams {
+                amount_in: utils::scale(3_000, USDC_DECIMALS),
+                min_lp_amount_out: 1,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let pool_before =
+        utils::get_account::<Pool>(&test_setup.program_test_ctx, test_setup.pool_pda).await;
+    let senior_aum_before = pool_before.senior_aum_usd;
+    let junior_aum_before = pool_before.tranche_aum_usd - senior_aum_before;
+
+    // Makes ETH price to drop from 1500 to 1400, the pool loses 1k USD
+    {
+        let eth_test_oracle_pda = test_setup.custodies_info[1].custom_oracle_pda;
+        let eth_custody_pda = test_setup.custodies_info[1].custody_pda;
+
+        let publish_time = utils::get_current_unix_timestamp(&test_setup.program_test_ctx).await;
+
+        instructions::test_set_custom_oracle_price(
+            &test_setup.program_test_ctx,
+            admin_a,
+            &test_setup.payer_keypair,
+            &test_setup.pool_pda,
+            &eth_custody_pda,
+            &eth_test_oracle_pda,
+            SetCustomOraclePriceParams {
+                price: utils::scale(1_400, ETH_DECIMALS),
+                expo: -(ETH_DECIMALS as i32),
+                conf: utils::scale(10, ETH_DECIMALS),
+                ema: utils::scale(1_400, ETH_DECIMALS),
+                publish_time,
+            },
+            &multisig_signers,
+        )
+        .await
+        .unwrap();
+    }
+
+    utils::warp_forward(&test_setup.program_test_ctx, 1).await;
+
+    instructions::test_update_pool_aum(
+        &test_setup.program_test_ctx,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+    )
+    .await
+    .unwrap();
+
+    // The loss is taken by the junior tranche
+    let pool_after =
+        utils::get_account::<Pool>(&test_setup.program_test_ctx, test_setup.pool_pda).await;
+    let senior_aum_after = pool_after.senior_aum_usd;
+    let junior_aum_after = pool_after.tranche_aum_usd - senior_aum_after;
+
+    assert_eq!(senior_aum_after, senior_aum_before);
+    assert!(
+        (junior_aum_before - junior_aum_after).abs_diff(utils::scale(1_000, USDC_DECIMALS) as u128)
+            < utils::scale(20, USDC_DECIMALS) as u128
+    );
+
+    // Alice: Remove 10% of senior LP tokens for USDC, valued at 10% of the senior tranche
+    {
+        let alice_usdc_pda = utils::find_associated_token_account(&alice.pubkey(), usdc_mint).0;
+        let alice_lp_pda =
+            utils::find_associated_token_account(&alice.pubkey(), &test_setup.lp_token_mint_pda).0;
+
+        let lp_amount_in =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_lp_pda).await / 10;
+
+        let usdc_balance_before =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_usdc_pda).await;
+
+        instructions::test_remove_liquidity(
+            &test_setup.program_test_ctx,
+            alice,
+            &test_setup.payer_keypair,
+            &test_setup.pool_pda,
+            usdc_mint,
+            RemoveLiquidityParams {
+                lp_amount_in,
+                min_amount_out: 1,
+            },
+        )
+        .await
+        .unwrap();
+
+        let usdc_balance_after =
+            utils::get_token_account_balance(&test_setup.program_test_ctx, alice_usdc_pda).await;
+
+        // Removal fees and the price confidence interval are below 5%
+        let senior_share_usd = (senior_aum_after / 10) as u64;
+        let received_usd = usdc_balance_after - usdc_balance_before;
+
+        assert!(received_usd <= senior_share_usd);
+        assert!(received_usd * 100 >= senior_share_usd * 95);
+
+        let pool_removed =
+            utils::get_account::<Pool>(&test_setup.program_test_ctx, test_setup.pool_pda).await;
+        let senior_aum_removed = pool_removed.senior_aum_usd;
+
+        assert!(
+            (senior_aum_after - senior_aum_removed).abs_diff(senior_aum_after / 10)
+                < senior_aum_after / 100
+        );
+    }
+}
@@ -1,7 +1,8 @@
 pub mod basic_interactions;
 pub mod liquidity;
 pub mod lp_token;
+pub mod pool;
 pub mod position;
 pub mod swap;
 
-pub use {basic_interactions::*, liquidity::*, lp_token::*, position::*, swap::*};
+pub use {basic_interactions::*, liquidity::*, lp_token::*, pool::*, position::*, swap::*};
@@ -0,0 +1,113 @@
+use {
+    crate::{instructions, utils},
+    maplit::hashmap,
+    solana_sdk::signer::keypair::Keypair,
+};
+
+const USDC_DECIMALS: u8 = 6;
+const ETH_DECIMALS: u8 = 9;
+
+pub async fn crank_pool() {
+    let test_setup = utils::TestSetup::new(
+        vec![utils::UserParam {
+            name: "alice",
+            token_balances: hashmap! {
+                "usdc" => utils::scale(1_000, USDC_DECIMALS),
+                "eth" => utils::scale(1, ETH_DECIMALS),
+            },
+        }],
+        vec![
+            utils::MintParam {
+                name: "usdc",
+                decimals: USDC_DECIMALS,
+            },
+            utils::MintParam {
+                name: "eth",
+                decimals: ETH_DECIMALS,
+            },
+        ],
+        vec!["admin_a", "admin_b", "admin_c"],
+        "main_pool",
+        vec![
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "usdc",
+                    is_stable: true,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(50.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1, USDC_DECIMALS),
+                    initial_conf: utils::scale_f64(0.01, USDC_DECIMALS),
+                    pricing_params: None,
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(1_000, USDC_DECIMALS),
+                payer_user_name: "alice",
+            },
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "eth",
+                    is_stable: false,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(50.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1_500, ETH_DECIMALS),
+                    initial_conf: utils::scale(10, ETH_DECIMALS),
+                    pricing_params: None,
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(1, ETH_DECIMALS),
+                payer_user_name: "alice",
+            },
+        ],
+    )
+    .await;
+
+    // Refresh all custodies, default pool config allows a crank every 30 seconds.
+    // Each crank is sent by a new keeper so transactions are never duplicates.
+    instructions::test_update_pool_aum(
+        &test_setup.program_test_ctx,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+    )
+    .await
+    .unwrap();
+
+    assert!(instructions::test_crank_pool(
+        &test_setup.program_test_ctx,
+        &Keypair::new(),
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+    )
+    .await
+    .is_err());
+
+    utils::warp_forward(&test_setup.program_test_ctx, 30).await;
+
+    instructions::test_crank_pool(
+        &test_setup.program_test_ctx,
+        &Keypair::new(),
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+    )
+    .await
+    .unwrap();
+
+    // Second crank inside the interval fails
+    utils::warp_forward(&test_setup.program_test_ctx, 29).await;
+
+    assert!(instructions::test_crank_pool(
+        &test_setup.program_test_ctx,
+        &Keypair::new(),
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+    )
+    .await
+    .is_err());
+}
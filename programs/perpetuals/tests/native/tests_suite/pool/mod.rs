@@ -0,0 +1,3 @@
+pub mod crank_pool;
+
+pub use crank_pool::*;
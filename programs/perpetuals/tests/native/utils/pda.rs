@@ -1,9 +1,32 @@
-use {perpetuals::state::position::Side, solana_sdk::pubkey::Pubkey};
+use {
+    anchor_lang::prelude::AccountMeta,
+    perpetuals::state::{multisig::Multisig, position::Side},
+    solana_sdk::pubkey::Pubkey,
+};
 
 pub fn get_multisig_pda() -> (Pubkey, u8) {
     Pubkey::find_program_address(&["multisig".as_ref()], &perpetuals::id())
 }
 
+// Proposal address is derived from instruction accounts and data,
+// admin (index 0) and the proposal itself (index 2) are not part of the hash
+pub fn get_proposal_pda(accounts_meta: &[AccountMeta], instruction_data: &[u8]) -> (Pubkey, u8) {
+    let instruction_accounts: Vec<Pubkey> = accounts_meta
+        .iter()
+        .enumerate()
+        // skip admin, proposal and placeholders of omitted optional accounts
+        .filter(|(idx, meta)| *idx != 0 && *idx != 2 && meta.pubkey != perpetuals::id())
+        .map(|(_, meta)| meta.pubkey)
+        .collect();
+
+    let instruction_hash = Multisig::get_instruction_hash(&instruction_accounts, instruction_data);
+
+    Pubkey::find_program_address(
+        &["proposal".as_ref(), instruction_hash.as_ref()],
+        &perpetuals::id(),
+    )
+}
+
 pub fn get_transfer_authority_pda() -> (Pubkey, u8) {
     Pubkey::find_program_address(&["transfer_authority".as_ref()], &perpetuals::id())
 }
@@ -30,6 +53,20 @@ pub fn get_lp_token_mint_pda(pool_pda: &Pubkey) -> (Pubkey, u8) {
     )
 }
 
+pub fn get_junior_lp_token_mint_pda(pool_pda: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &["junior_lp_token_mint".as_ref(), pool_pda.as_ref()],
+        &perpetuals::id(),
+    )
+}
+
+pub fn get_keeper_rewards_pda(pool_pda: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &["keeper_rewards".as_ref(), pool_pda.as_ref()],
+        &perpetuals::id(),
+    )
+}
+
 pub fn get_custody_pda(pool_pda: &Pubkey, custody_token_mint: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(
         &[
@@ -59,6 +96,24 @@ pub fn get_position_pda(
     )
 }
 
+pub fn get_user_deposit_pda(owner: &Pubkey, pool_pda: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &["user_deposit".as_ref(), owner.as_ref(), pool_pda.as_ref()],
+        &perpetuals::id(),
+    )
+}
+
+pub fn get_redemption_queue_pda(pool_pda: &Pubkey, custody_pda: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            "redemption_queue".as_ref(),
+            pool_pda.as_ref(),
+            custody_pda.as_ref(),
+        ],
+        &perpetuals::id(),
+    )
+}
+
 pub fn get_custody_token_account_pda(
     pool_pda: &Pubkey,
     custody_token_mint: &Pubkey,
@@ -83,3 +138,7 @@ pub fn get_custom_oracle_account(pool_pda: &Pubkey, custody_mint: &Pubkey) -> (P
         &perpetuals::id(),
     )
 }
+
+pub fn get_user_stats_pda(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&["user_stats".as_ref(), owner.as_ref()], &perpetuals::id())
+}